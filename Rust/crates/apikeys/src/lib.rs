@@ -0,0 +1,10 @@
+// Hashed, scoped API keys for server-to-server calls
+pub mod key;
+pub mod store;
+pub mod verifier;
+pub mod middleware;
+
+pub use key::{scopes, ApiKey, ApiKeyPrincipal, NewApiKey};
+pub use store::ApiKeyStore;
+pub use verifier::ApiKeyVerifier;
+pub use middleware::ApiKeyAuth;
@@ -0,0 +1,136 @@
+use actix_web::{
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::key::ApiKeyPrincipal;
+use crate::verifier::ApiKeyVerifier;
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+fn unauthorized() -> Error {
+    actix_web::error::ErrorUnauthorized(serde_json::json!({
+        "error": "Unauthorized",
+        "message": "Valid API key required"
+    }))
+}
+
+/// Requires an active `X-API-Key` holding `scope`, and puts its
+/// ApiKeyPrincipal in the request extensions
+#[derive(Clone)]
+pub struct ApiKeyAuth {
+    verifier: ApiKeyVerifier,
+    scope: &'static str,
+    public_paths: Arc<Vec<&'static str>>,
+}
+
+impl ApiKeyAuth {
+    pub fn new(verifier: ApiKeyVerifier, scope: &'static str) -> Self {
+        Self {
+            verifier,
+            scope,
+            public_paths: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Let a path through without a key, e.g. health checks
+    pub fn public_path(mut self, path: &'static str) -> Self {
+        Arc::make_mut(&mut self.public_paths).push(path);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ApiKeyAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ApiKeyAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiKeyAuthMiddleware {
+            service: Rc::new(service),
+            auth: self.clone(),
+        }))
+    }
+}
+
+pub struct ApiKeyAuthMiddleware<S> {
+    service: Rc<S>,
+    auth: ApiKeyAuth,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiKeyAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        if self.auth.public_paths.iter().any(|p| req.path() == *p) {
+            return Box::pin(async move { service.call(req).await });
+        }
+
+        let key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string());
+        let verifier = self.auth.verifier.clone();
+        let scope = self.auth.scope;
+
+        Box::pin(async move {
+            let path = req.path().to_string();
+            let principal = match key {
+                Some(key) => verifier.verify(&key).await.unwrap_or_else(|e| {
+                    tracing::error!("API key lookup failed: {}", e);
+                    None
+                }),
+                None => None,
+            };
+
+            let principal = match principal {
+                Some(principal) => principal,
+                None => {
+                    tracing::warn!("Unauthorized request to {} - Invalid or missing API key", path);
+                    return Err(unauthorized());
+                }
+            };
+            if !principal.has_scope(scope) {
+                tracing::warn!("API key {} lacks scope {} for {}", principal.prefix, scope, path);
+                return Err(actix_web::error::ErrorForbidden(serde_json::json!({
+                    "error": format!("Forbidden: API key requires scope {}", scope)
+                })));
+            }
+
+            req.extensions_mut().insert(principal);
+            service.call(req).await
+        })
+    }
+}
+
+impl FromRequest for ApiKeyPrincipal {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<ApiKeyPrincipal>().cloned().ok_or_else(unauthorized))
+    }
+}
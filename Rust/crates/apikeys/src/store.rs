@@ -0,0 +1,110 @@
+use anyhow::Result;
+use sqlx::MySqlPool;
+
+use crate::key::{generate_api_key, hash_api_key, ApiKey, NewApiKey};
+
+const COLUMNS: &str =
+    "id, prefix, name, owner, scopes, created_by, expires_at, last_used_at, revoked_at, created_at";
+
+/// The `api_keys` table, shared by every service that issues or checks keys
+#[derive(Clone)]
+pub struct ApiKeyStore {
+    pool: MySqlPool,
+}
+
+impl ApiKeyStore {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create a random key. Returns it in full, the only time it is available.
+    pub async fn create(&self, new_key: &NewApiKey) -> Result<(ApiKey, String)> {
+        let (key, prefix) = generate_api_key();
+        let id = self.insert(new_key, &prefix, &hash_api_key(&key)).await?;
+        let stored = self.find_by_id(id).await?
+            .ok_or_else(|| anyhow::anyhow!("API key {} vanished after insert", id))?;
+        Ok((stored, key))
+    }
+
+    /// Store a key generated elsewhere, e.g. one from a legacy env variable.
+    /// Returns false if it is already stored.
+    pub async fn import(&self, new_key: &NewApiKey, key: &str) -> Result<bool> {
+        let hash = hash_api_key(key);
+        if self.find_by_hash(&hash).await?.is_some() {
+            return Ok(false);
+        }
+        // Legacy keys have no prefix of their own; a slice of the hash tells them apart
+        self.insert(new_key, &hash[..8], &hash).await?;
+        Ok(true)
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKey>> {
+        let keys = sqlx::query_as::<_, ApiKey>(&format!(
+            "SELECT {} FROM api_keys ORDER BY created_at DESC",
+            COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(keys)
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<ApiKey>> {
+        let key = sqlx::query_as::<_, ApiKey>(&format!("SELECT {} FROM api_keys WHERE id = ?", COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(key)
+    }
+
+    pub async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        let key = sqlx::query_as::<_, ApiKey>(&format!("SELECT {} FROM api_keys WHERE key_hash = ?", COLUMNS))
+            .bind(key_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(key)
+    }
+
+    /// Returns false if the key does not exist or was already revoked
+    pub async fn revoke(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE api_keys SET revoked_at = NOW() WHERE id = ? AND revoked_at IS NULL")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Record use, at most once a minute per key
+    pub async fn touch(&self, id: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE api_keys SET last_used_at = NOW()
+             WHERE id = ? AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL 1 MINUTE)"
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn insert(&self, new_key: &NewApiKey, prefix: &str, key_hash: &str) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO api_keys (prefix, key_hash, name, owner, scopes, created_by, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(prefix)
+        .bind(key_hash)
+        .bind(&new_key.name)
+        .bind(&new_key.owner)
+        .bind(new_key.scopes.join(","))
+        .bind(new_key.created_by)
+        .bind(new_key.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+}
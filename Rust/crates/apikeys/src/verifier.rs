@@ -0,0 +1,71 @@
+use anyhow::Result;
+use chrono::Utc;
+use hashlink::LruCache;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::key::{hash_api_key, ApiKeyPrincipal};
+use crate::store::ApiKeyStore;
+
+const CACHE_CAPACITY: usize = 1_000;
+// Revoked keys keep working on other instances for at most this long
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+// Verdict for a key hash, None for rejected keys
+type CachedVerdict = (Option<ApiKeyPrincipal>, Instant);
+
+/// Checks presented keys against the store, with a short local cache so
+/// busy callers do not cost a query per request
+#[derive(Clone)]
+pub struct ApiKeyVerifier {
+    store: ApiKeyStore,
+    cache: Arc<Mutex<LruCache<String, CachedVerdict>>>,
+}
+
+impl ApiKeyVerifier {
+    pub fn new(store: ApiKeyStore) -> Self {
+        Self {
+            store,
+            cache: Arc::new(Mutex::new(LruCache::new(CACHE_CAPACITY))),
+        }
+    }
+
+    /// The caller behind the key, None for unknown, revoked or expired keys
+    pub async fn verify(&self, key: &str) -> Result<Option<ApiKeyPrincipal>> {
+        let key_hash = hash_api_key(key);
+
+        if let Some(cached) = self.cached(&key_hash) {
+            return Ok(cached.filter(|p| p.expires_at.is_none_or(|expires_at| expires_at > Utc::now())));
+        }
+
+        let principal = match self.store.find_by_hash(&key_hash).await? {
+            Some(key) if key.is_active() => {
+                if let Err(e) = self.store.touch(key.id).await {
+                    tracing::error!("Failed to record use of API key {}: {}", key.prefix, e);
+                }
+                Some(ApiKeyPrincipal::from(&key))
+            }
+            _ => None,
+        };
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key_hash, (principal.clone(), Instant::now()));
+        }
+        Ok(principal)
+    }
+
+    /// Drop cached verdicts, after a key is revoked on this instance
+    pub fn clear_cache(&self) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.clear();
+        }
+    }
+
+    fn cached(&self, key_hash: &str) -> Option<Option<ApiKeyPrincipal>> {
+        let mut cache = self.cache.lock().ok()?;
+        match cache.get(key_hash) {
+            Some((principal, at)) if at.elapsed() < CACHE_TTL => Some(principal.clone()),
+            _ => None,
+        }
+    }
+}
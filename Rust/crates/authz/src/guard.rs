@@ -0,0 +1,388 @@
+// Role and permission guards on the claims AuthMiddleware put in the request.
+// Wrap them inside AuthMiddleware: `.wrap(Require::permission(..)).wrap(AuthMiddleware::from_env())`
+use actix_web::{
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::jwt::Claims;
+
+/// Permission scopes checked by the services
+pub mod permissions {
+    pub const ALL: &str = "*";
+    pub const PAYMENTS_REFUND: &str = "payments:refund";
+    pub const AUDIT_VERIFY: &str = "audit:verify";
+    pub const USERS_READ: &str = "users:read";
+    pub const ROLES_ASSIGN: &str = "roles:assign";
+    pub const USERS_UNLOCK: &str = "users:unlock";
+    pub const USERS_DELETE: &str = "users:delete";
+    pub const API_KEYS_MANAGE: &str = "api_keys:manage";
+    pub const OAUTH_CLIENTS_MANAGE: &str = "oauth_clients:manage";
+    pub const CHAT_MODERATE: &str = "chat:moderate";
+    pub const KYC_REVIEW: &str = "kyc:review";
+    pub const REPORTS_READ: &str = "reports:read";
+    pub const RECONCILIATION_MANAGE: &str = "reconciliation:manage";
+    pub const MARKETPLACE_SELL: &str = "marketplace:sell";
+}
+
+pub mod roles {
+    pub const ADMIN: &str = "admin";
+    pub const USER: &str = "user";
+}
+
+// Second-factor methods in the amr claim
+const AMR_SECOND_FACTORS: &[&str] = &["otp", "rec", "hwk"];
+
+/// How long a second factor counts as recent, from STEP_UP_MAX_AGE_SECS
+pub fn step_up_max_age() -> u64 {
+    std::env::var("STEP_UP_MAX_AGE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(600)
+}
+
+/// Who a token was issued to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Principal<'a> {
+    User { user_id: i32, email: &'a str },
+    /// A backend service authenticated with client_credentials; its scopes
+    /// are in `permissions`
+    Service { client_id: &'a str },
+}
+
+impl Claims {
+    pub fn principal(&self) -> Principal<'_> {
+        match &self.client_id {
+            Some(client_id) => Principal::Service { client_id },
+            None => Principal::User { user_id: self.user_id, email: &self.sub },
+        }
+    }
+
+    pub fn is_service(&self) -> bool {
+        self.client_id.is_some()
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission || p == permissions::ALL)
+    }
+
+    /// True if the session passed a second factor within the last `max_age_secs`
+    pub fn has_recent_mfa(&self, max_age_secs: u64) -> bool {
+        let second_factor = self.amr.iter().any(|m| AMR_SECOND_FACTORS.contains(&m.as_str()));
+        let recent = self.auth_time
+            .is_some_and(|t| chrono::Utc::now().timestamp() - t <= max_age_secs as i64);
+        second_factor && recent
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Requirement {
+    Role(&'static str),
+    AnyRole(&'static [&'static str]),
+    Permission(&'static str),
+    Verified,
+    RecentMfa(u64),
+    Service,
+}
+
+impl Requirement {
+    fn is_met(&self, claims: &Claims) -> bool {
+        match self {
+            Requirement::Role(role) => claims.has_role(role),
+            Requirement::AnyRole(roles) => roles.iter().any(|role| claims.has_role(role)),
+            Requirement::Permission(permission) => claims.has_permission(permission),
+            Requirement::Verified => !claims.restricted,
+            Requirement::RecentMfa(max_age) => claims.has_recent_mfa(*max_age),
+            Requirement::Service => claims.is_service(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Requirement::Role(role) => format!("role {}", role),
+            Requirement::AnyRole(roles) => format!("one of roles {}", roles.join(", ")),
+            Requirement::Permission(permission) => format!("permission {}", permission),
+            Requirement::Verified => "a verified email".to_string(),
+            Requirement::RecentMfa(_) => "recent two-factor authentication, use /auth/mfa/step-up".to_string(),
+            Requirement::Service => "a service client token".to_string(),
+        }
+    }
+}
+
+fn forbidden(requirement: &Requirement) -> Error {
+    actix_web::error::ErrorForbidden(serde_json::json!({
+        "error": format!("Forbidden: requires {}", requirement.describe())
+    }))
+}
+
+fn unauthorized() -> Error {
+    actix_web::error::ErrorUnauthorized(serde_json::json!({"error": "Unauthorized"}))
+}
+
+/// Route guard: 401 without claims, 403 when the requirement is not met
+#[derive(Clone, Copy)]
+pub struct Require {
+    requirement: Requirement,
+}
+
+impl Require {
+    pub fn role(role: &'static str) -> Self {
+        Self { requirement: Requirement::Role(role) }
+    }
+
+    pub fn any_role(roles: &'static [&'static str]) -> Self {
+        Self { requirement: Requirement::AnyRole(roles) }
+    }
+
+    pub fn permission(permission: &'static str) -> Self {
+        Self { requirement: Requirement::Permission(permission) }
+    }
+
+    /// Rejects restricted tokens, issued to accounts with an unverified email
+    pub fn verified() -> Self {
+        Self { requirement: Requirement::Verified }
+    }
+
+    /// Requires a second factor within STEP_UP_MAX_AGE_SECS, for sensitive actions
+    pub fn recent_mfa() -> Self {
+        Self { requirement: Requirement::RecentMfa(step_up_max_age()) }
+    }
+
+    /// Only service principals; needs `AuthMiddleware::accept_service_clients`
+    pub fn service() -> Self {
+        Self { requirement: Requirement::Service }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Require
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireService {
+            service: Rc::new(service),
+            requirement: self.requirement,
+        }))
+    }
+}
+
+pub struct RequireService<S> {
+    service: Rc<S>,
+    requirement: Requirement,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let allowed = req.extensions().get::<Claims>().map(|claims| self.requirement.is_met(claims));
+
+        match allowed {
+            Some(true) => {
+                let service = self.service.clone();
+                Box::pin(async move { service.call(req).await })
+            }
+            Some(false) => {
+                tracing::warn!("Denied {} {}: requires {}", req.method(), req.path(), self.requirement.describe());
+                let error = forbidden(&self.requirement);
+                Box::pin(async move { Err(error) })
+            }
+            None => Box::pin(async { Err(unauthorized()) }),
+        }
+    }
+}
+
+/// Claims of the authenticated user, as an extractor
+impl FromRequest for Claims {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<Claims>().cloned().ok_or_else(unauthorized))
+    }
+}
+
+/// A permission usable as a type, for the `Permitted` extractor
+pub trait Scope {
+    const PERMISSION: &'static str;
+}
+
+macro_rules! scopes {
+    ($($name:ident => $permission:path),* $(,)?) => {
+        $(
+            pub struct $name;
+
+            impl Scope for $name {
+                const PERMISSION: &'static str = $permission;
+            }
+        )*
+    };
+}
+
+scopes! {
+    RefundPayments => permissions::PAYMENTS_REFUND,
+    VerifyAudit => permissions::AUDIT_VERIFY,
+    ReadUsers => permissions::USERS_READ,
+    AssignRoles => permissions::ROLES_ASSIGN,
+    ModerateChat => permissions::CHAT_MODERATE,
+    ManageApiKeys => permissions::API_KEYS_MANAGE,
+    ManageOAuthClients => permissions::OAUTH_CLIENTS_MANAGE,
+    ReviewKyc => permissions::KYC_REVIEW,
+    ReadReports => permissions::REPORTS_READ,
+    ManageReconciliation => permissions::RECONCILIATION_MANAGE,
+    SellOnMarketplace => permissions::MARKETPLACE_SELL,
+}
+
+/// Extractor for claims that carry the permission of `S`, e.g. `Permitted<RefundPayments>`
+pub struct Permitted<S: Scope> {
+    pub claims: Claims,
+    scope: PhantomData<S>,
+}
+
+impl<S: Scope> FromRequest for Permitted<S> {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let requirement = Requirement::Permission(S::PERMISSION);
+        let result = match req.extensions().get::<Claims>() {
+            Some(claims) if requirement.is_met(claims) => Ok(Permitted {
+                claims: claims.clone(),
+                scope: PhantomData,
+            }),
+            Some(_) => Err(forbidden(&requirement)),
+            None => Err(unauthorized()),
+        };
+        ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{init_service, try_call_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    fn claims(roles: &[&str], permissions: &[&str]) -> Claims {
+        Claims {
+            sub: "a@example.com".to_string(),
+            user_id: 1,
+            exp: 0,
+            iat: None,
+            jti: None,
+            iss: None,
+            aud: None,
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            restricted: false,
+            amr: vec!["pwd".to_string()],
+            auth_time: Some(chrono::Utc::now().timestamp()),
+            client_id: None,
+            sid: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_require_permission_guard() {
+        // Stand-in for AuthMiddleware: claims come from a test header
+        let app = init_service(
+            App::new().service(
+                web::resource("/refund")
+                    .wrap(Require::permission(permissions::PAYMENTS_REFUND))
+                    .wrap_fn(|req, srv| {
+                        let granted = req.headers()
+                            .get("x-test-permission")
+                            .and_then(|h| h.to_str().ok())
+                            .map(|permission| claims(&[], &[permission]));
+                        if let Some(c) = granted {
+                            req.extensions_mut().insert(c);
+                        }
+                        srv.call(req)
+                    })
+                    .route(web::post().to(|claims: Permitted<RefundPayments>| async move {
+                        HttpResponse::Ok().body(claims.claims.user_id.to_string())
+                    })),
+            ),
+        )
+        .await;
+
+        let status = |permission: Option<&str>| {
+            let mut req = TestRequest::post().uri("/refund");
+            if let Some(permission) = permission {
+                req = req.insert_header(("x-test-permission", permission.to_string()));
+            }
+            let response = try_call_service(&app, req.to_request());
+            async move {
+                match response.await {
+                    Ok(res) => res.status().as_u16(),
+                    Err(e) => e.as_response_error().status_code().as_u16(),
+                }
+            }
+        };
+
+        assert_eq!(status(None).await, 401);
+        assert_eq!(status(Some(permissions::USERS_READ)).await, 403);
+        assert_eq!(status(Some(permissions::PAYMENTS_REFUND)).await, 200);
+        assert_eq!(status(Some(permissions::ALL)).await, 200);
+    }
+
+    #[test]
+    fn test_role_requirements() {
+        let admin = claims(&[roles::ADMIN], &[permissions::ALL]);
+        let user = claims(&[roles::USER], &[permissions::PAYMENTS_REFUND]);
+        assert!(Requirement::Role(roles::ADMIN).is_met(&admin));
+        assert!(!Requirement::Role(roles::ADMIN).is_met(&user));
+        assert!(Requirement::AnyRole(&[roles::ADMIN, roles::USER]).is_met(&user));
+        assert!(admin.has_permission(permissions::ROLES_ASSIGN));
+        assert!(!user.has_permission(permissions::ROLES_ASSIGN));
+
+        let unverified = Claims { restricted: true, ..user.clone() };
+        assert!(Requirement::Verified.is_met(&user));
+        assert!(!Requirement::Verified.is_met(&unverified));
+    }
+
+    #[test]
+    fn test_recent_mfa_requirement() {
+        let password_only = claims(&[roles::USER], &[]);
+        let stepped_up = Claims { amr: vec!["pwd".to_string(), "otp".to_string()], ..password_only.clone() };
+        let stale = Claims { auth_time: Some(chrono::Utc::now().timestamp() - 3600), ..stepped_up.clone() };
+        assert!(!Requirement::RecentMfa(600).is_met(&password_only));
+        assert!(Requirement::RecentMfa(600).is_met(&stepped_up));
+        assert!(!Requirement::RecentMfa(600).is_met(&stale));
+    }
+
+    #[test]
+    fn test_service_principal() {
+        let user = claims(&[roles::USER], &[]);
+        let service = Claims { client_id: Some("svc_worker".to_string()), user_id: 0, ..user.clone() };
+        assert_eq!(user.principal(), Principal::User { user_id: 1, email: "a@example.com" });
+        assert_eq!(service.principal(), Principal::Service { client_id: "svc_worker" });
+        assert!(Requirement::Service.is_met(&service));
+        assert!(!Requirement::Service.is_met(&user));
+    }
+}
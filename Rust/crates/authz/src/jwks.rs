@@ -0,0 +1,91 @@
+// JWKS published by auth-service, fetched over HTTP and cached
+use anyhow::{Result, anyhow};
+use jsonwebtoken::jwk::JwkSet;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+// Auth-service publishes new keys at least this long before signing with them
+const CACHE_TTL: Duration = Duration::from_secs(600);
+// Unknown kids trigger a refetch, but not more often than this
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct JwksCache {
+    url: String,
+    client: reqwest::Client,
+    keys: RwLock<Option<(Arc<JwkSet>, Instant)>>,
+    last_fetch: Mutex<Option<Instant>>,
+}
+
+impl JwksCache {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+            keys: RwLock::new(None),
+            last_fetch: Mutex::new(None),
+        }
+    }
+
+    /// Key set that should contain `kid`. A stale set is still used when
+    /// auth-service cannot be reached.
+    pub async fn key_set(&self, kid: &str) -> Result<Arc<JwkSet>> {
+        let cached = self.keys.read().ok().and_then(|keys| keys.clone());
+
+        if let Some((keys, fetched_at)) = &cached {
+            if fetched_at.elapsed() < CACHE_TTL && keys.find(kid).is_some() {
+                return Ok(keys.clone());
+            }
+        }
+
+        if !self.may_fetch() {
+            return cached.map(|(keys, _)| keys).ok_or_else(|| anyhow!("JWKS not loaded yet"));
+        }
+
+        match self.fetch().await {
+            Ok(keys) => {
+                let keys = Arc::new(keys);
+                if let Ok(mut slot) = self.keys.write() {
+                    *slot = Some((keys.clone(), Instant::now()));
+                }
+                Ok(keys)
+            }
+            Err(e) => {
+                tracing::error!("Failed to fetch JWKS from {}: {}", self.url, e);
+                cached.map(|(keys, _)| keys).ok_or(e)
+            }
+        }
+    }
+
+    fn may_fetch(&self) -> bool {
+        match self.last_fetch.lock() {
+            Ok(mut last_fetch) => {
+                if last_fetch.map(|at| at.elapsed() < MIN_REFETCH_INTERVAL).unwrap_or(false) {
+                    return false;
+                }
+                *last_fetch = Some(Instant::now());
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    async fn fetch(&self) -> Result<JwkSet> {
+        let response = self.client
+            .get(&self.url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("JWKS endpoint returned {}", response.status()));
+        }
+
+        Ok(response.json::<JwkSet>().await?)
+    }
+}
+
+/// Key set from a file, for setups where auth-service is not reachable
+pub fn load_file(path: &str) -> Result<JwkSet> {
+    let content = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read JWKS file {}: {}", path, e))?;
+    serde_json::from_str(&content).map_err(|e| anyhow!("Invalid JWKS file {}: {}", path, e))
+}
@@ -1,34 +1,286 @@
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
+use jsonwebtoken::{decode, decode_header, DecodingKey, Validation, Algorithm};
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
 use serde::{Deserialize, Serialize};
 use anyhow::{Result, anyhow};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::jwks::{self, JwksCache};
+use crate::revocation::TokenRevocation;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String,      // email
     pub user_id: i32,
     pub exp: i64,         // expiration timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>, // issued at, compared with the user's revocation watermark
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // token id, used by the denylist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>, // scopes granted by the roles, "*" for everything
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub restricted: bool, // email not verified yet, see Require::verified
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>, // login methods, "otp", "rec" or "hwk" after a second factor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>, // when the user last authenticated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>, // set on client_credentials tokens, see Claims::principal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>, // login session, revoked with TokenRevocation::revoke_session
+}
+
+/// Claim checks on top of the signature, configurable per service
+#[derive(Debug, Clone)]
+pub struct ValidationConfig {
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub leeway: u64, // seconds of clock skew allowed on exp
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            issuer: None,
+            audience: None,
+            leeway: 60,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// JWT_ISSUER, JWT_AUDIENCE and JWT_LEEWAY_SECS
+    pub fn from_env() -> Self {
+        let non_empty = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Self {
+            issuer: non_empty("JWT_ISSUER"),
+            audience: non_empty("JWT_AUDIENCE"),
+            leeway: non_empty("JWT_LEEWAY_SECS").and_then(|v| v.parse().ok()).unwrap_or(60),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum KeySource {
+    Secret(String), // legacy shared HS256 secret
+    Jwks(Arc<JwkSet>),
+    Shared(Arc<RwLock<JwkSet>>), // kept up to date by the owner, e.g. the issuer itself
+    Remote(Arc<JwksCache>),
 }
 
 #[derive(Clone)]
 pub struct JwtValidator {
-    secret: String,
+    keys: KeySource,
+    config: ValidationConfig,
+    revocation: Option<TokenRevocation>,
 }
 
+static FROM_ENV: OnceLock<JwtValidator> = OnceLock::new();
+
 impl JwtValidator {
+    /// HS256 validator that also rejects revoked tokens
     pub fn new(secret: String) -> Self {
-        Self { secret }
+        Self {
+            keys: KeySource::Secret(secret),
+            config: ValidationConfig::default(),
+            revocation: Some(TokenRevocation::shared()),
+        }
+    }
+
+    /// Validator for tokens signed by one of the keys in the set
+    pub fn from_jwks(keys: JwkSet) -> Self {
+        Self {
+            keys: KeySource::Jwks(Arc::new(keys)),
+            config: ValidationConfig::default(),
+            revocation: Some(TokenRevocation::shared()),
+        }
+    }
+
+    /// Validator over a key set the caller replaces as keys rotate
+    pub fn from_shared_jwks(keys: Arc<RwLock<JwkSet>>) -> Self {
+        Self {
+            keys: KeySource::Shared(keys),
+            config: ValidationConfig::default(),
+            revocation: Some(TokenRevocation::shared()),
+        }
+    }
+
+    /// Process-wide validator configured from the environment:
+    /// JWT_JWKS_URL (fetched and cached), else JWT_JWKS_FILE, else the
+    /// legacy JWT_SECRET. Claim checks come from ValidationConfig::from_env.
+    pub fn from_env() -> Self {
+        FROM_ENV
+            .get_or_init(|| {
+                let keys = if let Ok(url) = std::env::var("JWT_JWKS_URL") {
+                    KeySource::Remote(Arc::new(JwksCache::new(url)))
+                } else if let Ok(path) = std::env::var("JWT_JWKS_FILE") {
+                    KeySource::Jwks(Arc::new(jwks::load_file(&path).expect("Failed to load JWT_JWKS_FILE")))
+                } else {
+                    tracing::warn!("JWT_JWKS_URL not set, verifying tokens with the shared JWT_SECRET");
+                    KeySource::Secret(
+                        std::env::var("JWT_SECRET")
+                            .ok()
+                            .filter(|secret| !secret.is_empty())
+                            .expect("Set JWT_JWKS_URL, JWT_JWKS_FILE or JWT_SECRET"),
+                    )
+                };
+
+                Self {
+                    keys,
+                    config: ValidationConfig::from_env(),
+                    revocation: Some(TokenRevocation::shared()),
+                }
+            })
+            .clone()
+    }
+
+    pub fn with_config(mut self, config: ValidationConfig) -> Self {
+        self.config = config;
+        self
     }
 
-    pub fn verify_token(&self, token: &str) -> Result<Claims> {
-        let validation = Validation::new(Algorithm::HS256);
-        
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.secret.as_bytes()),
-            &validation,
-        )
-        .map_err(|e| anyhow!("Invalid token: {}", e))?;
+    /// Signature and claim checks only, for callers that handle revocation themselves
+    pub fn without_revocation(mut self) -> Self {
+        self.revocation = None;
+        self
+    }
+
+    pub async fn verify_token(&self, token: &str) -> Result<Claims> {
+        let header = decode_header(token).map_err(|e| anyhow!("Invalid token: {}", e))?;
+
+        let (key, algorithm) = match &self.keys {
+            KeySource::Secret(secret) => (DecodingKey::from_secret(secret.as_bytes()), Algorithm::HS256),
+            KeySource::Jwks(keys) => jwk_key(keys, header.kid.as_deref())?,
+            KeySource::Shared(keys) => {
+                let keys = keys.read().map_err(|_| anyhow!("Key set lock poisoned"))?;
+                jwk_key(&keys, header.kid.as_deref())?
+            }
+            KeySource::Remote(cache) => {
+                let kid = header.kid.as_deref().ok_or_else(|| anyhow!("Invalid token: missing kid"))?;
+                let keys = cache.key_set(kid).await?;
+                jwk_key(&keys, Some(kid))?
+            }
+        };
+
+        // The key decides the algorithm, never the token header
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = self.config.leeway;
+        let mut required = vec!["exp"];
+        if let Some(issuer) = &self.config.issuer {
+            validation.set_issuer(&[issuer]);
+            required.push("iss");
+        }
+        match &self.config.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                required.push("aud");
+            }
+            None => validation.validate_aud = false,
+        }
+        validation.set_required_spec_claims(&required);
+
+        let token_data = decode::<Claims>(token, &key, &validation)
+            .map_err(|e| anyhow!("Invalid token: {}", e))?;
+
+        if let Some(revocation) = &self.revocation {
+            if revocation.is_revoked(&token_data.claims).await {
+                return Err(anyhow!("Token has been revoked"));
+            }
+        }
 
         Ok(token_data.claims)
     }
 }
+
+fn jwk_key(keys: &JwkSet, kid: Option<&str>) -> Result<(DecodingKey, Algorithm)> {
+    let kid = kid.ok_or_else(|| anyhow!("Invalid token: missing kid"))?;
+    let jwk = keys.find(kid).ok_or_else(|| anyhow!("Invalid token: unknown kid {}", kid))?;
+
+    let algorithm = match (&jwk.common.key_algorithm, &jwk.algorithm) {
+        (Some(alg), _) => Algorithm::from_str(&alg.to_string()).map_err(|e| anyhow!("Unsupported key algorithm: {}", e))?,
+        (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+        (None, AlgorithmParameters::OctetKeyPair(_)) => Algorithm::EdDSA,
+        (None, _) => return Err(anyhow!("Unsupported key type for kid {}", kid)),
+    };
+    // Symmetric keys have no business in a published key set
+    if matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+        return Err(anyhow!("Unsupported key algorithm for kid {}", kid));
+    }
+
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| anyhow!("Invalid key {}: {}", kid, e))?;
+    Ok((key, algorithm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    #[tokio::test]
+    async fn test_tokens_without_jti_still_verify() {
+        let exp = chrono::Utc::now().timestamp() + 60;
+        let legacy = serde_json::json!({ "sub": "a@example.com", "user_id": 1, "exp": exp });
+        let token = encode(&Header::default(), &legacy, &EncodingKey::from_secret(b"secret")).unwrap();
+
+        let claims = JwtValidator::new("secret".to_string())
+            .without_revocation()
+            .verify_token(&token)
+            .await
+            .unwrap();
+        assert_eq!(claims.user_id, 1);
+        assert!(claims.jti.is_none() && claims.iat.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_eddsa_token_verified_against_jwks() {
+        use jsonwebtoken::jwk::{CommonParameters, EllipticCurve, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType};
+        use ring::signature::{Ed25519KeyPair, KeyPair};
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let public_key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap().public_key().as_ref().to_vec();
+        let jwk = Jwk {
+            common: CommonParameters {
+                key_algorithm: Some(KeyAlgorithm::EdDSA),
+                key_id: Some("k1".to_string()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(public_key),
+            }),
+        };
+        let validator = JwtValidator::from_jwks(JwkSet { keys: vec![jwk] })
+            .with_config(ValidationConfig {
+                issuer: Some("auth-service".to_string()),
+                ..Default::default()
+            })
+            .without_revocation();
+
+        let exp = chrono::Utc::now().timestamp() + 60;
+        let sign = |kid: &str, iss: &str| {
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some(kid.to_string());
+            let claims = serde_json::json!({ "sub": "a@example.com", "user_id": 1, "exp": exp, "iss": iss });
+            encode(&header, &claims, &EncodingKey::from_ed_der(pkcs8.as_ref())).unwrap()
+        };
+
+        assert!(validator.verify_token(&sign("k1", "auth-service")).await.is_ok());
+        assert!(validator.verify_token(&sign("k1", "someone-else")).await.is_err());
+        assert!(validator.verify_token(&sign("k2", "auth-service")).await.is_err());
+
+        // A token signed with the HS256 secret must not pass as the published key
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("k1".to_string());
+        let forged = encode(&header, &serde_json::json!({ "sub": "x", "user_id": 1, "exp": exp, "iss": "auth-service" }), &EncodingKey::from_secret(b"k1")).unwrap();
+        assert!(validator.verify_token(&forged).await.is_err());
+    }
+}
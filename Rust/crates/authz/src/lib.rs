@@ -1,6 +1,11 @@
 // Authorization helpers
 pub mod jwt;
+pub mod jwks;
+pub mod guard;
 pub mod middleware;
+pub mod revocation;
 
-pub use jwt::{Claims, JwtValidator};
+pub use jwt::{Claims, JwtValidator, ValidationConfig};
+pub use guard::{permissions, roles, step_up_max_age, Permitted, Principal, Require};
 pub use middleware::AuthMiddleware;
+pub use revocation::TokenRevocation;
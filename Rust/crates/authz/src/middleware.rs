@@ -4,24 +4,49 @@ use actix_web::{
 };
 use futures_util::future::LocalBoxFuture;
 use std::future::{ready, Ready};
+use std::rc::Rc;
 
 use crate::jwt::JwtValidator;
 
+/// Verifies the bearer token and puts its Claims in the request. Tokens of
+/// service clients are refused unless `accept_service_clients` is set.
 pub struct AuthMiddleware {
     validator: JwtValidator,
+    accept_services: bool,
 }
 
 impl AuthMiddleware {
+    /// Legacy HS256 shared-secret validation
     pub fn new(jwt_secret: String) -> Self {
         Self {
             validator: JwtValidator::new(jwt_secret),
+            accept_services: false,
         }
     }
+
+    /// Validation configured from the environment, see JwtValidator::from_env
+    pub fn from_env() -> Self {
+        Self {
+            validator: JwtValidator::from_env(),
+            accept_services: false,
+        }
+    }
+
+    pub fn with_validator(validator: JwtValidator) -> Self {
+        Self { validator, accept_services: false }
+    }
+
+    /// Also let client_credentials tokens through, for routes backend
+    /// services call
+    pub fn accept_service_clients(mut self) -> Self {
+        self.accept_services = true;
+        self
+    }
 }
 
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -33,20 +58,22 @@ where
 
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(AuthMiddlewareService {
-            service,
+            service: Rc::new(service),
             validator: self.validator.clone(),
+            accept_services: self.accept_services,
         }))
     }
 }
 
 pub struct AuthMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
     validator: JwtValidator,
+    accept_services: bool,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -93,25 +120,25 @@ where
             }
         };
 
-        // Verify token
-        let claims = match self.validator.verify_token(&token) {
-            Ok(claims) => claims,
-            Err(e) => {
-                return Box::pin(async move {
-                    Err(actix_web::error::ErrorUnauthorized(
-                        serde_json::json!({"error": format!("Unauthorized: {}", e)}),
-                    ))
-                });
-            }
-        };
+        let validator = self.validator.clone();
+        let service = self.service.clone();
+        let accept_services = self.accept_services;
 
-        // Insert claims into request extensions
-        req.extensions_mut().insert(claims.clone());
+        Box::pin(async move {
+            // Verify token (may fetch the JWKS)
+            let claims = validator.verify_token(&token).await.map_err(|e| {
+                actix_web::error::ErrorUnauthorized(serde_json::json!({"error": format!("Unauthorized: {}", e)}))
+            })?;
+            if claims.is_service() && !accept_services {
+                return Err(actix_web::error::ErrorForbidden(
+                    serde_json::json!({"error": "Service client tokens are not accepted here"}),
+                ));
+            }
 
-        let fut = self.service.call(req);
+            // Insert claims into request extensions
+            req.extensions_mut().insert(claims);
 
-        Box::pin(async move {
-            let res = fut.await?;
+            let res = service.call(req).await?;
             Ok(res)
         })
     }
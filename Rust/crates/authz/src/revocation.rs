@@ -0,0 +1,203 @@
+// Access token revocation: a Redis denylist of token and session ids plus a
+// per-user watermark, with a small in-process cache in front of Redis
+use actix_web::web;
+use chrono::Utc;
+use common::cache::RedisCache;
+use hashlink::LruCache;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::jwt::Claims;
+
+const LOCAL_CACHE_CAPACITY: usize = 10_000;
+// How long a "not revoked" answer is trusted before asking Redis again
+const LOCAL_CACHE_TTL: Duration = Duration::from_secs(5);
+// Must outlive the longest-lived access token
+const WATERMARK_TTL: u64 = 86400;
+
+#[derive(Clone, Copy)]
+enum Cached {
+    Revoked,
+    NotRevoked,
+    Watermark(Option<i64>),
+}
+
+#[derive(Clone)]
+pub struct TokenRevocation {
+    redis: RedisCache,
+    local: Arc<Mutex<LruCache<String, (Cached, Instant)>>>,
+}
+
+static SHARED: OnceLock<TokenRevocation> = OnceLock::new();
+
+impl TokenRevocation {
+    pub fn new(redis: RedisCache) -> Self {
+        Self {
+            redis,
+            local: Arc::new(Mutex::new(LruCache::new(LOCAL_CACHE_CAPACITY))),
+        }
+    }
+
+    /// Process-wide instance on REDIS_URL, shared by every validator so
+    /// the local cache is too
+    pub fn shared() -> Self {
+        SHARED
+            .get_or_init(|| {
+                let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+                let redis = RedisCache::new(&redis_url).expect("Invalid REDIS_URL");
+                Self::new(redis)
+            })
+            .clone()
+    }
+
+    /// Deny a single access token until it expires
+    pub fn revoke_token(&self, jti: &str, exp: i64) -> anyhow::Result<()> {
+        let ttl = (exp - Utc::now().timestamp()).max(1) as u64;
+        self.redis.set(&denylist_key(jti), &true, ttl)?;
+        self.remember(denylist_key(jti), Cached::Revoked);
+        Ok(())
+    }
+
+    /// Deny every access token of a login session
+    pub fn revoke_session(&self, sid: &str) -> anyhow::Result<()> {
+        self.redis.set(&session_key(sid), &true, WATERMARK_TTL)?;
+        self.remember(session_key(sid), Cached::Revoked);
+        Ok(())
+    }
+
+    /// Invalidate every access token of the user issued up to now
+    pub fn revoke_user_tokens(&self, user_id: i32) -> anyhow::Result<()> {
+        let now = Utc::now().timestamp();
+        self.redis.set(&watermark_key(user_id), &now, WATERMARK_TTL)?;
+        self.remember(watermark_key(user_id), Cached::Watermark(Some(now)));
+        Ok(())
+    }
+
+    /// Redis errors are logged and the token is let through, like the rate
+    /// limiters: an outage must not lock every user out
+    pub async fn is_revoked(&self, claims: &Claims) -> bool {
+        if let Some(jti) = &claims.jti {
+            if self.denied(denylist_key(jti)).await {
+                return true;
+            }
+        }
+        if let Some(sid) = &claims.sid {
+            if self.denied(session_key(sid)).await {
+                return true;
+            }
+        }
+
+        // Service tokens are short-lived and not tied to a user
+        if claims.is_service() {
+            return false;
+        }
+
+        match self.watermark(claims.user_id).await {
+            Some(watermark) => issued_before(claims.iat, watermark),
+            None => false,
+        }
+    }
+
+    async fn denied(&self, key: String) -> bool {
+        match self.cached(&key) {
+            Some(Cached::Revoked) => return true,
+            Some(Cached::NotRevoked) => return false,
+            _ => {}
+        }
+
+        let lookup_key = key.clone();
+        match self.lookup(move |redis| redis.exists(&lookup_key)).await {
+            Ok(revoked) => {
+                self.remember(key, if revoked { Cached::Revoked } else { Cached::NotRevoked });
+                revoked
+            }
+            Err(e) => {
+                tracing::error!("Token denylist lookup failed: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn watermark(&self, user_id: i32) -> Option<i64> {
+        let key = watermark_key(user_id);
+        if let Some(Cached::Watermark(watermark)) = self.cached(&key) {
+            return watermark;
+        }
+
+        let lookup_key = key.clone();
+        match self.lookup(move |redis| redis.get::<i64>(&lookup_key)).await {
+            Ok(watermark) => {
+                self.remember(key, Cached::Watermark(watermark));
+                watermark
+            }
+            Err(e) => {
+                tracing::error!("Token watermark lookup failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Redis calls block, so cache misses run on the blocking thread pool
+    /// rather than stalling the actix worker
+    async fn lookup<T, E, F>(&self, call: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        E: std::error::Error + Send + Sync + 'static,
+        F: FnOnce(&RedisCache) -> Result<T, E> + Send + 'static,
+    {
+        let redis = self.redis.clone();
+        Ok(web::block(move || call(&redis)).await??)
+    }
+
+    fn cached(&self, key: &str) -> Option<Cached> {
+        let mut local = self.local.lock().ok()?;
+        let (value, stored_at) = *local.get(key)?;
+        // Revocations are permanent, everything else expires locally
+        if matches!(value, Cached::Revoked) || stored_at.elapsed() < LOCAL_CACHE_TTL {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn remember(&self, key: String, value: Cached) {
+        if let Ok(mut local) = self.local.lock() {
+            local.insert(key, (value, Instant::now()));
+        }
+    }
+}
+
+/// Whether a token was issued before the watermark. `iat` has whole seconds,
+/// so a token from the same second counts as newer: a token issued right after
+/// "log out everywhere" must work, and older tokens of that second belong to
+/// sessions the logout revokes by session id.
+fn issued_before(iat: Option<i64>, watermark: i64) -> bool {
+    // Tokens without iat predate revocation support
+    iat.is_none_or(|iat| iat < watermark)
+}
+
+fn denylist_key(jti: &str) -> String {
+    format!("revoked_jti:{}", jti)
+}
+
+fn session_key(sid: &str) -> String {
+    format!("revoked_sid:{}", sid)
+}
+
+fn watermark_key(user_id: i32) -> String {
+    format!("token_watermark:{}", user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_issued_in_the_watermark_second_is_accepted() {
+        let watermark = 1_760_000_000;
+        assert!(issued_before(Some(watermark - 1), watermark));
+        assert!(!issued_before(Some(watermark), watermark));
+        assert!(!issued_before(Some(watermark + 1), watermark));
+        assert!(issued_before(None, watermark));
+    }
+}
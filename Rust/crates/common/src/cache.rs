@@ -62,6 +62,43 @@ impl RedisCache {
         conn.set(key, serialized)
     }
 
+    // Set only if the key does not exist yet (for locks), returns whether it was set
+    pub fn set_nx(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<bool, RedisError> {
+        let mut conn = self.get_connection()?;
+        let result: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .query(&mut conn)?;
+
+        Ok(result.is_some())
+    }
+
+    // Delete the key only if it still holds the value (releasing a lock taken
+    // with set_nx), returns whether it was deleted
+    pub fn delete_if_equals(&self, key: &str, value: &str) -> Result<bool, RedisError> {
+        let mut conn = self.get_connection()?;
+        let deleted: i64 = redis::Script::new(
+            "if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('DEL', KEYS[1]) else return 0 end",
+        )
+        .key(key)
+        .arg(value)
+        .invoke(&mut conn)?;
+
+        Ok(deleted == 1)
+    }
+
+    // Publish a JSON message on a pub/sub channel
+    pub fn publish<T: Serialize>(&self, channel: &str, message: &T) -> Result<(), RedisError> {
+        let mut conn = self.get_connection()?;
+        let serialized = serde_json::to_string(message)
+            .map_err(|e| RedisError::from((redis::ErrorKind::TypeError, "JSON serialize error", e.to_string())))?;
+
+        conn.publish(channel, serialized)
+    }
+
     // Increment counter (for rate limiting)
     pub fn increment(&self, key: &str, ttl_seconds: u64) -> Result<i64, RedisError> {
         let mut conn = self.get_connection()?;
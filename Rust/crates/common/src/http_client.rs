@@ -1,14 +1,171 @@
 // HTTP client wrapper for inter-service communication
-#[allow(dead_code)]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+/// Tokens are renewed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+/// Bounds every request, the token request included: it runs under the
+/// token lock and a hung auth-service would otherwise stall every caller
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Credentials of a service client registered in auth-service
+#[derive(Debug, Clone)]
+pub struct ClientCredentials {
+    pub token_url: String, // e.g. http://auth-service:8081/oauth/token
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>, // space-separated, every scope of the client when None
+}
+
+impl ClientCredentials {
+    /// From OAUTH_TOKEN_URL, OAUTH_CLIENT_ID, OAUTH_CLIENT_SECRET and the
+    /// optional OAUTH_SCOPE; None if the service has no client
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            token_url: std::env::var("OAUTH_TOKEN_URL").ok()?,
+            client_id: std::env::var("OAUTH_CLIENT_ID").ok()?,
+            client_secret: std::env::var("OAUTH_CLIENT_SECRET").ok()?,
+            scope: std::env::var("OAUTH_SCOPE").ok().filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+}
+
+/// reqwest client that, given client credentials, sends a bearer token
+/// obtained from auth-service. The token is cached until shortly before it
+/// expires, and fetched again when a request comes back 401.
+#[derive(Clone)]
 pub struct HttpClient {
     client: reqwest::Client,
+    credentials: Option<Arc<ClientCredentials>>,
+    token: Arc<Mutex<Option<CachedToken>>>,
 }
 
 impl HttpClient {
     pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
         Self {
-            client: reqwest::Client::new(),
+            client,
+            credentials: None,
+            token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn with_client_credentials(credentials: ClientCredentials) -> Self {
+        Self {
+            credentials: Some(Arc::new(credentials)),
+            ..Self::new()
+        }
+    }
+
+    /// Authenticated when ClientCredentials::from_env finds a client
+    pub fn from_env() -> Self {
+        match ClientCredentials::from_env() {
+            Some(credentials) => Self::with_client_credentials(credentials),
+            None => Self::new(),
+        }
+    }
+
+    pub fn client_id(&self) -> Option<&str> {
+        self.credentials.as_deref().map(|c| c.client_id.as_str())
+    }
+
+    /// Current access token, fetched if missing or about to expire
+    pub async fn access_token(&self) -> Result<String> {
+        let credentials = self.credentials.as_ref()
+            .ok_or_else(|| anyhow!("No client credentials configured"))?;
+
+        // Held across the fetch so concurrent requests share one token request
+        let mut token = self.token.lock().await;
+        if let Some(cached) = token.as_ref().filter(|t| t.refresh_at > Instant::now()) {
+            return Ok(cached.access_token.clone());
+        }
+
+        let fetched = self.fetch_token(credentials).await?;
+        let access_token = fetched.access_token.clone();
+        *token = Some(fetched);
+        Ok(access_token)
+    }
+
+    async fn fetch_token(&self, credentials: &ClientCredentials) -> Result<CachedToken> {
+        let mut form = vec![("grant_type", "client_credentials")];
+        if let Some(scope) = &credentials.scope {
+            form.push(("scope", scope));
         }
+
+        let response = self.client
+            .post(&credentials.token_url)
+            .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
+            .form(&form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Token request for {} failed: {}", credentials.client_id, error_text));
+        }
+
+        let token = response.json::<TokenResponse>().await?;
+        let lifetime = Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN);
+        Ok(CachedToken {
+            access_token: token.access_token,
+            refresh_at: Instant::now() + lifetime,
+        })
+    }
+
+    async fn clear_token(&self) {
+        *self.token.lock().await = None;
+    }
+
+    /// Send a request built with `request`, with the bearer token when the
+    /// client has credentials. A 401 means the token was revoked or the
+    /// signing key rotated, so a new token is fetched and the request retried
+    /// once.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        if self.credentials.is_none() {
+            return Ok(request.send().await?);
+        }
+
+        let retry = request.try_clone();
+        let response = request.bearer_auth(self.access_token().await?).send().await?;
+        match retry {
+            Some(retry) if response.status() == reqwest::StatusCode::UNAUTHORIZED => {
+                self.clear_token().await;
+                Ok(retry.bearer_auth(self.access_token().await?).send().await?)
+            }
+            _ => Ok(response),
+        }
+    }
+
+    pub fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    pub async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        self.send(self.client.get(url)).await
+    }
+
+    pub async fn post_json<T: Serialize + ?Sized>(&self, url: &str, body: &T) -> Result<reqwest::Response> {
+        self.send(self.client.post(url).json(body)).await
     }
 }
 
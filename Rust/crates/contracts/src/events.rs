@@ -1,15 +1,172 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+/// Topic of user lifecycle events, keyed by user id
+pub const USER_EVENTS_TOPIC: &str = "user-events";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserCreatedEvent {
     pub user_id: i32,
     pub email: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub email_verified: bool, // accounts from an external identity provider start verified
     pub timestamp: DateTime<Utc>,
 }
 
+/// Something about the user changed; fields are set when they are part of the change
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserUpdatedEvent {
+    pub user_id: i32,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Logins refused after too many failed attempts, until `locked_until`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserLockedEvent {
+    pub user_id: i32,
+    pub email: String,
+    pub locked_until: DateTime<Utc>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserDeletedEvent {
+    pub user_id: i32,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Payload of USER_EVENTS_TOPIC, tagged with its type
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum UserEvent {
+    #[serde(rename = "user.created")]
+    Created(UserCreatedEvent),
+    #[serde(rename = "user.email_verified")]
+    EmailVerified(UserUpdatedEvent),
+    /// Profile change made in core-service, `name` always set
+    #[serde(rename = "user.updated")]
+    Updated(UserUpdatedEvent),
+    #[serde(rename = "user.password_changed")]
+    PasswordChanged(UserUpdatedEvent),
+    #[serde(rename = "user.locked")]
+    Locked(UserLockedEvent),
+    #[serde(rename = "user.deleted")]
+    Deleted(UserDeletedEvent),
+}
+
+impl UserEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            UserEvent::Created(_) => "user.created",
+            UserEvent::EmailVerified(_) => "user.email_verified",
+            UserEvent::Updated(_) => "user.updated",
+            UserEvent::PasswordChanged(_) => "user.password_changed",
+            UserEvent::Locked(_) => "user.locked",
+            UserEvent::Deleted(_) => "user.deleted",
+        }
+    }
+
+    pub fn user_id(&self) -> i32 {
+        match self {
+            UserEvent::Created(e) => e.user_id,
+            UserEvent::EmailVerified(e) | UserEvent::Updated(e) | UserEvent::PasswordChanged(e) => e.user_id,
+            UserEvent::Locked(e) => e.user_id,
+            UserEvent::Deleted(e) => e.user_id,
+        }
+    }
+}
+
+/// A UserEvent as published. Delivery is at least once, `event_id` tells
+/// redeliveries apart.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserEventMessage {
+    pub event_id: String,
+    #[serde(flatten)]
+    pub event: UserEvent,
+}
+
+/// Requests from auth-service for every service to export or erase a user's data
+pub const PRIVACY_REQUESTS_TOPIC: &str = "privacy-requests";
+/// Answers to privacy requests, one per service
+pub const PRIVACY_REPORTS_TOPIC: &str = "privacy-reports";
+/// Reports carry whole export parts, the topic is created with this `max.message.bytes`
+pub const PRIVACY_REPORT_MAX_BYTES: usize = 20 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyRequestKind {
+    Export,
+    Erasure,
+}
+
+impl PrivacyRequestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrivacyRequestKind::Export => "export",
+            PrivacyRequestKind::Erasure => "erasure",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrivacyRequestEvent {
+    pub request_id: i64,
+    pub kind: PrivacyRequestKind,
     pub user_id: i32,
     pub timestamp: DateTime<Utc>,
 }
+
+/// A service done with a privacy request. `data` holds its part of an
+/// export, `error` is set when it could not finish.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrivacyReportEvent {
+    pub request_id: i64,
+    pub service: String,
+    pub kind: PrivacyRequestKind,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PrivacyReportEvent {
+    /// Report of a finished request, or of the error that stopped it
+    pub fn from_result(request: &PrivacyRequestEvent, service: &str, result: Result<Option<serde_json::Value>, String>) -> Self {
+        let (data, error) = match result {
+            Ok(data) => (data, None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            request_id: request.request_id,
+            service: service.to_string(),
+            kind: request.kind,
+            data,
+            error,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// The report as sent; an export too large for the topic is reported as
+    /// an error instead of being dropped by the broker
+    pub fn to_payload(&self) -> serde_json::Result<String> {
+        let payload = serde_json::to_string(self)?;
+        if payload.len() <= PRIVACY_REPORT_MAX_BYTES {
+            return Ok(payload);
+        }
+        serde_json::to_string(&Self {
+            request_id: self.request_id,
+            service: self.service.clone(),
+            kind: self.kind,
+            data: None,
+            error: Some(format!("Export part of {} bytes exceeds the report limit", payload.len())),
+            timestamp: self.timestamp,
+        })
+    }
+}
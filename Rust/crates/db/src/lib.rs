@@ -1,3 +1,3 @@
 pub mod pool;
 
-pub use pool::create_pool;
+pub use pool::{create_pool, create_pool_with_size};
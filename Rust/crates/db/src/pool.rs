@@ -1,7 +1,17 @@
 use sqlx::{MySqlPool, Pool, MySql};
+use sqlx::mysql::MySqlPoolOptions;
 use anyhow::Result;
 
 pub async fn create_pool(database_url: &str) -> Result<Pool<MySql>> {
     let pool = MySqlPool::connect(database_url).await?;
     Ok(pool)
 }
+
+// Small pool for background and reporting work so it cannot exhaust the main one
+pub async fn create_pool_with_size(database_url: &str, max_connections: u32) -> Result<Pool<MySql>> {
+    let pool = MySqlPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(database_url)
+        .await?;
+    Ok(pool)
+}
@@ -8,3 +8,35 @@ pub struct PaymentCreatedEvent {
     pub status: String,
     pub timestamp: String,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoicePaidEvent {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub user_id: i32,
+    pub customer_email: Option<String>,
+    pub amount: f64,
+    pub currency: String,
+    pub attachment: Option<EventAttachment>,
+    pub timestamp: String,
+}
+
+/// A file carried inline in an event, base64 encoded
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content_base64: String,
+}
+
+/// An account email requested by auth-service, sent by worker-service
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountEmailEvent {
+    pub kind: String, // "email_verification", "password_reset" or "email_change"
+    pub user_id: i32,
+    pub email: String,
+    pub name: String,
+    pub link: String, // carries the single-use token
+    pub expires_at: String,
+    pub timestamp: String,
+}
@@ -1,7 +1,26 @@
 use rdkafka::config::ClientConfig;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::message::Message;
+use rdkafka::message::{BorrowedMessage, Message};
 use anyhow::Result;
+use std::future::Future;
+use std::time::Duration;
+
+// Backoff between attempts at a message whose handler failed
+const RETRY_BASE: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+
+/// A message its handler can never process, such as a payload that does not
+/// parse. `consume_async` logs it and moves on instead of retrying it.
+#[derive(Debug)]
+pub struct InvalidMessage(pub String);
+
+impl std::fmt::Display for InvalidMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid message: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidMessage {}
 
 pub struct KafkaConsumer {
     consumer: StreamConsumer,
@@ -9,10 +28,13 @@ pub struct KafkaConsumer {
 
 impl KafkaConsumer {
     pub fn new(brokers: &str, group_id: &str, topics: &[&str]) -> Result<Self> {
+        // Offsets are committed in the background, but only stored once a
+        // message has been handled, so a crash redelivers it
         let consumer: StreamConsumer = ClientConfig::new()
             .set("bootstrap.servers", brokers)
             .set("group.id", group_id)
             .set("enable.auto.commit", "true")
+            .set("enable.auto.offset.store", "false")
             .set("auto.offset.reset", "earliest")
             .create()?;
 
@@ -28,21 +50,57 @@ impl KafkaConsumer {
         loop {
             match self.consumer.recv().await {
                 Ok(message) => {
-                    let key = message
-                        .key()
-                        .and_then(|k| std::str::from_utf8(k).ok())
-                        .unwrap_or("")
-                        .to_string();
-
-                    let payload = message
-                        .payload()
-                        .and_then(|p| std::str::from_utf8(p).ok())
-                        .unwrap_or("")
-                        .to_string();
+                    let (key, payload) = key_and_payload(&message);
 
                     if let Err(e) = handler(key, payload) {
                         tracing::error!("Error handling message: {}", e);
                     }
+                    self.store_offset(&message);
+                }
+                Err(e) => {
+                    tracing::error!("Kafka error: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Like `consume`, for handlers that await (database writes and such).
+    /// Messages are handled one at a time, in partition order, and never
+    /// skipped: a failed message is retried with backoff until its handler
+    /// succeeds, unless the handler rejects it as an `InvalidMessage`.
+    pub async fn consume_async<F, Fut>(&self, mut handler: F) -> Result<()>
+    where
+        F: FnMut(String, String) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        loop {
+            match self.consumer.recv().await {
+                Ok(message) => {
+                    let (key, payload) = key_and_payload(&message);
+
+                    let mut attempt: u32 = 1;
+                    loop {
+                        match handler(key.clone(), payload.clone()).await {
+                            Ok(()) => break,
+                            Err(e) if e.is::<InvalidMessage>() => {
+                                tracing::error!(
+                                    "Skipping message {} at {}/{}:{}: {}",
+                                    key, message.topic(), message.partition(), message.offset(), e
+                                );
+                                break;
+                            }
+                            Err(e) => {
+                                let delay = RETRY_BASE.saturating_mul(2u32.saturating_pow(attempt - 1)).min(RETRY_MAX);
+                                tracing::warn!(
+                                    "Error handling message {} at {}/{}:{} (attempt {}), retrying in {:?}: {}",
+                                    key, message.topic(), message.partition(), message.offset(), attempt, delay, e
+                                );
+                                tokio::time::sleep(delay).await;
+                                attempt = attempt.saturating_add(1);
+                            }
+                        }
+                    }
+                    self.store_offset(&message);
                 }
                 Err(e) => {
                     tracing::error!("Kafka error: {}", e);
@@ -50,4 +108,30 @@ impl KafkaConsumer {
             }
         }
     }
+
+    /// Mark the message as handled; the offset after it is committed with the
+    /// next background commit
+    fn store_offset(&self, message: &BorrowedMessage<'_>) {
+        if let Err(e) = self.consumer.store_offset_from_message(message) {
+            // The partition was reassigned while the message was handled; its
+            // new owner gets it again
+            tracing::warn!("Failed to store offset of {}/{}:{}: {}", message.topic(), message.partition(), message.offset(), e);
+        }
+    }
+}
+
+fn key_and_payload(message: &BorrowedMessage<'_>) -> (String, String) {
+    let key = message
+        .key()
+        .and_then(|k| std::str::from_utf8(k).ok())
+        .unwrap_or("")
+        .to_string();
+
+    let payload = message
+        .payload()
+        .and_then(|p| std::str::from_utf8(p).ok())
+        .unwrap_or("")
+        .to_string();
+
+    (key, payload)
 }
@@ -19,6 +19,17 @@ impl KafkaProducer {
         Ok(Self { producer })
     }
 
+    /// For topics created with a larger `max.message.bytes` than the 1 MB default
+    pub fn with_max_message_bytes(brokers: &str, max_bytes: usize) -> Result<Self> {
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", "5000")
+            .set("message.max.bytes", max_bytes.to_string())
+            .create()?;
+
+        Ok(Self { producer })
+    }
+
     pub async fn send_message(&self, topic: &str, key: &str, payload: &str) -> Result<()> {
         let record = FutureRecord::to(topic)
             .key(key)
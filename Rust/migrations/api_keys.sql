@@ -0,0 +1,23 @@
+-- API Keys Migration
+-- Date: 2026-10-18
+-- Description: Hashed, scoped API keys for server-to-server calls
+
+-- ============================================
+-- 1. Create api_keys table
+-- ============================================
+CREATE TABLE IF NOT EXISTS api_keys (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    prefix VARCHAR(16) NOT NULL, -- visible part of the key, e.g. rk_<prefix>_...
+    key_hash CHAR(64) NOT NULL, -- SHA-256 of the full key, the key itself is shown once
+    name VARCHAR(100) NOT NULL,
+    owner VARCHAR(100) NOT NULL, -- service or team holding the key
+    scopes VARCHAR(500) NOT NULL, -- comma-separated, e.g. 'auth,payments:read'
+    created_by INT NULL,
+    expires_at TIMESTAMP NULL,
+    last_used_at TIMESTAMP NULL,
+    revoked_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_key_hash (key_hash),
+    INDEX idx_prefix (prefix),
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
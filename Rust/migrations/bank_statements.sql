@@ -0,0 +1,50 @@
+-- Bank Statement Reconciliation Migration
+-- Date: 2026-10-18
+-- Description: Store imported CAMT.053 / MT940 statements and their auto-match results
+
+-- ============================================
+-- 1. Create bank_statements table
+-- ============================================
+CREATE TABLE IF NOT EXISTS bank_statements (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    format VARCHAR(20) NOT NULL, -- 'camt053' or 'mt940'
+    account VARCHAR(64) NOT NULL, -- IBAN or bank account id
+    statement_ref VARCHAR(64) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    statement_date DATE NULL,
+    imported_by INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_account_statement (account, statement_ref),
+    INDEX idx_statement_date (statement_date)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create bank_statement_lines table
+-- ============================================
+CREATE TABLE IF NOT EXISTS bank_statement_lines (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    statement_id BIGINT NOT NULL,
+    line_no INT NOT NULL,
+    booking_date DATE NULL,
+    value_date DATE NULL,
+    amount DOUBLE NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    direction VARCHAR(10) NOT NULL, -- 'credit' or 'debit'
+    reference VARCHAR(255) NULL,
+    bank_reference VARCHAR(255) NULL,
+    description TEXT NULL,
+    match_status VARCHAR(20) NOT NULL DEFAULT 'unmatched', -- 'matched', 'unmatched', 'ambiguous', 'ignored'
+    matched_type VARCHAR(20) NULL, -- 'payment', 'payout' or 'transfer' (id of the payment)
+    matched_id BIGINT NULL,
+    matched_by VARCHAR(10) NULL, -- 'auto' or 'manual'
+    match_candidates JSON NULL,
+    match_note VARCHAR(500) NULL,
+    resolved_by INT NULL,
+    resolved_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (statement_id) REFERENCES bank_statements(id) ON DELETE CASCADE,
+    UNIQUE KEY unique_statement_line (statement_id, line_no),
+    INDEX idx_match_status (match_status),
+    INDEX idx_matched (matched_type, matched_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,40 @@
+-- Contacts Migration
+-- Date: 2026-10-19
+-- Description: Friend requests, contact lists and user blocks in core-service, consulted by chat-service and the gateway
+
+-- ============================================
+-- 1. Create contact_requests table
+-- ============================================
+-- One row per sender and addressee, sending again reuses it
+CREATE TABLE IF NOT EXISTS contact_requests (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    requester_id INT NOT NULL,
+    addressee_id INT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'accepted', 'declined', 'canceled'
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    responded_at TIMESTAMP NULL,
+    UNIQUE KEY uq_requester_addressee (requester_id, addressee_id),
+    INDEX idx_addressee_status (addressee_id, status)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create contacts table
+-- ============================================
+-- Two rows per pair, one for each side
+CREATE TABLE IF NOT EXISTS contacts (
+    user_id INT NOT NULL,
+    contact_id INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, contact_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 3. Create user_blocks table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_blocks (
+    blocker_id INT NOT NULL,
+    blocked_id INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (blocker_id, blocked_id),
+    INDEX idx_blocked_id (blocked_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
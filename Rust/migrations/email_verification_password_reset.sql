@@ -0,0 +1,28 @@
+-- Email Verification and Password Reset Migration
+-- Date: 2026-10-18
+-- Description: Verified flag on users and single-use tokens mailed to them
+
+-- ============================================
+-- 1. Add email_verified_at to users
+-- ============================================
+ALTER TABLE users
+    ADD COLUMN email_verified_at TIMESTAMP NULL AFTER password;
+
+-- Accounts created before verification existed are trusted as they are
+UPDATE users SET email_verified_at = created_at WHERE email_verified_at IS NULL;
+
+-- ============================================
+-- 2. Create user_tokens table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_tokens (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    purpose VARCHAR(30) NOT NULL, -- 'email_verification', 'password_reset'
+    token_hash CHAR(64) NOT NULL, -- SHA-256 of the token, the token itself is only mailed
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL, -- set when used or replaced by a newer token
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_token_hash (token_hash),
+    INDEX idx_user_purpose (user_id, purpose),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
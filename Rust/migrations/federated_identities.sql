@@ -0,0 +1,19 @@
+-- Federated Identities Migration
+-- Date: 2026-10-19
+-- Description: Accounts at external OpenID Connect providers linked to users
+
+-- ============================================
+-- 1. Create user_identities table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_identities (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    provider VARCHAR(50) NOT NULL, -- name from FEDERATED_PROVIDERS
+    subject VARCHAR(255) NOT NULL, -- `sub` of the provider's ID tokens
+    email VARCHAR(255) NULL, -- as the provider reported it when linked
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_login_at TIMESTAMP NULL,
+    UNIQUE KEY unique_provider_subject (provider, subject),
+    INDEX idx_user_id (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,59 @@
+-- Invoices Migration
+-- Date: 2026-10-18
+-- Description: Invoices with line items, tax, discounts and a yearly number sequence
+
+-- ============================================
+-- 1. Create invoice_sequences table
+-- ============================================
+CREATE TABLE IF NOT EXISTS invoice_sequences (
+    prefix VARCHAR(20) PRIMARY KEY, -- e.g. 'INV-2026'
+    next_value BIGINT NOT NULL DEFAULT 1
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create invoices table
+-- ============================================
+CREATE TABLE IF NOT EXISTS invoices (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    number VARCHAR(32) NULL, -- assigned when finalized
+    user_id INT NOT NULL, -- customer billed
+    created_by INT NOT NULL,
+    customer_name VARCHAR(255) NOT NULL,
+    customer_email VARCHAR(255) NULL,
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    status VARCHAR(20) NOT NULL DEFAULT 'draft', -- 'draft', 'open', 'paid', 'void'
+    discount_percent DOUBLE NOT NULL DEFAULT 0,
+    subtotal DOUBLE NOT NULL DEFAULT 0,
+    discount_amount DOUBLE NOT NULL DEFAULT 0,
+    tax_amount DOUBLE NOT NULL DEFAULT 0,
+    total DOUBLE NOT NULL DEFAULT 0,
+    due_date DATE NOT NULL,
+    memo TEXT NULL,
+    payment_id INT NULL,
+    issued_at TIMESTAMP NULL,
+    paid_at TIMESTAMP NULL,
+    voided_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_number (number),
+    INDEX idx_user_id (user_id),
+    INDEX idx_created_by (created_by),
+    INDEX idx_payment_id (payment_id),
+    INDEX idx_status_due (status, due_date)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 3. Create invoice_line_items table
+-- ============================================
+CREATE TABLE IF NOT EXISTS invoice_line_items (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    invoice_id BIGINT NOT NULL,
+    position INT NOT NULL,
+    description VARCHAR(500) NOT NULL,
+    quantity DOUBLE NOT NULL,
+    unit_price DOUBLE NOT NULL,
+    tax_rate DOUBLE NOT NULL DEFAULT 0, -- percent
+    amount DOUBLE NOT NULL, -- quantity * unit_price
+    INDEX idx_invoice_position (invoice_id, position),
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
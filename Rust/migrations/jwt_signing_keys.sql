@@ -0,0 +1,19 @@
+-- JWT Signing Keys Migration
+-- Date: 2026-10-18
+-- Description: Rotating asymmetric keys auth-service signs access tokens with
+
+-- ============================================
+-- 1. Create jwt_signing_keys table
+-- ============================================
+CREATE TABLE IF NOT EXISTS jwt_signing_keys (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    kid CHAR(36) NOT NULL, -- key id, sent in the token header
+    algorithm VARCHAR(10) NOT NULL, -- 'RS256', 'EdDSA'
+    private_key_pem TEXT NOT NULL, -- PKCS#8
+    public_jwk TEXT NOT NULL, -- published in /.well-known/jwks.json
+    activates_at TIMESTAMP NOT NULL, -- tokens are signed with the key from then on
+    expires_at TIMESTAMP NULL, -- removed from the JWKS after this, set on rotation
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_kid (kid),
+    INDEX idx_expires_at (expires_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,58 @@
+-- KYC Migration
+-- Date: 2026-10-19
+-- Description: Identity verification in core-service: applications with encrypted documents, reviewed by compliance staff
+
+-- ============================================
+-- 1. Add kyc_verified_at to user_profiles
+-- ============================================
+ALTER TABLE user_profiles
+    ADD COLUMN kyc_verified_at TIMESTAMP NULL AFTER discoverable; -- set when identity documents are approved
+
+-- ============================================
+-- 2. Create kyc_applications table
+-- ============================================
+CREATE TABLE IF NOT EXISTS kyc_applications (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'draft', -- 'draft', 'pending', 'approved', 'rejected'
+    reason VARCHAR(500) NULL, -- shown to the user on rejection
+    submitted_at TIMESTAMP NULL,
+    reviewed_by INT NULL,
+    reviewed_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_user_id (user_id),
+    INDEX idx_status_submitted (status, submitted_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 3. Create kyc_documents table
+-- ============================================
+-- Files are AES-256-GCM encrypted under KYC_STORAGE_DIR
+CREATE TABLE IF NOT EXISTS kyc_documents (
+    application_id BIGINT NOT NULL,
+    kind VARCHAR(20) NOT NULL, -- 'id_front', 'id_back', 'selfie'
+    content_type VARCHAR(50) NOT NULL,
+    size_bytes BIGINT NOT NULL, -- before encryption
+    file_path VARCHAR(500) NOT NULL,
+    uploaded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (application_id, kind),
+    FOREIGN KEY (application_id) REFERENCES kyc_applications(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 4. Reviewer role
+-- ============================================
+INSERT IGNORE INTO roles (name, description) VALUES
+    ('compliance', 'Reviews identity verification applications');
+
+INSERT IGNORE INTO role_permissions (role_id, permission)
+SELECT id, 'kyc:review' FROM roles WHERE name = 'compliance';
+
+-- ============================================
+-- 5. Create payment_limit_locks table
+-- ============================================
+-- Gateway locks the user's row while it sums the last 24 hours and reserves
+-- a payment, so parallel payments are checked one after another
+CREATE TABLE IF NOT EXISTS payment_limit_locks (
+    user_id INT PRIMARY KEY
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,26 @@
+-- Login Attempts Migration
+-- Date: 2026-10-18
+-- Description: Record of login attempts for brute-force and password spraying detection
+
+-- ============================================
+-- 1. Create login_attempts table
+-- ============================================
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NULL, -- NULL for unknown emails and throttled attempts
+    email VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45) NOT NULL,
+    success BOOLEAN NOT NULL,
+    reason VARCHAR(30) NULL, -- 'unknown_email', 'bad_password', 'throttled'
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_ip_created (ip_address, created_at),
+    INDEX idx_email_created (email, created_at),
+    INDEX idx_created (created_at),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Grant lockout removal to support
+-- ============================================
+INSERT IGNORE INTO role_permissions (role_id, permission)
+SELECT id, 'users:unlock' FROM roles WHERE name = 'support';
@@ -0,0 +1,48 @@
+-- Marketplace Payouts Migration
+-- Date: 2026-10-18
+-- Description: Connected seller accounts, split payments and seller payouts
+
+-- ============================================
+-- 1. Create connected_accounts table
+-- ============================================
+CREATE TABLE IF NOT EXISTS connected_accounts (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    stripe_account_id VARCHAR(255) NOT NULL,
+    display_name VARCHAR(255) NOT NULL,
+    default_currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    payout_schedule VARCHAR(20) NOT NULL DEFAULT 'weekly', -- 'daily', 'weekly' or 'manual'
+    status VARCHAR(20) NOT NULL DEFAULT 'onboarding', -- 'onboarding', 'active' or 'disabled'
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_stripe_account (stripe_account_id),
+    INDEX idx_user_id (user_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Add split payment columns to payments
+-- ============================================
+ALTER TABLE payments
+    ADD COLUMN destination_account_id INT NULL AFTER stripe_client_secret,
+    ADD COLUMN application_fee_amount DOUBLE NULL AFTER destination_account_id,
+    ADD INDEX idx_destination_account (destination_account_id);
+
+-- ============================================
+-- 3. Create payouts table
+-- ============================================
+CREATE TABLE IF NOT EXISTS payouts (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    connected_account_id INT NOT NULL,
+    amount DOUBLE NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'in_transit', 'paid', 'failed', 'canceled'
+    stripe_payout_id VARCHAR(255) NULL,
+    failure_reason TEXT NULL,
+    arrival_date DATE NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_stripe_payout (stripe_payout_id),
+    INDEX idx_account_created (connected_account_id, created_at),
+    INDEX idx_status (status),
+    FOREIGN KEY (connected_account_id) REFERENCES connected_accounts(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
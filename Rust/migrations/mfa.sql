@@ -0,0 +1,35 @@
+-- Two-Factor Authentication Migration
+-- Date: 2026-10-18
+-- Description: TOTP secrets, recovery codes and login method tracking on sessions
+
+-- ============================================
+-- 1. Create user_mfa table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_mfa (
+    user_id INT PRIMARY KEY,
+    secret VARCHAR(64) NOT NULL, -- base32 TOTP secret
+    enabled_at TIMESTAMP NULL, -- NULL while the enrollment is not confirmed
+    last_used_step BIGINT NULL, -- last accepted time step, so a code works only once
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create mfa_recovery_codes table
+-- ============================================
+CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    code_hash CHAR(64) NOT NULL, -- SHA-256 of the normalized code
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_user_code (user_id, code_hash),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 3. Track how each session authenticated
+-- ============================================
+ALTER TABLE refresh_tokens
+    ADD COLUMN amr VARCHAR(50) NULL AFTER family_id, -- e.g. 'pwd' or 'pwd,otp'
+    ADD COLUMN auth_time TIMESTAMP NULL AFTER amr;
@@ -0,0 +1,19 @@
+-- OAuth Clients Migration
+-- Date: 2026-10-19
+-- Description: Service clients for the OAuth2 client_credentials grant
+
+-- ============================================
+-- 1. Create oauth_clients table
+-- ============================================
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    client_id VARCHAR(32) NOT NULL, -- e.g. svc_<16 chars>
+    secret_hash CHAR(64) NOT NULL, -- SHA-256 of the client secret, the secret itself is shown once
+    name VARCHAR(100) NOT NULL, -- service holding the client
+    scopes VARCHAR(500) NOT NULL, -- comma-separated, become the permissions of its tokens
+    created_by INT NULL,
+    revoked_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_client_id (client_id),
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
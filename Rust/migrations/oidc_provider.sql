@@ -0,0 +1,24 @@
+-- OIDC Provider Migration
+-- Date: 2026-10-19
+-- Description: OIDC client apps in oauth_clients and the consents users give them
+
+-- ============================================
+-- 1. OIDC clients
+-- ============================================
+ALTER TABLE oauth_clients
+    ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'service' AFTER name, -- 'service' (client_credentials) or 'oidc' (authorization code)
+    ADD COLUMN redirect_uris VARCHAR(2000) NULL AFTER scopes; -- space-separated, matched exactly
+
+-- ============================================
+-- 2. Create oauth_consents table
+-- ============================================
+CREATE TABLE IF NOT EXISTS oauth_consents (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    client_id VARCHAR(32) NOT NULL,
+    scopes VARCHAR(500) NOT NULL, -- space-separated
+    granted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_user_client (user_id, client_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (client_id) REFERENCES oauth_clients(client_id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
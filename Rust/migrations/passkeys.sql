@@ -0,0 +1,20 @@
+-- Passkeys Migration
+-- Date: 2026-10-19
+-- Description: WebAuthn credentials of users, for passwordless login and as a second factor
+
+-- ============================================
+-- 1. Create user_passkeys table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_passkeys (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    credential_id VARCHAR(255) NOT NULL, -- base64url, as the authenticator returns it
+    public_key BLOB NOT NULL, -- COSE_Key
+    sign_count BIGINT NOT NULL DEFAULT 0, -- last signature counter, a lower one means a cloned key
+    name VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMP NULL,
+    UNIQUE KEY unique_credential_id (credential_id),
+    INDEX idx_user_id (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
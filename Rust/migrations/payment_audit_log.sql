@@ -0,0 +1,72 @@
+-- Payment Audit Log Migration
+-- Date: 2026-10-18
+-- Description: Append-only, hash-chained audit trail of gateway payment actions
+
+-- ============================================
+-- 1. Create payment_audit_log table
+-- ============================================
+-- before_state/after_state are TEXT rather than JSON so the stored bytes are
+-- exactly the ones that were hashed
+CREATE TABLE IF NOT EXISTS payment_audit_log (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    payment_id INT NOT NULL,
+    action VARCHAR(20) NOT NULL, -- 'create', 'retrieve', 'refund', 'capture', 'cancel', 'webhook'
+    actor_type VARCHAR(20) NOT NULL, -- 'user', 'stripe'
+    actor_id VARCHAR(64) NULL, -- not hashed: cleared by an erasure
+    actor_ref CHAR(64) NULL, -- hashed: HMAC of actor_id (PRIVACY_PSEUDONYM_KEY)
+    source_ip VARCHAR(45) NULL, -- not hashed: cleared by an erasure
+    source_ip_ref CHAR(64) NULL, -- hashed: HMAC of source_ip
+    request_id VARCHAR(64) NOT NULL,
+    before_state MEDIUMTEXT NULL,
+    after_state MEDIUMTEXT NULL,
+    prev_hash CHAR(64) NOT NULL,
+    entry_hash CHAR(64) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    UNIQUE KEY unique_entry_hash (entry_hash),
+    INDEX idx_payment_id (payment_id),
+    INDEX idx_request_id (request_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create audit_chain_head table
+-- ============================================
+-- Single row holding the latest hash; appends lock it to serialize the chain
+CREATE TABLE IF NOT EXISTS audit_chain_head (
+    id TINYINT PRIMARY KEY,
+    last_hash CHAR(64) NOT NULL,
+    last_entry_id BIGINT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+INSERT IGNORE INTO audit_chain_head (id, last_hash, last_entry_id)
+VALUES (1, '0000000000000000000000000000000000000000000000000000000000000000', NULL);
+
+-- ============================================
+-- 3. Reject updates and deletes
+-- ============================================
+DROP TRIGGER IF EXISTS payment_audit_log_no_update;
+DROP TRIGGER IF EXISTS payment_audit_log_no_delete;
+
+-- actor_id and source_ip may only be cleared, for account erasures
+DELIMITER //
+CREATE TRIGGER payment_audit_log_no_update
+BEFORE UPDATE ON payment_audit_log
+FOR EACH ROW
+BEGIN
+    IF NOT (NEW.id <=> OLD.id AND NEW.payment_id <=> OLD.payment_id AND NEW.action <=> OLD.action
+            AND NEW.actor_type <=> OLD.actor_type AND NEW.actor_ref <=> OLD.actor_ref
+            AND (NEW.actor_id IS NULL OR NEW.actor_id <=> OLD.actor_id)
+            AND NEW.source_ip_ref <=> OLD.source_ip_ref
+            AND (NEW.source_ip IS NULL OR NEW.source_ip <=> OLD.source_ip)
+            AND NEW.request_id <=> OLD.request_id
+            AND NEW.before_state <=> OLD.before_state AND NEW.after_state <=> OLD.after_state
+            AND NEW.prev_hash <=> OLD.prev_hash AND NEW.entry_hash <=> OLD.entry_hash
+            AND NEW.created_at <=> OLD.created_at) THEN
+        SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'payment_audit_log is append-only';
+    END IF;
+END//
+DELIMITER ;
+
+CREATE TRIGGER payment_audit_log_no_delete
+BEFORE DELETE ON payment_audit_log
+FOR EACH ROW
+    SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'payment_audit_log is append-only';
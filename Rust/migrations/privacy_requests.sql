@@ -0,0 +1,44 @@
+-- Privacy Requests Migration
+-- Date: 2026-10-19
+-- Description: User data exports and account erasures, tracked per service until each one reports back
+
+-- ============================================
+-- 1. Create privacy_requests table
+-- ============================================
+-- No foreign key on user_id: an erasure request outlives the user it deletes
+CREATE TABLE IF NOT EXISTS privacy_requests (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    kind VARCHAR(20) NOT NULL, -- 'export' or 'erasure'
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'completed', 'failed'
+    file_path VARCHAR(500) NULL, -- archive of a completed export
+    error TEXT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    requested_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, -- last sent to the services, reset on retry
+    completed_at TIMESTAMP NULL,
+    INDEX idx_user_id (user_id),
+    INDEX idx_status_requested (status, requested_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create privacy_request_parts table
+-- ============================================
+CREATE TABLE IF NOT EXISTS privacy_request_parts (
+    request_id BIGINT NOT NULL,
+    service VARCHAR(50) NOT NULL, -- 'core-service', 'gateway', 'chat-service'
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'completed', 'failed'
+    data LONGTEXT NULL, -- export part as JSON, dropped once the archive is written
+    error TEXT NULL,
+    completed_at TIMESTAMP NULL,
+    PRIMARY KEY (request_id, service),
+    FOREIGN KEY (request_id) REFERENCES privacy_requests(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 3. Pseudonym of erased payment owners
+-- ============================================
+-- Erased users' payments move to user_id 0 and keep an HMAC of the old id,
+-- so accounting can still group them per customer
+ALTER TABLE payments
+    ADD COLUMN user_pseudonym CHAR(64) NULL AFTER user_id,
+    ADD INDEX idx_user_pseudonym (user_pseudonym);
@@ -0,0 +1,23 @@
+-- Refresh Tokens Migration
+-- Date: 2026-10-18
+-- Description: Hashed, rotating refresh tokens grouped in families for reuse detection
+
+-- ============================================
+-- 1. Create refresh_tokens table
+-- ============================================
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    family_id CHAR(36) NOT NULL, -- one family per login, shared by rotated tokens
+    token_hash CHAR(64) NOT NULL, -- SHA-256 of the token, the token itself is never stored
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL, -- set when the token is rotated
+    revoked_at TIMESTAMP NULL,
+    revoked_reason VARCHAR(30) NULL, -- 'logout', 'logout_all', 'reuse_detected'
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_token_hash (token_hash),
+    INDEX idx_family_id (family_id),
+    INDEX idx_user_id (user_id),
+    INDEX idx_expires_at (expires_at),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
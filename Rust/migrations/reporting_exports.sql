@@ -0,0 +1,65 @@
+-- Reporting & Exports Migration
+-- Date: 2026-10-18
+-- Description: Refunds, disputes and async export jobs for payment reporting
+
+-- ============================================
+-- 1. Create refunds table
+-- ============================================
+CREATE TABLE IF NOT EXISTS refunds (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    payment_id INT NOT NULL,
+    amount DOUBLE NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'succeeded', 'failed', 'canceled'
+    reason VARCHAR(50) NULL,
+    stripe_refund_id VARCHAR(255) NULL,
+    failure_reason TEXT NULL,
+    created_by INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_stripe_refund (stripe_refund_id),
+    INDEX idx_payment_id (payment_id),
+    INDEX idx_created_at (created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create disputes table
+-- ============================================
+CREATE TABLE IF NOT EXISTS disputes (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    payment_id INT NOT NULL,
+    stripe_dispute_id VARCHAR(255) NOT NULL,
+    amount DOUBLE NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    status VARCHAR(30) NOT NULL,
+    reason VARCHAR(50) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_stripe_dispute (stripe_dispute_id),
+    INDEX idx_payment_id (payment_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 3. Create export_jobs table
+-- ============================================
+CREATE TABLE IF NOT EXISTS export_jobs (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    requested_by INT NOT NULL,
+    kind VARCHAR(20) NOT NULL, -- 'payments' or 'refunds'
+    format VARCHAR(20) NOT NULL, -- 'csv' or 'parquet'
+    filters JSON NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'queued', -- 'queued', 'running', 'completed', 'failed'
+    row_count BIGINT NOT NULL DEFAULT 0,
+    file_path VARCHAR(500) NULL,
+    error TEXT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP NULL,
+    completed_at TIMESTAMP NULL,
+    INDEX idx_requested_by (requested_by),
+    INDEX idx_status (status)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 4. Index payments by creation time for reports
+-- ============================================
+ALTER TABLE payments ADD INDEX idx_created_at (created_at);
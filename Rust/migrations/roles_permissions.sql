@@ -0,0 +1,78 @@
+-- Roles and Permissions Migration
+-- Date: 2026-10-18
+-- Description: Roles with permission scopes, assigned to users and embedded in access tokens
+
+-- ============================================
+-- 1. Create roles table
+-- ============================================
+CREATE TABLE IF NOT EXISTS roles (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(50) NOT NULL,
+    description VARCHAR(255) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY unique_name (name)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Create role_permissions table
+-- ============================================
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role_id INT NOT NULL,
+    permission VARCHAR(100) NOT NULL, -- e.g. 'payments:refund', '*' for everything
+    PRIMARY KEY (role_id, permission),
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 3. Create user_roles table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id INT NOT NULL,
+    role_id INT NOT NULL,
+    assigned_by INT NULL, -- admin who assigned the role, NULL for defaults
+    assigned_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, role_id),
+    INDEX idx_role_id (role_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 4. Seed roles
+-- ============================================
+INSERT IGNORE INTO roles (name, description) VALUES
+    ('admin', 'Full access'),
+    ('user', 'Default role of registered users'),
+    ('support', 'Read access to users and the payment audit chain'),
+    ('moderator', 'Can read any chat room'),
+    ('finance', 'Platform-wide payment reports, exports and bank reconciliation'),
+    ('merchant', 'Sells on the marketplace and refunds payments');
+
+INSERT IGNORE INTO role_permissions (role_id, permission)
+SELECT id, '*' FROM roles WHERE name = 'admin';
+
+INSERT IGNORE INTO role_permissions (role_id, permission)
+SELECT r.id, p.permission FROM roles r
+JOIN (SELECT 'payments:refund' AS permission UNION SELECT 'marketplace:sell') p
+WHERE r.name = 'merchant';
+
+INSERT IGNORE INTO role_permissions (role_id, permission)
+SELECT r.id, p.permission FROM roles r
+JOIN (SELECT 'users:read' AS permission UNION SELECT 'audit:verify') p
+WHERE r.name = 'support';
+
+INSERT IGNORE INTO role_permissions (role_id, permission)
+SELECT id, 'chat:moderate' FROM roles WHERE name = 'moderator';
+
+INSERT IGNORE INTO role_permissions (role_id, permission)
+SELECT r.id, p.permission FROM roles r
+JOIN (SELECT 'reports:read' AS permission UNION SELECT 'reconciliation:manage') p
+WHERE r.name = 'finance';
+
+-- Existing users get the default role
+INSERT IGNORE INTO user_roles (user_id, role_id)
+SELECT u.id, r.id FROM users u JOIN roles r ON r.name = 'user';
+
+-- Users already selling keep their seller accounts
+INSERT IGNORE INTO user_roles (user_id, role_id)
+SELECT DISTINCT c.user_id, r.id FROM connected_accounts c JOIN roles r ON r.name = 'merchant';
@@ -0,0 +1,21 @@
+-- User Events Outbox Migration
+-- Date: 2026-10-19
+-- Description: Events written with the change that causes them and published to Kafka afterwards
+
+-- ============================================
+-- 1. Create event_outbox table
+-- ============================================
+CREATE TABLE IF NOT EXISTS event_outbox (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY, -- publishing order
+    event_id CHAR(36) NOT NULL,
+    topic VARCHAR(100) NOT NULL,
+    message_key VARCHAR(100) NOT NULL, -- Kafka key, the user id for user events
+    event_type VARCHAR(50) NOT NULL,
+    payload TEXT NOT NULL, -- JSON message
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    published_at TIMESTAMP NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    last_error VARCHAR(500) NULL,
+    UNIQUE KEY unique_event_id (event_id),
+    INDEX idx_published_at (published_at, id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,18 @@
+-- User Profile Settings Migration
+-- Date: 2026-10-19
+-- Description: Profile fields and avatar kept by core-service, email changes confirmed through auth-service
+
+-- ============================================
+-- 1. Add profile fields to user_profiles
+-- ============================================
+ALTER TABLE user_profiles
+    ADD COLUMN phone VARCHAR(20) NULL AFTER email_verified, -- E.164, e.g. '+84901234567'
+    ADD COLUMN locale VARCHAR(20) NULL AFTER phone, -- e.g. 'vi-VN'
+    ADD COLUMN timezone VARCHAR(64) NULL AFTER locale, -- IANA name, e.g. 'Asia/Ho_Chi_Minh'
+    ADD COLUMN avatar_updated_at TIMESTAMP NULL AFTER timezone; -- set while the user has an avatar
+
+-- ============================================
+-- 2. Address to switch to, on email change tokens
+-- ============================================
+ALTER TABLE user_tokens
+    ADD COLUMN new_email VARCHAR(255) NULL AFTER purpose; -- purpose 'email_change' only
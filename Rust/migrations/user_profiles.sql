@@ -0,0 +1,25 @@
+-- User Profiles Migration
+-- Date: 2026-10-19
+-- Description: Core-service copy of the users it serves, kept current from the user-events topic
+
+-- ============================================
+-- 1. Create user_profiles table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_profiles (
+    user_id INT PRIMARY KEY, -- users.id in auth-service
+    name VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    locked_until TIMESTAMP NULL,
+    deleted_at TIMESTAMP NULL, -- kept so late redeliveries do not bring the user back
+    event_at TIMESTAMP(6) NOT NULL, -- time of the last event applied, older ones are skipped
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX idx_email (email)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ============================================
+-- 2. Seed from the users that exist today
+-- ============================================
+INSERT IGNORE INTO user_profiles (user_id, name, email, email_verified, event_at)
+SELECT id, name, email, email_verified_at IS NOT NULL, created_at FROM users;
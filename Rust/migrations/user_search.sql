@@ -0,0 +1,22 @@
+-- User Search Migration
+-- Date: 2026-10-19
+-- Description: Discoverability setting and indexes for paginated user search in core-service
+
+-- ============================================
+-- 1. Add discoverable to user_profiles
+-- ============================================
+ALTER TABLE user_profiles
+    ADD COLUMN discoverable BOOLEAN NOT NULL DEFAULT TRUE AFTER timezone; -- FALSE hides the user from other users' searches
+
+-- ============================================
+-- 2. Indexes for prefix search and sorting
+-- ============================================
+CREATE INDEX idx_name ON user_profiles (name);
+CREATE INDEX idx_created_at ON user_profiles (created_at);
+
+-- ============================================
+-- 3. Full-text indexes
+-- ============================================
+-- One per column list MATCH() uses: name only for users, name and email for admins
+CREATE FULLTEXT INDEX ft_name ON user_profiles (name);
+CREATE FULLTEXT INDEX ft_name_email ON user_profiles (name, email);
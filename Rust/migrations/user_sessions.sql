@@ -0,0 +1,21 @@
+-- User Sessions Migration
+-- Date: 2026-10-19
+-- Description: One row per login with its device, IP and activity, linked to its refresh token family
+
+-- ============================================
+-- 1. Create user_sessions table
+-- ============================================
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    family_id CHAR(36) NOT NULL, -- refresh_tokens.family_id of the login, sid claim of its access tokens
+    device VARCHAR(100) NOT NULL, -- label parsed from the user agent, e.g. 'Chrome on Windows'
+    user_agent VARCHAR(255) NULL,
+    ip VARCHAR(45) NOT NULL, -- of the last login or refresh
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMP NULL,
+    UNIQUE KEY unique_family_id (family_id),
+    INDEX idx_user_last_seen (user_id, last_seen_at),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
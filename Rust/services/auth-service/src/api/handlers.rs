@@ -1,6 +1,13 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
-use crate::service::AuthService;
+use authz::{Claims, Permitted};
+use authz::guard::{AssignRoles, ManageApiKeys, ManageOAuthClients};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crate::domain::{AuthorizationRequest, PublicKeyCredential, TokenPair};
+use crate::middleware::client_info;
+use crate::service::{AccountService, ApiKeyService, AuthService, FederationService, KeyService, LoginOutcome, MfaService, AuthorizeOutcome, OAuthClientService, OidcService, PasskeyService, PrivacyService, RoleService, SessionService};
+use crate::service::oauth_client_service::GRANT_CLIENT_CREDENTIALS;
+use crate::service::oidc_service::GRANT_AUTHORIZATION_CODE;
 
 #[derive(Serialize)]
 struct HealthResponse {
@@ -21,9 +28,118 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Deserialize)]
+pub struct MfaLoginRequest {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct MfaCodeRequest {
+    pub code: String, // TOTP code, or a recovery code where accepted
+}
+
+#[derive(Deserialize)]
+pub struct MfaTokenRequest {
+    pub mfa_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct MfaPasskeyLoginRequest {
+    pub mfa_token: String,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Deserialize)]
+pub struct RegisterPasskeyRequest {
+    pub name: Option<String>,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Deserialize)]
+pub struct ErasureRequest {
+    #[serde(default)]
+    pub password: String, // accounts without a password need a recent sign-in instead
+}
+
+#[derive(Deserialize)]
+pub struct FederatedLoginRequest {
+    pub state: String,
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTokenRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct EmailChangeRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirmRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct AssignRoleRequest {
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub owner: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateOAuthClientRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub redirect_uris: Option<Vec<String>>, // makes it an OIDC client
+}
+
+/// Form body of the token endpoint; the client may authenticate with HTTP
+/// Basic instead of client_id and client_secret
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>, // space-separated
+    // authorization_code grant
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+}
+
+/// Answer of the user to a consent prompt, with the authorization request
+#[derive(Deserialize)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub request: AuthorizationRequest,
+    pub approve: bool,
+}
+
 #[derive(Serialize)]
 pub struct AuthResponse {
-    pub token: String,
+    #[serde(flatten)]
+    pub tokens: TokenPair,
     pub user: crate::domain::UserPublic,
 }
 
@@ -36,17 +152,23 @@ pub async fn health_check() -> impl Responder {
 
 pub async fn register(
     auth_service: web::Data<AuthService>,
+    account_service: web::Data<AccountService>,
+    req: HttpRequest,
     request: web::Json<RegisterRequest>,
 ) -> impl Responder {
-    match auth_service.register(&request.name, &request.email, &request.password).await {
-        Ok((token, user)) => {
-            HttpResponse::Created().json(AuthResponse { token, user })
+    match auth_service.register(&request.name, &request.email, &request.password, &client_info(&req)).await {
+        Ok((tokens, user)) => {
+            // The account exists either way; the user can ask for another email
+            if let Err(e) = account_service.request_email_verification(user.id).await {
+                tracing::error!("Failed to send verification email to user {}: {}", user.id, e);
+            }
+            HttpResponse::Created().json(AuthResponse { tokens, user })
         }
         Err(e) => {
             tracing::error!("Register error: {}", e);
             let error_msg = e.to_string();
             
-            if error_msg.contains("already registered") {
+            if error_msg.contains("already registered") || error_msg.starts_with("Password") {
                 HttpResponse::BadRequest().json(serde_json::json!({
                     "error": error_msg
                 }))
@@ -61,12 +183,11 @@ pub async fn register(
 
 pub async fn login(
     auth_service: web::Data<AuthService>,
+    req: HttpRequest,
     request: web::Json<LoginRequest>,
 ) -> impl Responder {
-    match auth_service.login(&request.email, &request.password).await {
-        Ok((token, user)) => {
-            HttpResponse::Ok().json(AuthResponse { token, user })
-        }
+    match auth_service.login(&request.email, &request.password, &client_info(&req)).await {
+        Ok(outcome) => login_outcome_response(outcome),
         Err(e) => {
             tracing::error!("Login error: {}", e);
             let error_msg = e.to_string();
@@ -75,6 +196,10 @@ pub async fn login(
                 HttpResponse::Unauthorized().json(serde_json::json!({
                     "error": "Invalid credentials"
                 }))
+            } else if error_msg.contains("temporarily locked") {
+                HttpResponse::Locked().json(serde_json::json!({ "error": error_msg }))
+            } else if error_msg.contains("Too many") {
+                HttpResponse::TooManyRequests().json(serde_json::json!({ "error": error_msg }))
             } else {
                 HttpResponse::InternalServerError().json(serde_json::json!({
                     "error": "Login failed"
@@ -83,3 +208,1237 @@ pub async fn login(
         }
     }
 }
+
+/// Tokens, or the challenge for the second factor
+fn login_outcome_response(outcome: LoginOutcome) -> HttpResponse {
+    match outcome {
+        LoginOutcome::Authenticated(tokens, user) => {
+            HttpResponse::Ok().json(AuthResponse { tokens, user })
+        }
+        LoginOutcome::MfaRequired { mfa_token, expires_in, methods } => {
+            HttpResponse::Ok().json(serde_json::json!({
+                "mfa_required": true,
+                "mfa_token": mfa_token,
+                "expires_in": expires_in,
+                "methods": methods
+            }))
+        }
+    }
+}
+
+pub async fn login_mfa(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    request: web::Json<MfaLoginRequest>,
+) -> impl Responder {
+    match auth_service.complete_mfa_login(&request.mfa_token, &request.code, &client_info(&req)).await {
+        Ok((tokens, user)) => HttpResponse::Ok().json(AuthResponse { tokens, user }),
+        Err(e) => mfa_error("MFA login failed", e),
+    }
+}
+
+pub async fn refresh(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    request: web::Json<RefreshRequest>,
+) -> impl Responder {
+    match auth_service.refresh(&request.refresh_token, &client_info(&req)).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => {
+            tracing::error!("Refresh error: {}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("refresh token") {
+                HttpResponse::Unauthorized().json(serde_json::json!({
+                    "error": error_msg
+                }))
+            } else {
+                HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Failed to refresh token"
+                }))
+            }
+        }
+    }
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+/// Claims of the bearer token, for the few routes outside AuthMiddleware
+async fn authenticate(req: &HttpRequest, auth_service: &AuthService) -> Result<Claims, HttpResponse> {
+    let claims = match bearer_token(req) {
+        Some(token) => auth_service.verify_access_token(token).await,
+        None => Err(anyhow::anyhow!("Missing token")),
+    };
+    let claims = claims.map_err(|e| {
+        HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": format!("Unauthorized: {}", e)
+        }))
+    })?;
+    if claims.is_service() {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Service client tokens are not accepted here"
+        })));
+    }
+    Ok(claims)
+}
+
+pub async fn jwks(key_service: web::Data<KeyService>) -> impl Responder {
+    // Verifiers cache the set themselves; keep intermediaries in line with them
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(key_service.jwks())
+}
+
+pub async fn logout(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    request: web::Json<RefreshRequest>,
+) -> impl Responder {
+    match auth_service.logout(&request.refresh_token, bearer_token(&req)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            tracing::error!("Logout error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Logout failed"
+            }))
+        }
+    }
+}
+
+pub async fn logout_all(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match auth_service.logout_all(claims.user_id).await {
+        Ok(revoked) => HttpResponse::Ok().json(serde_json::json!({
+            "revoked_tokens": revoked
+        })),
+        Err(e) => {
+            tracing::error!("Logout-all error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Logout failed"
+            }))
+        }
+    }
+}
+
+// ============================================
+// Login sessions
+// ============================================
+
+fn session_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn list_sessions(
+    auth_service: web::Data<AuthService>,
+    session_service: web::Data<SessionService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match session_service.list(claims.user_id, claims.sid.as_deref()).await {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => session_error("Failed to list sessions", e),
+    }
+}
+
+pub async fn revoke_session(
+    auth_service: web::Data<AuthService>,
+    session_service: web::Data<SessionService>,
+    req: HttpRequest,
+    session_id: web::Path<i64>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match session_service.revoke(claims.user_id, session_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => session_error("Failed to revoke session", e),
+    }
+}
+
+/// Log out everywhere except the session making the request
+pub async fn revoke_other_sessions(
+    auth_service: web::Data<AuthService>,
+    session_service: web::Data<SessionService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    // Tokens from before sessions were recorded cannot tell which one is theirs
+    let current_sid = match claims.sid.as_deref() {
+        Some(sid) => sid,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Token has no session, refresh it first"
+        })),
+    };
+
+    match session_service.revoke_others(claims.user_id, current_sid).await {
+        Ok(revoked) => HttpResponse::Ok().json(serde_json::json!({
+            "revoked_sessions": revoked
+        })),
+        Err(e) => session_error("Failed to revoke sessions", e),
+    }
+}
+
+// ============================================
+// Email verification and password reset
+// ============================================
+
+fn token_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("Invalid or expired") || error_msg.contains("already verified") || error_msg.starts_with("Password")
+        || error_msg.contains("Invalid email") || error_msg.contains("current address") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("already registered") {
+        HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+/// Restricted tokens are accepted here: verifying is how they get lifted
+pub async fn request_email_verification(
+    auth_service: web::Data<AuthService>,
+    account_service: web::Data<AccountService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match account_service.request_email_verification(claims.user_id).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => token_error("Failed to send verification email", e),
+    }
+}
+
+pub async fn confirm_email(
+    account_service: web::Data<AccountService>,
+    request: web::Json<ConfirmTokenRequest>,
+) -> impl Responder {
+    match account_service.confirm_email(&request.token).await {
+        // Tokens issued from now on are no longer restricted
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({ "email_verified": true })),
+        Err(e) => token_error("Failed to verify email", e),
+    }
+}
+
+/// Called by core-service on behalf of the user, with their access token
+pub async fn request_email_change(
+    auth_service: web::Data<AuthService>,
+    account_service: web::Data<AccountService>,
+    req: HttpRequest,
+    request: web::Json<EmailChangeRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    if let Some(response) = require_recent_mfa(&claims, &auth_service).await {
+        return response;
+    }
+
+    match account_service.request_email_change(claims.user_id, &request.email).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => token_error("Failed to send email change confirmation", e),
+    }
+}
+
+pub async fn confirm_email_change(
+    account_service: web::Data<AccountService>,
+    request: web::Json<ConfirmTokenRequest>,
+) -> impl Responder {
+    match account_service.confirm_email_change(&request.token).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => token_error("Failed to change email", e),
+    }
+}
+
+pub async fn request_password_reset(
+    account_service: web::Data<AccountService>,
+    request: web::Json<PasswordResetRequest>,
+) -> impl Responder {
+    match account_service.request_password_reset(&request.email).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => token_error("Failed to send password reset email", e),
+    }
+}
+
+pub async fn confirm_password_reset(
+    account_service: web::Data<AccountService>,
+    request: web::Json<PasswordResetConfirmRequest>,
+) -> impl Responder {
+    match account_service.reset_password(&request.token, &request.new_password).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => token_error("Failed to reset password", e),
+    }
+}
+
+// ============================================
+// Two-factor authentication
+// ============================================
+
+fn mfa_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("Invalid") {
+        HttpResponse::Unauthorized().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Too many") {
+        HttpResponse::TooManyRequests().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("already enabled") || error_msg.contains("not enabled") || error_msg.contains("No enrollment") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn enroll_mfa(
+    auth_service: web::Data<AuthService>,
+    mfa_service: web::Data<MfaService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match mfa_service.enroll(claims.user_id).await {
+        Ok(enrollment) => HttpResponse::Ok().json(enrollment),
+        Err(e) => mfa_error("Failed to start MFA enrollment", e),
+    }
+}
+
+/// Recovery codes are only shown in this response
+pub async fn confirm_mfa(
+    auth_service: web::Data<AuthService>,
+    mfa_service: web::Data<MfaService>,
+    req: HttpRequest,
+    request: web::Json<MfaCodeRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match mfa_service.confirm(claims.user_id, &request.code).await {
+        Ok(codes) => HttpResponse::Ok().json(serde_json::json!({ "recovery_codes": codes })),
+        Err(e) => mfa_error("Failed to enable MFA", e),
+    }
+}
+
+pub async fn regenerate_recovery_codes(
+    auth_service: web::Data<AuthService>,
+    mfa_service: web::Data<MfaService>,
+    req: HttpRequest,
+    request: web::Json<MfaCodeRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match mfa_service.regenerate_recovery_codes(claims.user_id, &request.code).await {
+        Ok(codes) => HttpResponse::Ok().json(serde_json::json!({ "recovery_codes": codes })),
+        Err(e) => mfa_error("Failed to regenerate recovery codes", e),
+    }
+}
+
+pub async fn disable_mfa(
+    auth_service: web::Data<AuthService>,
+    mfa_service: web::Data<MfaService>,
+    req: HttpRequest,
+    request: web::Json<MfaCodeRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match mfa_service.disable(claims.user_id, &request.code).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => mfa_error("Failed to disable MFA", e),
+    }
+}
+
+/// New tokens carrying a fresh second factor, for sensitive operations
+pub async fn step_up(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    request: web::Json<MfaCodeRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match auth_service.step_up(claims.user_id, &request.code, &client_info(&req)).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => mfa_error("Step-up failed", e),
+    }
+}
+
+// ============================================
+// Federated login (external OpenID Connect providers)
+// ============================================
+
+fn federation_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("Invalid") || error_msg.contains("rejected") || error_msg.contains("did not return")
+        || error_msg.contains("returned no")
+    {
+        tracing::warn!("{}: {}", context, e);
+        HttpResponse::Unauthorized().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Unknown identity provider") || error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("already registered") || error_msg.contains("Cannot unlink") {
+        HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("unavailable") {
+        HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn list_identity_providers(federation_service: web::Data<FederationService>) -> impl Responder {
+    HttpResponse::Ok().json(federation_service.providers())
+}
+
+/// URL of the provider's login page to send the browser to
+pub async fn start_federated_login(
+    federation_service: web::Data<FederationService>,
+    provider: web::Path<String>,
+) -> impl Responder {
+    match federation_service.start(&provider).await {
+        Ok(url) => HttpResponse::Ok().json(serde_json::json!({ "authorization_url": url })),
+        Err(e) => federation_error("Failed to start login", e),
+    }
+}
+
+/// The code and state the provider redirected the browser back with
+pub async fn login_federated(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    request: web::Json<FederatedLoginRequest>,
+) -> impl Responder {
+    match auth_service.federated_login(&request.state, &request.code, &client_info(&req)).await {
+        Ok(outcome) => login_outcome_response(outcome),
+        Err(e) => federation_error("Federated login failed", e),
+    }
+}
+
+pub async fn list_identities(
+    auth_service: web::Data<AuthService>,
+    federation_service: web::Data<FederationService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match federation_service.list_identities(claims.user_id).await {
+        Ok(identities) => HttpResponse::Ok().json(identities),
+        Err(e) => federation_error("Failed to list linked identities", e),
+    }
+}
+
+pub async fn unlink_identity(
+    auth_service: web::Data<AuthService>,
+    federation_service: web::Data<FederationService>,
+    req: HttpRequest,
+    identity_id: web::Path<i64>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    if let Some(response) = require_recent_mfa(&claims, &auth_service).await {
+        return response;
+    }
+
+    match federation_service.unlink(claims.user_id, identity_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => federation_error("Failed to unlink identity", e),
+    }
+}
+
+// ============================================
+// Data export and account erasure (GDPR)
+// ============================================
+
+fn privacy_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("Invalid password") {
+        HttpResponse::Unauthorized().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Recent sign-in required") {
+        HttpResponse::Forbidden().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("expired") {
+        HttpResponse::Gone().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Export is") || error_msg.contains("Only a failed") {
+        HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn request_data_export(
+    auth_service: web::Data<AuthService>,
+    privacy_service: web::Data<PrivacyService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match privacy_service.request_export(claims.user_id).await {
+        Ok(request) => HttpResponse::Accepted().json(request),
+        Err(e) => privacy_error("Failed to request data export", e),
+    }
+}
+
+pub async fn request_account_erasure(
+    auth_service: web::Data<AuthService>,
+    privacy_service: web::Data<PrivacyService>,
+    req: HttpRequest,
+    request: web::Json<ErasureRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    if let Some(response) = require_recent_mfa(&claims, &auth_service).await {
+        return response;
+    }
+
+    match privacy_service.request_erasure(claims.user_id, &request.password, claims.auth_time).await {
+        Ok(request) => HttpResponse::Accepted().json(request),
+        Err(e) => privacy_error("Failed to request account erasure", e),
+    }
+}
+
+pub async fn list_privacy_requests(
+    auth_service: web::Data<AuthService>,
+    privacy_service: web::Data<PrivacyService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match privacy_service.list(claims.user_id).await {
+        Ok(requests) => HttpResponse::Ok().json(requests),
+        Err(e) => privacy_error("Failed to list privacy requests", e),
+    }
+}
+
+pub async fn get_privacy_request(
+    auth_service: web::Data<AuthService>,
+    privacy_service: web::Data<PrivacyService>,
+    req: HttpRequest,
+    request_id: web::Path<i64>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match privacy_service.get(claims.user_id, request_id.into_inner()).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(e) => privacy_error("Failed to get privacy request", e),
+    }
+}
+
+pub async fn download_data_export(
+    auth_service: web::Data<AuthService>,
+    privacy_service: web::Data<PrivacyService>,
+    req: HttpRequest,
+    request_id: web::Path<i64>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match privacy_service.download(claims.user_id, request_id.into_inner()).await {
+        Ok((filename, content)) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+            .body(content),
+        Err(e) => privacy_error("Failed to download data export", e),
+    }
+}
+
+/// Progress of any user's request, erasures included
+pub async fn admin_get_privacy_request(
+    _claims: Claims,
+    privacy_service: web::Data<PrivacyService>,
+    request_id: web::Path<i64>,
+) -> impl Responder {
+    match privacy_service.find(request_id.into_inner()).await {
+        Ok(request) => HttpResponse::Ok().json(serde_json::json!({
+            "user_id": request.user_id,
+            "request": request,
+        })),
+        Err(e) => privacy_error("Failed to get privacy request", e),
+    }
+}
+
+/// Ask the services again after a failed request, once the cause is fixed
+pub async fn admin_retry_privacy_request(
+    claims: Claims,
+    privacy_service: web::Data<PrivacyService>,
+    request_id: web::Path<i64>,
+) -> impl Responder {
+    let request_id = request_id.into_inner();
+    match privacy_service.retry(request_id).await {
+        Ok(request) => {
+            tracing::info!("Admin {} retried privacy request {}", claims.user_id, request_id);
+            HttpResponse::Accepted().json(request)
+        }
+        Err(e) => privacy_error("Failed to retry privacy request", e),
+    }
+}
+
+// ============================================
+// Passkeys
+// ============================================
+
+fn passkey_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("Invalid") {
+        HttpResponse::Unauthorized().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Unsupported") || error_msg.contains("already registered")
+        || error_msg.contains("limit") || error_msg.contains("No passkey")
+    {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+/// Adding or removing a passkey changes how the account can be entered, so
+/// users who already have a second factor must have used it recently
+async fn require_recent_mfa(claims: &Claims, auth_service: &AuthService) -> Option<HttpResponse> {
+    match auth_service.second_factors(claims.user_id).await {
+        Ok(methods) if methods.is_empty() || claims.has_recent_mfa(authz::step_up_max_age()) => None,
+        Ok(_) => Some(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Recent two-factor authentication required, use /api/v1/auth/mfa/step-up",
+            "step_up_required": true
+        }))),
+        Err(e) => Some(passkey_error("Failed to check two-factor authentication", e)),
+    }
+}
+
+pub async fn list_passkeys(
+    auth_service: web::Data<AuthService>,
+    passkey_service: web::Data<PasskeyService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match passkey_service.list(claims.user_id).await {
+        Ok(passkeys) => HttpResponse::Ok().json(passkeys),
+        Err(e) => passkey_error("Failed to list passkeys", e),
+    }
+}
+
+/// Options to pass to navigator.credentials.create()
+pub async fn passkey_registration_options(
+    auth_service: web::Data<AuthService>,
+    passkey_service: web::Data<PasskeyService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    if let Some(response) = require_recent_mfa(&claims, &auth_service).await {
+        return response;
+    }
+
+    match passkey_service.registration_options(claims.user_id).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => passkey_error("Failed to start passkey registration", e),
+    }
+}
+
+pub async fn register_passkey(
+    auth_service: web::Data<AuthService>,
+    passkey_service: web::Data<PasskeyService>,
+    req: HttpRequest,
+    request: web::Json<RegisterPasskeyRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    if let Some(response) = require_recent_mfa(&claims, &auth_service).await {
+        return response;
+    }
+
+    match passkey_service.register(claims.user_id, request.name.as_deref(), &request.credential).await {
+        Ok(passkey) => HttpResponse::Created().json(passkey),
+        Err(e) => passkey_error("Failed to register passkey", e),
+    }
+}
+
+pub async fn delete_passkey(
+    auth_service: web::Data<AuthService>,
+    passkey_service: web::Data<PasskeyService>,
+    req: HttpRequest,
+    passkey_id: web::Path<i64>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    if let Some(response) = require_recent_mfa(&claims, &auth_service).await {
+        return response;
+    }
+
+    match passkey_service.delete(claims.user_id, passkey_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => passkey_error("Failed to delete passkey", e),
+    }
+}
+
+/// Options for a passwordless login, before the user is known
+pub async fn passkey_login_options(passkey_service: web::Data<PasskeyService>) -> impl Responder {
+    match passkey_service.authentication_options(None).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => passkey_error("Failed to start passkey login", e),
+    }
+}
+
+pub async fn login_passkey(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    credential: web::Json<PublicKeyCredential>,
+) -> impl Responder {
+    match auth_service.passkey_login(&credential, &client_info(&req)).await {
+        Ok((tokens, user)) => HttpResponse::Ok().json(AuthResponse { tokens, user }),
+        Err(e) => passkey_error("Passkey login failed", e),
+    }
+}
+
+pub async fn login_mfa_passkey_options(
+    auth_service: web::Data<AuthService>,
+    request: web::Json<MfaTokenRequest>,
+) -> impl Responder {
+    match auth_service.mfa_passkey_options(&request.mfa_token).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => passkey_error("Failed to start passkey verification", e),
+    }
+}
+
+pub async fn login_mfa_passkey(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    request: web::Json<MfaPasskeyLoginRequest>,
+) -> impl Responder {
+    match auth_service.complete_mfa_login_passkey(&request.mfa_token, &request.credential, &client_info(&req)).await {
+        Ok((tokens, user)) => HttpResponse::Ok().json(AuthResponse { tokens, user }),
+        Err(e) => passkey_error("MFA login failed", e),
+    }
+}
+
+pub async fn step_up_passkey_options(
+    auth_service: web::Data<AuthService>,
+    passkey_service: web::Data<PasskeyService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match passkey_service.authentication_options(Some(claims.user_id)).await {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(e) => passkey_error("Failed to start passkey verification", e),
+    }
+}
+
+pub async fn step_up_passkey(
+    auth_service: web::Data<AuthService>,
+    req: HttpRequest,
+    credential: web::Json<PublicKeyCredential>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match auth_service.step_up_passkey(claims.user_id, &credential, &client_info(&req)).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => passkey_error("Step-up failed", e),
+    }
+}
+
+// ============================================
+// Admin: role assignment
+// ============================================
+
+fn role_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Cannot") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn list_roles(
+    _claims: Claims,
+    role_service: web::Data<RoleService>,
+) -> impl Responder {
+    match role_service.list_roles().await {
+        Ok(roles) => HttpResponse::Ok().json(roles),
+        Err(e) => role_error("Failed to list roles", e),
+    }
+}
+
+pub async fn get_user_roles(
+    _claims: Claims,
+    role_service: web::Data<RoleService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    match role_service.user_grants(user_id.into_inner()).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => role_error("Failed to get user roles", e),
+    }
+}
+
+pub async fn assign_role(
+    admin: Permitted<AssignRoles>,
+    role_service: web::Data<RoleService>,
+    user_id: web::Path<i32>,
+    request: web::Json<AssignRoleRequest>,
+) -> impl Responder {
+    match role_service.assign_role(user_id.into_inner(), &request.role, admin.claims.user_id).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => role_error("Failed to assign role", e),
+    }
+}
+
+pub async fn unlock_user(
+    _claims: Claims,
+    account_service: web::Data<AccountService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    match account_service.unlock(user_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => role_error("Failed to unlock user", e),
+    }
+}
+
+pub async fn delete_user(
+    _claims: Claims,
+    account_service: web::Data<AccountService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    match account_service.delete_user(user_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => role_error("Failed to delete user", e),
+    }
+}
+
+pub async fn remove_role(
+    admin: Permitted<AssignRoles>,
+    role_service: web::Data<RoleService>,
+    path: web::Path<(i32, String)>,
+) -> impl Responder {
+    let (user_id, role) = path.into_inner();
+    match role_service.remove_role(user_id, &role, admin.claims.user_id).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => role_error("Failed to remove role", e),
+    }
+}
+
+// ============================================
+// API keys for backend services
+// ============================================
+
+fn api_key_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("must not") || error_msg.contains("scope") || error_msg.contains("redirect URI") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+/// The full key is only in this response
+pub async fn create_api_key(
+    admin: Permitted<ManageApiKeys>,
+    api_key_service: web::Data<ApiKeyService>,
+    request: web::Json<CreateApiKeyRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+    match api_key_service
+        .create(&request.name, &request.owner, request.scopes, request.expires_in_days, admin.claims.user_id)
+        .await
+    {
+        Ok((api_key, key)) => HttpResponse::Created().json(serde_json::json!({
+            "api_key": api_key,
+            "key": key
+        })),
+        Err(e) => api_key_error("Failed to create API key", e),
+    }
+}
+
+pub async fn list_api_keys(
+    _admin: Permitted<ManageApiKeys>,
+    api_key_service: web::Data<ApiKeyService>,
+) -> impl Responder {
+    match api_key_service.list().await {
+        Ok(keys) => HttpResponse::Ok().json(keys),
+        Err(e) => api_key_error("Failed to list API keys", e),
+    }
+}
+
+pub async fn revoke_api_key(
+    admin: Permitted<ManageApiKeys>,
+    api_key_service: web::Data<ApiKeyService>,
+    key_id: web::Path<i64>,
+) -> impl Responder {
+    match api_key_service.revoke(key_id.into_inner(), admin.claims.user_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => api_key_error("Failed to revoke API key", e),
+    }
+}
+
+// ============================================
+// OAuth2 client credentials for backend services
+// ============================================
+
+fn oauth_error(status: actix_web::http::StatusCode, error: &str, description: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .insert_header(("Cache-Control", "no-store"))
+        .json(serde_json::json!({
+            "error": error,
+            "error_description": description
+        }))
+}
+
+/// client_id and client_secret from an `Authorization: Basic` header
+fn basic_credentials(req: &HttpRequest) -> Option<(String, String)> {
+    let encoded = req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some((client_id.to_string(), client_secret.to_string()))
+}
+
+/// RFC 6749 token endpoint: client_credentials for services,
+/// authorization_code for OIDC apps
+pub async fn oauth_token(
+    oauth_client_service: web::Data<OAuthClientService>,
+    oidc_service: web::Data<OidcService>,
+    req: HttpRequest,
+    request: web::Form<TokenRequest>,
+) -> impl Responder {
+    use actix_web::http::StatusCode;
+
+    let request = request.into_inner();
+    if request.grant_type != GRANT_CLIENT_CREDENTIALS && request.grant_type != GRANT_AUTHORIZATION_CODE {
+        return oauth_error(StatusCode::BAD_REQUEST, "unsupported_grant_type", "Only client_credentials and authorization_code are supported");
+    }
+    let credentials = basic_credentials(&req)
+        .or_else(|| request.client_id.clone().zip(request.client_secret.clone()));
+    let (client_id, client_secret) = match credentials {
+        Some(credentials) => credentials,
+        None => return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Client authentication required"),
+    };
+
+    if request.grant_type == GRANT_AUTHORIZATION_CODE {
+        return exchange_authorization_code(&oidc_service, &client_id, &client_secret, &request).await;
+    }
+
+    match oauth_client_service.client_credentials(&client_id, &client_secret, request.scope.as_deref()).await {
+        Ok(token) => HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(token),
+        Err(e) => {
+            let error_msg = e.to_string();
+            if error_msg.contains("Invalid client") {
+                tracing::warn!("Rejected client_credentials for {}", client_id);
+                oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", &error_msg)
+            } else if error_msg.contains("Invalid scope") {
+                oauth_error(StatusCode::BAD_REQUEST, "invalid_scope", &error_msg)
+            } else {
+                tracing::error!("Token endpoint error: {}", e);
+                oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", "Failed to issue token")
+            }
+        }
+    }
+}
+
+async fn exchange_authorization_code(
+    oidc_service: &OidcService,
+    client_id: &str,
+    client_secret: &str,
+    request: &TokenRequest,
+) -> HttpResponse {
+    use actix_web::http::StatusCode;
+
+    let (code, redirect_uri, code_verifier) = match (&request.code, &request.redirect_uri, &request.code_verifier) {
+        (Some(code), Some(redirect_uri), Some(code_verifier)) => (code, redirect_uri, code_verifier),
+        _ => return oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "code, redirect_uri and code_verifier are required"),
+    };
+
+    match oidc_service.exchange_code(client_id, client_secret, code, redirect_uri, code_verifier).await {
+        Ok(token) => HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(token),
+        Err(e) => {
+            let error_msg = e.to_string();
+            if error_msg.contains("Invalid client") {
+                tracing::warn!("Rejected authorization_code for {}", client_id);
+                oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", &error_msg)
+            } else if error_msg.contains("Invalid grant") {
+                oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", &error_msg)
+            } else {
+                tracing::error!("Token endpoint error: {}", e);
+                oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", "Failed to issue token")
+            }
+        }
+    }
+}
+
+fn oauth_client_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("must not") || error_msg.contains("scope") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+/// The client secret is only in this response
+pub async fn create_oauth_client(
+    admin: Permitted<ManageOAuthClients>,
+    oauth_client_service: web::Data<OAuthClientService>,
+    request: web::Json<CreateOAuthClientRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+    match oauth_client_service.create(&request.name, request.scopes, request.redirect_uris, admin.claims.user_id).await {
+        Ok((client, client_secret)) => HttpResponse::Created().json(serde_json::json!({
+            "client": client,
+            "client_secret": client_secret
+        })),
+        Err(e) => oauth_client_error("Failed to create OAuth client", e),
+    }
+}
+
+pub async fn list_oauth_clients(
+    _admin: Permitted<ManageOAuthClients>,
+    oauth_client_service: web::Data<OAuthClientService>,
+) -> impl Responder {
+    match oauth_client_service.list().await {
+        Ok(clients) => HttpResponse::Ok().json(clients),
+        Err(e) => oauth_client_error("Failed to list OAuth clients", e),
+    }
+}
+
+pub async fn revoke_oauth_client(
+    admin: Permitted<ManageOAuthClients>,
+    oauth_client_service: web::Data<OAuthClientService>,
+    id: web::Path<i64>,
+) -> impl Responder {
+    match oauth_client_service.revoke(id.into_inner(), admin.claims.user_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => oauth_client_error("Failed to revoke OAuth client", e),
+    }
+}
+
+// ============================================
+// OpenID Connect provider
+// ============================================
+
+pub async fn openid_configuration(oidc_service: web::Data<OidcService>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=3600"))
+        .json(oidc_service.discovery())
+}
+
+fn authorize_response(result: anyhow::Result<AuthorizeOutcome>) -> HttpResponse {
+    match result {
+        Ok(AuthorizeOutcome::Redirect(redirect_to)) => {
+            HttpResponse::Ok().json(serde_json::json!({ "redirect_to": redirect_to }))
+        }
+        Ok(AuthorizeOutcome::ConsentRequired { client_id, client_name, scopes }) => {
+            HttpResponse::Ok().json(serde_json::json!({
+                "consent_required": true,
+                "client": { "client_id": client_id, "name": client_name },
+                "scopes": scopes
+            }))
+        }
+        // Unknown client or redirect URI: never redirect anywhere
+        Err(e) if e.to_string().contains("Invalid") => {
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))
+        }
+        Err(e) => {
+            tracing::error!("Authorization error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Authorization failed"
+            }))
+        }
+    }
+}
+
+/// Authorization request of a client app, on behalf of the logged-in user.
+/// The frontend sends the browser to `redirect_to`, or shows the consent
+/// prompt and posts the answer.
+pub async fn authorize(
+    auth_service: web::Data<AuthService>,
+    oidc_service: web::Data<OidcService>,
+    req: HttpRequest,
+    request: web::Query<AuthorizationRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    authorize_response(oidc_service.authorize(&claims, &request, None).await)
+}
+
+pub async fn authorize_consent(
+    auth_service: web::Data<AuthService>,
+    oidc_service: web::Data<OidcService>,
+    req: HttpRequest,
+    request: web::Json<ConsentRequest>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    authorize_response(oidc_service.authorize(&claims, &request.request, Some(request.approve)).await)
+}
+
+pub async fn userinfo(
+    oidc_service: web::Data<OidcService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let token = match bearer_token(&req) {
+        Some(token) => token,
+        None => return HttpResponse::Unauthorized()
+            .insert_header(("WWW-Authenticate", "Bearer"))
+            .finish(),
+    };
+
+    match oidc_service.userinfo(token).await {
+        Ok(info) => HttpResponse::Ok().json(info),
+        Err(e) if e.to_string().contains("Invalid") => HttpResponse::Unauthorized()
+            .insert_header(("WWW-Authenticate", "Bearer error=\"invalid_token\""))
+            .finish(),
+        Err(e) => {
+            tracing::error!("Userinfo error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to load user info"
+            }))
+        }
+    }
+}
+
+pub async fn list_consents(
+    auth_service: web::Data<AuthService>,
+    oidc_service: web::Data<OidcService>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match oidc_service.list_consents(claims.user_id).await {
+        Ok(consents) => HttpResponse::Ok().json(consents),
+        Err(e) => session_error("Failed to list consents", e),
+    }
+}
+
+pub async fn revoke_consent(
+    auth_service: web::Data<AuthService>,
+    oidc_service: web::Data<OidcService>,
+    req: HttpRequest,
+    client_id: web::Path<String>,
+) -> impl Responder {
+    let claims = match authenticate(&req, &auth_service).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+
+    match oidc_service.revoke_consent(claims.user_id, &client_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => session_error("Failed to revoke consent", e),
+    }
+}
@@ -1,11 +1,125 @@
 use actix_web::web;
+use authz::{permissions, AuthMiddleware, Require};
+use crate::middleware::{AUTHORIZE_PATH, DISCOVERY_PATH, JWKS_PATH, TOKEN_PATH, USERINFO_PATH};
+use crate::service::KeyService;
 use super::handlers;
 
-pub fn configure(cfg: &mut web::ServiceConfig) {
+pub fn configure(cfg: &mut web::ServiceConfig, key_service: &KeyService) {
     cfg.route("/health", web::get().to(handlers::health_check))
+        .route(JWKS_PATH, web::get().to(handlers::jwks))
+        // client_credentials grant of backend services, authorization_code of OIDC apps
+        .route(TOKEN_PATH, web::post().to(handlers::oauth_token))
+        // OpenID Connect provider
+        .route(DISCOVERY_PATH, web::get().to(handlers::openid_configuration))
+        .route(AUTHORIZE_PATH, web::get().to(handlers::authorize))
+        .route(AUTHORIZE_PATH, web::post().to(handlers::authorize_consent))
+        .route(USERINFO_PATH, web::get().to(handlers::userinfo))
+        .route(USERINFO_PATH, web::post().to(handlers::userinfo))
         .service(
             web::scope("/api/v1/auth")
                 .route("/login", web::post().to(handlers::login))
+                .route("/login/mfa", web::post().to(handlers::login_mfa))
+                .route("/login/mfa/passkey/options", web::post().to(handlers::login_mfa_passkey_options))
+                .route("/login/mfa/passkey", web::post().to(handlers::login_mfa_passkey))
+                // Passwordless login
+                .route("/login/passkey/options", web::post().to(handlers::passkey_login_options))
+                .route("/login/passkey", web::post().to(handlers::login_passkey))
+                // Login at an external OpenID Connect provider
+                .route("/federated/providers", web::get().to(handlers::list_identity_providers))
+                .route("/login/federated", web::post().to(handlers::login_federated))
+                .route("/login/federated/{provider}", web::post().to(handlers::start_federated_login))
                 .route("/register", web::post().to(handlers::register))
+                .route("/refresh", web::post().to(handlers::refresh))
+                .route("/logout", web::post().to(handlers::logout))
+                // Requires the access token of the user
+                .route("/logout-all", web::post().to(handlers::logout_all))
+                // Login sessions of the user, with their access token
+                .route("/sessions", web::get().to(handlers::list_sessions))
+                .route("/sessions/revoke-others", web::post().to(handlers::revoke_other_sessions))
+                .route("/sessions/{session_id}", web::delete().to(handlers::revoke_session))
+                // Data export and account erasure, with the access token of the user
+                .route("/privacy/export", web::post().to(handlers::request_data_export))
+                .route("/privacy/erasure", web::post().to(handlers::request_account_erasure))
+                .route("/privacy/requests", web::get().to(handlers::list_privacy_requests))
+                .route("/privacy/requests/{request_id}", web::get().to(handlers::get_privacy_request))
+                .route("/privacy/requests/{request_id}/download", web::get().to(handlers::download_data_export))
+                // Client apps the user let log them in
+                .route("/consents", web::get().to(handlers::list_consents))
+                .route("/consents/{client_id}", web::delete().to(handlers::revoke_consent))
+                .route("/verify-email/request", web::post().to(handlers::request_email_verification))
+                .route("/verify-email/confirm", web::post().to(handlers::confirm_email))
+                .route("/email-change/request", web::post().to(handlers::request_email_change))
+                .route("/email-change/confirm", web::post().to(handlers::confirm_email_change))
+                .route("/password-reset/request", web::post().to(handlers::request_password_reset))
+                .route("/password-reset/confirm", web::post().to(handlers::confirm_password_reset))
+                // Two-factor authentication, with the access token of the user
+                .route("/mfa/enroll", web::post().to(handlers::enroll_mfa))
+                .route("/mfa/confirm", web::post().to(handlers::confirm_mfa))
+                .route("/mfa/recovery-codes", web::post().to(handlers::regenerate_recovery_codes))
+                .route("/mfa/disable", web::post().to(handlers::disable_mfa))
+                .route("/mfa/step-up", web::post().to(handlers::step_up))
+                .route("/mfa/step-up/passkey/options", web::post().to(handlers::step_up_passkey_options))
+                .route("/mfa/step-up/passkey", web::post().to(handlers::step_up_passkey))
+                // Passkeys of the user, with their access token
+                .route("/passkeys", web::get().to(handlers::list_passkeys))
+                .route("/passkeys/register/options", web::post().to(handlers::passkey_registration_options))
+                .route("/passkeys/register", web::post().to(handlers::register_passkey))
+                .route("/passkeys/{passkey_id}", web::delete().to(handlers::delete_passkey))
+                // Provider accounts linked to the user, with their access token
+                .route("/identities", web::get().to(handlers::list_identities))
+                .route("/identities/{identity_id}", web::delete().to(handlers::unlink_identity))
+        )
+        // API keys of backend services
+        .service(
+            web::scope("/api/v1/api-keys")
+                .wrap(Require::permission(permissions::API_KEYS_MANAGE))
+                .wrap(AuthMiddleware::with_validator(key_service.validator()))
+                .route("", web::get().to(handlers::list_api_keys))
+                .route("", web::post().to(handlers::create_api_key))
+                .route("/{key_id}", web::delete().to(handlers::revoke_api_key))
+        )
+        // OAuth2 clients of backend services
+        .service(
+            web::scope("/api/v1/oauth-clients")
+                .wrap(Require::permission(permissions::OAUTH_CLIENTS_MANAGE))
+                .wrap(AuthMiddleware::with_validator(key_service.validator()))
+                .route("", web::get().to(handlers::list_oauth_clients))
+                .route("", web::post().to(handlers::create_oauth_client))
+                .route("/{id}", web::delete().to(handlers::revoke_oauth_client))
+        )
+        // Lift a login lockout, delete users and follow privacy requests; registered ahead of the admin scope, which would claim the path
+        .service(
+            web::resource("/api/v1/admin/users/{user_id}/unlock")
+                .wrap(Require::permission(permissions::USERS_UNLOCK))
+                .wrap(AuthMiddleware::with_validator(key_service.validator()))
+                .route(web::post().to(handlers::unlock_user))
+        )
+        .service(
+            web::resource("/api/v1/admin/users/{user_id}")
+                .wrap(Require::permission(permissions::USERS_DELETE))
+                .wrap(AuthMiddleware::with_validator(key_service.validator()))
+                .route(web::delete().to(handlers::delete_user))
+        )
+        .service(
+            web::resource("/api/v1/admin/privacy-requests/{request_id}")
+                .wrap(Require::permission(permissions::USERS_READ))
+                .wrap(AuthMiddleware::with_validator(key_service.validator()))
+                .route(web::get().to(handlers::admin_get_privacy_request))
+        )
+        .service(
+            web::resource("/api/v1/admin/privacy-requests/{request_id}/retry")
+                .wrap(Require::permission(permissions::USERS_DELETE))
+                .wrap(AuthMiddleware::with_validator(key_service.validator()))
+                .route(web::post().to(handlers::admin_retry_privacy_request))
+        )
+        // Role management, admins only
+        .service(
+            web::scope("/api/v1/admin")
+                .wrap(Require::permission(permissions::ROLES_ASSIGN))
+                .wrap(AuthMiddleware::with_validator(key_service.validator()))
+                .route("/roles", web::get().to(handlers::list_roles))
+                .route("/users/{user_id}/roles", web::get().to(handlers::get_user_roles))
+                .route("/users/{user_id}/roles", web::post().to(handlers::assign_role))
+                .route("/users/{user_id}/roles/{role}", web::delete().to(handlers::remove_role))
         );
 }
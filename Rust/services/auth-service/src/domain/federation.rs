@@ -0,0 +1,193 @@
+// Login through external OpenID Connect providers (Google, Microsoft,
+// corporate SSO): their configuration and what we accept from their ID tokens
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// An external OpenID Connect provider users can log in with
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub name: String,         // in API paths and user_identities.provider
+    pub display_name: String, // on the login button
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: String, // space-separated
+    /// Take emails as verified without `email_verified`, for corporate SSO
+    /// that owns the email domain
+    pub trust_email: bool,
+}
+
+impl ProviderConfig {
+    /// Names listed in FEDERATED_PROVIDERS, e.g. `google,microsoft`
+    pub fn names_from_env() -> Vec<String> {
+        std::env::var("FEDERATED_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    /// FEDERATED_<NAME>_ISSUER, _CLIENT_ID and _CLIENT_SECRET, with the
+    /// optional _DISPLAY_NAME, _SCOPES (default `openid email profile`) and
+    /// _TRUST_EMAIL
+    pub fn from_env(name: &str) -> Result<Self> {
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(anyhow!("Provider names may only have letters, digits and underscores"));
+        }
+        let prefix = format!("FEDERATED_{}_", name.to_uppercase());
+        let var = |key: &str| std::env::var(format!("{}{}", prefix, key)).ok().filter(|value| !value.is_empty());
+        let required = |key: &str| var(key).ok_or_else(|| anyhow!("{}{} is not set", prefix, key));
+
+        let scopes = var("SCOPES").unwrap_or_else(|| "openid email profile".to_string());
+        if !scopes.split_whitespace().any(|scope| scope == "openid") {
+            return Err(anyhow!("{}SCOPES must include openid", prefix));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            display_name: var("DISPLAY_NAME").unwrap_or_else(|| name.to_string()),
+            issuer: required("ISSUER")?.trim_end_matches('/').to_string(),
+            client_id: required("CLIENT_ID")?,
+            client_secret: required("CLIENT_SECRET")?,
+            scopes,
+            trust_email: var("TRUST_EMAIL").is_some_and(|value| value == "true"),
+        })
+    }
+}
+
+/// OpenID Provider Metadata (OIDC Discovery 3), the parts a relying party uses
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Login sent to a provider, kept under its `state` until the user comes back
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingFederatedLogin {
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+/// Claims read from a provider's ID token; iss, aud and exp are checked
+/// with the signature
+#[derive(Debug, Deserialize)]
+pub struct ExternalIdTokenClaims {
+    pub sub: String,
+    pub azp: Option<String>,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<serde_json::Value>, // a bool, or "true" from some providers
+    pub name: Option<String>,
+}
+
+impl ExternalIdTokenClaims {
+    /// The user behind a token that answers the login sent with `nonce`
+    pub fn into_identity(self, provider: &ProviderConfig, nonce: &str) -> Result<ExternalIdentity> {
+        if self.nonce.as_deref() != Some(nonce) {
+            return Err(anyhow!("Invalid ID token: nonce does not match the login"));
+        }
+        // With several audiences, azp names the client the token was issued to
+        if self.azp.as_deref().is_some_and(|azp| azp != provider.client_id) {
+            return Err(anyhow!("Invalid ID token: issued to another client"));
+        }
+        if self.sub.is_empty() {
+            return Err(anyhow!("Invalid ID token: no subject"));
+        }
+
+        let email_verified = provider.trust_email
+            || matches!(&self.email_verified, Some(serde_json::Value::Bool(true)))
+            || matches!(&self.email_verified, Some(serde_json::Value::String(s)) if s == "true");
+        Ok(ExternalIdentity {
+            provider: provider.name.clone(),
+            subject: self.sub,
+            email: self.email.map(|email| email.trim().to_lowercase()).filter(|email| email.contains('@')),
+            email_verified,
+            name: self.name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()),
+        })
+    }
+}
+
+/// A user as a provider knows them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+/// Provider account linked to a user
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct FederatedIdentity {
+    pub id: i64,
+    #[serde(skip_serializing)]
+    pub user_id: i32,
+    pub provider: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(trust_email: bool) -> ProviderConfig {
+        ProviderConfig {
+            name: "corp".to_string(),
+            display_name: "Corp SSO".to_string(),
+            issuer: "https://sso.corp.example".to_string(),
+            client_id: "rushtech".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: "openid email".to_string(),
+            trust_email,
+        }
+    }
+
+    fn claims(value: serde_json::Value) -> ExternalIdTokenClaims {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_identity_from_claims() {
+        let identity = claims(serde_json::json!({
+            "sub": "1234", "nonce": "n-1", "email": " An@Example.com ", "email_verified": true, "name": "An Nguyen"
+        }))
+        .into_identity(&provider(false), "n-1")
+        .unwrap();
+        assert_eq!(identity.subject, "1234");
+        assert_eq!(identity.email.as_deref(), Some("an@example.com"));
+        assert!(identity.email_verified);
+
+        // Some providers send the flag as a string, others not at all
+        let verified = |value: serde_json::Value, trust_email: bool| {
+            claims(serde_json::json!({ "sub": "1", "nonce": "n", "email": "a@b.c", "email_verified": value }))
+                .into_identity(&provider(trust_email), "n")
+                .unwrap()
+                .email_verified
+        };
+        assert!(verified(serde_json::json!("true"), false));
+        assert!(!verified(serde_json::json!(false), false));
+        assert!(!verified(serde_json::Value::Null, false));
+        assert!(verified(serde_json::Value::Null, true));
+    }
+
+    #[test]
+    fn test_nonce_and_azp_must_match() {
+        let token = || claims(serde_json::json!({ "sub": "1", "nonce": "n-1", "azp": "rushtech" }));
+        assert!(token().into_identity(&provider(false), "n-2").is_err());
+        assert!(token().into_identity(&provider(false), "n-1").is_ok());
+
+        let other_client = claims(serde_json::json!({ "sub": "1", "nonce": "n-1", "azp": "someone-else" }));
+        assert!(other_client.into_identity(&provider(false), "n-1").is_err());
+        let no_nonce = claims(serde_json::json!({ "sub": "1" }));
+        assert!(no_nonce.into_identity(&provider(false), "n-1").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+/// Why a login attempt failed, stored in `login_attempts.reason`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginFailure {
+    UnknownEmail,
+    BadPassword,
+    Throttled, // rejected by a delay, lockout or IP block before checking the password
+}
+
+impl LoginFailure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginFailure::UnknownEmail => "unknown_email",
+            LoginFailure::BadPassword => "bad_password",
+            LoginFailure::Throttled => "throttled",
+        }
+    }
+}
+
+/// Progressive delays, then a temporary lockout, by failed attempts in a row
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    pub delay_after: i64,      // failures allowed before delays start
+    pub max_delay_secs: u64,
+    pub lock_threshold: i64,   // failures that lock the account
+    pub lock_duration_secs: u64,
+}
+
+impl LockoutPolicy {
+    pub fn from_env() -> Self {
+        let env = |name: &str, default: u64| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        Self {
+            delay_after: env("LOGIN_DELAY_AFTER_FAILURES", 3) as i64,
+            max_delay_secs: env("LOGIN_MAX_DELAY_SECS", 60),
+            lock_threshold: env("LOGIN_LOCKOUT_THRESHOLD", 10) as i64,
+            lock_duration_secs: env("LOGIN_LOCKOUT_MINUTES", 15) * 60,
+        }
+    }
+
+    /// Wait before the next attempt after `failures` failures: 1s, 2s, 4s...
+    pub fn delay_for(&self, failures: i64) -> Option<u64> {
+        if failures < self.delay_after {
+            return None;
+        }
+        let exponent = (failures - self.delay_after).min(16) as u32;
+        Some(2u64.pow(exponent).min(self.max_delay_secs))
+    }
+
+    pub fn locks(&self, failures: i64) -> bool {
+        failures >= self.lock_threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_policy_escalates() {
+        let policy = LockoutPolicy {
+            delay_after: 3,
+            max_delay_secs: 60,
+            lock_threshold: 10,
+            lock_duration_secs: 900,
+        };
+        assert_eq!(policy.delay_for(2), None);
+        assert_eq!(policy.delay_for(3), Some(1));
+        assert_eq!(policy.delay_for(5), Some(4));
+        assert_eq!(policy.delay_for(40), Some(60));
+        assert!(!policy.locks(9));
+        assert!(policy.locks(10));
+    }
+}
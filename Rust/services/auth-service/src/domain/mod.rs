@@ -1,4 +1,29 @@
 // Domain entities and business rules
 pub mod user;
+pub mod token;
+pub mod signing_key;
+pub mod role;
+pub mod mfa;
+pub mod login_attempt;
+pub mod oauth_client;
+pub mod session;
+pub mod passkey;
+pub mod password;
+pub mod oidc;
+pub mod federation;
+pub mod outbox;
+pub mod privacy;
 
 pub use user::{User, UserPublic};
+pub use token::{Authentication, RefreshToken, TokenPair, TokenPurpose, UserToken};
+pub use signing_key::SigningKey;
+pub use role::{Grants, Role, RoleWithPermissions};
+pub use mfa::UserMfa;
+pub use login_attempt::{LockoutPolicy, LoginFailure};
+pub use oauth_client::{ClientToken, OAuthClient};
+pub use session::{ClientInfo, SessionView, UserSession};
+pub use passkey::{Passkey, PublicKeyCredential, RelyingParty};
+pub use oidc::{AuthorizationRequest, Consent, OidcToken};
+pub use federation::{ExternalIdentity, FederatedIdentity, ProviderConfig};
+pub use outbox::OutboxEvent;
+pub use privacy::{PrivacyRequest, PrivacyRequestPart};
@@ -0,0 +1,51 @@
+use contracts::events::{UserEvent, UserEventMessage};
+use sqlx::FromRow;
+
+/// Event waiting in the outbox to be published
+#[derive(Debug, Clone, FromRow)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event_id: String,
+    pub topic: String,
+    pub message_key: String,
+    pub event_type: String,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+/// Event id and JSON message of a user event
+pub fn user_event_message(event: &UserEvent) -> serde_json::Result<(String, String)> {
+    let event_id = uuid::Uuid::new_v4().to_string();
+    let payload = serde_json::to_string(&UserEventMessage {
+        event_id: event_id.clone(),
+        event: event.clone(),
+    })?;
+    Ok((event_id, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use contracts::events::UserCreatedEvent;
+
+    #[test]
+    fn test_user_event_message_is_tagged() {
+        let event = UserEvent::Created(UserCreatedEvent {
+            user_id: 7,
+            email: "an@example.com".to_string(),
+            name: "An".to_string(),
+            email_verified: false,
+            timestamp: Utc::now(),
+        });
+        let (event_id, payload) = user_event_message(&event).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(json["type"], "user.created");
+        assert_eq!(json["event_id"], event_id.as_str());
+        assert_eq!(json["user_id"], 7);
+
+        let message: UserEventMessage = serde_json::from_str(&payload).unwrap();
+        assert!(matches!(message.event, UserEvent::Created(ref created) if created.email == "an@example.com"));
+    }
+}
@@ -0,0 +1,498 @@
+// WebAuthn (passkey) ceremonies: parsing what the authenticator returns and
+// checking it against the challenge, the relying party and the stored key
+use anyhow::{Result, anyhow};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use ciborium::value::Value;
+use rand::RngCore;
+use ring::{digest, signature};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// COSE algorithms offered to authenticators, preferred first
+pub const COSE_ES256: i64 = -7;
+pub const COSE_EDDSA: i64 = -8;
+pub const COSE_RS256: i64 = -257;
+pub const SUPPORTED_ALGORITHMS: &[i64] = &[COSE_ES256, COSE_EDDSA, COSE_RS256];
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+
+/// Registered passkey of a user
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Passkey {
+    pub id: i64,
+    #[serde(skip_serializing)]
+    pub user_id: i32,
+    pub credential_id: String, // base64url
+    #[serde(skip_serializing)]
+    pub public_key: Vec<u8>, // COSE_Key
+    #[serde(skip_serializing)]
+    pub sign_count: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Relying party: the site passkeys are scoped to
+#[derive(Debug, Clone)]
+pub struct RelyingParty {
+    pub id: String,           // domain, e.g. example.com
+    pub name: String,         // shown by the authenticator
+    pub origins: Vec<String>, // accepted origins of clientDataJSON
+}
+
+/// PublicKeyCredential as JSON-encoded by the browser, binary fields in base64url
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublicKeyCredential {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub response: AuthenticatorResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthenticatorResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: Option<String>, // registration only
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: Option<String>, // assertion only
+    pub signature: Option<String>,          // assertion only
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+/// Credential created by a registration ceremony, ready to be stored
+#[derive(Debug, Clone)]
+pub struct NewPasskey {
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+struct AuthenticatorData {
+    rp_id_hash: Vec<u8>,
+    flags: u8,
+    sign_count: u32,
+    credential: Option<(Vec<u8>, Vec<u8>)>, // credential id and COSE key, on registration
+}
+
+/// 32 random bytes, base64url, for one ceremony
+pub fn generate_challenge() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn decode_b64url(value: &str) -> Result<Vec<u8>> {
+    // Some clients keep the padding
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| anyhow!("Invalid passkey response: bad base64url"))
+}
+
+/// Challenge in the clientDataJSON, to find the pending ceremony before
+/// anything else is checked
+pub fn client_challenge(credential: &PublicKeyCredential) -> Result<String> {
+    let client_data = decode_b64url(&credential.response.client_data_json)?;
+    let client_data: ClientData = serde_json::from_slice(&client_data)
+        .map_err(|_| anyhow!("Invalid passkey response: bad clientDataJSON"))?;
+    Ok(client_data.challenge)
+}
+
+/// Steps 7-12 of the ceremonies: type, challenge and origin; returns the
+/// hash that authenticator signatures cover
+fn verify_client_data(rp: &RelyingParty, credential: &PublicKeyCredential, ceremony: &str, challenge: &str) -> Result<Vec<u8>> {
+    let raw = decode_b64url(&credential.response.client_data_json)?;
+    let client_data: ClientData = serde_json::from_slice(&raw)
+        .map_err(|_| anyhow!("Invalid passkey response: bad clientDataJSON"))?;
+
+    if credential.credential_type != "public-key" || client_data.ceremony != ceremony {
+        return Err(anyhow!("Invalid passkey response: wrong ceremony"));
+    }
+    if client_data.challenge.trim_end_matches('=') != challenge {
+        return Err(anyhow!("Invalid passkey response: challenge mismatch"));
+    }
+    if !rp.origins.iter().any(|origin| origin == &client_data.origin) {
+        return Err(anyhow!("Invalid passkey response: origin {} not allowed", client_data.origin));
+    }
+    Ok(digest::digest(&digest::SHA256, &raw).as_ref().to_vec())
+}
+
+fn parse_authenticator_data(bytes: &[u8]) -> Result<AuthenticatorData> {
+    let invalid = || anyhow!("Invalid passkey response: bad authenticator data");
+    if bytes.len() < 37 {
+        return Err(invalid());
+    }
+    let flags = bytes[32];
+    let sign_count = u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]);
+
+    let credential = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+        // aaguid (16), credential id length (2), credential id, COSE key
+        let rest = bytes.get(37 + 16..).ok_or_else(invalid)?;
+        let id_len = u16::from_be_bytes([*rest.first().ok_or_else(invalid)?, *rest.get(1).ok_or_else(invalid)?]) as usize;
+        let credential_id = rest.get(2..2 + id_len).ok_or_else(invalid)?.to_vec();
+        let key_bytes = rest.get(2 + id_len..).ok_or_else(invalid)?;
+        // The key is followed by extensions, if any: read one CBOR item
+        let mut reader = key_bytes;
+        let _: Value = ciborium::de::from_reader(&mut reader).map_err(|_| invalid())?;
+        let key_len = key_bytes.len() - reader.len();
+        Some((credential_id, key_bytes[..key_len].to_vec()))
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash: bytes[..32].to_vec(),
+        flags,
+        sign_count,
+        credential,
+    })
+}
+
+fn verify_rp_and_presence(rp: &RelyingParty, auth_data: &AuthenticatorData, require_user_verification: bool) -> Result<()> {
+    if auth_data.rp_id_hash != digest::digest(&digest::SHA256, rp.id.as_bytes()).as_ref() {
+        return Err(anyhow!("Invalid passkey response: wrong relying party"));
+    }
+    if auth_data.flags & FLAG_USER_PRESENT == 0 {
+        return Err(anyhow!("Invalid passkey response: user not present"));
+    }
+    if require_user_verification && auth_data.flags & FLAG_USER_VERIFIED == 0 {
+        return Err(anyhow!("Invalid passkey response: user not verified"));
+    }
+    Ok(())
+}
+
+/// Registration ceremony (WebAuthn §7.1). Accepts the `none` attestation we
+/// ask for and `packed` self-attestation; attestation is not a trust
+/// decision here.
+pub fn verify_registration(rp: &RelyingParty, credential: &PublicKeyCredential, challenge: &str) -> Result<NewPasskey> {
+    let client_data_hash = verify_client_data(rp, credential, "webauthn.create", challenge)?;
+
+    let attestation = credential.response.attestation_object.as_deref()
+        .ok_or_else(|| anyhow!("Invalid passkey response: missing attestationObject"))?;
+    let attestation: Value = ciborium::de::from_reader(decode_b64url(attestation)?.as_slice())
+        .map_err(|_| anyhow!("Invalid passkey response: bad attestationObject"))?;
+    let fmt = map_text(&attestation, "fmt").and_then(|v| v.as_text())
+        .ok_or_else(|| anyhow!("Invalid passkey response: bad attestationObject"))?;
+    let auth_data_bytes = map_text(&attestation, "authData").and_then(|v| v.as_bytes())
+        .ok_or_else(|| anyhow!("Invalid passkey response: bad attestationObject"))?;
+    let att_stmt = map_text(&attestation, "attStmt").and_then(|v| v.as_map())
+        .ok_or_else(|| anyhow!("Invalid passkey response: bad attestationObject"))?;
+
+    let auth_data = parse_authenticator_data(auth_data_bytes)?;
+    verify_rp_and_presence(rp, &auth_data, false)?;
+    let (credential_id, public_key) = auth_data.credential.clone()
+        .ok_or_else(|| anyhow!("Invalid passkey response: no credential"))?;
+    let key = CoseKey::parse(&public_key)?;
+    if URL_SAFE_NO_PAD.encode(&credential_id) != credential.id.trim_end_matches('=') {
+        return Err(anyhow!("Invalid passkey response: credential id mismatch"));
+    }
+
+    match fmt {
+        "none" if att_stmt.is_empty() => {}
+        "packed" => {
+            let statement = Value::Map(att_stmt.clone());
+            if map_text(&statement, "x5c").is_some() {
+                return Err(anyhow!("Unsupported attestation: request attestation \"none\""));
+            }
+            let alg = map_text(&statement, "alg").and_then(|v| v.as_integer()).map(i128::from);
+            let sig = map_text(&statement, "sig").and_then(|v| v.as_bytes())
+                .ok_or_else(|| anyhow!("Invalid passkey response: bad attestation statement"))?;
+            if alg != Some(key.alg as i128) {
+                return Err(anyhow!("Invalid passkey response: attestation algorithm mismatch"));
+            }
+            key.verify(&[auth_data_bytes.as_slice(), &client_data_hash].concat(), sig)?;
+        }
+        other => return Err(anyhow!("Unsupported attestation format {}", other)),
+    }
+
+    Ok(NewPasskey {
+        credential_id: URL_SAFE_NO_PAD.encode(credential_id),
+        public_key,
+        sign_count: auth_data.sign_count,
+    })
+}
+
+/// Authentication ceremony (WebAuthn §7.2) against a stored passkey.
+/// Returns the signature counter, for the caller to check with
+/// `sign_count_advanced`.
+pub fn verify_assertion(
+    rp: &RelyingParty,
+    credential: &PublicKeyCredential,
+    challenge: &str,
+    passkey: &Passkey,
+    require_user_verification: bool,
+) -> Result<u32> {
+    if credential.id.trim_end_matches('=') != passkey.credential_id {
+        return Err(anyhow!("Invalid passkey response: credential id mismatch"));
+    }
+    let client_data_hash = verify_client_data(rp, credential, "webauthn.get", challenge)?;
+
+    let auth_data_bytes = decode_b64url(
+        credential.response.authenticator_data.as_deref()
+            .ok_or_else(|| anyhow!("Invalid passkey response: missing authenticatorData"))?,
+    )?;
+    let signature = decode_b64url(
+        credential.response.signature.as_deref()
+            .ok_or_else(|| anyhow!("Invalid passkey response: missing signature"))?,
+    )?;
+
+    let auth_data = parse_authenticator_data(&auth_data_bytes)?;
+    verify_rp_and_presence(rp, &auth_data, require_user_verification)?;
+    CoseKey::parse(&passkey.public_key)?
+        .verify(&[auth_data_bytes.as_slice(), &client_data_hash].concat(), &signature)?;
+
+    Ok(auth_data.sign_count)
+}
+
+/// Authenticators that keep a counter must increase it on every use; a
+/// counter going backwards means the credential was cloned. Both zero is
+/// an authenticator without a counter, as synced passkeys are.
+pub fn sign_count_advanced(stored: i64, received: u32) -> bool {
+    (stored == 0 && received == 0) || received as i64 > stored
+}
+
+fn map_text<'a>(map: &'a Value, key: &str) -> Option<&'a Value> {
+    map.as_map()?
+        .iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn map_int(map: &[(Value, Value)], key: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(k, _)| k.as_integer().map(i128::from) == Some(key as i128))
+        .map(|(_, v)| v)
+}
+
+/// Credential public key, from its COSE_Key encoding
+struct CoseKey {
+    alg: i64,
+    key: Vec<u8>, // in the form ring's verifiers take
+}
+
+impl CoseKey {
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let invalid = || anyhow!("Invalid passkey response: bad credential public key");
+        let value: Value = ciborium::de::from_reader(bytes).map_err(|_| invalid())?;
+        let map = value.as_map().ok_or_else(invalid)?;
+        let int = |key: i64| map_int(map, key).and_then(|v| v.as_integer()).map(i128::from);
+        let bytes_at = |key: i64| map_int(map, key).and_then(|v| v.as_bytes()).ok_or_else(invalid);
+
+        // kty 1, alg 3, crv -1, x -2 / n -1, y -3 / e -2
+        let alg = int(3).ok_or_else(invalid)? as i64;
+        let key = match (int(1), alg) {
+            (Some(2), COSE_ES256) if int(-1) == Some(1) => {
+                // Uncompressed P-256 point
+                [&[0x04][..], bytes_at(-2)?, bytes_at(-3)?].concat()
+            }
+            (Some(1), COSE_EDDSA) if int(-1) == Some(6) => bytes_at(-2)?.clone(),
+            (Some(3), COSE_RS256) => {
+                let n = bytes_at(-1)?;
+                let e = bytes_at(-2)?;
+                // n and e, length-prefixed, split again in verify
+                [&(n.len() as u32).to_be_bytes()[..], n, e].concat()
+            }
+            _ => return Err(anyhow!("Unsupported passkey algorithm {}", alg)),
+        };
+        Ok(Self { alg, key })
+    }
+
+    fn verify(&self, message: &[u8], sig: &[u8]) -> Result<()> {
+        let verified = match self.alg {
+            COSE_ES256 => signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, &self.key)
+                .verify(message, sig)
+                .is_ok(),
+            COSE_EDDSA => signature::UnparsedPublicKey::new(&signature::ED25519, &self.key)
+                .verify(message, sig)
+                .is_ok(),
+            COSE_RS256 => {
+                let n_len = u32::from_be_bytes([self.key[0], self.key[1], self.key[2], self.key[3]]) as usize;
+                let (n, e) = self.key[4..].split_at(n_len);
+                signature::RsaPublicKeyComponents { n, e }
+                    .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, sig)
+                    .is_ok()
+            }
+            _ => false,
+        };
+        if verified {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid passkey signature"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    const ORIGIN: &str = "https://pay.example.com";
+
+    fn rp() -> RelyingParty {
+        RelyingParty {
+            id: "pay.example.com".to_string(),
+            name: "RushTech".to_string(),
+            origins: vec![ORIGIN.to_string()],
+        }
+    }
+
+    fn cbor(value: &Value) -> Vec<u8> {
+        let mut out = Vec::new();
+        ciborium::ser::into_writer(value, &mut out).unwrap();
+        out
+    }
+
+    /// Software authenticator with an ES256 key and a signature counter
+    struct SoftwareAuthenticator {
+        key: EcdsaKeyPair,
+        credential_id: Vec<u8>,
+        counter: u32,
+        rng: SystemRandom,
+    }
+
+    impl SoftwareAuthenticator {
+        fn new() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+            let mut credential_id = vec![0u8; 16];
+            rand::thread_rng().fill_bytes(&mut credential_id);
+            Self { key, credential_id, counter: 0, rng }
+        }
+
+        fn cose_key(&self) -> Vec<u8> {
+            let point = self.key.public_key().as_ref();
+            cbor(&Value::Map(vec![
+                (Value::from(1), Value::from(2)),
+                (Value::from(3), Value::from(COSE_ES256)),
+                (Value::from(-1), Value::from(1)),
+                (Value::from(-2), Value::Bytes(point[1..33].to_vec())),
+                (Value::from(-3), Value::Bytes(point[33..].to_vec())),
+            ]))
+        }
+
+        fn client_data(ceremony: &str, challenge: &str, origin: &str) -> String {
+            let json = serde_json::json!({ "type": ceremony, "challenge": challenge, "origin": origin });
+            URL_SAFE_NO_PAD.encode(json.to_string())
+        }
+
+        fn auth_data(&mut self, rp_id: &str, flags: u8, attested: bool) -> Vec<u8> {
+            self.counter += 1;
+            let mut data = digest::digest(&digest::SHA256, rp_id.as_bytes()).as_ref().to_vec();
+            data.push(flags | if attested { FLAG_ATTESTED_CREDENTIAL } else { 0 });
+            data.extend_from_slice(&self.counter.to_be_bytes());
+            if attested {
+                data.extend_from_slice(&[0u8; 16]);
+                data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                data.extend_from_slice(&self.cose_key());
+            }
+            data
+        }
+
+        fn create(&mut self, challenge: &str, origin: &str) -> PublicKeyCredential {
+            let auth_data = self.auth_data("pay.example.com", FLAG_USER_PRESENT | FLAG_USER_VERIFIED, true);
+            let attestation = cbor(&Value::Map(vec![
+                (Value::from("fmt"), Value::from("none")),
+                (Value::from("attStmt"), Value::Map(vec![])),
+                (Value::from("authData"), Value::Bytes(auth_data)),
+            ]));
+            PublicKeyCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                credential_type: "public-key".to_string(),
+                response: AuthenticatorResponse {
+                    client_data_json: Self::client_data("webauthn.create", challenge, origin),
+                    attestation_object: Some(URL_SAFE_NO_PAD.encode(attestation)),
+                    authenticator_data: None,
+                    signature: None,
+                },
+            }
+        }
+
+        fn get(&mut self, challenge: &str, flags: u8) -> PublicKeyCredential {
+            let auth_data = self.auth_data("pay.example.com", flags, false);
+            let client_data = Self::client_data("webauthn.get", challenge, ORIGIN);
+            let client_data_hash = digest::digest(&digest::SHA256, &URL_SAFE_NO_PAD.decode(&client_data).unwrap());
+            let signature = self.key
+                .sign(&self.rng, &[auth_data.as_slice(), client_data_hash.as_ref()].concat())
+                .unwrap();
+            PublicKeyCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                credential_type: "public-key".to_string(),
+                response: AuthenticatorResponse {
+                    client_data_json: client_data,
+                    attestation_object: None,
+                    authenticator_data: Some(URL_SAFE_NO_PAD.encode(auth_data)),
+                    signature: Some(URL_SAFE_NO_PAD.encode(signature.as_ref())),
+                },
+            }
+        }
+    }
+
+    fn stored(new: NewPasskey) -> Passkey {
+        Passkey {
+            id: 1,
+            user_id: 7,
+            credential_id: new.credential_id,
+            public_key: new.public_key,
+            sign_count: new.sign_count as i64,
+            name: "Test key".to_string(),
+            created_at: Utc::now(),
+            last_used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_registration_and_assertion_with_software_authenticator() {
+        let mut authenticator = SoftwareAuthenticator::new();
+        let challenge = generate_challenge();
+        let created = authenticator.create(&challenge, ORIGIN);
+        assert_eq!(client_challenge(&created).unwrap(), challenge);
+
+        let new = verify_registration(&rp(), &created, &challenge).unwrap();
+        assert_eq!(new.sign_count, 1);
+        let passkey = stored(new);
+
+        let challenge = generate_challenge();
+        let assertion = authenticator.get(&challenge, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+        let sign_count = verify_assertion(&rp(), &assertion, &challenge, &passkey, true).unwrap();
+        assert!(sign_count_advanced(passkey.sign_count, sign_count));
+        assert!(!sign_count_advanced(5, 5));
+        assert!(sign_count_advanced(0, 0));
+    }
+
+    #[test]
+    fn test_rejects_tampered_or_misdirected_responses() {
+        let mut authenticator = SoftwareAuthenticator::new();
+        let challenge = generate_challenge();
+        assert!(verify_registration(&rp(), &authenticator.create(&challenge, "https://evil.example"), &challenge).is_err());
+        assert!(verify_registration(&rp(), &authenticator.create(&challenge, ORIGIN), &generate_challenge()).is_err());
+        let passkey = stored(verify_registration(&rp(), &authenticator.create(&challenge, ORIGIN), &challenge).unwrap());
+
+        // Another challenge, a missing user verification and a forged signature
+        let challenge = generate_challenge();
+        let assertion = authenticator.get(&challenge, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+        assert!(verify_assertion(&rp(), &assertion, &generate_challenge(), &passkey, false).is_err());
+        let presence_only = authenticator.get(&challenge, FLAG_USER_PRESENT);
+        assert!(verify_assertion(&rp(), &presence_only, &challenge, &passkey, true).is_err());
+        assert!(verify_assertion(&rp(), &presence_only, &challenge, &passkey, false).is_ok());
+
+        let mut forged = authenticator.get(&challenge, FLAG_USER_PRESENT);
+        let other = SoftwareAuthenticator::new().get(&challenge, FLAG_USER_PRESENT);
+        forged.response.signature = other.response.signature;
+        assert!(verify_assertion(&rp(), &forged, &challenge, &passkey, false).is_err());
+    }
+}
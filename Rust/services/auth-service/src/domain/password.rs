@@ -0,0 +1,182 @@
+use anyhow::{Result, anyhow};
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+// Bcrypt only reads the first 72 bytes, Argon2 has no such limit; this
+// bounds the hashing work an attacker can ask for
+const MAX_PASSWORD_LEN: usize = 128;
+
+/// Stored for accounts created through an external identity provider; no
+/// password matches it until the user sets one with a reset
+pub const UNUSABLE_PASSWORD: &str = "!";
+
+fn env_u32(name: &str, default: u32) -> u32 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Result of checking a password against a stored hash
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PasswordMatch {
+    No,
+    Yes,
+    /// Right password, but the hash is bcrypt or has outdated Argon2 parameters
+    YesRehash,
+}
+
+/// Hashes new passwords with Argon2id and verifies both Argon2 PHC strings
+/// and legacy bcrypt hashes
+#[derive(Debug, Clone)]
+pub struct PasswordHasher {
+    params: Params,
+}
+
+impl PasswordHasher {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self> {
+        let params = Params::new(memory_kib, iterations, parallelism, None)
+            .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+        Ok(Self { params })
+    }
+
+    /// ARGON2_MEMORY_KIB, ARGON2_ITERATIONS and ARGON2_PARALLELISM, defaulting
+    /// to the OWASP recommendation of 19 MiB, 2 passes, 1 lane
+    pub fn from_env() -> Self {
+        Self::new(
+            env_u32("ARGON2_MEMORY_KIB", 19 * 1024),
+            env_u32("ARGON2_ITERATIONS", 2),
+            env_u32("ARGON2_PARALLELISM", 1),
+        )
+        .expect("Invalid ARGON2_* settings")
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String> {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt).map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+        let hash = self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+        Ok(hash.to_string())
+    }
+
+    pub fn verify(&self, password: &str, stored: &str) -> Result<PasswordMatch> {
+        if stored.starts_with("$2") {
+            let matches = bcrypt::verify(password, stored)
+                .map_err(|e| anyhow!("Password verification failed: {}", e))?;
+            return Ok(if matches { PasswordMatch::YesRehash } else { PasswordMatch::No });
+        }
+
+        let parsed = PasswordHash::new(stored).map_err(|e| anyhow!("Password verification failed: {}", e))?;
+        // The hash carries its own algorithm and parameters
+        if Argon2::default().verify_password(password.as_bytes(), &parsed).is_err() {
+            return Ok(PasswordMatch::No);
+        }
+        Ok(if self.is_current(&parsed) { PasswordMatch::Yes } else { PasswordMatch::YesRehash })
+    }
+
+    fn is_current(&self, parsed: &PasswordHash) -> bool {
+        let params = match Params::try_from(parsed) {
+            Ok(params) => params,
+            Err(_) => return false,
+        };
+        parsed.algorithm == Algorithm::Argon2id.ident()
+            && parsed.version == Some(Version::V0x13.into())
+            && params.m_cost() == self.params.m_cost()
+            && params.t_cost() == self.params.t_cost()
+            && params.p_cost() == self.params.p_cost()
+    }
+}
+
+fn common_passwords() -> &'static HashSet<&'static str> {
+    static LIST: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    LIST.get_or_init(|| {
+        include_str!("common_passwords.txt")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
+    })
+}
+
+/// Rules for new passwords, on register and reset
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+}
+
+impl PasswordPolicy {
+    /// PASSWORD_MIN_LENGTH, default 8
+    pub fn from_env() -> Self {
+        Self { min_length: env_u32("PASSWORD_MIN_LENGTH", 8) as usize }
+    }
+
+    pub fn check(&self, password: &str, email: &str) -> Result<()> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(anyhow!("Password must be at least {} characters", self.min_length));
+        }
+        if length > MAX_PASSWORD_LEN {
+            return Err(anyhow!("Password must be at most {} characters", MAX_PASSWORD_LEN));
+        }
+
+        let lowered = password.to_lowercase();
+        if common_passwords().contains(lowered.as_str()) {
+            return Err(anyhow!("Password is too common, choose another one"));
+        }
+        let local_part = email.split('@').next().unwrap_or_default().to_lowercase();
+        if local_part.len() >= 4 && lowered.contains(&local_part) {
+            return Err(anyhow!("Password must not contain your email address"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small parameters so the tests stay fast
+    fn hasher() -> PasswordHasher {
+        PasswordHasher::new(1024, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_argon2_hashes_verify_and_upgrade() {
+        let hash = hasher().hash("correct horse battery").unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert_eq!(hasher().verify("correct horse battery", &hash).unwrap(), PasswordMatch::Yes);
+        assert_eq!(hasher().verify("wrong horse battery", &hash).unwrap(), PasswordMatch::No);
+
+        // Stronger settings make existing hashes outdated
+        let stronger = PasswordHasher::new(2048, 1, 1).unwrap();
+        assert_eq!(stronger.verify("correct horse battery", &hash).unwrap(), PasswordMatch::YesRehash);
+    }
+
+    #[test]
+    fn test_legacy_bcrypt_hashes_verify_and_need_rehash() {
+        let legacy = bcrypt::hash("correct horse battery", 4).unwrap();
+        assert_eq!(hasher().verify("correct horse battery", &legacy).unwrap(), PasswordMatch::YesRehash);
+        assert_eq!(hasher().verify("wrong horse battery", &legacy).unwrap(), PasswordMatch::No);
+        assert!(hasher().verify("anything", "not-a-hash").is_err());
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let policy = PasswordPolicy { min_length: 8 };
+        assert!(policy.check("short", "an@example.com").is_err());
+        assert!(policy.check(&"x".repeat(MAX_PASSWORD_LEN + 1), "an@example.com").is_err());
+        assert!(policy.check("Password123", "an@example.com").is_err());
+        assert!(policy.check("QWERTYUIOP", "an@example.com").is_err());
+        assert!(policy.check("nguyenvan2024!", "nguyenvan@example.com").is_err());
+        assert!(policy.check("plum-orbit-cactus", "an@example.com").is_ok());
+    }
+}
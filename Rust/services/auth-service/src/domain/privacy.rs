@@ -0,0 +1,120 @@
+// GDPR requests: a copy of the user's data gathered from every service, or
+// the erasure of it
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::io::Write;
+use std::path::Path;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_FAILED: &str = "failed";
+
+/// Services that hold user data besides auth-service, PRIVACY_SERVICES
+/// (comma-separated) to override
+pub fn services_from_env() -> Vec<String> {
+    std::env::var("PRIVACY_SERVICES")
+        .unwrap_or_else(|_| "core-service,gateway,chat-service".to_string())
+        .split(',')
+        .map(|service| service.trim().to_string())
+        .filter(|service| !service.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PrivacyRequest {
+    pub id: i64,
+    #[serde(skip_serializing)]
+    pub user_id: i32,
+    pub kind: String,
+    pub status: String,
+    #[serde(skip_serializing)]
+    pub file_path: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    #[sqlx(skip)]
+    pub parts: Vec<PrivacyRequestPart>,
+}
+
+/// Progress of one service on a request
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PrivacyRequestPart {
+    pub service: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Where a request stands once its parts are in: None while a service has
+/// not reported, the first error if one failed
+pub fn outcome(parts: &[PrivacyRequestPart]) -> Option<Result<(), String>> {
+    if let Some(failed) = parts.iter().find(|part| part.status == STATUS_FAILED) {
+        return Some(Err(format!(
+            "{}: {}",
+            failed.service,
+            failed.error.as_deref().unwrap_or("failed")
+        )));
+    }
+    if parts.iter().any(|part| part.status == STATUS_PENDING) {
+        return None;
+    }
+    Some(Ok(()))
+}
+
+/// Write the export archive, one JSON file per service
+pub fn write_archive(path: &Path, files: &[(String, String)]) -> Result<()> {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in files {
+        zip.start_file(format!("{}.json", name), options)?;
+        zip.write_all(content.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn part(service: &str, status: &str) -> PrivacyRequestPart {
+        PrivacyRequestPart {
+            service: service.to_string(),
+            status: status.to_string(),
+            error: (status == STATUS_FAILED).then(|| "database unavailable".to_string()),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_outcome_waits_for_every_service() {
+        assert!(outcome(&[part("core-service", STATUS_COMPLETED), part("gateway", STATUS_PENDING)]).is_none());
+        assert_eq!(outcome(&[part("core-service", STATUS_COMPLETED), part("gateway", STATUS_COMPLETED)]), Some(Ok(())));
+        assert_eq!(
+            outcome(&[part("gateway", STATUS_PENDING), part("chat-service", STATUS_FAILED)]),
+            Some(Err("chat-service: database unavailable".to_string()))
+        );
+        assert_eq!(outcome(&[]), Some(Ok(())));
+    }
+
+    #[test]
+    fn test_archive_has_a_file_per_service() {
+        let path = std::env::temp_dir().join(format!("privacy-test-{}.zip", uuid::Uuid::new_v4()));
+        let files = vec![
+            ("auth".to_string(), r#"{"email":"an@example.com"}"#.to_string()),
+            ("gateway".to_string(), r#"{"payments":[]}"#.to_string()),
+        ];
+        write_archive(&path, &files).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut content = String::new();
+        archive.by_name("auth.json").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, r#"{"email":"an@example.com"}"#);
+        std::fs::remove_file(path).unwrap();
+    }
+}
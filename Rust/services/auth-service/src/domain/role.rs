@@ -0,0 +1,60 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Role {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoleWithPermissions {
+    #[serde(flatten)]
+    pub role: Role,
+    pub permissions: Vec<String>,
+}
+
+/// What a user may do, as embedded in their access tokens
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Grants {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+impl Grants {
+    /// Build from (role, permission) rows, permission NULL for roles without any
+    pub fn from_rows(rows: Vec<(String, Option<String>)>) -> Self {
+        let mut grants = Grants::default();
+        for (role, permission) in rows {
+            if !grants.roles.contains(&role) {
+                grants.roles.push(role);
+            }
+            if let Some(permission) = permission {
+                if !grants.permissions.contains(&permission) {
+                    grants.permissions.push(permission);
+                }
+            }
+        }
+        grants.roles.sort();
+        grants.permissions.sort();
+        grants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grants_merge_roles_and_deduplicate_permissions() {
+        let grants = Grants::from_rows(vec![
+            ("user".to_string(), Some("payments:refund".to_string())),
+            ("support".to_string(), Some("users:read".to_string())),
+            ("support".to_string(), Some("payments:refund".to_string())),
+            ("guest".to_string(), None),
+        ]);
+        assert_eq!(grants.roles, vec!["guest", "support", "user"]);
+        assert_eq!(grants.permissions, vec!["payments:refund", "users:read"]);
+    }
+}
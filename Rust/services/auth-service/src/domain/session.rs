@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::net::IpAddr;
+
+const MAX_USER_AGENT_LEN: usize = 255;
+
+/// A login, from the first token pair until it is revoked or left to
+/// expire. Rotated refresh tokens stay in the session through `family_id`.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct UserSession {
+    pub id: i64,
+    #[serde(skip_serializing)]
+    pub family_id: String, // refresh token family, also the sid claim of its access tokens
+    pub device: String,
+    pub user_agent: Option<String>,
+    pub ip: String, // of the last login or refresh
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    #[serde(skip_serializing)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Session as listed to its user
+#[derive(Debug, Serialize)]
+pub struct SessionView {
+    #[serde(flatten)]
+    pub session: UserSession,
+    pub current: bool, // the session of the token making the request
+}
+
+/// Where a request comes from, recorded on the session
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub ip: String,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn new(ip: String, user_agent: Option<&str>) -> Self {
+        let user_agent = user_agent
+            .map(|ua| ua.trim())
+            .filter(|ua| !ua.is_empty())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        Self { ip, user_agent }
+    }
+
+    pub fn device(&self) -> String {
+        device_label(self.user_agent.as_deref())
+    }
+}
+
+/// Address of the client. X-Real-IP and X-Forwarded-For are only believed
+/// from a trusted proxy: then X-Real-IP, else the rightmost X-Forwarded-For
+/// entry that is not itself a trusted proxy. Headers that do not hold an IP
+/// are ignored, so the result fits the VARCHAR(45) columns.
+pub fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    real_ip: Option<&str>,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpAddr],
+) -> String {
+    let peer = match peer {
+        Some(peer) => peer,
+        None => return "unknown".to_string(),
+    };
+    if !trusted_proxies.contains(&peer) {
+        return peer.to_string();
+    }
+    if let Some(ip) = real_ip.and_then(|s| s.trim().parse::<IpAddr>().ok()) {
+        return ip.to_string();
+    }
+    // Entries are appended by each proxy, only the ones added by trusted
+    // proxies can be believed
+    for entry in forwarded_for.unwrap_or("").split(',').rev() {
+        match entry.trim().parse::<IpAddr>() {
+            Ok(ip) if trusted_proxies.contains(&ip) => continue,
+            Ok(ip) => return ip.to_string(),
+            Err(_) => break,
+        }
+    }
+    peer.to_string()
+}
+
+/// Short label like "Chrome on Windows" for a User-Agent header. Order
+/// matters: Edge and Opera also claim Chrome, Chrome also claims Safari.
+pub fn device_label(user_agent: Option<&str>) -> String {
+    let ua = match user_agent {
+        Some(ua) => ua,
+        None => return "Unknown device".to_string(),
+    };
+
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ]
+    .iter()
+    .find(|(token, _)| ua.contains(token))
+    .map(|(_, name)| *name);
+
+    let os = [
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ]
+    .iter()
+    .find(|(token, _)| ua.contains(token))
+    .map(|(_, name)| *name);
+
+    match (browser, os) {
+        (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        // API clients: the product token, e.g. "okhttp/4.9.0" -> "okhttp"
+        (None, None) => ua.split(['/', ' ']).next().filter(|s| !s.is_empty()).unwrap_or("Unknown device").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_label() {
+        let chrome = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+        let edge = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0";
+        let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0";
+
+        assert_eq!(device_label(Some(chrome)), "Chrome on Windows");
+        assert_eq!(device_label(Some(edge)), "Edge on Windows");
+        assert_eq!(device_label(Some(iphone)), "Safari on iOS");
+        assert_eq!(device_label(Some(firefox)), "Firefox on Linux");
+        assert_eq!(device_label(Some("okhttp/4.9.0")), "okhttp");
+        assert_eq!(device_label(None), "Unknown device");
+        assert_eq!(ClientInfo::new("1.2.3.4".to_string(), Some("  ")).device(), "Unknown device");
+    }
+
+    #[test]
+    fn test_forwarding_headers_need_a_trusted_proxy() {
+        let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let trusted = [proxy];
+
+        // Straight from the client, its headers are ignored
+        assert_eq!(resolve_client_ip(Some(client), Some("1.1.1.1"), Some("1.1.1.1"), &trusted), "203.0.113.7");
+        assert_eq!(resolve_client_ip(Some(client), Some("1.1.1.1"), None, &[]), "203.0.113.7");
+
+        assert_eq!(resolve_client_ip(Some(proxy), Some("198.51.100.1"), None, &trusted), "198.51.100.1");
+        // The client can prepend anything, the proxy appends what it saw
+        assert_eq!(resolve_client_ip(Some(proxy), None, Some("1.1.1.1, 203.0.113.7, 10.0.0.2"), &trusted), "203.0.113.7");
+        assert_eq!(resolve_client_ip(None, None, None, &trusted), "unknown");
+    }
+
+    #[test]
+    fn test_client_ip_must_be_an_address() {
+        let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+        let long = "x".repeat(100);
+
+        assert_eq!(resolve_client_ip(Some(proxy), Some(&long), Some(&long), &[proxy]), "10.0.0.2");
+        assert_eq!(resolve_client_ip(Some(proxy), Some("not an ip"), Some("garbage, 203.0.113.7"), &[proxy]), "203.0.113.7");
+        assert_eq!(resolve_client_ip(Some(proxy), None, Some("203.0.113.7, garbage"), &[proxy]), "10.0.0.2");
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, KeyAlgorithm, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use sqlx::FromRow;
+
+/// A stored token signing key. Keys are published in the JWKS from creation,
+/// used for signing from `activates_at`, and dropped from the JWKS at
+/// `expires_at` once every token they signed has expired.
+#[derive(Debug, Clone, FromRow)]
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: String,       // 'RS256' or 'EdDSA'
+    pub private_key_pem: String, // PKCS#8
+    pub public_jwk: String,      // JSON
+    pub activates_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A freshly generated key pair, not stored yet
+pub struct NewSigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub private_key_pem: String,
+    pub public_jwk: Jwk,
+}
+
+pub fn parse_algorithm(value: &str) -> Result<Algorithm> {
+    match value {
+        "RS256" => Ok(Algorithm::RS256),
+        "EdDSA" => Ok(Algorithm::EdDSA),
+        "HS256" => Ok(Algorithm::HS256),
+        other => Err(anyhow!("Unsupported signing algorithm: {}", other)),
+    }
+}
+
+pub fn generate_signing_key(algorithm: Algorithm) -> Result<NewSigningKey> {
+    let kid = uuid::Uuid::new_v4().to_string();
+    let (private_key_pem, key_algorithm, parameters) = match algorithm {
+        Algorithm::EdDSA => {
+            use ring::signature::{Ed25519KeyPair, KeyPair};
+
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+                .map_err(|_| anyhow!("Failed to generate Ed25519 key"))?;
+            let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+                .map_err(|_| anyhow!("Failed to load Ed25519 key"))?;
+            let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+            });
+            let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref().to_vec()));
+            (pem, KeyAlgorithm::EdDSA, parameters)
+        }
+        Algorithm::RS256 => {
+            use rsa::pkcs8::{EncodePrivateKey, LineEnding};
+            use rsa::traits::PublicKeyParts;
+
+            let private_key = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 2048)
+                .map_err(|e| anyhow!("Failed to generate RSA key: {}", e))?;
+            let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be()),
+            });
+            let pem = private_key
+                .to_pkcs8_pem(LineEnding::LF)
+                .map_err(|e| anyhow!("Failed to encode RSA key: {}", e))?
+                .to_string();
+            (pem, KeyAlgorithm::RS256, parameters)
+        }
+        other => return Err(anyhow!("Cannot generate a key pair for {:?}", other)),
+    };
+
+    let public_jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.clone()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+
+    Ok(NewSigningKey {
+        kid,
+        algorithm,
+        private_key_pem,
+        public_jwk,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::jwk::JwkSet;
+    use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+
+    #[test]
+    fn test_generated_keys_sign_and_verify_through_jwk() {
+        for algorithm in [Algorithm::EdDSA, Algorithm::RS256] {
+            let key = generate_signing_key(algorithm).unwrap();
+            let encoding_key = match algorithm {
+                Algorithm::EdDSA => EncodingKey::from_ed_pem(key.private_key_pem.as_bytes()).unwrap(),
+                _ => EncodingKey::from_rsa_pem(key.private_key_pem.as_bytes()).unwrap(),
+            };
+
+            let mut header = Header::new(algorithm);
+            header.kid = Some(key.kid.clone());
+            let claims = serde_json::json!({ "sub": "a@example.com", "exp": Utc::now().timestamp() + 60 });
+            let token = encode(&header, &claims, &encoding_key).unwrap();
+
+            // Round-trip the JWK through JSON, as verifiers receive it
+            let jwks: JwkSet = serde_json::from_value(serde_json::json!({ "keys": [key.public_jwk] })).unwrap();
+            let jwk = jwks.find(&key.kid).unwrap();
+            let decoded = decode::<serde_json::Value>(&token, &DecodingKey::from_jwk(jwk).unwrap(), &Validation::new(algorithm));
+            assert!(decoded.is_ok(), "{:?} token did not verify", algorithm);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+
+use super::mfa::{AMR_FEDERATED, AMR_PASSKEY, AMR_PASSWORD};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+
+/// A stored refresh token. Tokens issued by rotation share the family of the
+/// login that started the session.
+#[derive(Debug, Clone, FromRow)]
+pub struct RefreshToken {
+    pub id: i64,
+    pub user_id: i32,
+    pub family_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,    // set once the token has been rotated
+    pub revoked_at: Option<DateTime<Utc>>, // logout or reuse detection
+    pub amr: Option<String>,                // comma-separated methods of the login
+    pub auth_time: Option<DateTime<Utc>>,
+}
+
+impl RefreshToken {
+    pub fn authentication(&self) -> Authentication {
+        match (&self.amr, self.auth_time) {
+            (Some(amr), Some(auth_time)) => Authentication {
+                amr: amr.split(',').map(|m| m.to_string()).collect(),
+                auth_time,
+            },
+            // Sessions from before MFA existed were password logins
+            _ => Authentication::password(),
+        }
+    }
+}
+
+/// How and when the user logged in, carried into every token of the session
+#[derive(Debug, Clone)]
+pub struct Authentication {
+    pub amr: Vec<String>,
+    pub auth_time: DateTime<Utc>,
+}
+
+impl Authentication {
+    pub fn password() -> Self {
+        Self {
+            amr: vec![AMR_PASSWORD.to_string()],
+            auth_time: Utc::now(),
+        }
+    }
+
+    pub fn with_second_factor(method: &str) -> Self {
+        Self::with_factors(AMR_PASSWORD, method)
+    }
+
+    /// `first_factor` is what the login challenge was created after
+    pub fn with_factors(first_factor: &str, method: &str) -> Self {
+        Self {
+            amr: vec![first_factor.to_string(), method.to_string()],
+            auth_time: Utc::now(),
+        }
+    }
+
+    /// Passwordless login with a user-verified passkey
+    pub fn passkey() -> Self {
+        Self {
+            amr: vec![AMR_PASSKEY.to_string()],
+            auth_time: Utc::now(),
+        }
+    }
+
+    /// Login at an external OpenID Connect provider
+    pub fn federated() -> Self {
+        Self {
+            amr: vec![AMR_FEDERATED.to_string()],
+            auth_time: Utc::now(),
+        }
+    }
+}
+
+/// Single-use token mailed to the user
+#[derive(Debug, Clone, FromRow)]
+pub struct UserToken {
+    pub id: i64,
+    pub user_id: i32,
+    pub new_email: Option<String>, // email change only
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl UserToken {
+    /// Neither used nor expired at `now`
+    pub fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenPurpose {
+    EmailVerification,
+    PasswordReset,
+    EmailChange,
+}
+
+impl TokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::EmailVerification => "email_verification",
+            TokenPurpose::PasswordReset => "password_reset",
+            TokenPurpose::EmailChange => "email_change",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub token: String, // access JWT
+    pub refresh_token: String,
+    pub expires_in: i64, // access token lifetime in seconds
+}
+
+/// New opaque token (refresh, email verification, password reset):
+/// 32 random bytes, hex encoded
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Only the SHA-256 of an opaque token is stored
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_refresh_tokens_are_random_and_hashed() {
+        let a = generate_opaque_token();
+        let b = generate_opaque_token();
+        assert_eq!(a.len(), 64);
+        assert_ne!(a, b);
+        assert_eq!(hash_token(&a), hash_token(&a));
+        assert_ne!(hash_token(&a), a);
+    }
+
+    fn user_token(expires_at: DateTime<Utc>) -> UserToken {
+        UserToken {
+            id: 1,
+            user_id: 42,
+            new_email: None,
+            expires_at,
+            used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_user_token_expires() {
+        let now = Utc::now();
+        assert!(user_token(now + Duration::minutes(30)).is_usable(now));
+        assert!(!user_token(now).is_usable(now));
+        assert!(!user_token(now - Duration::seconds(1)).is_usable(now));
+    }
+
+    #[test]
+    fn test_user_token_is_single_use() {
+        let now = Utc::now();
+        let mut token = user_token(now + Duration::minutes(30));
+        assert!(token.is_usable(now));
+
+        token.used_at = Some(now);
+        assert!(!token.is_usable(now));
+    }
+
+    #[test]
+    fn test_user_token_stored_as_hash() {
+        let token = generate_opaque_token();
+        let stored = hash_token(&token);
+        assert_eq!(stored.len(), 64);
+        assert!(!stored.contains(&token));
+        // Looked up by hashing what the user presents
+        assert_eq!(hash_token(&token), stored);
+        assert_ne!(hash_token(&generate_opaque_token()), stored);
+        assert_ne!(hash_token(&stored), stored);
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -8,6 +9,13 @@ pub struct User {
     pub email: String,
     #[serde(skip_serializing)]
     pub password: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+impl User {
+    pub fn is_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -15,14 +23,45 @@ pub struct UserPublic {
     pub id: i32,
     pub name: String,
     pub email: String,
+    pub email_verified: bool,
 }
 
 impl From<User> for UserPublic {
     fn from(user: User) -> Self {
         Self {
+            email_verified: user.is_verified(),
             id: user.id,
             name: user.name,
             email: user.email,
         }
     }
 }
+
+/// Lowercased address if it looks deliverable: one @, something on both
+/// sides, a dot in the domain, no spaces
+pub fn normalize_email(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    let (local, domain) = email.split_once('@')?;
+    let valid = !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !domain.contains('@')
+        && !email.chars().any(char::is_whitespace)
+        && email.len() <= 255;
+    valid.then_some(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(normalize_email(" An@Example.com "), Some("an@example.com".to_string()));
+        assert_eq!(normalize_email("an@example"), None);
+        assert_eq!(normalize_email("@example.com"), None);
+        assert_eq!(normalize_email("an@@example.com"), None);
+        assert_eq!(normalize_email("a n@example.com"), None);
+    }
+}
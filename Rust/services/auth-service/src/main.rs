@@ -8,10 +8,13 @@ use actix_web::{web, App, HttpServer};
 use actix_cors::Cors;
 use common::config::AppConfig;
 use common::cache::RedisCache;
-use repo::UserRepository;
-use service::AuthService;
+use authz::TokenRevocation;
+use messaging::kafka_producer::KafkaProducer;
+use repo::{LoginAttemptRepository, MfaRepository, ConsentRepository, IdentityRepository, OAuthClientRepository, OutboxRepository, PasskeyRepository, PrivacyRepository, RefreshTokenRepository, RoleRepository, SessionRepository, SigningKeyRepository, UserRepository, UserTokenRepository};
+use service::{AccountService, ApiKeyService, AuthService, FederationService, KeyService, LockoutService, MfaService, OAuthClientService, OidcService, OutboxPublisher, PasskeyService, PrivacyService, RoleService, SessionService};
 use middleware::rate_limit::RateLimiter;
-use middleware::api_key::ApiKeyAuth;
+use middleware::{DISCOVERY_PATH, JWKS_PATH, TOKEN_PATH, USERINFO_PATH};
+use apikeys::{scopes, ApiKeyAuth, ApiKeyStore, ApiKeyVerifier};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -28,25 +31,133 @@ async fn main() -> std::io::Result<()> {
     let redis_cache = RedisCache::new(&redis_url)
         .expect("Failed to connect to Redis");
     
-    // Load API keys from environment (comma-separated)
-    let api_keys: Vec<String> = std::env::var("AUTH_API_KEYS")
-        .unwrap_or_else(|_| {
-            tracing::warn!("AUTH_API_KEYS not set, using default key (INSECURE for production!)");
-            "dev-key-12345".to_string()
-        })
+    // Kafka producer for account emails and user events
+    let kafka_brokers = std::env::var("KAFKA_BROKERS")
+        .unwrap_or_else(|_| "localhost:9092".to_string());
+    let producer = KafkaProducer::new(&kafka_brokers)
+        .expect("Failed to create Kafka producer");
+
+    // API keys live in the database; AUTH_API_KEYS (comma-separated) is only imported
+    let api_key_verifier = ApiKeyVerifier::new(ApiKeyStore::new(pool.clone()));
+    let api_key_service = ApiKeyService::new(ApiKeyStore::new(pool.clone()), api_key_verifier.clone());
+    let legacy_keys: Vec<String> = std::env::var("AUTH_API_KEYS")
+        .unwrap_or_default()
         .split(',')
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
         .collect();
-    
-    tracing::info!("🔑 Loaded {} API key(s)", api_keys.len());
-    
+    if !legacy_keys.is_empty() {
+        let imported = api_key_service.import_legacy_keys(&legacy_keys).await
+            .expect("Failed to import AUTH_API_KEYS");
+        tracing::warn!(
+            "🔑 AUTH_API_KEYS is deprecated: {} key(s) imported, manage keys through /api/v1/api-keys instead",
+            imported
+        );
+    }
+
     // Initialize layers
     let user_repo = UserRepository::new(pool.clone());
-    let auth_service = AuthService::new(user_repo);
+    let refresh_token_repo = RefreshTokenRepository::new(pool.clone());
+    let key_service = KeyService::new(SigningKeyRepository::new(pool.clone()), redis_cache.clone());
+    key_service.rotate_if_due().await.expect("Failed to create signing key");
+    key_service.reload().await.expect("Failed to load signing keys");
+    let role_repo = RoleRepository::new(pool.clone());
+    let oauth_client_service = OAuthClientService::new(OAuthClientRepository::new(pool.clone()), key_service.clone());
+    let oidc_service = OidcService::new(
+        OAuthClientRepository::new(pool.clone()),
+        ConsentRepository::new(pool.clone()),
+        user_repo.clone(),
+        redis_cache.clone(),
+        key_service.clone(),
+    );
+    let role_service = RoleService::new(role_repo.clone(), user_repo.clone(), TokenRevocation::shared());
+    let mfa_service = MfaService::new(MfaRepository::new(pool.clone()), user_repo.clone(), redis_cache.clone());
+    let lockout_service = LockoutService::new(
+        LoginAttemptRepository::new(pool.clone()),
+        OutboxRepository::new(pool.clone()),
+        redis_cache.clone(),
+    );
+    let session_service = SessionService::new(
+        SessionRepository::new(pool.clone()),
+        refresh_token_repo.clone(),
+        TokenRevocation::shared(),
+        redis_cache.clone(),
+    );
+    let passkey_service = PasskeyService::new(PasskeyRepository::new(pool.clone()), user_repo.clone(), redis_cache.clone());
+    let federation_service = FederationService::new(
+        IdentityRepository::new(pool.clone()),
+        user_repo.clone(),
+        role_repo.clone(),
+        PasskeyRepository::new(pool.clone()),
+        redis_cache.clone(),
+    );
+    let auth_service = AuthService::new(
+        user_repo.clone(),
+        refresh_token_repo,
+        role_repo,
+        TokenRevocation::shared(),
+        key_service.clone(),
+        mfa_service.clone(),
+        lockout_service.clone(),
+        session_service.clone(),
+        passkey_service.clone(),
+        federation_service.clone(),
+    );
+    let privacy_service = PrivacyService::new(PrivacyRepository::new(pool.clone()), user_repo.clone(), auth_service.clone());
+    let account_service = AccountService::new(
+        user_repo,
+        UserTokenRepository::new(pool.clone()),
+        auth_service.clone(),
+        lockout_service.clone(),
+        producer.clone(),
+    );
+
+    // User events reach Kafka through the outbox
+    let outbox_publisher = OutboxPublisher::new(OutboxRepository::new(pool.clone()), producer, redis_cache.clone());
+    tokio::spawn(outbox_publisher.run());
+
+    // Reports of the other services on data exports and erasures
+    tokio::spawn(privacy_service.clone().run_report_consumer(kafka_brokers.clone()));
+
+    // Name changes made in core-service
+    tokio::spawn(account_service.clone().run_profile_consumer(kafka_brokers));
+
+    let attempts_retention_days = std::env::var("LOGIN_ATTEMPTS_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(90);
+
+    // Rotate signing keys on schedule and pick up keys created by other instances,
+    // drop old login attempts and clean up privacy requests
+    let rotation_keys = key_service.clone();
+    let purge_lockout = lockout_service.clone();
+    let privacy_cleanup = privacy_service.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(300));
+        loop {
+            interval.tick().await;
+            if let Err(e) = rotation_keys.rotate_if_due().await {
+                tracing::error!("Signing key rotation failed: {}", e);
+            }
+            if let Err(e) = rotation_keys.reload().await {
+                tracing::error!("Failed to reload signing keys: {}", e);
+            }
+            if let Err(e) = purge_lockout.purge_attempts(chrono::Duration::days(attempts_retention_days)).await {
+                tracing::error!("Failed to purge login attempts: {}", e);
+            }
+            if let Err(e) = privacy_cleanup.cleanup().await {
+                tracing::error!("Privacy request cleanup failed: {}", e);
+            }
+        }
+    });
     
     // API Key authentication (must have valid key to access)
-    let api_key_auth = ApiKeyAuth::new(api_keys);
+    let api_key_auth = ApiKeyAuth::new(api_key_verifier, scopes::AUTH)
+        .public_path("/health")
+        .public_path(JWKS_PATH)
+        .public_path(TOKEN_PATH)
+        .public_path(DISCOVERY_PATH)
+        .public_path(USERINFO_PATH);
     
     // Rate limiter: 10 requests per minute
     // 10 tokens capacity, 10/60 = 0.166... tokens/second refill rate
@@ -56,15 +167,26 @@ async fn main() -> std::io::Result<()> {
     tracing::info!("🔐 Auth Service starting on http://{}", server_address);
     tracing::info!("🛡️  API Key authentication: ENABLED");
     tracing::info!("🛡️  Rate limiting: 10 requests per minute per IP");
-    tracing::info!("📍 IP detection: X-Real-IP > X-Forwarded-For > Connection IP");
+    tracing::info!("📍 IP detection: X-Real-IP > X-Forwarded-For (from TRUSTED_PROXIES only) > Connection IP");
     
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
             .app_data(web::Data::new(auth_service.clone()))
+            .app_data(web::Data::new(key_service.clone()))
+            .app_data(web::Data::new(role_service.clone()))
+            .app_data(web::Data::new(account_service.clone()))
+            .app_data(web::Data::new(mfa_service.clone()))
+            .app_data(web::Data::new(api_key_service.clone()))
+            .app_data(web::Data::new(oauth_client_service.clone()))
+            .app_data(web::Data::new(session_service.clone()))
+            .app_data(web::Data::new(passkey_service.clone()))
+            .app_data(web::Data::new(oidc_service.clone()))
+            .app_data(web::Data::new(federation_service.clone()))
+            .app_data(web::Data::new(privacy_service.clone()))
             .wrap(api_key_auth.clone())  // First: Check API key
             .wrap(rate_limiter.clone())  // Then: Rate limit by real IP
-            .configure(api::routes::configure)
+            .configure(|cfg| api::routes::configure(cfg, &key_service))
     })
     .bind(&server_address)?
     .run()
@@ -1,2 +1,60 @@
 pub mod rate_limit;
-pub mod api_key;
+
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+/// Public signing keys, fetched by every service that verifies tokens
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+/// OAuth2 token endpoint; clients authenticate with their own credentials
+pub const TOKEN_PATH: &str = "/oauth/token";
+
+/// OpenID Provider metadata, public like the JWKS
+pub const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+/// OIDC userinfo; client apps authenticate with the access token they got
+pub const USERINFO_PATH: &str = "/oauth/userinfo";
+
+/// OIDC authorization endpoint, called by our frontend for the logged-in user
+pub const AUTHORIZE_PATH: &str = "/oauth/authorize";
+
+/// Address and User-Agent of the client, recorded on login sessions
+pub fn client_info(req: &actix_web::HttpRequest) -> crate::domain::ClientInfo {
+    let user_agent = req.headers()
+        .get("User-Agent")
+        .and_then(|h| h.to_str().ok());
+    crate::domain::ClientInfo::new(client_ip(req), user_agent)
+}
+
+/// Client address, see `resolve_client_ip`. X-Real-IP and X-Forwarded-For
+/// are only read from the proxies in TRUSTED_PROXIES.
+pub fn client_ip(req: &actix_web::HttpRequest) -> String {
+    let header = |name: &str| req.headers().get(name).and_then(|h| h.to_str().ok());
+    crate::domain::session::resolve_client_ip(
+        req.peer_addr().map(|addr| addr.ip()),
+        header("X-Real-IP"),
+        header("X-Forwarded-For"),
+        trusted_proxies(),
+    )
+}
+
+/// TRUSTED_PROXIES, comma-separated IPs of the load balancers and backends
+/// allowed to tell the client address
+fn trusted_proxies() -> &'static [IpAddr] {
+    static PROXIES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+    PROXIES.get_or_init(|| {
+        std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| match s.parse::<IpAddr>() {
+                Ok(ip) => Some(ip),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid TRUSTED_PROXIES entry {}", s);
+                    None
+                }
+            })
+            .collect()
+    })
+}
@@ -8,6 +8,8 @@ use common::cache::RedisCache;
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use super::{client_ip, DISCOVERY_PATH, JWKS_PATH};
+
 #[derive(Serialize, Deserialize, Clone)]
 struct TokenBucket {
     tokens: f64,
@@ -130,8 +132,8 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let path = req.path().to_string();
         
-        // Skip rate limiting for health check endpoints
-        if path == "/health" || path == "/health/" {
+        // Skip rate limiting for health check endpoints and the public signing keys
+        if path == "/health" || path == "/health/" || path == JWKS_PATH || path == DISCOVERY_PATH {
             let fut = self.service.call(req);
             return Box::pin(async move {
                 let res = fut.await?;
@@ -139,27 +141,7 @@ where
             });
         }
         
-        // Priority 1: X-Real-IP header (from trusted backend with API key)
-        // Priority 2: X-Forwarded-For header (first IP in chain)
-        // Priority 3: Connection IP
-        let ip = req
-            .headers()
-            .get("X-Real-IP")
-            .and_then(|h| h.to_str().ok())
-            .map(|s| s.to_string())
-            .or_else(|| {
-                req.headers()
-                    .get("X-Forwarded-For")
-                    .and_then(|h| h.to_str().ok())
-                    .and_then(|s| s.split(',').next())
-                    .map(|s| s.trim().to_string())
-            })
-            .unwrap_or_else(|| {
-                req.connection_info()
-                    .realip_remote_addr()
-                    .unwrap_or("unknown")
-                    .to_string()
-            });
+        let ip = client_ip(req.request());
 
         let path = req.path().to_string();
         let key = format!("rate_limit:auth:{}:{}", ip, path);
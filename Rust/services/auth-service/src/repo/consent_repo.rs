@@ -0,0 +1,66 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::Consent;
+
+#[derive(Clone)]
+pub struct ConsentRepository {
+    pool: MySqlPool,
+}
+
+impl ConsentRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find(&self, user_id: i32, client_id: &str) -> Result<Option<Consent>> {
+        let consent = sqlx::query_as::<_, Consent>(
+            "SELECT c.client_id, o.name AS client_name, c.scopes, c.granted_at
+             FROM oauth_consents c JOIN oauth_clients o ON o.client_id = c.client_id
+             WHERE c.user_id = ? AND c.client_id = ?"
+        )
+        .bind(user_id)
+        .bind(client_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(consent)
+    }
+
+    /// Record the scopes, replacing what the user allowed the client before
+    pub async fn grant(&self, user_id: i32, client_id: &str, scopes: &[String]) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO oauth_consents (user_id, client_id, scopes) VALUES (?, ?, ?)
+             ON DUPLICATE KEY UPDATE scopes = VALUES(scopes), granted_at = NOW()"
+        )
+        .bind(user_id)
+        .bind(client_id)
+        .bind(scopes.join(" "))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_for_user(&self, user_id: i32) -> Result<Vec<Consent>> {
+        let consents = sqlx::query_as::<_, Consent>(
+            "SELECT c.client_id, o.name AS client_name, c.scopes, c.granted_at
+             FROM oauth_consents c JOIN oauth_clients o ON o.client_id = c.client_id
+             WHERE c.user_id = ? ORDER BY c.granted_at DESC"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(consents)
+    }
+
+    pub async fn revoke(&self, user_id: i32, client_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM oauth_consents WHERE user_id = ? AND client_id = ?")
+            .bind(user_id)
+            .bind(client_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,74 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::{ExternalIdentity, FederatedIdentity};
+
+const COLUMNS: &str = "id, user_id, provider, email, created_at, last_login_at";
+
+#[derive(Clone)]
+pub struct IdentityRepository {
+    pool: MySqlPool,
+}
+
+impl IdentityRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, user_id: i32, identity: &ExternalIdentity) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO user_identities (user_id, provider, subject, email, last_login_at) VALUES (?, ?, ?, ?, NOW())"
+        )
+        .bind(user_id)
+        .bind(&identity.provider)
+        .bind(&identity.subject)
+        .bind(&identity.email)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+
+    pub async fn find(&self, provider: &str, subject: &str) -> Result<Option<FederatedIdentity>> {
+        let identity = sqlx::query_as::<_, FederatedIdentity>(&format!(
+            "SELECT {} FROM user_identities WHERE provider = ? AND subject = ?",
+            COLUMNS
+        ))
+        .bind(provider)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(identity)
+    }
+
+    pub async fn list_for_user(&self, user_id: i32) -> Result<Vec<FederatedIdentity>> {
+        let identities = sqlx::query_as::<_, FederatedIdentity>(&format!(
+            "SELECT {} FROM user_identities WHERE user_id = ? ORDER BY created_at",
+            COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(identities)
+    }
+
+    pub async fn record_login(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE user_identities SET last_login_at = NOW() WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: i64, user_id: i32) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM user_identities WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
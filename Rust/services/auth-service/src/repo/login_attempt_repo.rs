@@ -0,0 +1,54 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crate::domain::LoginFailure;
+
+#[derive(Clone)]
+pub struct LoginAttemptRepository {
+    pool: MySqlPool,
+}
+
+impl LoginAttemptRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// `failure` is None for a successful login
+    pub async fn record(&self, user_id: Option<i32>, email: &str, ip_address: &str, failure: Option<LoginFailure>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO login_attempts (user_id, email, ip_address, success, reason) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(ip_address)
+        .bind(failure.is_none())
+        .bind(failure.map(|f| f.as_str()))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Distinct emails with a failed attempt from the IP since `since`
+    pub async fn failed_emails_from_ip(&self, ip_address: &str, since: DateTime<Utc>) -> Result<i64> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(DISTINCT email) FROM login_attempts
+             WHERE ip_address = ? AND success = FALSE AND created_at >= ?"
+        )
+        .bind(ip_address)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0)
+    }
+
+    pub async fn purge_older_than(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM login_attempts WHERE created_at < ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
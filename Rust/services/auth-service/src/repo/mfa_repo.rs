@@ -0,0 +1,155 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::UserMfa;
+
+#[derive(Clone)]
+pub struct MfaRepository {
+    pool: MySqlPool,
+}
+
+impl MfaRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find(&self, user_id: i32) -> Result<Option<UserMfa>> {
+        let mfa = sqlx::query_as::<_, UserMfa>(
+            "SELECT secret, enabled_at FROM user_mfa WHERE user_id = ?"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(mfa)
+    }
+
+    pub async fn is_enabled(&self, user_id: i32) -> Result<bool> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM user_mfa WHERE user_id = ? AND enabled_at IS NOT NULL"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0 > 0)
+    }
+
+    /// Start (or restart) an enrollment. Does nothing once MFA is enabled.
+    pub async fn save_pending_secret(&self, user_id: i32, secret: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO user_mfa (user_id, secret) VALUES (?, ?)
+             ON DUPLICATE KEY UPDATE secret = IF(enabled_at IS NULL, VALUES(secret), secret)"
+        )
+        .bind(user_id)
+        .bind(secret)
+        .execute(&self.pool)
+        .await?;
+
+        // 1 for an insert, 2 for an update, 0 if the row was left as is
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Enable MFA and store the recovery codes in one go
+    pub async fn enable(&self, user_id: i32, step: i64, recovery_code_hashes: &[String]) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE user_mfa SET enabled_at = NOW(), last_used_step = ? WHERE user_id = ? AND enabled_at IS NULL"
+        )
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        Self::insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn disable(&self, user_id: i32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM user_mfa WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Record a used time step. Returns false if that step (or a later one)
+    /// was already used, so a code works only once.
+    pub async fn use_step(&self, user_id: i32, step: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_mfa SET last_used_step = ?
+             WHERE user_id = ? AND (last_used_step IS NULL OR last_used_step < ?)"
+        )
+        .bind(step)
+        .bind(user_id)
+        .bind(step)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn replace_recovery_codes(&self, user_id: i32, code_hashes: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        Self::insert_recovery_codes(&mut tx, user_id, code_hashes).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Burn a recovery code. Returns false if it does not exist or was used.
+    pub async fn use_recovery_code(&self, user_id: i32, code_hash: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE mfa_recovery_codes SET used_at = NOW() WHERE user_id = ? AND code_hash = ? AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn remaining_recovery_codes(&self, user_id: i32) -> Result<i64> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM mfa_recovery_codes WHERE user_id = ? AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0)
+    }
+
+    async fn insert_recovery_codes(
+        tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+        user_id: i32,
+        code_hashes: &[String],
+    ) -> Result<()> {
+        for code_hash in code_hashes {
+            sqlx::query("INSERT INTO mfa_recovery_codes (user_id, code_hash) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(code_hash)
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+}
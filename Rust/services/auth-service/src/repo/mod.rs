@@ -1,4 +1,30 @@
 // Database repositories
 pub mod user_repo;
+pub mod refresh_token_repo;
+pub mod signing_key_repo;
+pub mod role_repo;
+pub mod user_token_repo;
+pub mod mfa_repo;
+pub mod login_attempt_repo;
+pub mod oauth_client_repo;
+pub mod session_repo;
+pub mod passkey_repo;
+pub mod consent_repo;
+pub mod identity_repo;
+pub mod outbox_repo;
+pub mod privacy_repo;
 
 pub use user_repo::UserRepository;
+pub use refresh_token_repo::RefreshTokenRepository;
+pub use signing_key_repo::SigningKeyRepository;
+pub use role_repo::RoleRepository;
+pub use user_token_repo::UserTokenRepository;
+pub use mfa_repo::MfaRepository;
+pub use login_attempt_repo::LoginAttemptRepository;
+pub use oauth_client_repo::OAuthClientRepository;
+pub use session_repo::SessionRepository;
+pub use passkey_repo::PasskeyRepository;
+pub use consent_repo::ConsentRepository;
+pub use identity_repo::IdentityRepository;
+pub use outbox_repo::OutboxRepository;
+pub use privacy_repo::PrivacyRepository;
@@ -0,0 +1,84 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::OAuthClient;
+
+const COLUMNS: &str = "id, client_id, secret_hash, name, kind, scopes, redirect_uris, created_by, revoked_at, created_at";
+
+#[derive(Clone)]
+pub struct OAuthClientRepository {
+    pool: MySqlPool,
+}
+
+impl OAuthClientRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create(
+        &self,
+        client_id: &str,
+        secret_hash: &str,
+        name: &str,
+        kind: &str,
+        scopes: &[String],
+        redirect_uris: Option<&[String]>,
+        created_by: i32,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO oauth_clients (client_id, secret_hash, name, kind, scopes, redirect_uris, created_by) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(client_id)
+        .bind(secret_hash)
+        .bind(name)
+        .bind(kind)
+        .bind(scopes.join(","))
+        .bind(redirect_uris.map(|uris| uris.join(" ")))
+        .bind(created_by)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+
+    pub async fn find_by_client_id(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+        let client = sqlx::query_as::<_, OAuthClient>(&format!(
+            "SELECT {} FROM oauth_clients WHERE client_id = ?",
+            COLUMNS
+        ))
+        .bind(client_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(client)
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<OAuthClient>> {
+        let client = sqlx::query_as::<_, OAuthClient>(&format!("SELECT {} FROM oauth_clients WHERE id = ?", COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(client)
+    }
+
+    pub async fn list(&self) -> Result<Vec<OAuthClient>> {
+        let clients = sqlx::query_as::<_, OAuthClient>(&format!(
+            "SELECT {} FROM oauth_clients ORDER BY created_at DESC",
+            COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(clients)
+    }
+
+    pub async fn revoke(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE oauth_clients SET revoked_at = NOW() WHERE id = ? AND revoked_at IS NULL")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,98 @@
+use sqlx::{MySql, MySqlPool, Transaction};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use contracts::events::{UserEvent, USER_EVENTS_TOPIC};
+use crate::domain::OutboxEvent;
+use crate::domain::outbox::user_event_message;
+
+const COLUMNS: &str = "id, event_id, topic, message_key, event_type, payload, attempts";
+
+#[derive(Clone)]
+pub struct OutboxRepository {
+    pool: MySqlPool,
+}
+
+impl OutboxRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Queue a user event in the transaction of the change, so neither is
+    /// saved without the other
+    pub async fn insert_user_event(tx: &mut Transaction<'_, MySql>, event: &UserEvent) -> Result<()> {
+        let (event_id, payload) = user_event_message(event)?;
+        Self::insert(tx, &event_id, USER_EVENTS_TOPIC, &event.user_id().to_string(), event.event_type(), &payload).await
+    }
+
+    /// Queue any message, `event_id` telling it apart
+    pub async fn insert(
+        tx: &mut Transaction<'_, MySql>,
+        event_id: &str,
+        topic: &str,
+        message_key: &str,
+        event_type: &str,
+        payload: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO event_outbox (event_id, topic, message_key, event_type, payload) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(event_id)
+        .bind(topic)
+        .bind(message_key)
+        .bind(event_type)
+        .bind(payload)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Queue a user event for a change not kept in the database
+    pub async fn add_user_event(&self, event: &UserEvent) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::insert_user_event(&mut tx, event).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Oldest events first
+    pub async fn unpublished(&self, limit: i64) -> Result<Vec<OutboxEvent>> {
+        let events = sqlx::query_as::<_, OutboxEvent>(&format!(
+            "SELECT {} FROM event_outbox WHERE published_at IS NULL ORDER BY id LIMIT ?",
+            COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    pub async fn mark_published(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE event_outbox SET published_at = NOW(), attempts = attempts + 1 WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn record_failure(&self, id: i64, error: &str) -> Result<()> {
+        sqlx::query("UPDATE event_outbox SET attempts = attempts + 1, last_error = ? WHERE id = ?")
+            .bind(error.chars().take(500).collect::<String>())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_published_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM event_outbox WHERE published_at IS NOT NULL AND published_at < ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
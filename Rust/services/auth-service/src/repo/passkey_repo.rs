@@ -0,0 +1,96 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::Passkey;
+use crate::domain::passkey::NewPasskey;
+
+const COLUMNS: &str = "id, user_id, credential_id, public_key, sign_count, name, created_at, last_used_at";
+
+#[derive(Clone)]
+pub struct PasskeyRepository {
+    pool: MySqlPool,
+}
+
+impl PasskeyRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, user_id: i32, passkey: &NewPasskey, name: &str) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO user_passkeys (user_id, credential_id, public_key, sign_count, name) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(user_id)
+        .bind(&passkey.credential_id)
+        .bind(&passkey.public_key)
+        .bind(passkey.sign_count as i64)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Passkey>> {
+        let passkey = sqlx::query_as::<_, Passkey>(&format!("SELECT {} FROM user_passkeys WHERE id = ?", COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(passkey)
+    }
+
+    pub async fn find_by_credential_id(&self, credential_id: &str) -> Result<Option<Passkey>> {
+        let passkey = sqlx::query_as::<_, Passkey>(&format!("SELECT {} FROM user_passkeys WHERE credential_id = ?", COLUMNS))
+            .bind(credential_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(passkey)
+    }
+
+    pub async fn list_for_user(&self, user_id: i32) -> Result<Vec<Passkey>> {
+        let passkeys = sqlx::query_as::<_, Passkey>(&format!(
+            "SELECT {} FROM user_passkeys WHERE user_id = ? ORDER BY created_at",
+            COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(passkeys)
+    }
+
+    pub async fn count_for_user(&self, user_id: i32) -> Result<i64> {
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_passkeys WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count.0)
+    }
+
+    /// Store the counter of a verified assertion. Fails if another request
+    /// moved the counter in the meantime.
+    pub async fn record_use(&self, id: i64, previous_count: i64, sign_count: u32) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_passkeys SET sign_count = ?, last_used_at = NOW() WHERE id = ? AND sign_count = ?"
+        )
+        .bind(sign_count as i64)
+        .bind(id)
+        .bind(previous_count)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn delete(&self, id: i64, user_id: i32) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM user_passkeys WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,315 @@
+use sqlx::{MySql, MySqlPool, Transaction};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use contracts::events::{PrivacyRequestEvent, PrivacyRequestKind, PRIVACY_REQUESTS_TOPIC};
+use crate::domain::{PrivacyRequest, PrivacyRequestPart};
+use crate::domain::password::UNUSABLE_PASSWORD;
+use super::OutboxRepository;
+
+const COLUMNS: &str = "id, user_id, kind, status, file_path, error, created_at, completed_at";
+
+#[derive(Clone)]
+pub struct PrivacyRepository {
+    pool: MySqlPool,
+}
+
+impl PrivacyRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record the request with a pending part per service and queue it for them
+    pub async fn create(&self, user_id: i32, kind: PrivacyRequestKind, services: &[String]) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query("INSERT INTO privacy_requests (user_id, kind) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(kind.as_str())
+            .execute(&mut *tx)
+            .await?;
+        let id = result.last_insert_id() as i64;
+
+        for service in services {
+            sqlx::query("INSERT INTO privacy_request_parts (request_id, service) VALUES (?, ?)")
+                .bind(id)
+                .bind(service)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        Self::queue(&mut tx, id, kind, user_id).await?;
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Ask the services that failed or never answered again. Returns false
+    /// unless the request had failed.
+    pub async fn retry(&self, request: &PrivacyRequest, kind: PrivacyRequestKind) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE privacy_requests SET status = 'pending', error = NULL, requested_at = NOW(), completed_at = NULL \
+             WHERE id = ? AND status = 'failed'"
+        )
+        .bind(request.id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        // Services answer again only for a pending part. Every export part is
+        // asked again, the data of completed ones was dropped with the failure.
+        sqlx::query(
+            "UPDATE privacy_request_parts SET status = 'pending', error = NULL, completed_at = NULL \
+             WHERE request_id = ? AND (status <> 'completed' OR ?)"
+        )
+        .bind(request.id)
+        .bind(kind == PrivacyRequestKind::Export)
+        .execute(&mut *tx)
+        .await?;
+        Self::queue(&mut tx, request.id, kind, request.user_id).await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn queue(tx: &mut Transaction<'_, MySql>, id: i64, kind: PrivacyRequestKind, user_id: i32) -> Result<()> {
+        let event = PrivacyRequestEvent { request_id: id, kind, user_id, timestamp: Utc::now() };
+        OutboxRepository::insert(
+            tx,
+            &uuid::Uuid::new_v4().to_string(),
+            PRIVACY_REQUESTS_TOPIC,
+            &user_id.to_string(),
+            &format!("privacy.{}", kind.as_str()),
+            &serde_json::to_string(&event)?,
+        )
+        .await
+    }
+
+    /// The request with the progress of each service
+    pub async fn find(&self, id: i64) -> Result<Option<PrivacyRequest>> {
+        let request = sqlx::query_as::<_, PrivacyRequest>(&format!(
+            "SELECT {} FROM privacy_requests WHERE id = ?",
+            COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match request {
+            Some(mut request) => {
+                request.parts = self.parts(id).await?;
+                Ok(Some(request))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_for_user(&self, user_id: i32, limit: i64) -> Result<Vec<PrivacyRequest>> {
+        let requests = sqlx::query_as::<_, PrivacyRequest>(&format!(
+            "SELECT {} FROM privacy_requests WHERE user_id = ? ORDER BY id DESC LIMIT ?",
+            COLUMNS
+        ))
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(requests)
+    }
+
+    pub async fn find_pending(&self, user_id: i32, kind: PrivacyRequestKind) -> Result<Option<i64>> {
+        let id: Option<(i64,)> = sqlx::query_as(
+            "SELECT id FROM privacy_requests WHERE user_id = ? AND kind = ? AND status = 'pending' ORDER BY id DESC LIMIT 1"
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(id.map(|(id,)| id))
+    }
+
+    pub async fn parts(&self, request_id: i64) -> Result<Vec<PrivacyRequestPart>> {
+        let parts = sqlx::query_as::<_, PrivacyRequestPart>(
+            "SELECT service, status, error, completed_at FROM privacy_request_parts WHERE request_id = ? ORDER BY service"
+        )
+        .bind(request_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(parts)
+    }
+
+    /// Store a service's report. Returns false for a report already received
+    /// or not expected.
+    pub async fn record_report(&self, request_id: i64, service: &str, data: Option<&str>, error: Option<&str>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE privacy_request_parts SET status = IF(? IS NULL, 'completed', 'failed'), data = ?, error = ?, completed_at = NOW() \
+             WHERE request_id = ? AND service = ? AND status = 'pending'"
+        )
+        .bind(error)
+        .bind(data)
+        .bind(error)
+        .bind(request_id)
+        .bind(service)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Export parts by service
+    pub async fn part_data(&self, request_id: i64) -> Result<Vec<(String, Option<String>)>> {
+        let parts = sqlx::query_as(
+            "SELECT service, data FROM privacy_request_parts WHERE request_id = ? ORDER BY service"
+        )
+        .bind(request_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(parts)
+    }
+
+    /// Only a pending request is completed; returns false if another
+    /// instance got there first
+    pub async fn mark_completed(&self, id: i64, file_path: Option<&str>) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE privacy_requests SET status = 'completed', file_path = ?, completed_at = NOW() WHERE id = ? AND status = 'pending'"
+        )
+        .bind(file_path)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        // The parts are in the archive now
+        sqlx::query("UPDATE privacy_request_parts SET data = NULL WHERE request_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn mark_failed(&self, id: i64, error: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE privacy_requests SET status = 'failed', error = ?, completed_at = NOW() WHERE id = ? AND status = 'pending'")
+            .bind(error)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE privacy_request_parts SET data = NULL WHERE request_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Requests still waiting on a service since before `before`
+    pub async fn find_stale(&self, before: DateTime<Utc>) -> Result<Vec<i64>> {
+        let ids: Vec<(i64,)> = sqlx::query_as(
+            "SELECT id FROM privacy_requests WHERE status = 'pending' AND requested_at < ? ORDER BY id"
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Archives of exports completed before `before`
+    pub async fn find_expired_exports(&self, before: DateTime<Utc>) -> Result<Vec<(i64, String)>> {
+        let exports = sqlx::query_as(
+            "SELECT id, file_path FROM privacy_requests WHERE kind = 'export' AND file_path IS NOT NULL AND completed_at < ?"
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(exports)
+    }
+
+    pub async fn clear_file(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE privacy_requests SET file_path = NULL WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Everything auth-service keeps about the user, as JSON
+    pub async fn export_user(&self, user_id: i32) -> Result<serde_json::Value> {
+        let queries = [
+            ("account", "SELECT JSON_OBJECT('id', id, 'name', name, 'email', email, 'email_verified_at', email_verified_at, \
+                'created_at', created_at) FROM users WHERE id = ?"),
+            ("roles", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('role', r.name, 'assigned_at', ur.assigned_at)), JSON_ARRAY()) \
+                FROM user_roles ur JOIN roles r ON r.id = ur.role_id WHERE ur.user_id = ?"),
+            ("sessions", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('device', device, 'user_agent', user_agent, 'ip', ip, \
+                'created_at', created_at, 'last_seen_at', last_seen_at, 'revoked_at', revoked_at)), JSON_ARRAY()) \
+                FROM user_sessions WHERE user_id = ?"),
+            ("passkeys", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('name', name, 'created_at', created_at, \
+                'last_used_at', last_used_at)), JSON_ARRAY()) FROM user_passkeys WHERE user_id = ?"),
+            ("linked_identities", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('provider', provider, 'email', email, \
+                'created_at', created_at, 'last_login_at', last_login_at)), JSON_ARRAY()) FROM user_identities WHERE user_id = ?"),
+            ("consents", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('client_id', client_id, 'scopes', scopes, \
+                'granted_at', granted_at)), JSON_ARRAY()) FROM oauth_consents WHERE user_id = ?"),
+            ("login_attempts", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('ip_address', ip_address, 'success', success, \
+                'reason', reason, 'created_at', created_at)), JSON_ARRAY()) FROM login_attempts WHERE user_id = ?"),
+        ];
+
+        let mut export = serde_json::Map::new();
+        for (name, query) in queries {
+            let json: Option<String> = sqlx::query_scalar(query)
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .flatten();
+            let value = match json {
+                Some(json) => serde_json::from_str(&json)?,
+                None => serde_json::Value::Null,
+            };
+            export.insert(name.to_string(), value);
+        }
+        export.insert("two_factor_enabled".to_string(), serde_json::Value::Bool(
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM user_mfa WHERE user_id = ? AND enabled_at IS NOT NULL")
+                .bind(user_id)
+                .fetch_one(&self.pool)
+                .await? > 0,
+        ));
+
+        Ok(serde_json::Value::Object(export))
+    }
+
+    /// Remove every way to log in as the user; the account itself stays until
+    /// the other services have erased their data
+    pub async fn erase_credentials(&self, user_id: i32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE users SET password = ? WHERE id = ?")
+            .bind(UNUSABLE_PASSWORD)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        for table in ["user_mfa", "mfa_recovery_codes", "user_passkeys", "user_identities", "user_tokens", "oauth_consents"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE user_id = ?", table))
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Login attempts are kept by email, even after the user row is gone
+    pub async fn delete_login_attempts(&self, email: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM login_attempts WHERE email = ?")
+            .bind(email)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,86 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crate::domain::{Authentication, RefreshToken};
+
+#[derive(Clone)]
+pub struct RefreshTokenRepository {
+    pool: MySqlPool,
+}
+
+impl RefreshTokenRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        user_id: i32,
+        family_id: &str,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+        authentication: &Authentication,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at, amr, auth_time) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(user_id)
+        .bind(family_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .bind(authentication.amr.join(","))
+        .bind(authentication.auth_time)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+
+    pub async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>> {
+        let token = sqlx::query_as::<_, RefreshToken>(
+            "SELECT id, user_id, family_id, expires_at, used_at, revoked_at, amr, auth_time FROM refresh_tokens WHERE token_hash = ?"
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Claim a token for rotation. Returns false if it was already used or
+    /// revoked, including by a concurrent request.
+    pub async fn mark_used(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET used_at = NOW() WHERE id = ? AND used_at IS NULL AND revoked_at IS NULL"
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn revoke_family(&self, family_id: &str, reason: &str) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW(), revoked_reason = ? WHERE family_id = ? AND revoked_at IS NULL"
+        )
+        .bind(reason)
+        .bind(family_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn revoke_all_for_user(&self, user_id: i32, reason: &str) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW(), revoked_reason = ? WHERE user_id = ? AND revoked_at IS NULL"
+        )
+        .bind(reason)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
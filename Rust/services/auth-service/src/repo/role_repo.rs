@@ -0,0 +1,89 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::{Grants, Role};
+
+#[derive(Clone)]
+pub struct RoleRepository {
+    pool: MySqlPool,
+}
+
+impl RoleRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_all(&self) -> Result<Vec<Role>> {
+        let roles = sqlx::query_as::<_, Role>(
+            "SELECT id, name, description FROM roles ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Option<Role>> {
+        let role = sqlx::query_as::<_, Role>(
+            "SELECT id, name, description FROM roles WHERE name = ?"
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(role)
+    }
+
+    pub async fn permissions_of_role(&self, role_id: i32) -> Result<Vec<String>> {
+        let permissions: Vec<(String,)> = sqlx::query_as(
+            "SELECT permission FROM role_permissions WHERE role_id = ? ORDER BY permission"
+        )
+        .bind(role_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(permissions.into_iter().map(|(p,)| p).collect())
+    }
+
+    /// Roles of the user and the permissions they grant
+    pub async fn grants_for_user(&self, user_id: i32) -> Result<Grants> {
+        let rows: Vec<(String, Option<String>)> = sqlx::query_as(
+            "SELECT r.name, rp.permission
+             FROM user_roles ur
+             JOIN roles r ON r.id = ur.role_id
+             LEFT JOIN role_permissions rp ON rp.role_id = r.id
+             WHERE ur.user_id = ?"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Grants::from_rows(rows))
+    }
+
+    /// Returns false if the user already had the role
+    pub async fn assign(&self, user_id: i32, role_id: i32, assigned_by: Option<i32>) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT IGNORE INTO user_roles (user_id, role_id, assigned_by) VALUES (?, ?, ?)"
+        )
+        .bind(user_id)
+        .bind(role_id)
+        .bind(assigned_by)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Returns false if the user did not have the role
+    pub async fn remove(&self, user_id: i32, role_id: i32) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM user_roles WHERE user_id = ? AND role_id = ?"
+        )
+        .bind(user_id)
+        .bind(role_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,79 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crate::domain::{ClientInfo, UserSession};
+
+const COLUMNS: &str = "id, family_id, device, user_agent, ip, created_at, last_seen_at, revoked_at";
+
+#[derive(Clone)]
+pub struct SessionRepository {
+    pool: MySqlPool,
+}
+
+impl SessionRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, user_id: i32, family_id: &str, client: &ClientInfo) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO user_sessions (user_id, family_id, device, user_agent, ip) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(user_id)
+        .bind(family_id)
+        .bind(client.device())
+        .bind(&client.user_agent)
+        .bind(&client.ip)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+
+    /// Record activity on the session, on each refresh
+    pub async fn touch(&self, family_id: &str, ip: &str) -> Result<()> {
+        sqlx::query("UPDATE user_sessions SET last_seen_at = NOW(), ip = ? WHERE family_id = ?")
+            .bind(ip)
+            .bind(family_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_for_user(&self, id: i64, user_id: i32) -> Result<Option<UserSession>> {
+        let session = sqlx::query_as::<_, UserSession>(&format!(
+            "SELECT {} FROM user_sessions WHERE id = ? AND user_id = ?",
+            COLUMNS
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Sessions not revoked and seen since `active_since`, most recent first
+    pub async fn list_active(&self, user_id: i32, active_since: DateTime<Utc>) -> Result<Vec<UserSession>> {
+        let sessions = sqlx::query_as::<_, UserSession>(&format!(
+            "SELECT {} FROM user_sessions WHERE user_id = ? AND revoked_at IS NULL AND last_seen_at > ? ORDER BY last_seen_at DESC",
+            COLUMNS
+        ))
+        .bind(user_id)
+        .bind(active_since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    pub async fn revoke(&self, family_id: &str) -> Result<bool> {
+        let result = sqlx::query("UPDATE user_sessions SET revoked_at = NOW() WHERE family_id = ? AND revoked_at IS NULL")
+            .bind(family_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,57 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crate::domain::SigningKey;
+
+#[derive(Clone)]
+pub struct SigningKeyRepository {
+    pool: MySqlPool,
+}
+
+impl SigningKeyRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Keys that belong in the JWKS, oldest first
+    pub async fn find_published(&self) -> Result<Vec<SigningKey>> {
+        let keys = sqlx::query_as::<_, SigningKey>(
+            "SELECT kid, algorithm, private_key_pem, public_jwk, activates_at, created_at
+             FROM jwt_signing_keys
+             WHERE expires_at IS NULL OR expires_at > NOW()
+             ORDER BY activates_at ASC, id ASC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(keys)
+    }
+
+    pub async fn create(&self, kid: &str, algorithm: &str, private_key_pem: &str, public_jwk: &str, activates_at: DateTime<Utc>) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO jwt_signing_keys (kid, algorithm, private_key_pem, public_jwk, activates_at) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(kid)
+        .bind(algorithm)
+        .bind(private_key_pem)
+        .bind(public_jwk)
+        .bind(activates_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+
+    /// Schedule the retirement of every key other than `kid` that has no expiry yet
+    pub async fn expire_others(&self, kid: &str, expires_at: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE jwt_signing_keys SET expires_at = ? WHERE kid <> ? AND expires_at IS NULL"
+        )
+        .bind(expires_at)
+        .bind(kid)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,6 +1,9 @@
 use sqlx::MySqlPool;
 use anyhow::Result;
+use chrono::Utc;
+use contracts::events::{UserCreatedEvent, UserDeletedEvent, UserEvent, UserUpdatedEvent};
 use crate::domain::User;
+use super::OutboxRepository;
 
 #[derive(Clone)]
 pub struct UserRepository {
@@ -14,7 +17,7 @@ impl UserRepository {
 
     pub async fn find_by_email(&self, email: &str) -> Result<Option<User>> {
         let user = sqlx::query_as::<_, User>(
-            "SELECT id, name, email, password FROM users WHERE email = ?"
+            "SELECT id, name, email, password, email_verified_at FROM users WHERE email = ?"
         )
         .bind(email)
         .fetch_optional(&self.pool)
@@ -25,7 +28,7 @@ impl UserRepository {
 
     pub async fn find_by_id(&self, id: i32) -> Result<Option<User>> {
         let user = sqlx::query_as::<_, User>(
-            "SELECT id, name, email, password FROM users WHERE id = ?"
+            "SELECT id, name, email, password, email_verified_at FROM users WHERE id = ?"
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -34,17 +37,31 @@ impl UserRepository {
         Ok(user)
     }
 
-    pub async fn create(&self, name: &str, email: &str, hashed_password: &str) -> Result<i32> {
+    /// Insert the user and queue user.created
+    pub async fn create(&self, name: &str, email: &str, hashed_password: &str, email_verified: bool) -> Result<i32> {
+        let mut tx = self.pool.begin().await?;
         let result = sqlx::query(
-            "INSERT INTO users (name, email, password) VALUES (?, ?, ?)"
+            "INSERT INTO users (name, email, password, email_verified_at) VALUES (?, ?, ?, IF(?, NOW(), NULL))"
         )
         .bind(name)
         .bind(email)
         .bind(hashed_password)
-        .execute(&self.pool)
+        .bind(email_verified)
+        .execute(&mut *tx)
         .await?;
+        let user_id = result.last_insert_id() as i32;
+
+        let event = UserEvent::Created(UserCreatedEvent {
+            user_id,
+            email: email.to_string(),
+            name: name.to_string(),
+            email_verified,
+            timestamp: Utc::now(),
+        });
+        OutboxRepository::insert_user_event(&mut tx, &event).await?;
 
-        Ok(result.last_insert_id() as i32)
+        tx.commit().await?;
+        Ok(user_id)
     }
 
     pub async fn email_exists(&self, email: &str) -> Result<bool> {
@@ -57,4 +74,127 @@ impl UserRepository {
 
         Ok(count.0 > 0)
     }
+
+    /// Queues user.email_verified unless it already was
+    pub async fn mark_email_verified(&self, id: i32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE users SET email_verified_at = NOW() WHERE id = ? AND email_verified_at IS NULL"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 1 {
+            let (email,): (String,) = sqlx::query_as("SELECT email FROM users WHERE id = ?")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+            let event = UserEvent::EmailVerified(UserUpdatedEvent {
+                user_id: id,
+                email: Some(email),
+                name: None,
+                timestamp: Utc::now(),
+            });
+            OutboxRepository::insert_user_event(&mut tx, &event).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Switch to an address the user proved they own. Queues
+    /// user.email_verified with the new address; false if it is taken.
+    pub async fn change_email(&self, id: i32, email: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query("UPDATE users SET email = ?, email_verified_at = NOW() WHERE id = ?")
+            .bind(email)
+            .bind(id)
+            .execute(&mut *tx)
+            .await;
+        match result {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+
+        let event = UserEvent::EmailVerified(UserUpdatedEvent {
+            user_id: id,
+            email: Some(email.to_string()),
+            name: None,
+            timestamp: Utc::now(),
+        });
+        OutboxRepository::insert_user_event(&mut tx, &event).await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Name changed in core-service, no event
+    pub async fn set_name(&self, id: i32, name: &str) -> Result<()> {
+        sqlx::query("UPDATE users SET name = ? WHERE id = ?")
+            .bind(name)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// A new password chosen by the user; queues user.password_changed
+    pub async fn change_password(&self, id: i32, hashed_password: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE users SET password = ? WHERE id = ?")
+            .bind(hashed_password)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let (email,): (String,) = sqlx::query_as("SELECT email FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let event = UserEvent::PasswordChanged(UserUpdatedEvent {
+            user_id: id,
+            email: Some(email),
+            name: None,
+            timestamp: Utc::now(),
+        });
+        OutboxRepository::insert_user_event(&mut tx, &event).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Same password under a new hash, no event
+    pub async fn update_password(&self, id: i32, hashed_password: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE users SET password = ? WHERE id = ?"
+        )
+        .bind(hashed_password)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete the user (sessions, tokens, passkeys and links cascade) and
+    /// queue user.deleted. Returns false if there was no such user.
+    pub async fn delete(&self, id: i32) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        let event = UserEvent::Deleted(UserDeletedEvent { user_id: id, timestamp: Utc::now() });
+        OutboxRepository::insert_user_event(&mut tx, &event).await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
 }
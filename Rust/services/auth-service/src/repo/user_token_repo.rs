@@ -0,0 +1,89 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crate::domain::{TokenPurpose, UserToken};
+
+#[derive(Clone)]
+pub struct UserTokenRepository {
+    pool: MySqlPool,
+}
+
+impl UserTokenRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a new token, invalidating the user's outstanding ones for the same purpose
+    pub async fn create(
+        &self,
+        user_id: i32,
+        purpose: TokenPurpose,
+        token_hash: &str,
+        new_email: Option<&str>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE user_tokens SET used_at = NOW() WHERE user_id = ? AND purpose = ? AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query(
+            "INSERT INTO user_tokens (user_id, purpose, token_hash, new_email, expires_at) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .bind(token_hash)
+        .bind(new_email)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.last_insert_id() as i64)
+    }
+
+    pub async fn find_by_hash(&self, purpose: TokenPurpose, token_hash: &str) -> Result<Option<UserToken>> {
+        let token = sqlx::query_as::<_, UserToken>(
+            "SELECT id, user_id, new_email, expires_at, used_at FROM user_tokens WHERE purpose = ? AND token_hash = ?"
+        )
+        .bind(purpose.as_str())
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Claim the token, burning the user's other outstanding tokens for the
+    /// same purpose with it. Returns false if it was already used, including
+    /// by a concurrent request.
+    pub async fn mark_used(&self, id: i64, user_id: i32, purpose: TokenPurpose) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE user_tokens SET used_at = NOW() WHERE id = ? AND used_at IS NULL"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "UPDATE user_tokens SET used_at = NOW() WHERE user_id = ? AND purpose = ? AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+}
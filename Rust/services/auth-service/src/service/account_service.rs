@@ -0,0 +1,246 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+use messaging::events::AccountEmailEvent;
+use contracts::events::{UserEvent, UserEventMessage, USER_EVENTS_TOPIC};
+use messaging::kafka_consumer::{InvalidMessage, KafkaConsumer};
+use messaging::kafka_producer::KafkaProducer;
+
+use crate::domain::{TokenPurpose, User, UserToken};
+use crate::domain::token::{generate_opaque_token, hash_token};
+use crate::domain::user::normalize_email;
+use crate::repo::{UserRepository, UserTokenRepository};
+use super::{AuthService, LockoutService};
+
+const ACCOUNT_EMAIL_TOPIC: &str = "account-email-events";
+
+/// Email verification and password reset, both through single-use tokens
+/// mailed by worker-service
+#[derive(Clone)]
+pub struct AccountService {
+    user_repo: UserRepository,
+    token_repo: UserTokenRepository,
+    auth_service: AuthService,
+    lockout_service: LockoutService,
+    kafka_producer: KafkaProducer,
+    app_base_url: String,
+    verification_ttl: Duration,
+    reset_ttl: Duration,
+}
+
+impl AccountService {
+    pub fn new(
+        user_repo: UserRepository,
+        token_repo: UserTokenRepository,
+        auth_service: AuthService,
+        lockout_service: LockoutService,
+        kafka_producer: KafkaProducer,
+    ) -> Self {
+        let app_base_url = std::env::var("APP_BASE_URL")
+            .unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let verification_hours = std::env::var("EMAIL_VERIFICATION_TTL_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(24);
+        let reset_minutes = std::env::var("PASSWORD_RESET_TTL_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(60);
+
+        Self {
+            user_repo,
+            token_repo,
+            auth_service,
+            lockout_service,
+            kafka_producer,
+            app_base_url: app_base_url.trim_end_matches('/').to_string(),
+            verification_ttl: Duration::hours(verification_hours),
+            reset_ttl: Duration::minutes(reset_minutes),
+        }
+    }
+
+    /// Mail a verification link, unless the email is already verified
+    pub async fn request_email_verification(&self, user_id: i32) -> Result<()> {
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        if user.is_verified() {
+            return Err(anyhow!("Email already verified"));
+        }
+
+        self.send_token(&user, TokenPurpose::EmailVerification, None).await
+    }
+
+    pub async fn confirm_email(&self, token: &str) -> Result<()> {
+        let user_id = self.consume(TokenPurpose::EmailVerification, token).await?;
+        self.user_repo.mark_email_verified(user_id).await?;
+        tracing::info!("User {} verified their email", user_id);
+        Ok(())
+    }
+
+    /// Mail a confirmation link to the new address; the account keeps the
+    /// old one until it is used
+    pub async fn request_email_change(&self, user_id: i32, new_email: &str) -> Result<()> {
+        let new_email = normalize_email(new_email).ok_or_else(|| anyhow!("Invalid email address"))?;
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        if user.email.eq_ignore_ascii_case(&new_email) {
+            return Err(anyhow!("Email is already the current address"));
+        }
+        if self.user_repo.email_exists(&new_email).await? {
+            return Err(anyhow!("Email already registered"));
+        }
+
+        self.send_token(&user, TokenPurpose::EmailChange, Some(&new_email)).await
+    }
+
+    /// Switch to the new address. Tokens name the user by email, so every
+    /// session ends and the user logs in again with the new one.
+    pub async fn confirm_email_change(&self, token: &str) -> Result<()> {
+        let stored = self.find_valid(TokenPurpose::EmailChange, token).await?;
+        let new_email = stored.new_email.ok_or_else(|| anyhow!("Invalid or expired token"))?;
+        let user_id = self.consume(TokenPurpose::EmailChange, token).await?;
+        if !self.user_repo.change_email(user_id, &new_email).await? {
+            return Err(anyhow!("Email already registered"));
+        }
+
+        self.auth_service.logout_all(user_id).await?;
+        tracing::info!("User {} changed their email", user_id);
+        Ok(())
+    }
+
+    /// Mail a reset link. Unknown emails succeed silently so the endpoint
+    /// does not reveal which addresses have accounts.
+    pub async fn request_password_reset(&self, email: &str) -> Result<()> {
+        match self.user_repo.find_by_email(email).await? {
+            Some(user) => self.send_token(&user, TokenPurpose::PasswordReset, None).await,
+            None => {
+                tracing::info!("Password reset requested for unknown email");
+                Ok(())
+            }
+        }
+    }
+
+    /// Set the new password, end every session of the user and lift any login lockout
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<()> {
+        if new_password.is_empty() {
+            return Err(anyhow!("Password must not be empty"));
+        }
+
+        // A password the policy rejects leaves the token usable for another try
+        let stored = self.find_valid(TokenPurpose::PasswordReset, token).await?;
+        let user = self.user_repo.find_by_id(stored.user_id).await?
+            .ok_or_else(|| anyhow!("Invalid or expired token"))?;
+        self.auth_service.check_password(new_password, &user.email)?;
+
+        let user_id = self.consume(TokenPurpose::PasswordReset, token).await?;
+        self.auth_service.set_password(user_id, &user.email, new_password).await?;
+        // Receiving the mail proves ownership of the address too
+        self.user_repo.mark_email_verified(user_id).await?;
+
+        self.auth_service.logout_all(user_id).await?;
+        self.unlock(user_id).await?;
+        tracing::info!("User {} reset their password", user_id);
+        Ok(())
+    }
+
+    /// Lift the login lockout of a user
+    pub async fn unlock(&self, user_id: i32) -> Result<()> {
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        self.lockout_service.unlock(&user.email);
+        Ok(())
+    }
+
+    /// Delete an account for good. Its tokens stop working right away and
+    /// the other services drop the user on user.deleted.
+    pub async fn delete_user(&self, user_id: i32) -> Result<()> {
+        self.auth_service.logout_all(user_id).await?;
+        if !self.user_repo.delete(user_id).await? {
+            return Err(anyhow!("User not found"));
+        }
+        tracing::info!("User {} deleted", user_id);
+        Ok(())
+    }
+
+    /// Keep users.name in line with the profile in core-service, which
+    /// announces changes with user.updated
+    pub async fn run_profile_consumer(self, brokers: String) {
+        let consumer = match KafkaConsumer::new(&brokers, "auth-user-group", &[USER_EVENTS_TOPIC]) {
+            Ok(consumer) => consumer,
+            Err(e) => {
+                tracing::error!("Failed to start user events consumer: {}", e);
+                return;
+            }
+        };
+
+        let result = consumer.consume_async(|_key, payload| {
+            let user_repo = self.user_repo.clone();
+            async move {
+                let message: UserEventMessage = serde_json::from_str(&payload)
+                    .map_err(|e| InvalidMessage(format!("Parse error: {}", e)))?;
+                match message.event {
+                    UserEvent::Updated(event) => match event.name {
+                        Some(name) => user_repo.set_name(event.user_id, &name).await,
+                        None => Ok(()),
+                    },
+                    _ => Ok(()),
+                }
+            }
+        }).await;
+        if let Err(e) = result {
+            tracing::error!("User events consumer stopped: {}", e);
+        }
+    }
+
+    /// Mail a token to the user, or to the address they are switching to
+    async fn send_token(&self, user: &User, purpose: TokenPurpose, new_email: Option<&str>) -> Result<()> {
+        let token = generate_opaque_token();
+        let ttl = match purpose {
+            TokenPurpose::EmailVerification | TokenPurpose::EmailChange => self.verification_ttl,
+            TokenPurpose::PasswordReset => self.reset_ttl,
+        };
+        let expires_at = Utc::now() + ttl;
+        self.token_repo.create(user.id, purpose, &hash_token(&token), new_email, expires_at).await?;
+
+        let path = match purpose {
+            TokenPurpose::EmailVerification => "verify-email",
+            TokenPurpose::PasswordReset => "reset-password",
+            TokenPurpose::EmailChange => "confirm-email-change",
+        };
+        let event = AccountEmailEvent {
+            kind: purpose.as_str().to_string(),
+            user_id: user.id,
+            email: new_email.unwrap_or(&user.email).to_string(),
+            name: user.name.clone(),
+            link: format!("{}/{}?token={}", self.app_base_url, path, token),
+            expires_at: expires_at.to_rfc3339(),
+            timestamp: Utc::now().to_rfc3339(),
+        };
+
+        let payload = serde_json::to_string(&event)?;
+        self.kafka_producer
+            .send_message(ACCOUNT_EMAIL_TOPIC, &user.id.to_string(), &payload)
+            .await
+            .map_err(|e| anyhow!("Failed to queue {} email: {}", purpose.as_str(), e))
+    }
+
+    /// The stored token, if it is still usable
+    async fn find_valid(&self, purpose: TokenPurpose, token: &str) -> Result<UserToken> {
+        self.token_repo
+            .find_by_hash(purpose, &hash_token(token))
+            .await?
+            .filter(|stored| stored.is_usable(Utc::now()))
+            .ok_or_else(|| anyhow!("Invalid or expired token"))
+    }
+
+    /// Validate and burn a token, along with any other the user still holds
+    /// for the purpose (an older reset link must not outlive the reset).
+    /// Returns its user.
+    async fn consume(&self, purpose: TokenPurpose, token: &str) -> Result<i32> {
+        let stored = self.find_valid(purpose, token).await?;
+        if !self.token_repo.mark_used(stored.id, stored.user_id, purpose).await? {
+            return Err(anyhow!("Invalid or expired token"));
+        }
+
+        Ok(stored.user_id)
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{Result, anyhow};
+use apikeys::{scopes, ApiKey, ApiKeyStore, ApiKeyVerifier, NewApiKey};
+use chrono::{Duration, Utc};
+
+const KNOWN_SCOPES: &[&str] = &[scopes::ALL, scopes::AUTH, scopes::PAYMENTS_READ, scopes::CHAT_READ, scopes::KYC_READ, scopes::CONTACTS_READ];
+
+/// Issuing and revoking API keys for backend services
+#[derive(Clone)]
+pub struct ApiKeyService {
+    store: ApiKeyStore,
+    verifier: ApiKeyVerifier,
+}
+
+impl ApiKeyService {
+    pub fn new(store: ApiKeyStore, verifier: ApiKeyVerifier) -> Self {
+        Self { store, verifier }
+    }
+
+    /// Returns the stored key and the full key, which is shown only once
+    pub async fn create(
+        &self,
+        name: &str,
+        owner: &str,
+        scopes: Vec<String>,
+        expires_in_days: Option<i64>,
+        created_by: i32,
+    ) -> Result<(ApiKey, String)> {
+        if name.trim().is_empty() || owner.trim().is_empty() {
+            return Err(anyhow!("Name and owner must not be empty"));
+        }
+        if scopes.is_empty() {
+            return Err(anyhow!("At least one scope is required"));
+        }
+        if let Some(unknown) = scopes.iter().find(|s| !KNOWN_SCOPES.contains(&s.as_str())) {
+            return Err(anyhow!("Unknown scope {}", unknown));
+        }
+
+        let new_key = NewApiKey {
+            name: name.trim().to_string(),
+            owner: owner.trim().to_string(),
+            scopes,
+            created_by: Some(created_by),
+            expires_at: expires_in_days.map(|days| Utc::now() + Duration::days(days)),
+        };
+        let (key, secret) = self.store.create(&new_key).await?;
+        tracing::info!("User {} created API key {} for {}", created_by, key.prefix, key.owner);
+        Ok((key, secret))
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKey>> {
+        self.store.list().await
+    }
+
+    pub async fn revoke(&self, id: i64, revoked_by: i32) -> Result<()> {
+        if !self.store.revoke(id).await? {
+            return Err(anyhow!("API key not found or already revoked"));
+        }
+        // Other instances notice within the verifier cache lifetime
+        self.verifier.clear_cache();
+        tracing::info!("User {} revoked API key {}", revoked_by, id);
+        Ok(())
+    }
+
+    /// Move keys from the legacy AUTH_API_KEYS variable into the store, so
+    /// existing callers keep working and can be rotated from there
+    pub async fn import_legacy_keys(&self, keys: &[String]) -> Result<usize> {
+        let mut imported = 0;
+        for key in keys {
+            let new_key = NewApiKey {
+                name: "Imported from AUTH_API_KEYS".to_string(),
+                owner: "legacy".to_string(),
+                scopes: vec![scopes::AUTH.to_string()],
+                created_by: None,
+                expires_at: None,
+            };
+            if self.store.import(&new_key, key).await? {
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+}
@@ -1,85 +1,433 @@
 use anyhow::{Result, anyhow};
-use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{encode, Header, EncodingKey};
+use authz::{roles, Claims, TokenRevocation};
 use chrono::{Utc, Duration};
-use serde::{Serialize, Deserialize};
+use std::sync::OnceLock;
 
-use crate::domain::UserPublic;
-use crate::repo::UserRepository;
+use crate::domain::{Authentication, ClientInfo, Grants, LoginFailure, PublicKeyCredential, TokenPair, User, UserPublic};
+use crate::domain::mfa::{AMR_PASSKEY, AMR_PASSWORD};
+use crate::domain::password::{PasswordHasher, PasswordMatch, PasswordPolicy, UNUSABLE_PASSWORD};
+use crate::domain::token::{generate_opaque_token, hash_token};
+use crate::repo::{RefreshTokenRepository, RoleRepository, UserRepository};
+use super::{FederationService, KeyService, LockoutService, MfaService, PasskeyService, SessionService};
+use super::mfa_service::MFA_CHALLENGE_TTL_SECS;
 
-#[derive(Serialize, Deserialize)]
-struct Claims {
-    sub: String,
-    user_id: i32,
-    exp: i64,
+/// Lifetime of access tokens, ACCESS_TOKEN_TTL_MINUTES (default 15)
+pub fn access_token_ttl() -> Duration {
+    let minutes = std::env::var("ACCESS_TOKEN_TTL_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(15);
+    Duration::minutes(minutes)
+}
+
+/// Lifetime of refresh tokens, REFRESH_TOKEN_TTL_DAYS (default 30)
+pub fn refresh_token_ttl() -> Duration {
+    let days = std::env::var("REFRESH_TOKEN_TTL_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(30);
+    Duration::days(days)
+}
+
+/// Hash checked for unknown emails, so they cost as much as a wrong password
+fn dummy_password_hash(hasher: &PasswordHasher) -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hasher.hash("not-a-real-password").expect("Failed to hash dummy password"))
+}
+
+pub enum LoginOutcome {
+    Authenticated(TokenPair, UserPublic),
+    /// Password was right, a second factor is due with this token
+    MfaRequired { mfa_token: String, expires_in: u64, methods: Vec<&'static str> },
 }
 
 #[derive(Clone)]
 pub struct AuthService {
     user_repo: UserRepository,
-    jwt_secret: String,
+    refresh_token_repo: RefreshTokenRepository,
+    role_repo: RoleRepository,
+    revocation: TokenRevocation,
+    key_service: KeyService,
+    mfa_service: MfaService,
+    lockout_service: LockoutService,
+    session_service: SessionService,
+    passkey_service: PasskeyService,
+    federation_service: FederationService,
+    password_hasher: PasswordHasher,
+    password_policy: PasswordPolicy,
+    access_token_ttl: Duration,
+    refresh_token_ttl: Duration,
 }
 
 impl AuthService {
-    pub fn new(user_repo: UserRepository) -> Self {
-        let jwt_secret = std::env::var("JWT_SECRET")
-            .unwrap_or_else(|_| "RushTech@2025xAjxh".to_string());
-
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repo: UserRepository,
+        refresh_token_repo: RefreshTokenRepository,
+        role_repo: RoleRepository,
+        revocation: TokenRevocation,
+        key_service: KeyService,
+        mfa_service: MfaService,
+        lockout_service: LockoutService,
+        session_service: SessionService,
+        passkey_service: PasskeyService,
+        federation_service: FederationService,
+    ) -> Self {
         Self {
             user_repo,
-            jwt_secret,
+            refresh_token_repo,
+            role_repo,
+            revocation,
+            key_service,
+            mfa_service,
+            lockout_service,
+            session_service,
+            passkey_service,
+            federation_service,
+            password_hasher: PasswordHasher::from_env(),
+            password_policy: PasswordPolicy::from_env(),
+            access_token_ttl: access_token_ttl(),
+            refresh_token_ttl: refresh_token_ttl(),
         }
     }
 
-    pub async fn register(&self, name: &str, email: &str, password: &str) -> Result<(String, UserPublic)> {
+    pub async fn register(&self, name: &str, email: &str, password: &str, client: &ClientInfo) -> Result<(TokenPair, UserPublic)> {
         // Check if email already exists
         if self.user_repo.email_exists(email).await? {
             return Err(anyhow!("Email already registered"));
         }
+        self.password_policy.check(password, email)?;
 
         // Hash password
-        let hashed_password = hash(password, DEFAULT_COST)
-            .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+        let hashed_password = self.hash_password(password).await?;
 
         // Create user
-        let user_id = self.user_repo.create(name, email, &hashed_password).await?;
+        let user_id = self.user_repo.create(name, email, &hashed_password, false).await?;
+        if let Some(role) = self.role_repo.find_by_name(roles::USER).await? {
+            self.role_repo.assign(user_id, role.id, None).await?;
+        }
 
-        // Generate token
-        let token = self.generate_token(user_id, email)?;
+        // Generate tokens
+        let tokens = self.issue_tokens(user_id, email, false, &Authentication::password(), None, client).await?;
 
         let user_public = UserPublic {
             id: user_id,
             name: name.to_string(),
             email: email.to_string(),
+            email_verified: false,
+        };
+
+        Ok((tokens, user_public))
+    }
+
+    /// First login step. Users with two-factor authentication get a
+    /// challenge to answer with `complete_mfa_login` instead of tokens.
+    pub async fn login(&self, email: &str, password: &str, client: &ClientInfo) -> Result<LoginOutcome> {
+        let ip = client.ip.as_str();
+        self.lockout_service.check(email, ip).await?;
+
+        // Always hash so the response time does not reveal whether the email exists
+        let user = self.user_repo.find_by_email(email).await?;
+        let password_match = self.verify_password(password, user.as_ref().map(|u| u.password.clone())).await?;
+
+        let user = match user {
+            Some(user) if password_match != PasswordMatch::No => user,
+            Some(user) => {
+                self.lockout_service.record_failure(Some(user.id), email, ip, LoginFailure::BadPassword).await;
+                return Err(anyhow!("Invalid credentials"));
+            }
+            None => {
+                self.lockout_service.record_failure(None, email, ip, LoginFailure::UnknownEmail).await;
+                return Err(anyhow!("Invalid credentials"));
+            }
+        };
+        self.lockout_service.record_success(user.id, email, ip).await;
+        if password_match == PasswordMatch::YesRehash {
+            self.rehash_password(user.id, password).await;
+        }
+
+        self.finish_login(user, Authentication::password(), client).await
+    }
+
+    /// Login with the code an external identity provider redirected back
+    /// with; the account is linked or created on the first login
+    pub async fn federated_login(&self, state: &str, code: &str, client: &ClientInfo) -> Result<LoginOutcome> {
+        let user_id = self.federation_service.complete(state, code).await?;
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        self.finish_login(user, Authentication::federated(), client).await
+    }
+
+    /// Tokens for a user who passed the first factor, or a challenge when
+    /// a second factor is set up
+    async fn finish_login(&self, user: User, authentication: Authentication, client: &ClientInfo) -> Result<LoginOutcome> {
+        let methods = self.second_factors(user.id).await?;
+        if !methods.is_empty() {
+            let first_factor = authentication.amr.first().map(String::as_str).unwrap_or(AMR_PASSWORD);
+            return Ok(LoginOutcome::MfaRequired {
+                mfa_token: self.mfa_service.create_challenge(user.id, first_factor)?,
+                expires_in: MFA_CHALLENGE_TTL_SECS,
+                methods,
+            });
+        }
+
+        let tokens = self.start_session(&user, authentication, client).await?;
+        Ok(LoginOutcome::Authenticated(tokens, UserPublic::from(user)))
+    }
+
+    /// Second login step: a TOTP or recovery code for the challenge
+    pub async fn complete_mfa_login(&self, mfa_token: &str, code: &str, client: &ClientInfo) -> Result<(TokenPair, UserPublic)> {
+        let challenge = self.mfa_service.challenge(mfa_token)?;
+        let method = self.mfa_service.verify(challenge.user_id, code).await?;
+        self.mfa_service.clear_challenge(mfa_token);
+
+        let user = self.user_repo.find_by_id(challenge.user_id).await?
+            .ok_or_else(|| anyhow!("Invalid or expired MFA token"))?;
+        let tokens = self.start_session(&user, Authentication::with_factors(&challenge.first_factor, method), client).await?;
+        Ok((tokens, UserPublic::from(user)))
+    }
+
+    /// Second login step with a passkey, for options from `passkey_options`
+    pub async fn complete_mfa_login_passkey(&self, mfa_token: &str, credential: &PublicKeyCredential, client: &ClientInfo) -> Result<(TokenPair, UserPublic)> {
+        let challenge = self.mfa_service.challenge(mfa_token)?;
+        self.passkey_service.authenticate(Some(challenge.user_id), credential).await?;
+        self.mfa_service.clear_challenge(mfa_token);
+
+        let user = self.user_repo.find_by_id(challenge.user_id).await?
+            .ok_or_else(|| anyhow!("Invalid or expired MFA token"))?;
+        let tokens = self.start_session(&user, Authentication::with_factors(&challenge.first_factor, AMR_PASSKEY), client).await?;
+        Ok((tokens, UserPublic::from(user)))
+    }
+
+    /// Assertion options for the user of an MFA login challenge
+    pub async fn mfa_passkey_options(&self, mfa_token: &str) -> Result<serde_json::Value> {
+        let user_id = self.mfa_service.challenge_user(mfa_token)?;
+        self.passkey_service.authentication_options(Some(user_id)).await
+    }
+
+    /// Passwordless login: the passkey is the only factor
+    pub async fn passkey_login(&self, credential: &PublicKeyCredential, client: &ClientInfo) -> Result<(TokenPair, UserPublic)> {
+        let user_id = self.passkey_service.authenticate(None, credential).await?;
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("Invalid passkey"))?;
+        let tokens = self.start_session(&user, Authentication::passkey(), client).await?;
+        tracing::info!("User {} logged in with a passkey", user_id);
+        Ok((tokens, UserPublic::from(user)))
+    }
+
+    /// Fresh tokens for a logged-in user who just proved a second factor,
+    /// for actions that demand recent two-factor authentication
+    pub async fn step_up(&self, user_id: i32, code: &str, client: &ClientInfo) -> Result<TokenPair> {
+        let method = self.mfa_service.verify(user_id, code).await?;
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        self.start_session(&user, Authentication::with_second_factor(method), client).await
+    }
+
+    pub async fn step_up_passkey(&self, user_id: i32, credential: &PublicKeyCredential, client: &ClientInfo) -> Result<TokenPair> {
+        self.passkey_service.authenticate(Some(user_id), credential).await?;
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        self.start_session(&user, Authentication::with_second_factor(AMR_PASSKEY), client).await
+    }
+
+    /// Second factors the user can answer a login challenge with
+    pub async fn second_factors(&self, user_id: i32) -> Result<Vec<&'static str>> {
+        let mut methods = Vec::new();
+        if self.mfa_service.is_enabled(user_id).await? {
+            methods.push("totp");
+        }
+        if self.passkey_service.has_passkeys(user_id).await? {
+            methods.push("passkey");
+        }
+        Ok(methods)
+    }
+
+    /// Length and common-password rules for a new password
+    pub fn check_password(&self, password: &str, email: &str) -> Result<()> {
+        self.password_policy.check(password, email)
+    }
+
+    /// Set a new password chosen by the user, after checking it against the policy
+    pub async fn set_password(&self, user_id: i32, email: &str, password: &str) -> Result<()> {
+        self.password_policy.check(password, email)?;
+        let hashed_password = self.hash_password(password).await?;
+        self.user_repo.change_password(user_id, &hashed_password).await
+    }
+
+    /// Check the password again before an action that cannot be undone.
+    /// Accounts without one (federated or passkey only) must have signed in
+    /// within STEP_UP_MAX_AGE_SECS instead; `auth_time` comes from the token.
+    pub async fn confirm_password(&self, user_id: i32, password: &str, auth_time: Option<i64>) -> Result<()> {
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        if user.password == UNUSABLE_PASSWORD {
+            let recent = auth_time
+                .is_some_and(|t| Utc::now().timestamp() - t <= authz::step_up_max_age() as i64);
+            return if recent {
+                Ok(())
+            } else {
+                Err(anyhow!("Recent sign-in required: sign in again to confirm"))
+            };
+        }
+        match self.verify_password(password, Some(user.password)).await? {
+            PasswordMatch::No => Err(anyhow!("Invalid password")),
+            PasswordMatch::Yes | PasswordMatch::YesRehash => Ok(()),
+        }
+    }
+
+    /// Argon2 takes tens of milliseconds of CPU, so it runs on the blocking pool
+    async fn hash_password(&self, password: &str) -> Result<String> {
+        let hasher = self.password_hasher.clone();
+        let password = password.to_string();
+        tokio::task::spawn_blocking(move || hasher.hash(&password)).await?
+    }
+
+    async fn verify_password(&self, password: &str, stored: Option<String>) -> Result<PasswordMatch> {
+        let hasher = self.password_hasher.clone();
+        let password = password.to_string();
+        tokio::task::spawn_blocking(move || {
+            let usable = stored.as_deref().filter(|stored| *stored != UNUSABLE_PASSWORD);
+            let result = hasher.verify(&password, usable.unwrap_or_else(|| dummy_password_hash(&hasher)))?;
+            // The dummy hash only costs time, it never logs anyone in
+            Ok(if usable.is_some() { result } else { PasswordMatch::No })
+        })
+        .await?
+    }
+
+    /// Move a bcrypt or outdated Argon2 hash to the current settings, now
+    /// that the plain password is at hand. Login goes on if this fails.
+    async fn rehash_password(&self, user_id: i32, password: &str) {
+        let result = match self.hash_password(password).await {
+            Ok(hashed_password) => self.user_repo.update_password(user_id, &hashed_password).await,
+            Err(e) => Err(e),
         };
+        match result {
+            Ok(()) => tracing::info!("Rehashed password of user {}", user_id),
+            Err(e) => tracing::error!("Failed to rehash password of user {}: {}", user_id, e),
+        }
+    }
 
-        Ok((token, user_public))
+    async fn start_session(&self, user: &User, authentication: Authentication, client: &ClientInfo) -> Result<TokenPair> {
+        self.issue_tokens(user.id, &user.email, user.is_verified(), &authentication, None, client).await
     }
 
-    pub async fn login(&self, email: &str, password: &str) -> Result<(String, UserPublic)> {
-        // Find user by email
-        let user = self.user_repo.find_by_email(email).await?
-            .ok_or_else(|| anyhow!("Invalid credentials"))?;
+    /// Exchange a refresh token for a new access token and refresh token.
+    /// Presenting a token that was already rotated means it leaked, so the
+    /// whole family is revoked and the user has to log in again.
+    pub async fn refresh(&self, refresh_token: &str, client: &ClientInfo) -> Result<TokenPair> {
+        let stored = self.refresh_token_repo
+            .find_by_hash(&hash_token(refresh_token))
+            .await?
+            .ok_or_else(|| anyhow!("Invalid refresh token"))?;
+
+        if stored.revoked_at.is_some() {
+            return Err(anyhow!("Invalid refresh token"));
+        }
+        if stored.used_at.is_some() || !self.refresh_token_repo.mark_used(stored.id).await? {
+            // Denies the access tokens of the session by sid, including those of
+            // the current second that the watermark below lets through
+            self.session_service.end(stored.user_id, &stored.family_id, "reuse_detected").await?;
+            if let Err(e) = self.revocation.revoke_user_tokens(stored.user_id) {
+                tracing::error!("Failed to revoke access tokens of user {}: {}", stored.user_id, e);
+            }
+            tracing::warn!("Refresh token reuse detected for user {}, revoked family {}", stored.user_id, stored.family_id);
+            return Err(anyhow!("Refresh token reuse detected"));
+        }
+        if stored.expires_at <= Utc::now() {
+            return Err(anyhow!("Invalid refresh token"));
+        }
 
-        // Verify password
-        let password_match = verify(password, &user.password)
-            .map_err(|e| anyhow!("Password verification failed: {}", e))?;
+        let user = self.user_repo
+            .find_by_id(stored.user_id)
+            .await?
+            .ok_or_else(|| anyhow!("Invalid refresh token"))?;
+
+        // The session keeps how and when the user originally authenticated
+        self.issue_tokens(user.id, &user.email, user.is_verified(), &stored.authentication(), Some(&stored.family_id), client).await
+    }
+
+    /// End the session the refresh token belongs to, and deny the access
+    /// token of the request if one was sent
+    pub async fn logout(&self, refresh_token: &str, access_token: Option<&str>) -> Result<()> {
+        if let Some(stored) = self.refresh_token_repo.find_by_hash(&hash_token(refresh_token)).await? {
+            self.session_service.end(stored.user_id, &stored.family_id, "logout").await?;
+            tracing::info!("User {} logged out", stored.user_id);
+        }
 
-        if !password_match {
-            return Err(anyhow!("Invalid credentials"));
+        let claims = match access_token {
+            Some(token) => self.key_service.validator().without_revocation().verify_token(token).await.ok(),
+            None => None,
+        };
+        if let Some(Claims { jti: Some(jti), exp, .. }) = claims {
+            self.revocation.revoke_token(&jti, exp)?;
         }
+        Ok(())
+    }
 
-        // Generate token
-        let token = self.generate_token(user.id, &user.email)?;
+    /// Claims of an access token issued by this service that is still valid
+    pub async fn verify_access_token(&self, token: &str) -> Result<Claims> {
+        self.key_service.validator().verify_token(token).await
+    }
 
-        let user_public = UserPublic::from(user);
+    /// End every session of the user. Returns the number of revoked tokens.
+    pub async fn logout_all(&self, user_id: i32) -> Result<u64> {
+        self.revocation.revoke_user_tokens(user_id)?;
+        let revoked = self.refresh_token_repo.revoke_all_for_user(user_id, "logout_all").await?;
+        self.session_service.end_all(user_id).await?;
+        tracing::info!("User {} logged out of all devices ({} tokens revoked)", user_id, revoked);
+        Ok(revoked)
+    }
 
-        Ok((token, user_public))
+    async fn issue_tokens(
+        &self,
+        user_id: i32,
+        email: &str,
+        verified: bool,
+        authentication: &Authentication,
+        family_id: Option<&str>,
+        client: &ClientInfo,
+    ) -> Result<TokenPair> {
+        // A new family is a new login session
+        let family_id = match family_id {
+            Some(family_id) => {
+                self.session_service.touch(family_id, client).await?;
+                family_id.to_string()
+            }
+            None => {
+                let family_id = uuid::Uuid::new_v4().to_string();
+                self.session_service.start(user_id, &family_id, client).await?;
+                family_id
+            }
+        };
+
+        let grants = self.role_repo.grants_for_user(user_id).await?;
+        let token = self.generate_token(user_id, email, verified, grants, authentication, &family_id)?;
+
+        let refresh_token = generate_opaque_token();
+        self.refresh_token_repo
+            .create(user_id, &family_id, &hash_token(&refresh_token), Utc::now() + self.refresh_token_ttl, authentication)
+            .await?;
+
+        Ok(TokenPair {
+            token,
+            refresh_token,
+            expires_in: self.access_token_ttl.num_seconds(),
+        })
     }
 
-    fn generate_token(&self, user_id: i32, email: &str) -> Result<String> {
-        let expiration = Utc::now()
-            .checked_add_signed(Duration::hours(24))
+    fn generate_token(
+        &self,
+        user_id: i32,
+        email: &str,
+        verified: bool,
+        grants: Grants,
+        authentication: &Authentication,
+        family_id: &str,
+    ) -> Result<String> {
+        let now = Utc::now();
+        let expiration = now
+            .checked_add_signed(self.access_token_ttl)
             .ok_or_else(|| anyhow!("Invalid timestamp"))?
             .timestamp();
 
@@ -87,15 +435,20 @@ impl AuthService {
             sub: email.to_string(),
             user_id,
             exp: expiration,
+            iat: Some(now.timestamp()),
+            jti: Some(uuid::Uuid::new_v4().to_string()),
+            iss: Some(self.key_service.issuer().to_string()),
+            aud: self.key_service.audience().map(|aud| aud.to_string()),
+            roles: grants.roles,
+            permissions: grants.permissions,
+            // Until the email is verified only account endpoints accept the token
+            restricted: !verified,
+            amr: authentication.amr.clone(),
+            auth_time: Some(authentication.auth_time.timestamp()),
+            client_id: None,
+            sid: Some(family_id.to_string()),
         };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| anyhow!("Token generation failed: {}", e))?;
-
-        Ok(token)
+        self.key_service.sign(&claims)
     }
 }
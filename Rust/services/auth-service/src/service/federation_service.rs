@@ -0,0 +1,199 @@
+use anyhow::{Result, anyhow};
+use authz::roles;
+use common::cache::RedisCache;
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::domain::{ExternalIdentity, FederatedIdentity, ProviderConfig};
+use crate::domain::federation::PendingFederatedLogin;
+use crate::domain::oidc::pkce_challenge;
+use crate::domain::password::UNUSABLE_PASSWORD;
+use crate::domain::token::{generate_opaque_token, hash_token};
+use crate::repo::{IdentityRepository, PasskeyRepository, RoleRepository, UserRepository};
+use super::IdentityProvider;
+
+// Time the user has to log in at the provider
+const LOGIN_STATE_TTL_SECS: u64 = 600;
+
+/// Provider as offered on the login page
+#[derive(Debug, Serialize)]
+pub struct ProviderInfo {
+    pub name: String,
+    pub display_name: String,
+}
+
+/// Login through external OpenID Connect providers, with the provider
+/// accounts linked to users
+#[derive(Clone)]
+pub struct FederationService {
+    providers: Arc<Vec<IdentityProvider>>,
+    identity_repo: IdentityRepository,
+    user_repo: UserRepository,
+    role_repo: RoleRepository,
+    passkey_repo: PasskeyRepository,
+    redis: RedisCache,
+    redirect_uri: String,
+}
+
+impl FederationService {
+    pub fn new(
+        identity_repo: IdentityRepository,
+        user_repo: UserRepository,
+        role_repo: RoleRepository,
+        passkey_repo: PasskeyRepository,
+        redis: RedisCache,
+    ) -> Self {
+        let providers = ProviderConfig::names_from_env()
+            .into_iter()
+            .filter_map(|name| match ProviderConfig::from_env(&name) {
+                Ok(config) => Some(IdentityProvider::new(config)),
+                Err(e) => {
+                    tracing::error!("Identity provider {} is not set up: {}", name, e);
+                    None
+                }
+            })
+            .collect();
+        // The page of the app that posts the code and state back to us
+        let redirect_uri = std::env::var("FEDERATED_REDIRECT_URI").unwrap_or_else(|_| {
+            let base = std::env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+            format!("{}/auth/callback", base.trim_end_matches('/'))
+        });
+
+        Self {
+            providers: Arc::new(providers),
+            identity_repo,
+            user_repo,
+            role_repo,
+            passkey_repo,
+            redis,
+            redirect_uri,
+        }
+    }
+
+    pub fn providers(&self) -> Vec<ProviderInfo> {
+        self.providers
+            .iter()
+            .map(|provider| ProviderInfo {
+                name: provider.config().name.clone(),
+                display_name: provider.config().display_name.clone(),
+            })
+            .collect()
+    }
+
+    /// URL of the provider's login page; it redirects back to
+    /// FEDERATED_REDIRECT_URI with a code and the state
+    pub async fn start(&self, provider_name: &str) -> Result<String> {
+        let provider = self.provider(provider_name)?;
+        let state = generate_opaque_token();
+        let pending = PendingFederatedLogin {
+            provider: provider.config().name.clone(),
+            nonce: generate_opaque_token(),
+            code_verifier: generate_opaque_token(),
+        };
+
+        let url = provider
+            .authorization_url(&self.redirect_uri, &state, &pending.nonce, &pkce_challenge(&pending.code_verifier))
+            .await
+            .map_err(|e| {
+                tracing::error!("Identity provider {} is unavailable: {}", provider_name, e);
+                anyhow!("Identity provider is unavailable")
+            })?;
+        self.redis.set(&state_key(&state), &pending, LOGIN_STATE_TTL_SECS)?;
+        Ok(url)
+    }
+
+    /// Check the provider's answer to a login from `start` and return the
+    /// user it belongs to, linking or creating the account on first login
+    pub async fn complete(&self, state: &str, code: &str) -> Result<i32> {
+        let key = state_key(state);
+        let pending = self.redis.get::<PendingFederatedLogin>(&key)?
+            .ok_or_else(|| anyhow!("Invalid or expired login state"))?;
+        // Each state is answered once, even when two callbacks race
+        if !self.redis.set_nx(&format!("{}:used", key), "1", LOGIN_STATE_TTL_SECS)? {
+            return Err(anyhow!("Invalid or expired login state"));
+        }
+        self.redis.delete(&key)?;
+
+        let provider = self.provider(&pending.provider)?;
+        let identity = provider
+            .login(code, &self.redirect_uri, &pending.code_verifier, &pending.nonce)
+            .await?;
+        self.resolve_user(&identity).await
+    }
+
+    pub async fn list_identities(&self, user_id: i32) -> Result<Vec<FederatedIdentity>> {
+        self.identity_repo.list_for_user(user_id).await
+    }
+
+    /// Unlink a provider account, unless the user could not log in anymore
+    pub async fn unlink(&self, user_id: i32, id: i64) -> Result<()> {
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        let identities = self.identity_repo.list_for_user(user_id).await?;
+        if !identities.iter().any(|identity| identity.id == id) {
+            return Err(anyhow!("Linked identity not found"));
+        }
+        let other_logins = user.password != UNUSABLE_PASSWORD
+            || identities.len() > 1
+            || self.passkey_repo.count_for_user(user_id).await? > 0;
+        if !other_logins {
+            return Err(anyhow!("Cannot unlink the only way to log in, set a password first"));
+        }
+
+        if !self.identity_repo.delete(id, user_id).await? {
+            return Err(anyhow!("Linked identity not found"));
+        }
+        tracing::info!("User {} unlinked identity {}", user_id, id);
+        Ok(())
+    }
+
+    async fn resolve_user(&self, identity: &ExternalIdentity) -> Result<i32> {
+        if let Some(linked) = self.identity_repo.find(&identity.provider, &identity.subject).await? {
+            self.identity_repo.record_login(linked.id).await?;
+            return Ok(linked.user_id);
+        }
+
+        // Only an address the provider vouches for may reach an existing account
+        let email = identity.email.as_deref()
+            .filter(|_| identity.email_verified)
+            .ok_or_else(|| anyhow!("Identity provider did not return a verified email"))?;
+
+        let user_id = match self.user_repo.find_by_email(email).await? {
+            // An unverified account may have been registered by someone else
+            // with this address, linking it would let them in
+            Some(user) if !user.is_verified() => {
+                return Err(anyhow!("Email already registered, log in with your password and verify your email to link this account"));
+            }
+            Some(user) => {
+                tracing::info!("Linking {} identity to user {} by email", identity.provider, user.id);
+                user.id
+            }
+            None => self.create_user(identity, email).await?,
+        };
+
+        self.identity_repo.create(user_id, identity).await?;
+        Ok(user_id)
+    }
+
+    async fn create_user(&self, identity: &ExternalIdentity, email: &str) -> Result<i32> {
+        let name = identity.name.clone()
+            .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+        let user_id = self.user_repo.create(&name, email, UNUSABLE_PASSWORD, true).await?;
+        if let Some(role) = self.role_repo.find_by_name(roles::USER).await? {
+            self.role_repo.assign(user_id, role.id, None).await?;
+        }
+        tracing::info!("Created user {} on first {} login", user_id, identity.provider);
+        Ok(user_id)
+    }
+
+    fn provider(&self, name: &str) -> Result<&IdentityProvider> {
+        self.providers
+            .iter()
+            .find(|provider| provider.config().name == name)
+            .ok_or_else(|| anyhow!("Unknown identity provider"))
+    }
+}
+
+fn state_key(state: &str) -> String {
+    format!("federated_login:{}", hash_token(state))
+}
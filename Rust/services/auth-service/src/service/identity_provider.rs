@@ -0,0 +1,352 @@
+use anyhow::{Result, anyhow};
+use authz::jwks::JwksCache;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+use crate::domain::{ExternalIdentity, ProviderConfig};
+use crate::domain::federation::{ExternalIdTokenClaims, ProviderMetadata};
+use crate::domain::oidc::redirect_with;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+// Clock difference tolerated with the provider
+const LEEWAY_SECS: u64 = 60;
+// Asymmetric only: HS256 would be keyed with our own client secret
+const ACCEPTED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256, Algorithm::RS384, Algorithm::RS512,
+    Algorithm::PS256, Algorithm::PS384, Algorithm::PS512,
+    Algorithm::ES256, Algorithm::ES384, Algorithm::EdDSA,
+];
+
+struct Discovered {
+    metadata: ProviderMetadata,
+    jwks: JwksCache,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+/// Relying party of one external OpenID Connect provider. Its metadata is
+/// discovered on first use; signing keys follow the provider's JWKS.
+pub struct IdentityProvider {
+    config: ProviderConfig,
+    client: reqwest::Client,
+    discovered: OnceCell<Discovered>,
+}
+
+impl IdentityProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            discovered: OnceCell::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    /// Where to send the user's browser to log in at the provider
+    pub async fn authorization_url(&self, redirect_uri: &str, state: &str, nonce: &str, code_challenge: &str) -> Result<String> {
+        let discovered = self.discovered().await?;
+        Ok(redirect_with(&discovered.metadata.authorization_endpoint, &[
+            ("response_type", "code"),
+            ("client_id", &self.config.client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", &self.config.scopes),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", code_challenge),
+            ("code_challenge_method", "S256"),
+        ]))
+    }
+
+    /// Exchange the code the user came back with and check its ID token
+    pub async fn login(&self, code: &str, redirect_uri: &str, code_verifier: &str, nonce: &str) -> Result<ExternalIdentity> {
+        let discovered = self.discovered().await?;
+        let response = self.client
+            .post(&discovered.metadata.token_endpoint)
+            .timeout(HTTP_TIMEOUT)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", code_verifier),
+                ("client_id", &self.config.client_id),
+                ("client_secret", &self.config.client_secret),
+            ])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!("Token endpoint of {} returned {}: {}", self.config.name, status, body);
+            return Err(anyhow!("Identity provider rejected the login code"));
+        }
+        let id_token = response.json::<TokenResponse>().await?
+            .id_token
+            .ok_or_else(|| anyhow!("Identity provider returned no ID token"))?;
+
+        self.verify_id_token(&id_token, nonce).await
+    }
+
+    /// Signature, issuer, audience and expiry (OIDC Core 3.1.3.7), then the
+    /// nonce of the login
+    pub async fn verify_id_token(&self, id_token: &str, nonce: &str) -> Result<ExternalIdentity> {
+        let discovered = self.discovered().await?;
+        let header = decode_header(id_token).map_err(|e| anyhow!("Invalid ID token: {}", e))?;
+        if !ACCEPTED_ALGORITHMS.contains(&header.alg) {
+            return Err(anyhow!("Invalid ID token: algorithm {:?} not accepted", header.alg));
+        }
+        let kid = header.kid.ok_or_else(|| anyhow!("Invalid ID token: no key id"))?;
+        let keys = discovered.jwks.key_set(&kid).await?;
+        let jwk = keys.find(&kid).ok_or_else(|| anyhow!("Invalid ID token: unknown signing key"))?;
+        let key = DecodingKey::from_jwk(jwk).map_err(|e| anyhow!("Invalid ID token: {}", e))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&discovered.metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        validation.leeway = LEEWAY_SECS;
+        let claims = decode::<ExternalIdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| anyhow!("Invalid ID token: {}", e))?
+            .claims;
+
+        claims.into_identity(&self.config, nonce)
+    }
+
+    /// Discovery is retried on the next login when it fails
+    async fn discovered(&self) -> Result<&Discovered> {
+        self.discovered
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+                let response = self.client.get(&url).timeout(HTTP_TIMEOUT).send().await?;
+                if !response.status().is_success() {
+                    return Err(anyhow!("Discovery of {} returned {}", self.config.name, response.status()));
+                }
+                let metadata = response.json::<ProviderMetadata>().await?;
+                // OIDC Discovery 4.3: the metadata must be about the configured issuer
+                if metadata.issuer.trim_end_matches('/') != self.config.issuer {
+                    return Err(anyhow!("Discovery of {} returned the issuer {}", self.config.name, metadata.issuer));
+                }
+                let jwks = JwksCache::new(metadata.jwks_uri.clone());
+                Ok(Discovered { metadata, jwks })
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::oidc::{pkce_challenge, verify_pkce};
+    use crate::domain::token::generate_opaque_token;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const CLIENT_ID: &str = "rushtech";
+    const CLIENT_SECRET: &str = "mock-secret";
+    const REDIRECT_URI: &str = "http://localhost:3000/auth/callback";
+    const KID: &str = "mock-1";
+
+    /// How the mock misbehaves
+    #[derive(Default)]
+    struct MockOptions {
+        claims: serde_json::Value, // merged over the ID token claims
+        advertised_issuer: Option<String>,
+        sign_with_other_key: bool,
+    }
+
+    /// Local OpenID provider: discovery, JWKS, an authorize endpoint that
+    /// logs the user in right away and a token endpoint checking PKCE
+    struct MockIdp {
+        issuer: String,
+        pkcs8: Vec<u8>,
+        options: MockOptions,
+        codes: Mutex<HashMap<String, (String, String)>>, // code -> (code_challenge, id_token)
+    }
+
+    fn generate_pkcs8() -> Vec<u8> {
+        Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap().as_ref().to_vec()
+    }
+
+    async fn discovery(idp: web::Data<MockIdp>) -> HttpResponse {
+        HttpResponse::Ok().json(serde_json::json!({
+            "issuer": idp.options.advertised_issuer.clone().unwrap_or_else(|| idp.issuer.clone()),
+            "authorization_endpoint": format!("{}/authorize", idp.issuer),
+            "token_endpoint": format!("{}/token", idp.issuer),
+            "jwks_uri": format!("{}/jwks", idp.issuer)
+        }))
+    }
+
+    async fn jwks(idp: web::Data<MockIdp>) -> HttpResponse {
+        let public_key = Ed25519KeyPair::from_pkcs8(&idp.pkcs8).unwrap().public_key().as_ref().to_vec();
+        HttpResponse::Ok().json(serde_json::json!({
+            "keys": [{ "kty": "OKP", "crv": "Ed25519", "alg": "EdDSA", "kid": KID, "x": URL_SAFE_NO_PAD.encode(public_key) }]
+        }))
+    }
+
+    async fn authorize(idp: web::Data<MockIdp>, query: web::Query<HashMap<String, String>>) -> HttpResponse {
+        if query.get("client_id").map(String::as_str) != Some(CLIENT_ID)
+            || query.get("code_challenge_method").map(String::as_str) != Some("S256")
+        {
+            return HttpResponse::BadRequest().finish();
+        }
+        let now = chrono::Utc::now().timestamp();
+        let mut claims = serde_json::json!({
+            "iss": idp.issuer,
+            "aud": CLIENT_ID,
+            "sub": "248289761001",
+            "email": "an@example.com",
+            "email_verified": true,
+            "name": "An Nguyen",
+            "nonce": query.get("nonce"),
+            "iat": now,
+            "exp": now + 300
+        });
+        if let Some(overrides) = idp.options.claims.as_object() {
+            for (key, value) in overrides {
+                claims[key] = value.clone();
+            }
+        }
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(KID.to_string());
+        let pkcs8 = if idp.options.sign_with_other_key { generate_pkcs8() } else { idp.pkcs8.clone() };
+        let id_token = encode(&header, &claims, &EncodingKey::from_ed_der(&pkcs8)).unwrap();
+
+        let code = generate_opaque_token();
+        idp.codes.lock().unwrap().insert(code.clone(), (query["code_challenge"].clone(), id_token));
+        let location = redirect_with(&query["redirect_uri"], &[("code", &code), ("state", &query["state"])]);
+        HttpResponse::Found().insert_header(("Location", location)).finish()
+    }
+
+    async fn token(idp: web::Data<MockIdp>, form: web::Form<HashMap<String, String>>) -> HttpResponse {
+        let invalid_grant = HttpResponse::BadRequest().json(serde_json::json!({ "error": "invalid_grant" }));
+        if form.get("client_id").map(String::as_str) != Some(CLIENT_ID)
+            || form.get("client_secret").map(String::as_str) != Some(CLIENT_SECRET)
+        {
+            return HttpResponse::Unauthorized().json(serde_json::json!({ "error": "invalid_client" }));
+        }
+        let grant = form.get("code").and_then(|code| idp.codes.lock().unwrap().remove(code));
+        match grant {
+            Some((challenge, id_token)) if verify_pkce(form.get("code_verifier").map(String::as_str).unwrap_or_default(), &challenge) => {
+                HttpResponse::Ok().json(serde_json::json!({ "access_token": "mock", "token_type": "Bearer", "id_token": id_token }))
+            }
+            _ => invalid_grant,
+        }
+    }
+
+    /// Start a mock provider on a free port; returns its issuer URL
+    fn start_mock_idp(options: MockOptions) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let idp = web::Data::new(MockIdp {
+            issuer: issuer.clone(),
+            pkcs8: generate_pkcs8(),
+            options,
+            codes: Mutex::new(HashMap::new()),
+        });
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(idp.clone())
+                .route("/.well-known/openid-configuration", web::get().to(discovery))
+                .route("/jwks", web::get().to(jwks))
+                .route("/authorize", web::get().to(authorize))
+                .route("/token", web::post().to(token))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+        issuer
+    }
+
+    fn mock_provider(issuer: &str) -> IdentityProvider {
+        IdentityProvider::new(ProviderConfig {
+            name: "mock".to_string(),
+            display_name: "Mock".to_string(),
+            issuer: issuer.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: CLIENT_SECRET.to_string(),
+            scopes: "openid email profile".to_string(),
+            trust_email: false,
+        })
+    }
+
+    /// Follow the authorization URL like a browser whose user is logged in
+    /// at the provider; returns the code it redirects back with
+    async fn authorize_at(provider: &IdentityProvider, nonce: &str, code_verifier: &str) -> String {
+        let url = provider.authorization_url(REDIRECT_URI, "state-1", nonce, &pkce_challenge(code_verifier)).await.unwrap();
+        let browser = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+        let response = browser.get(&url).send().await.unwrap();
+        let location = reqwest::Url::parse(response.headers()["Location"].to_str().unwrap()).unwrap();
+        assert!(location.as_str().starts_with(REDIRECT_URI));
+        let query: HashMap<_, _> = location.query_pairs().into_owned().collect();
+        assert_eq!(query["state"], "state-1");
+        query["code"].clone()
+    }
+
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+    #[actix_web::test]
+    async fn test_login_with_mock_idp() {
+        let provider = mock_provider(&start_mock_idp(MockOptions::default()));
+
+        let code = authorize_at(&provider, "nonce-1", VERIFIER).await;
+        let identity = provider.login(&code, REDIRECT_URI, VERIFIER, "nonce-1").await.unwrap();
+        assert_eq!(identity, ExternalIdentity {
+            provider: "mock".to_string(),
+            subject: "248289761001".to_string(),
+            email: Some("an@example.com".to_string()),
+            email_verified: true,
+            name: Some("An Nguyen".to_string()),
+        });
+
+        // Codes are single use, and bound to the PKCE verifier
+        assert!(provider.login(&code, REDIRECT_URI, VERIFIER, "nonce-1").await.is_err());
+        let code = authorize_at(&provider, "nonce-2", VERIFIER).await;
+        let other_verifier = "x".repeat(43);
+        assert!(provider.login(&code, REDIRECT_URI, &other_verifier, "nonce-2").await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_id_tokens_that_do_not_fit_are_rejected() {
+        let provider = mock_provider(&start_mock_idp(MockOptions::default()));
+        let code = authorize_at(&provider, "nonce-1", VERIFIER).await;
+        let error = provider.login(&code, REDIRECT_URI, VERIFIER, "another-nonce").await.unwrap_err();
+        assert!(error.to_string().contains("nonce"));
+
+        let cases = [
+            MockOptions { claims: serde_json::json!({ "aud": "someone-else" }), ..Default::default() },
+            MockOptions { claims: serde_json::json!({ "iss": "https://evil.example" }), ..Default::default() },
+            MockOptions { claims: serde_json::json!({ "exp": chrono::Utc::now().timestamp() - 600 }), ..Default::default() },
+            MockOptions { sign_with_other_key: true, ..Default::default() },
+        ];
+        for options in cases {
+            let provider = mock_provider(&start_mock_idp(options));
+            let code = authorize_at(&provider, "nonce-1", VERIFIER).await;
+            let error = provider.login(&code, REDIRECT_URI, VERIFIER, "nonce-1").await.unwrap_err();
+            assert!(error.to_string().starts_with("Invalid ID token"), "{}", error);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_discovery_must_name_the_configured_issuer() {
+        let issuer = start_mock_idp(MockOptions {
+            advertised_issuer: Some("https://evil.example".to_string()),
+            ..Default::default()
+        });
+        let error = mock_provider(&issuer).authorization_url(REDIRECT_URI, "s", "n", "c").await.unwrap_err();
+        assert!(error.to_string().contains("returned the issuer"));
+    }
+}
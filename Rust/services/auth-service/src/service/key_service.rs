@@ -0,0 +1,209 @@
+use anyhow::{Result, anyhow};
+use authz::{JwtValidator, ValidationConfig};
+use chrono::{DateTime, Duration, Utc};
+use common::cache::RedisCache;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+
+use crate::domain::signing_key::{generate_signing_key, parse_algorithm};
+use crate::repo::SigningKeyRepository;
+use super::auth_service::access_token_ttl;
+
+const ROTATION_LOCK_KEY: &str = "lock:jwt_key_rotation";
+// Verifiers cache the JWKS for 10 minutes, so a new key is published at
+// least that long before anything is signed with it
+const ACTIVATION_DELAY_MINUTES: i64 = 15;
+// Extra time a retired key stays published after its last token expired
+const RETIREMENT_GRACE_MINUTES: i64 = 5;
+
+#[derive(Clone)]
+struct LoadedKey {
+    kid: String,
+    encoding_key: EncodingKey,
+    activates_at: DateTime<Utc>,
+}
+
+/// Signing keys of the access tokens. With RS256 or EdDSA (the default) the
+/// keys live in the database, are rotated on a schedule and published as a
+/// JWKS; HS256 keeps the legacy shared JWT_SECRET.
+#[derive(Clone)]
+pub struct KeyService {
+    repo: SigningKeyRepository,
+    redis: RedisCache,
+    algorithm: Algorithm,
+    secret: String,
+    rotation_interval: Duration,
+    validation: ValidationConfig,
+    keys: Arc<RwLock<Vec<LoadedKey>>>,
+    jwks: Arc<RwLock<JwkSet>>,
+}
+
+impl KeyService {
+    pub fn new(repo: SigningKeyRepository, redis: RedisCache) -> Self {
+        let algorithm = std::env::var("JWT_SIGNING_ALGORITHM")
+            .ok()
+            .map(|v| parse_algorithm(&v).expect("Invalid JWT_SIGNING_ALGORITHM"))
+            .unwrap_or(Algorithm::EdDSA);
+
+        // The shared secret only exists for HS256, which has no default for it
+        let secret = if algorithm == Algorithm::HS256 {
+            tracing::warn!("Signing tokens with the shared JWT_SECRET, set JWT_SIGNING_ALGORITHM to RS256 or EdDSA");
+            std::env::var("JWT_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty())
+                .expect("JWT_SECRET must be set when JWT_SIGNING_ALGORITHM is HS256")
+        } else {
+            String::new()
+        };
+        let rotation_days = std::env::var("JWT_KEY_ROTATION_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(30);
+
+        let mut validation = ValidationConfig::from_env();
+        validation.issuer = Some(validation.issuer.unwrap_or_else(|| "auth-service".to_string()));
+
+        Self {
+            repo,
+            redis,
+            algorithm,
+            secret,
+            rotation_interval: Duration::days(rotation_days),
+            validation,
+            keys: Arc::new(RwLock::new(Vec::new())),
+            jwks: Arc::new(RwLock::new(JwkSet { keys: Vec::new() })),
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        self.validation.issuer.as_deref().unwrap_or("auth-service")
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn audience(&self) -> Option<&str> {
+        self.validation.audience.as_deref()
+    }
+
+    /// Sign with the newest active key, tagging the token with its kid
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        if self.algorithm == Algorithm::HS256 {
+            return encode(&Header::default(), claims, &EncodingKey::from_secret(self.secret.as_bytes()))
+                .map_err(|e| anyhow!("Token generation failed: {}", e));
+        }
+
+        let keys = self.keys.read().map_err(|_| anyhow!("Signing keys lock poisoned"))?;
+        let now = Utc::now();
+        let key = keys
+            .iter()
+            .rev()
+            .find(|key| key.activates_at <= now)
+            .or_else(|| keys.first())
+            .ok_or_else(|| anyhow!("No signing key loaded"))?;
+
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(key.kid.clone());
+        encode(&header, claims, &key.encoding_key).map_err(|e| anyhow!("Token generation failed: {}", e))
+    }
+
+    /// Public keys of every published signing key
+    pub fn jwks(&self) -> JwkSet {
+        self.jwks.read().map(|jwks| jwks.clone()).unwrap_or(JwkSet { keys: Vec::new() })
+    }
+
+    /// Validator for tokens issued by this service, following the local keys as they rotate
+    pub fn validator(&self) -> JwtValidator {
+        let validator = if self.algorithm == Algorithm::HS256 {
+            JwtValidator::new(self.secret.clone())
+        } else {
+            JwtValidator::from_shared_jwks(self.jwks.clone())
+        };
+        validator.with_config(self.validation.clone())
+    }
+
+    /// Load the published keys from the database
+    pub async fn reload(&self) -> Result<()> {
+        if self.algorithm == Algorithm::HS256 {
+            return Ok(());
+        }
+
+        let mut loaded = Vec::new();
+        let mut public_keys: Vec<Jwk> = Vec::new();
+        for key in self.repo.find_published().await? {
+            let algorithm = parse_algorithm(&key.algorithm)?;
+            let encoding_key = match algorithm {
+                Algorithm::EdDSA => EncodingKey::from_ed_pem(key.private_key_pem.as_bytes()),
+                _ => EncodingKey::from_rsa_pem(key.private_key_pem.as_bytes()),
+            }
+            .map_err(|e| anyhow!("Invalid signing key {}: {}", key.kid, e))?;
+            let jwk = serde_json::from_str(&key.public_jwk)
+                .map_err(|e| anyhow!("Invalid public key {}: {}", key.kid, e))?;
+
+            public_keys.push(jwk);
+            loaded.push(LoadedKey {
+                kid: key.kid,
+                encoding_key,
+                activates_at: key.activates_at,
+            });
+        }
+
+        *self.keys.write().map_err(|_| anyhow!("Signing keys lock poisoned"))? = loaded;
+        *self.jwks.write().map_err(|_| anyhow!("Signing keys lock poisoned"))? = JwkSet { keys: public_keys };
+        Ok(())
+    }
+
+    /// Create a new key once the newest one is older than the rotation
+    /// interval. Previous keys stay published until the tokens they signed
+    /// have expired. Returns whether a key was created.
+    pub async fn rotate_if_due(&self) -> Result<bool> {
+        if self.algorithm == Algorithm::HS256 {
+            return Ok(false);
+        }
+
+        let published = self.repo.find_published().await?;
+        let newest = published.iter().map(|key| key.created_at).max();
+        if newest.map(|created_at| Utc::now() - created_at < self.rotation_interval).unwrap_or(false) {
+            return Ok(false);
+        }
+
+        // Only one instance rotates; the others pick the key up on reload
+        match self.redis.set_nx(ROTATION_LOCK_KEY, "1", 60) {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            // Without any key nothing can be signed, so go ahead regardless
+            Err(e) if published.is_empty() => tracing::error!("Key rotation lock failed: {}", e),
+            Err(e) => {
+                tracing::error!("Key rotation lock failed, skipping rotation: {}", e);
+                return Ok(false);
+            }
+        }
+
+        let key = generate_signing_key(self.algorithm)?;
+        // The first key is used right away, later ones once verifiers have seen them
+        let activates_at = if published.is_empty() {
+            Utc::now()
+        } else {
+            Utc::now() + Duration::minutes(ACTIVATION_DELAY_MINUTES)
+        };
+        let algorithm = match key.algorithm {
+            Algorithm::EdDSA => "EdDSA",
+            _ => "RS256",
+        };
+        self.repo
+            .create(&key.kid, algorithm, &key.private_key_pem, &serde_json::to_string(&key.public_jwk)?, activates_at)
+            .await?;
+
+        let retire_at = activates_at + access_token_ttl() + Duration::minutes(RETIREMENT_GRACE_MINUTES);
+        self.repo.expire_others(&key.kid, retire_at).await?;
+
+        if let Err(e) = self.redis.delete(ROTATION_LOCK_KEY) {
+            tracing::error!("Failed to release key rotation lock: {}", e);
+        }
+        tracing::info!("Created signing key {} ({}), active from {}", key.kid, algorithm, activates_at);
+        Ok(true)
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+use common::cache::RedisCache;
+use contracts::events::{UserEvent, UserLockedEvent};
+
+use crate::domain::{LockoutPolicy, LoginFailure};
+use crate::domain::token::hash_token;
+use crate::repo::{LoginAttemptRepository, OutboxRepository};
+
+// Failed attempts in a row are forgotten after a day without failures
+const FAILURES_TTL_SECS: u64 = 86400;
+
+/// Brute-force protection for login: per-account delays and lockout, and
+/// blocking of IPs that spray many accounts. Counters are keyed by email,
+/// so unknown emails behave exactly like existing accounts.
+#[derive(Clone)]
+pub struct LockoutService {
+    attempt_repo: LoginAttemptRepository,
+    outbox_repo: OutboxRepository,
+    redis: RedisCache,
+    policy: LockoutPolicy,
+    spray_threshold: i64,
+    spray_window: Duration,
+    ip_block_secs: u64,
+}
+
+impl LockoutService {
+    pub fn new(attempt_repo: LoginAttemptRepository, outbox_repo: OutboxRepository, redis: RedisCache) -> Self {
+        let env = |name: &str, default: i64| {
+            std::env::var(name).ok().and_then(|v| v.parse::<i64>().ok()).unwrap_or(default)
+        };
+        Self {
+            attempt_repo,
+            outbox_repo,
+            redis,
+            policy: LockoutPolicy::from_env(),
+            spray_threshold: env("LOGIN_SPRAY_THRESHOLD", 20),
+            spray_window: Duration::minutes(env("LOGIN_SPRAY_WINDOW_MINUTES", 10)),
+            ip_block_secs: env("LOGIN_IP_BLOCK_MINUTES", 60) as u64 * 60,
+        }
+    }
+
+    /// Refuse the attempt while the IP is blocked, the account locked or
+    /// its delay not over. Runs before the password is checked.
+    pub async fn check(&self, email: &str, ip: &str) -> Result<()> {
+        let account = account_key(email);
+        let refusal = if self.active(&ip_block_key(ip)).is_some() {
+            Some(anyhow!("Too many failed logins from this address, try again later"))
+        } else if let Some(secs) = self.active(&lock_key(&account)) {
+            Some(anyhow!("Account temporarily locked, reset your password or retry in {} seconds", secs))
+        } else {
+            self.active(&delay_key(&account))
+                .map(|secs| anyhow!("Too many failed attempts, retry in {} seconds", secs))
+        };
+
+        match refusal {
+            Some(e) => {
+                self.log_attempt(None, email, ip, Some(LoginFailure::Throttled)).await;
+                Err(e)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub async fn record_failure(&self, user_id: Option<i32>, email: &str, ip: &str, failure: LoginFailure) {
+        self.log_attempt(user_id, email, ip, Some(failure)).await;
+
+        let account = account_key(email);
+        match self.redis.increment(&failures_key(&account), FAILURES_TTL_SECS) {
+            Ok(failures) => {
+                if self.policy.locks(failures) {
+                    tracing::warn!("Locking login for {} after {} failed attempts", email, failures);
+                    self.hold(&lock_key(&account), self.policy.lock_duration_secs);
+                    if let Some(user_id) = user_id {
+                        self.announce_lock(user_id, email).await;
+                    }
+                } else if let Some(delay) = self.policy.delay_for(failures) {
+                    self.hold(&delay_key(&account), delay);
+                }
+            }
+            Err(e) => tracing::error!("Failed to count failed login: {}", e),
+        }
+
+        self.detect_spraying(ip).await;
+    }
+
+    pub async fn record_success(&self, user_id: i32, email: &str, ip: &str) {
+        self.log_attempt(Some(user_id), email, ip, None).await;
+        self.clear(email);
+    }
+
+    /// Lift the lockout and delays of an account, after a password reset or by an admin
+    pub fn unlock(&self, email: &str) {
+        self.clear(email);
+        tracing::info!("Login unlocked for {}", email);
+    }
+
+    pub async fn purge_attempts(&self, retention: Duration) -> Result<u64> {
+        self.attempt_repo.purge_older_than(Utc::now() - retention).await
+    }
+
+    /// One IP failing against many different accounts is password spraying
+    async fn detect_spraying(&self, ip: &str) {
+        let since = Utc::now() - self.spray_window;
+        match self.attempt_repo.failed_emails_from_ip(ip, since).await {
+            Ok(accounts) if accounts >= self.spray_threshold => {
+                tracing::warn!("Blocking {} after failed logins on {} accounts", ip, accounts);
+                self.hold(&ip_block_key(ip), self.ip_block_secs);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Password spraying check failed: {}", e),
+        }
+    }
+
+    /// Queue user.locked so the owner hears about it
+    async fn announce_lock(&self, user_id: i32, email: &str) {
+        let now = Utc::now();
+        let event = UserEvent::Locked(UserLockedEvent {
+            user_id,
+            email: email.to_string(),
+            locked_until: now + Duration::seconds(self.policy.lock_duration_secs as i64),
+            timestamp: now,
+        });
+        if let Err(e) = self.outbox_repo.add_user_event(&event).await {
+            tracing::error!("Failed to queue lock event for user {}: {}", user_id, e);
+        }
+    }
+
+    /// Seconds left on a hold key, None when there is none. Fails open.
+    fn active(&self, key: &str) -> Option<i64> {
+        match self.redis.get::<i64>(key) {
+            Ok(Some(until)) => Some(until - Utc::now().timestamp()).filter(|secs| *secs > 0),
+            Ok(None) => None,
+            Err(e) => {
+                tracing::error!("Login lockout lookup failed: {}", e);
+                None
+            }
+        }
+    }
+
+    fn hold(&self, key: &str, secs: u64) {
+        let until = Utc::now().timestamp() + secs as i64;
+        if let Err(e) = self.redis.set(key, &until, secs) {
+            tracing::error!("Failed to store login hold: {}", e);
+        }
+    }
+
+    fn clear(&self, email: &str) {
+        let account = account_key(email);
+        for key in [failures_key(&account), delay_key(&account), lock_key(&account)] {
+            if let Err(e) = self.redis.delete(&key) {
+                tracing::error!("Failed to clear login lockout: {}", e);
+            }
+        }
+    }
+
+    async fn log_attempt(&self, user_id: Option<i32>, email: &str, ip: &str, failure: Option<LoginFailure>) {
+        if let Err(e) = self.attempt_repo.record(user_id, email, ip, failure).await {
+            tracing::error!("Failed to record login attempt: {}", e);
+        }
+    }
+}
+
+fn account_key(email: &str) -> String {
+    hash_token(&email.trim().to_lowercase())
+}
+
+fn failures_key(account: &str) -> String {
+    format!("login_failures:{}", account)
+}
+
+fn delay_key(account: &str) -> String {
+    format!("login_delay:{}", account)
+}
+
+fn lock_key(account: &str) -> String {
+    format!("login_locked:{}", account)
+}
+
+fn ip_block_key(ip: &str) -> String {
+    format!("login_ip_blocked:{}", ip)
+}
@@ -0,0 +1,191 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use common::cache::RedisCache;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::mfa::{
+    generate_recovery_codes, generate_totp_secret, normalize_recovery_code, otpauth_uri, verify_totp,
+    AMR_RECOVERY_CODE, AMR_TOTP,
+};
+use crate::domain::token::{generate_opaque_token, hash_token};
+use crate::repo::{MfaRepository, UserRepository};
+
+// Login challenges are answered right away or not at all
+pub const MFA_CHALLENGE_TTL_SECS: u64 = 300;
+// Failed codes per user before verification is refused for the window
+const MAX_FAILED_CODES: i64 = 5;
+const FAILED_CODES_WINDOW_SECS: u64 = 300;
+
+#[derive(Debug, Serialize)]
+pub struct MfaEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String, // render as a QR code for authenticator apps
+}
+
+/// Login waiting for its second factor
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginChallenge {
+    pub user_id: i32,
+    pub first_factor: String,
+}
+
+/// TOTP enrollment, recovery codes and login challenges
+#[derive(Clone)]
+pub struct MfaService {
+    mfa_repo: MfaRepository,
+    user_repo: UserRepository,
+    redis: RedisCache,
+    issuer: String,
+}
+
+impl MfaService {
+    pub fn new(mfa_repo: MfaRepository, user_repo: UserRepository, redis: RedisCache) -> Self {
+        let issuer = std::env::var("MFA_ISSUER").unwrap_or_else(|_| "RushTech".to_string());
+        Self {
+            mfa_repo,
+            user_repo,
+            redis,
+            issuer,
+        }
+    }
+
+    pub async fn is_enabled(&self, user_id: i32) -> Result<bool> {
+        self.mfa_repo.is_enabled(user_id).await
+    }
+
+    /// New secret for the user to add to their authenticator app. MFA stays
+    /// off until a code from it is confirmed.
+    pub async fn enroll(&self, user_id: i32) -> Result<MfaEnrollment> {
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+
+        let secret = generate_totp_secret();
+        if !self.mfa_repo.save_pending_secret(user_id, &secret).await? {
+            return Err(anyhow!("Two-factor authentication is already enabled"));
+        }
+
+        Ok(MfaEnrollment {
+            otpauth_uri: otpauth_uri(&self.issuer, &user.email, &secret),
+            secret,
+        })
+    }
+
+    /// Enable MFA with a first code from the app. Returns the recovery codes,
+    /// which are not retrievable later.
+    pub async fn confirm(&self, user_id: i32, code: &str) -> Result<Vec<String>> {
+        let mfa = self.mfa_repo.find(user_id).await?
+            .ok_or_else(|| anyhow!("No enrollment in progress"))?;
+        if mfa.enabled_at.is_some() {
+            return Err(anyhow!("Two-factor authentication is already enabled"));
+        }
+
+        self.check_failures(user_id)?;
+        let step = match verify_totp(&mfa.secret, code, Utc::now()) {
+            Some(step) => step,
+            None => {
+                self.record_failure(user_id);
+                return Err(anyhow!("Invalid two-factor code"));
+            }
+        };
+
+        let codes = generate_recovery_codes();
+        let hashes: Vec<String> = codes.iter().map(|c| hash_token(&normalize_recovery_code(c))).collect();
+        if !self.mfa_repo.enable(user_id, step, &hashes).await? {
+            return Err(anyhow!("Two-factor authentication is already enabled"));
+        }
+
+        tracing::info!("User {} enabled two-factor authentication", user_id);
+        Ok(codes)
+    }
+
+    pub async fn disable(&self, user_id: i32, code: &str) -> Result<()> {
+        self.verify(user_id, code).await?;
+        self.mfa_repo.disable(user_id).await?;
+        tracing::info!("User {} disabled two-factor authentication", user_id);
+        Ok(())
+    }
+
+    pub async fn regenerate_recovery_codes(&self, user_id: i32, code: &str) -> Result<Vec<String>> {
+        self.verify(user_id, code).await?;
+
+        let codes = generate_recovery_codes();
+        let hashes: Vec<String> = codes.iter().map(|c| hash_token(&normalize_recovery_code(c))).collect();
+        self.mfa_repo.replace_recovery_codes(user_id, &hashes).await?;
+        Ok(codes)
+    }
+
+    /// Check a TOTP code or a recovery code. Returns the `amr` value of the
+    /// method used.
+    pub async fn verify(&self, user_id: i32, code: &str) -> Result<&'static str> {
+        let mfa = self.mfa_repo.find(user_id).await?
+            .filter(|mfa| mfa.enabled_at.is_some())
+            .ok_or_else(|| anyhow!("Two-factor authentication is not enabled"))?;
+
+        self.check_failures(user_id)?;
+
+        if let Some(step) = verify_totp(&mfa.secret, code, Utc::now()) {
+            if self.mfa_repo.use_step(user_id, step).await? {
+                return Ok(AMR_TOTP);
+            }
+        } else if self.mfa_repo.use_recovery_code(user_id, &hash_token(&normalize_recovery_code(code))).await? {
+            let remaining = self.mfa_repo.remaining_recovery_codes(user_id).await?;
+            tracing::warn!("User {} used a recovery code, {} left", user_id, remaining);
+            return Ok(AMR_RECOVERY_CODE);
+        }
+
+        self.record_failure(user_id);
+        Err(anyhow!("Invalid two-factor code"))
+    }
+
+    /// Opaque token standing for "first factor checked, second factor
+    /// pending"; `first_factor` is its amr method
+    pub fn create_challenge(&self, user_id: i32, first_factor: &str) -> Result<String> {
+        let token = generate_opaque_token();
+        let challenge = LoginChallenge { user_id, first_factor: first_factor.to_string() };
+        self.redis.set(&challenge_key(&token), &challenge, MFA_CHALLENGE_TTL_SECS)?;
+        Ok(token)
+    }
+
+    pub fn challenge_user(&self, token: &str) -> Result<i32> {
+        Ok(self.challenge(token)?.user_id)
+    }
+
+    pub fn challenge(&self, token: &str) -> Result<LoginChallenge> {
+        self.redis
+            .get::<LoginChallenge>(&challenge_key(token))?
+            .ok_or_else(|| anyhow!("Invalid or expired MFA token"))
+    }
+
+    pub fn clear_challenge(&self, token: &str) {
+        if let Err(e) = self.redis.delete(&challenge_key(token)) {
+            tracing::error!("Failed to clear MFA challenge: {}", e);
+        }
+    }
+
+    fn check_failures(&self, user_id: i32) -> Result<()> {
+        match self.redis.get::<i64>(&failures_key(user_id)) {
+            Ok(Some(failures)) if failures >= MAX_FAILED_CODES => {
+                Err(anyhow!("Too many invalid two-factor codes, try again later"))
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::error!("MFA failure counter lookup failed: {}", e);
+                Ok(())
+            }
+        }
+    }
+
+    fn record_failure(&self, user_id: i32) {
+        if let Err(e) = self.redis.increment(&failures_key(user_id), FAILED_CODES_WINDOW_SECS) {
+            tracing::error!("Failed to count invalid MFA code: {}", e);
+        }
+    }
+}
+
+fn challenge_key(token: &str) -> String {
+    format!("mfa_challenge:{}", hash_token(token))
+}
+
+fn failures_key(user_id: i32) -> String {
+    format!("mfa_failures:{}", user_id)
+}
@@ -1,4 +1,32 @@
 // Business logic / use cases
 pub mod auth_service;
+pub mod account_service;
+pub mod key_service;
+pub mod role_service;
+pub mod mfa_service;
+pub mod lockout_service;
+pub mod api_key_service;
+pub mod oauth_client_service;
+pub mod session_service;
+pub mod passkey_service;
+pub mod oidc_service;
+pub mod identity_provider;
+pub mod federation_service;
+pub mod outbox_publisher;
+pub mod privacy_service;
 
-pub use auth_service::AuthService;
+pub use auth_service::{AuthService, LoginOutcome};
+pub use account_service::AccountService;
+pub use key_service::KeyService;
+pub use role_service::RoleService;
+pub use mfa_service::MfaService;
+pub use lockout_service::LockoutService;
+pub use api_key_service::ApiKeyService;
+pub use oauth_client_service::OAuthClientService;
+pub use session_service::SessionService;
+pub use passkey_service::PasskeyService;
+pub use oidc_service::{AuthorizeOutcome, OidcService};
+pub use identity_provider::IdentityProvider;
+pub use federation_service::FederationService;
+pub use outbox_publisher::OutboxPublisher;
+pub use privacy_service::PrivacyService;
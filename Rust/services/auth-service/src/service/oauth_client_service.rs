@@ -0,0 +1,126 @@
+use anyhow::{Result, anyhow};
+use authz::Claims;
+use chrono::Utc;
+
+use crate::domain::{ClientToken, OAuthClient};
+use crate::domain::oauth_client::{generate_client_id, grant_scopes, is_valid_scope, KIND_OIDC, KIND_SERVICE};
+use crate::domain::oidc::{is_valid_redirect_uri, OIDC_SCOPES, SCOPE_OPENID};
+use crate::domain::token::{generate_opaque_token, hash_token};
+use crate::repo::OAuthClientRepository;
+use super::KeyService;
+use super::auth_service::access_token_ttl;
+
+pub const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+
+/// Service clients and the client_credentials grant
+#[derive(Clone)]
+pub struct OAuthClientService {
+    repo: OAuthClientRepository,
+    key_service: KeyService,
+}
+
+impl OAuthClientService {
+    pub fn new(repo: OAuthClientRepository, key_service: KeyService) -> Self {
+        Self { repo, key_service }
+    }
+
+    /// Returns the stored client and its secret, which is shown only once.
+    /// Clients with redirect URIs are OIDC apps, their scopes OIDC scopes.
+    pub async fn create(
+        &self,
+        name: &str,
+        scopes: Vec<String>,
+        redirect_uris: Option<Vec<String>>,
+        created_by: i32,
+    ) -> Result<(OAuthClient, String)> {
+        if name.trim().is_empty() {
+            return Err(anyhow!("Name must not be empty"));
+        }
+        if scopes.is_empty() {
+            return Err(anyhow!("At least one scope is required"));
+        }
+
+        let kind = match &redirect_uris {
+            Some(uris) => {
+                if uris.is_empty() {
+                    return Err(anyhow!("At least one redirect URI is required"));
+                }
+                if let Some(invalid) = uris.iter().find(|uri| !is_valid_redirect_uri(uri)) {
+                    return Err(anyhow!("Invalid redirect URI {}", invalid));
+                }
+                if let Some(invalid) = scopes.iter().find(|s| !OIDC_SCOPES.contains(&s.as_str())) {
+                    return Err(anyhow!("Invalid scope {} for an OIDC client", invalid));
+                }
+                if !scopes.iter().any(|s| s == SCOPE_OPENID) {
+                    return Err(anyhow!("OIDC clients need the openid scope"));
+                }
+                KIND_OIDC
+            }
+            None => {
+                if let Some(invalid) = scopes.iter().find(|s| !is_valid_scope(s)) {
+                    return Err(anyhow!("Invalid scope {}", invalid));
+                }
+                KIND_SERVICE
+            }
+        };
+
+        let client_id = generate_client_id(kind);
+        let secret = generate_opaque_token();
+        let id = self.repo
+            .create(&client_id, &hash_token(&secret), name.trim(), kind, &scopes, redirect_uris.as_deref(), created_by)
+            .await?;
+        let client = self.repo.find_by_id(id).await?
+            .ok_or_else(|| anyhow!("OAuth client not found"))?;
+        tracing::info!("User {} registered OAuth client {} ({})", created_by, client_id, client.name);
+        Ok((client, secret))
+    }
+
+    pub async fn list(&self) -> Result<Vec<OAuthClient>> {
+        self.repo.list().await
+    }
+
+    /// Tokens already issued to the client stay valid until they expire
+    pub async fn revoke(&self, id: i64, revoked_by: i32) -> Result<()> {
+        if !self.repo.revoke(id).await? {
+            return Err(anyhow!("OAuth client not found or already revoked"));
+        }
+        tracing::info!("User {} revoked OAuth client {}", revoked_by, id);
+        Ok(())
+    }
+
+    /// Access token for a service client. `scope` narrows the token down to
+    /// some of the client's scopes.
+    pub async fn client_credentials(&self, client_id: &str, client_secret: &str, scope: Option<&str>) -> Result<ClientToken> {
+        let client = self.repo.find_by_client_id(client_id).await?
+            .filter(|client| client.is_active() && !client.is_oidc() && client.secret_hash == hash_token(client_secret))
+            .ok_or_else(|| anyhow!("Invalid client credentials"))?;
+        let scopes = grant_scopes(scope, &client.scope_list())
+            .ok_or_else(|| anyhow!("Invalid scope requested"))?;
+
+        let ttl = access_token_ttl();
+        let now = Utc::now();
+        let claims = Claims {
+            sub: client.client_id.clone(),
+            user_id: 0,
+            exp: (now + ttl).timestamp(),
+            iat: Some(now.timestamp()),
+            jti: Some(uuid::Uuid::new_v4().to_string()),
+            iss: Some(self.key_service.issuer().to_string()),
+            aud: self.key_service.audience().map(|aud| aud.to_string()),
+            roles: Vec::new(),
+            permissions: scopes.clone(),
+            restricted: false,
+            amr: Vec::new(),
+            auth_time: None,
+            client_id: Some(client.client_id.clone()),
+            sid: None,
+        };
+
+        Ok(ClientToken {
+            access_token: self.key_service.sign(&claims)?,
+            token_type: "Bearer",
+            expires_in: ttl.num_seconds(),
+            scope: scopes.join(" "),
+        })
+    }
+}
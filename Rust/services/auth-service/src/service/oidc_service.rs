@@ -0,0 +1,261 @@
+use anyhow::{Result, anyhow};
+use authz::Claims;
+use chrono::Utc;
+use common::cache::RedisCache;
+use jsonwebtoken::Algorithm;
+
+use crate::domain::{AuthorizationRequest, Consent, OAuthClient, OidcToken};
+use crate::domain::oidc::{
+    redirect_with, verify_pkce, AuthorizationCode, IdTokenClaims, OidcAccess, SCOPE_EMAIL, SCOPE_OPENID,
+    SCOPE_PROFILE,
+};
+use crate::domain::token::{generate_opaque_token, hash_token};
+use crate::middleware::{AUTHORIZE_PATH, JWKS_PATH, TOKEN_PATH, USERINFO_PATH};
+use crate::repo::{ConsentRepository, OAuthClientRepository, UserRepository};
+use super::KeyService;
+use super::auth_service::access_token_ttl;
+
+pub const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+// Codes are exchanged by the client's backend right after the redirect
+const AUTHORIZATION_CODE_TTL_SECS: u64 = 60;
+const ID_TOKEN_TTL_SECS: i64 = 3600;
+
+pub enum AuthorizeOutcome {
+    /// Send the browser here: the client's redirect URI with a code or an error
+    Redirect(String),
+    /// The user has to allow the client these scopes first
+    ConsentRequired { client_id: String, client_name: String, scopes: Vec<String> },
+}
+
+/// OpenID Connect provider: authorization code flow with PKCE for the OIDC
+/// clients registered by admins, ID tokens signed with the service's keys
+#[derive(Clone)]
+pub struct OidcService {
+    client_repo: OAuthClientRepository,
+    consent_repo: ConsentRepository,
+    user_repo: UserRepository,
+    redis: RedisCache,
+    key_service: KeyService,
+    issuer: String,
+}
+
+impl OidcService {
+    pub fn new(
+        client_repo: OAuthClientRepository,
+        consent_repo: ConsentRepository,
+        user_repo: UserRepository,
+        redis: RedisCache,
+        key_service: KeyService,
+    ) -> Self {
+        // Public base URL of this service; ID tokens carry it as `iss`
+        let issuer = std::env::var("OIDC_ISSUER_URL")
+            .unwrap_or_else(|_| "http://localhost:8081".to_string())
+            .trim_end_matches('/')
+            .to_string();
+        if key_service.algorithm() == Algorithm::HS256 {
+            tracing::warn!("ID tokens cannot be verified by clients with HS256, set JWT_SIGNING_ALGORITHM to RS256 or EdDSA");
+        }
+        Self { client_repo, consent_repo, user_repo, redis, key_service, issuer }
+    }
+
+    /// OpenID Provider Metadata (OIDC Discovery 3)
+    pub fn discovery(&self) -> serde_json::Value {
+        serde_json::json!({
+            "issuer": self.issuer,
+            "authorization_endpoint": format!("{}{}", self.issuer, AUTHORIZE_PATH),
+            "token_endpoint": format!("{}{}", self.issuer, TOKEN_PATH),
+            "userinfo_endpoint": format!("{}{}", self.issuer, USERINFO_PATH),
+            "jwks_uri": format!("{}{}", self.issuer, JWKS_PATH),
+            "response_types_supported": ["code"],
+            "grant_types_supported": [GRANT_AUTHORIZATION_CODE, "client_credentials"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": [format!("{:?}", self.key_service.algorithm())],
+            "scopes_supported": [SCOPE_OPENID, SCOPE_PROFILE, SCOPE_EMAIL],
+            "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post"],
+            "code_challenge_methods_supported": ["S256"],
+            "claims_supported": ["sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "amr", "name", "email", "email_verified"]
+        })
+    }
+
+    /// Authorization endpoint for the logged-in user. `approve` is the
+    /// user's answer to a consent prompt, `None` when none was shown.
+    /// Errors are for requests that cannot be redirected back to the client.
+    pub async fn authorize(&self, claims: &Claims, request: &AuthorizationRequest, approve: Option<bool>) -> Result<AuthorizeOutcome> {
+        let client = self.client_repo.find_by_client_id(&request.client_id).await?
+            .filter(|client| client.is_active() && client.is_oidc())
+            .ok_or_else(|| anyhow!("Invalid client"))?;
+        if !client.allows_redirect_uri(&request.redirect_uri) {
+            return Err(anyhow!("Invalid redirect_uri for this client"));
+        }
+
+        let redirect_error = |error: &str, description: &str| {
+            let mut params = vec![("error", error), ("error_description", description)];
+            if let Some(state) = request.state.as_deref() {
+                params.push(("state", state));
+            }
+            Ok(AuthorizeOutcome::Redirect(redirect_with(&request.redirect_uri, &params)))
+        };
+
+        if request.response_type.as_deref() != Some("code") {
+            return redirect_error("unsupported_response_type", "Only the code response type is supported");
+        }
+        let scopes: Vec<String> = request.scope.as_deref().unwrap_or_default()
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        if !scopes.iter().any(|s| s == SCOPE_OPENID) || scopes.iter().any(|s| !client.scope_list().contains(s)) {
+            return redirect_error("invalid_scope", "Scope must include openid and only scopes of the client");
+        }
+        let code_challenge = match (request.code_challenge.as_deref(), request.code_challenge_method.as_deref()) {
+            (Some(challenge), Some("S256")) if !challenge.is_empty() => challenge.to_string(),
+            _ => return redirect_error("invalid_request", "PKCE with code_challenge_method S256 is required"),
+        };
+
+        match approve {
+            Some(false) => return redirect_error("access_denied", "The user denied the request"),
+            Some(true) => {
+                self.consent_repo.grant(claims.user_id, &client.client_id, &scopes).await?;
+                tracing::info!("User {} allowed {} the scopes {}", claims.user_id, client.client_id, scopes.join(" "));
+            }
+            None => {
+                let consent = self.consent_repo.find(claims.user_id, &client.client_id).await?;
+                if !consent.is_some_and(|consent| consent.covers(&scopes)) {
+                    return Ok(AuthorizeOutcome::ConsentRequired {
+                        client_id: client.client_id,
+                        client_name: client.name,
+                        scopes,
+                    });
+                }
+            }
+        }
+
+        let code = generate_opaque_token();
+        let grant = AuthorizationCode {
+            client_id: client.client_id.clone(),
+            user_id: claims.user_id,
+            redirect_uri: request.redirect_uri.clone(),
+            scopes,
+            nonce: request.nonce.clone(),
+            code_challenge,
+            amr: claims.amr.clone(),
+            auth_time: claims.auth_time,
+        };
+        self.redis.set(&code_key(&code), &grant, AUTHORIZATION_CODE_TTL_SECS)?;
+
+        let mut params = vec![("code", code.as_str())];
+        if let Some(state) = request.state.as_deref() {
+            params.push(("state", state));
+        }
+        Ok(AuthorizeOutcome::Redirect(redirect_with(&request.redirect_uri, &params)))
+    }
+
+    /// Token endpoint, authorization_code grant
+    pub async fn exchange_code(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<OidcToken> {
+        let client = self.authenticate_client(client_id, client_secret).await?;
+
+        let key = code_key(code);
+        let grant = self.redis.get::<AuthorizationCode>(&key)?
+            .ok_or_else(|| anyhow!("Invalid grant: unknown or expired code"))?;
+        // Single use, even when two exchanges race
+        if !self.redis.set_nx(&format!("{}:used", key), "1", AUTHORIZATION_CODE_TTL_SECS)? {
+            return Err(anyhow!("Invalid grant: code already used"));
+        }
+        self.redis.delete(&key)?;
+
+        if grant.client_id != client.client_id || grant.redirect_uri != redirect_uri {
+            return Err(anyhow!("Invalid grant: code was issued to another client or redirect_uri"));
+        }
+        if !verify_pkce(code_verifier, &grant.code_challenge) {
+            return Err(anyhow!("Invalid grant: PKCE verification failed"));
+        }
+        let user = self.user_repo.find_by_id(grant.user_id).await?
+            .ok_or_else(|| anyhow!("Invalid grant: user no longer exists"))?;
+
+        let has = |scope: &str| grant.scopes.iter().any(|s| s == scope);
+        let now = Utc::now().timestamp();
+        let id_token = IdTokenClaims {
+            iss: self.issuer.clone(),
+            sub: user.id.to_string(),
+            aud: client.client_id.clone(),
+            exp: now + ID_TOKEN_TTL_SECS,
+            iat: now,
+            auth_time: grant.auth_time,
+            nonce: grant.nonce.clone(),
+            amr: grant.amr.clone(),
+            name: has(SCOPE_PROFILE).then(|| user.name.clone()),
+            email: has(SCOPE_EMAIL).then(|| user.email.clone()),
+            email_verified: has(SCOPE_EMAIL).then(|| user.is_verified()),
+        };
+
+        // Opaque and only good for /userinfo: client apps do not get our API tokens
+        let access_token = generate_opaque_token();
+        let ttl = access_token_ttl();
+        let access = OidcAccess {
+            client_id: client.client_id.clone(),
+            user_id: user.id,
+            scopes: grant.scopes.clone(),
+        };
+        self.redis.set(&access_key(&access_token), &access, ttl.num_seconds() as u64)?;
+
+        tracing::info!("Issued OIDC tokens for user {} to {}", user.id, client.client_id);
+        Ok(OidcToken {
+            access_token,
+            token_type: "Bearer",
+            expires_in: ttl.num_seconds(),
+            scope: grant.scopes.join(" "),
+            id_token: self.key_service.sign(&id_token)?,
+        })
+    }
+
+    /// Claims about the user the access token was issued for, by its scopes
+    pub async fn userinfo(&self, access_token: &str) -> Result<serde_json::Value> {
+        let access = self.redis.get::<OidcAccess>(&access_key(access_token))?
+            .ok_or_else(|| anyhow!("Invalid access token"))?;
+        let user = self.user_repo.find_by_id(access.user_id).await?
+            .ok_or_else(|| anyhow!("Invalid access token"))?;
+
+        let mut info = serde_json::json!({ "sub": user.id.to_string() });
+        if access.scopes.iter().any(|s| s == SCOPE_PROFILE) {
+            info["name"] = serde_json::json!(user.name);
+        }
+        if access.scopes.iter().any(|s| s == SCOPE_EMAIL) {
+            info["email"] = serde_json::json!(user.email);
+            info["email_verified"] = serde_json::json!(user.is_verified());
+        }
+        Ok(info)
+    }
+
+    pub async fn list_consents(&self, user_id: i32) -> Result<Vec<Consent>> {
+        self.consent_repo.list_for_user(user_id).await
+    }
+
+    /// The app will have to ask again; tokens it already holds expire on their own
+    pub async fn revoke_consent(&self, user_id: i32, client_id: &str) -> Result<()> {
+        if !self.consent_repo.revoke(user_id, client_id).await? {
+            return Err(anyhow!("Consent not found"));
+        }
+        tracing::info!("User {} revoked the consent of {}", user_id, client_id);
+        Ok(())
+    }
+
+    async fn authenticate_client(&self, client_id: &str, client_secret: &str) -> Result<OAuthClient> {
+        self.client_repo.find_by_client_id(client_id).await?
+            .filter(|client| client.is_active() && client.is_oidc() && client.secret_hash == hash_token(client_secret))
+            .ok_or_else(|| anyhow!("Invalid client credentials"))
+    }
+}
+
+fn code_key(code: &str) -> String {
+    format!("oidc_code:{}", hash_token(code))
+}
+
+fn access_key(token: &str) -> String {
+    format!("oidc_access:{}", hash_token(token))
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use common::cache::RedisCache;
+use messaging::kafka_producer::KafkaProducer;
+
+use crate::repo::OutboxRepository;
+
+const BATCH_SIZE: i64 = 100;
+const LEASE_KEY: &str = "outbox_publisher";
+// Longer than a batch takes; a crashed instance frees the lease after it
+const LEASE_SECS: u64 = 30;
+
+/// Publishes the events of the outbox to Kafka in the order they were
+/// written. An event stays in the outbox until Kafka takes it, so consumers
+/// get every event at least once.
+#[derive(Clone)]
+pub struct OutboxPublisher {
+    outbox_repo: OutboxRepository,
+    producer: KafkaProducer,
+    redis: RedisCache,
+    poll_interval: std::time::Duration,
+    retention: Duration,
+}
+
+impl OutboxPublisher {
+    pub fn new(outbox_repo: OutboxRepository, producer: KafkaProducer, redis: RedisCache) -> Self {
+        let env = |name: &str, default: u64| {
+            std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(default)
+        };
+        Self {
+            outbox_repo,
+            producer,
+            redis,
+            poll_interval: std::time::Duration::from_millis(env("OUTBOX_POLL_INTERVAL_MS", 1000)),
+            retention: Duration::days(env("OUTBOX_RETENTION_DAYS", 7) as i64),
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        let mut last_cleanup = Utc::now();
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.publish_pending().await {
+                tracing::error!("Outbox publishing failed: {}", e);
+            }
+            if Utc::now() - last_cleanup > Duration::hours(1) {
+                last_cleanup = Utc::now();
+                match self.outbox_repo.delete_published_before(Utc::now() - self.retention).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Removed {} published events from the outbox", deleted),
+                    Err(e) => tracing::error!("Failed to clean up the outbox: {}", e),
+                }
+            }
+        }
+    }
+
+    /// Publish one batch. Only the instance holding the lease publishes, so
+    /// events keep their order; a failure stops the batch for the same reason.
+    /// The lease holds a token of its own, so a batch that outlives it does
+    /// not release the lease another instance has taken since.
+    pub async fn publish_pending(&self) -> Result<usize> {
+        let token = uuid::Uuid::new_v4().to_string();
+        if !self.redis.set_nx(LEASE_KEY, &token, LEASE_SECS)? {
+            return Ok(0);
+        }
+        let result = self.publish_batch().await;
+        match self.redis.delete_if_equals(LEASE_KEY, &token) {
+            Ok(true) => {}
+            Ok(false) => tracing::warn!("The outbox lease expired before the batch was published"),
+            Err(e) => tracing::error!("Failed to release the outbox lease: {}", e),
+        }
+        result
+    }
+
+    async fn publish_batch(&self) -> Result<usize> {
+        let events = self.outbox_repo.unpublished(BATCH_SIZE).await?;
+        let mut published = 0;
+        for event in events {
+            if let Err(e) = self.producer.send_message(&event.topic, &event.message_key, &event.payload).await {
+                tracing::warn!(
+                    "Failed to publish {} event {} (attempt {}): {}",
+                    event.event_type, event.event_id, event.attempts + 1, e
+                );
+                self.outbox_repo.record_failure(event.id, &e.to_string()).await?;
+                break;
+            }
+            self.outbox_repo.mark_published(event.id).await?;
+            published += 1;
+        }
+        Ok(published)
+    }
+}
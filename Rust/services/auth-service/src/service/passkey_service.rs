@@ -0,0 +1,212 @@
+use anyhow::{Result, anyhow};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use common::cache::RedisCache;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{Passkey, PublicKeyCredential, RelyingParty};
+use crate::domain::passkey::{
+    client_challenge, generate_challenge, sign_count_advanced, verify_assertion, verify_registration,
+    SUPPORTED_ALGORITHMS,
+};
+use crate::repo::{PasskeyRepository, UserRepository};
+
+// A ceremony is answered right away or not at all
+const CEREMONY_TTL_SECS: u64 = 300;
+const MAX_PASSKEYS_PER_USER: i64 = 10;
+
+/// What a pending challenge was issued for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Ceremony {
+    Register,
+    Login,  // passwordless, the user is whoever the passkey belongs to
+    Verify, // second factor of a known user
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingCeremony {
+    ceremony: Ceremony,
+    user_id: Option<i32>,
+}
+
+/// Passkey registration and the WebAuthn ceremonies that use them
+#[derive(Clone)]
+pub struct PasskeyService {
+    repo: PasskeyRepository,
+    user_repo: UserRepository,
+    redis: RedisCache,
+    rp: RelyingParty,
+}
+
+impl PasskeyService {
+    pub fn new(repo: PasskeyRepository, user_repo: UserRepository, redis: RedisCache) -> Self {
+        let id = std::env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string());
+        let name = std::env::var("WEBAUTHN_RP_NAME")
+            .or_else(|_| std::env::var("MFA_ISSUER"))
+            .unwrap_or_else(|_| "RushTech".to_string());
+        // Comma-separated, e.g. the web app and the admin console
+        let origins = std::env::var("WEBAUTHN_ORIGINS")
+            .or_else(|_| std::env::var("APP_BASE_URL"))
+            .unwrap_or_else(|_| "http://localhost:3000".to_string())
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+
+        Self {
+            repo,
+            user_repo,
+            redis,
+            rp: RelyingParty { id, name, origins },
+        }
+    }
+
+    pub async fn has_passkeys(&self, user_id: i32) -> Result<bool> {
+        Ok(self.repo.count_for_user(user_id).await? > 0)
+    }
+
+    pub async fn list(&self, user_id: i32) -> Result<Vec<Passkey>> {
+        self.repo.list_for_user(user_id).await
+    }
+
+    /// PublicKeyCredentialCreationOptions for navigator.credentials.create()
+    pub async fn registration_options(&self, user_id: i32) -> Result<serde_json::Value> {
+        let user = self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        let existing = self.repo.list_for_user(user_id).await?;
+        if existing.len() as i64 >= MAX_PASSKEYS_PER_USER {
+            return Err(anyhow!("Passkey limit of {} reached", MAX_PASSKEYS_PER_USER));
+        }
+
+        let challenge = self.start(Ceremony::Register, Some(user_id))?;
+        let params: Vec<_> = SUPPORTED_ALGORITHMS
+            .iter()
+            .map(|alg| serde_json::json!({ "type": "public-key", "alg": alg }))
+            .collect();
+        let exclude: Vec<_> = existing
+            .iter()
+            .map(|passkey| serde_json::json!({ "type": "public-key", "id": passkey.credential_id }))
+            .collect();
+
+        Ok(serde_json::json!({
+            "challenge": challenge,
+            "rp": { "id": self.rp.id, "name": self.rp.name },
+            "user": {
+                "id": URL_SAFE_NO_PAD.encode(user.id.to_be_bytes()),
+                "name": user.email,
+                "displayName": user.name
+            },
+            "pubKeyCredParams": params,
+            "excludeCredentials": exclude,
+            // Discoverable, so it can log in without an email
+            "authenticatorSelection": { "residentKey": "preferred", "userVerification": "preferred" },
+            "attestation": "none",
+            "timeout": CEREMONY_TTL_SECS * 1000
+        }))
+    }
+
+    pub async fn register(&self, user_id: i32, name: Option<&str>, credential: &PublicKeyCredential) -> Result<Passkey> {
+        let challenge = self.finish(credential, Ceremony::Register, Some(user_id))?;
+        let new = verify_registration(&self.rp, credential, &challenge)?;
+        if self.repo.find_by_credential_id(&new.credential_id).await?.is_some() {
+            return Err(anyhow!("Passkey already registered"));
+        }
+
+        let name = name
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .map(|n| n.chars().take(100).collect())
+            .unwrap_or_else(|| "Passkey".to_string());
+        let id = self.repo.create(user_id, &new, &name).await?;
+        tracing::info!("User {} registered passkey {}", user_id, id);
+        self.repo.find_by_id(id).await?
+            .ok_or_else(|| anyhow!("Passkey not found"))
+    }
+
+    pub async fn delete(&self, user_id: i32, id: i64) -> Result<()> {
+        if !self.repo.delete(id, user_id).await? {
+            return Err(anyhow!("Passkey not found"));
+        }
+        tracing::info!("User {} deleted passkey {}", user_id, id);
+        Ok(())
+    }
+
+    /// PublicKeyCredentialRequestOptions for navigator.credentials.get().
+    /// Without a user it is a passwordless login: any discoverable passkey
+    /// may answer, and it has to verify the user (PIN, biometrics).
+    pub async fn authentication_options(&self, user_id: Option<i32>) -> Result<serde_json::Value> {
+        let (ceremony, allow) = match user_id {
+            Some(user_id) => {
+                let passkeys = self.repo.list_for_user(user_id).await?;
+                if passkeys.is_empty() {
+                    return Err(anyhow!("No passkey registered"));
+                }
+                let allow: Vec<_> = passkeys
+                    .iter()
+                    .map(|passkey| serde_json::json!({ "type": "public-key", "id": passkey.credential_id }))
+                    .collect();
+                (Ceremony::Verify, allow)
+            }
+            None => (Ceremony::Login, Vec::new()),
+        };
+
+        let challenge = self.start(ceremony, user_id)?;
+        Ok(serde_json::json!({
+            "challenge": challenge,
+            "rpId": self.rp.id,
+            "allowCredentials": allow,
+            "userVerification": if user_id.is_some() { "preferred" } else { "required" },
+            "timeout": CEREMONY_TTL_SECS * 1000
+        }))
+    }
+
+    /// Check an assertion for options from `authentication_options` with the
+    /// same user. Returns the user the passkey belongs to.
+    pub async fn authenticate(&self, user_id: Option<i32>, credential: &PublicKeyCredential) -> Result<i32> {
+        let ceremony = if user_id.is_some() { Ceremony::Verify } else { Ceremony::Login };
+        let challenge = self.finish(credential, ceremony, user_id)?;
+
+        let passkey = self.repo.find_by_credential_id(credential.id.trim_end_matches('=')).await?
+            .filter(|passkey| user_id.is_none_or(|user_id| passkey.user_id == user_id))
+            .ok_or_else(|| anyhow!("Invalid passkey"))?;
+        let sign_count = verify_assertion(&self.rp, credential, &challenge, &passkey, user_id.is_none())?;
+
+        if !sign_count_advanced(passkey.sign_count, sign_count) {
+            tracing::warn!(
+                "Passkey {} of user {} went back from counter {} to {}, possibly cloned",
+                passkey.id, passkey.user_id, passkey.sign_count, sign_count
+            );
+            return Err(anyhow!("Invalid passkey: signature counter did not advance"));
+        }
+        if !self.repo.record_use(passkey.id, passkey.sign_count, sign_count).await? {
+            return Err(anyhow!("Invalid passkey: assertion already used"));
+        }
+        Ok(passkey.user_id)
+    }
+
+    fn start(&self, ceremony: Ceremony, user_id: Option<i32>) -> Result<String> {
+        let challenge = generate_challenge();
+        self.redis.set(&ceremony_key(&challenge), &PendingCeremony { ceremony, user_id }, CEREMONY_TTL_SECS)?;
+        Ok(challenge)
+    }
+
+    /// Take the pending ceremony of the response's challenge; each challenge
+    /// is answered once
+    fn finish(&self, credential: &PublicKeyCredential, ceremony: Ceremony, user_id: Option<i32>) -> Result<String> {
+        let challenge = client_challenge(credential)?.trim_end_matches('=').to_string();
+        let key = ceremony_key(&challenge);
+        self.redis.get::<PendingCeremony>(&key)?
+            .filter(|pending| pending.ceremony == ceremony && pending.user_id == user_id)
+            .ok_or_else(|| anyhow!("Invalid or expired passkey challenge"))?;
+        // Claimed with SET NX so two concurrent answers cannot both pass
+        if !self.redis.set_nx(&format!("{}:used", key), "1", CEREMONY_TTL_SECS)? {
+            return Err(anyhow!("Invalid or expired passkey challenge"));
+        }
+        self.redis.delete(&key)?;
+        Ok(challenge)
+    }
+}
+
+fn ceremony_key(challenge: &str) -> String {
+    format!("passkey_challenge:{}", challenge)
+}
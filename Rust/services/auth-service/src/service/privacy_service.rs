@@ -0,0 +1,234 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+use contracts::events::{PrivacyReportEvent, PrivacyRequestKind, PRIVACY_REPORTS_TOPIC};
+use messaging::kafka_consumer::{InvalidMessage, KafkaConsumer};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::domain::PrivacyRequest;
+use crate::domain::privacy::{self, STATUS_COMPLETED};
+use crate::repo::{PrivacyRepository, UserRepository};
+use super::AuthService;
+
+/// Data exports and account erasures. auth-service asks every service on
+/// privacy-requests, collects their reports, then writes the archive or
+/// deletes the account.
+#[derive(Clone)]
+pub struct PrivacyService {
+    privacy_repo: PrivacyRepository,
+    user_repo: UserRepository,
+    auth_service: AuthService,
+    services: Arc<Vec<String>>,
+    storage_dir: PathBuf,
+    request_timeout: Duration,
+    export_ttl: Duration,
+}
+
+impl PrivacyService {
+    pub fn new(privacy_repo: PrivacyRepository, user_repo: UserRepository, auth_service: AuthService) -> Self {
+        let env = |name: &str, default: i64| {
+            std::env::var(name).ok().and_then(|v| v.parse::<i64>().ok()).unwrap_or(default)
+        };
+        let storage_dir = std::env::var("PRIVACY_EXPORT_DIR").unwrap_or_else(|_| "./privacy-exports".to_string());
+
+        Self {
+            privacy_repo,
+            user_repo,
+            auth_service,
+            services: Arc::new(privacy::services_from_env()),
+            storage_dir: storage_dir.into(),
+            request_timeout: Duration::hours(env("PRIVACY_REQUEST_TIMEOUT_HOURS", 24)),
+            export_ttl: Duration::days(env("PRIVACY_EXPORT_TTL_DAYS", 7)),
+        }
+    }
+
+    /// Start gathering the user's data; a pending export is returned as is
+    pub async fn request_export(&self, user_id: i32) -> Result<PrivacyRequest> {
+        let id = match self.privacy_repo.find_pending(user_id, PrivacyRequestKind::Export).await? {
+            Some(id) => id,
+            None => {
+                let id = self.privacy_repo.create(user_id, PrivacyRequestKind::Export, &self.services).await?;
+                tracing::info!("User {} requested a data export ({})", user_id, id);
+                id
+            }
+        };
+        self.get(user_id, id).await
+    }
+
+    /// Erase the account. Logins stop working at once; the account is
+    /// deleted once every service has erased its data.
+    pub async fn request_erasure(&self, user_id: i32, password: &str, auth_time: Option<i64>) -> Result<PrivacyRequest> {
+        self.auth_service.confirm_password(user_id, password, auth_time).await?;
+        if let Some(id) = self.privacy_repo.find_pending(user_id, PrivacyRequestKind::Erasure).await? {
+            return self.get(user_id, id).await;
+        }
+
+        let id = self.privacy_repo.create(user_id, PrivacyRequestKind::Erasure, &self.services).await?;
+        self.privacy_repo.erase_credentials(user_id).await?;
+        self.auth_service.logout_all(user_id).await?;
+        tracing::info!("User {} requested erasure of their account ({})", user_id, id);
+        self.get(user_id, id).await
+    }
+
+    pub async fn get(&self, user_id: i32, id: i64) -> Result<PrivacyRequest> {
+        self.privacy_repo
+            .find(id)
+            .await?
+            .filter(|request| request.user_id == user_id)
+            .ok_or_else(|| anyhow!("Privacy request not found"))
+    }
+
+    /// Any user's request, for support to follow an erasure
+    pub async fn find(&self, id: i64) -> Result<PrivacyRequest> {
+        self.privacy_repo.find(id).await?.ok_or_else(|| anyhow!("Privacy request not found"))
+    }
+
+    /// Send a failed request to the services again
+    pub async fn retry(&self, id: i64) -> Result<PrivacyRequest> {
+        let request = self.find(id).await?;
+        let kind = if request.kind == PrivacyRequestKind::Export.as_str() {
+            PrivacyRequestKind::Export
+        } else {
+            PrivacyRequestKind::Erasure
+        };
+        if !self.privacy_repo.retry(&request, kind).await? {
+            return Err(anyhow!("Only a failed request can be retried"));
+        }
+        tracing::info!("Privacy request {} ({}) sent again", id, request.kind);
+        // The failure may have been our own last step, with every part in
+        self.finish_if_done(id).await?;
+        self.find(id).await
+    }
+
+    pub async fn list(&self, user_id: i32) -> Result<Vec<PrivacyRequest>> {
+        self.privacy_repo.list_for_user(user_id, 20).await
+    }
+
+    /// File name and content of a completed export
+    pub async fn download(&self, user_id: i32, id: i64) -> Result<(String, Vec<u8>)> {
+        let request = self.get(user_id, id).await?;
+        if request.kind != PrivacyRequestKind::Export.as_str() {
+            return Err(anyhow!("Privacy request not found"));
+        }
+        if request.status != STATUS_COMPLETED {
+            return Err(anyhow!("Export is {}", request.status));
+        }
+
+        let path = request.file_path.ok_or_else(|| anyhow!("Export has expired"))?;
+        let content = tokio::fs::read(&path).await.map_err(|_| anyhow!("Export has expired"))?;
+        Ok((format!("data-export-{}.zip", id), content))
+    }
+
+    /// Collect the reports of the other services until the consumer stops
+    pub async fn run_report_consumer(self, brokers: String) {
+        let consumer = match KafkaConsumer::new(&brokers, "auth-privacy-group", &[PRIVACY_REPORTS_TOPIC]) {
+            Ok(consumer) => consumer,
+            Err(e) => {
+                tracing::error!("Failed to start privacy report consumer: {}", e);
+                return;
+            }
+        };
+
+        let result = consumer.consume_async(|_key, payload| {
+            let service = self.clone();
+            async move {
+                let report: PrivacyReportEvent = serde_json::from_str(&payload)
+                    .map_err(|e| InvalidMessage(format!("Parse error: {}", e)))?;
+                service.handle_report(&report).await
+            }
+        }).await;
+        if let Err(e) = result {
+            tracing::error!("Privacy report consumer stopped: {}", e);
+        }
+    }
+
+    pub async fn handle_report(&self, report: &PrivacyReportEvent) -> Result<()> {
+        let data = report.data.as_ref().map(serde_json::to_string).transpose()?;
+        let recorded = self.privacy_repo
+            .record_report(report.request_id, &report.service, data.as_deref(), report.error.as_deref())
+            .await?;
+        if !recorded {
+            tracing::debug!("Ignored report of {} on privacy request {}", report.service, report.request_id);
+            return Ok(());
+        }
+        match &report.error {
+            Some(error) => tracing::warn!("{} failed privacy request {}: {}", report.service, report.request_id, error),
+            None => tracing::info!("{} completed privacy request {}", report.service, report.request_id),
+        }
+
+        self.finish_if_done(report.request_id).await
+    }
+
+    /// Fail requests a service never answered and drop expired archives
+    pub async fn cleanup(&self) -> Result<()> {
+        for id in self.privacy_repo.find_stale(Utc::now() - self.request_timeout).await? {
+            let waiting: Vec<String> = self.privacy_repo.parts(id).await?
+                .into_iter()
+                .filter(|part| part.status == privacy::STATUS_PENDING)
+                .map(|part| part.service)
+                .collect();
+            tracing::error!("Privacy request {} timed out waiting for {}", id, waiting.join(", "));
+            self.privacy_repo.mark_failed(id, &format!("No report from {}", waiting.join(", "))).await?;
+        }
+
+        for (id, path) in self.privacy_repo.find_expired_exports(Utc::now() - self.export_ttl).await? {
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                tracing::warn!("Failed to remove export archive {}: {}", path, e);
+            }
+            self.privacy_repo.clear_file(id).await?;
+        }
+        Ok(())
+    }
+
+    async fn finish_if_done(&self, id: i64) -> Result<()> {
+        let request = self.find(id).await?;
+        let result = match privacy::outcome(&request.parts) {
+            None => return Ok(()),
+            Some(Err(error)) => Err(anyhow!(error)),
+            Some(Ok(())) if request.kind == PrivacyRequestKind::Export.as_str() => self.write_export(&request).await.map(Some),
+            Some(Ok(())) => self.erase_account(&request).await.map(|()| None),
+        };
+
+        match result {
+            Ok(file_path) => {
+                if self.privacy_repo.mark_completed(id, file_path.as_deref()).await? {
+                    tracing::info!("Privacy request {} ({}) completed", id, request.kind);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Privacy request {} failed: {}", id, e);
+                self.privacy_repo.mark_failed(id, &e.to_string()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn write_export(&self, request: &PrivacyRequest) -> Result<String> {
+        let mut files = vec![("auth-service".to_string(), serde_json::to_string_pretty(&self.privacy_repo.export_user(request.user_id).await?)?)];
+        for (service, data) in self.privacy_repo.part_data(request.id).await? {
+            let data: serde_json::Value = match data {
+                Some(data) => serde_json::from_str(&data)?,
+                None => serde_json::Value::Null,
+            };
+            files.push((service, serde_json::to_string_pretty(&data)?));
+        }
+
+        tokio::fs::create_dir_all(&self.storage_dir).await?;
+        let path = self.storage_dir.join(format!("privacy-export-{}.zip", request.id));
+        let part_path = path.with_extension("part");
+        let writer_path = part_path.clone();
+        tokio::task::spawn_blocking(move || privacy::write_archive(&writer_path, &files)).await??;
+        tokio::fs::rename(&part_path, &path).await?;
+
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    /// Last step of an erasure, once the other services are done
+    async fn erase_account(&self, request: &PrivacyRequest) -> Result<()> {
+        if let Some(user) = self.user_repo.find_by_id(request.user_id).await? {
+            self.privacy_repo.delete_login_attempts(&user.email).await?;
+            self.user_repo.delete(user.id).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::{Result, anyhow};
+use authz::{roles, TokenRevocation};
+
+use crate::domain::{Grants, RoleWithPermissions};
+use crate::repo::{RoleRepository, UserRepository};
+
+#[derive(Clone)]
+pub struct RoleService {
+    role_repo: RoleRepository,
+    user_repo: UserRepository,
+    revocation: TokenRevocation,
+}
+
+impl RoleService {
+    pub fn new(role_repo: RoleRepository, user_repo: UserRepository, revocation: TokenRevocation) -> Self {
+        Self {
+            role_repo,
+            user_repo,
+            revocation,
+        }
+    }
+
+    pub async fn list_roles(&self) -> Result<Vec<RoleWithPermissions>> {
+        let mut result = Vec::new();
+        for role in self.role_repo.find_all().await? {
+            let permissions = self.role_repo.permissions_of_role(role.id).await?;
+            result.push(RoleWithPermissions { role, permissions });
+        }
+        Ok(result)
+    }
+
+    pub async fn user_grants(&self, user_id: i32) -> Result<Grants> {
+        self.ensure_user_exists(user_id).await?;
+        self.role_repo.grants_for_user(user_id).await
+    }
+
+    pub async fn assign_role(&self, user_id: i32, role_name: &str, admin_id: i32) -> Result<Grants> {
+        self.ensure_user_exists(user_id).await?;
+        let role = self.role_repo.find_by_name(role_name).await?
+            .ok_or_else(|| anyhow!("Role not found"))?;
+
+        if self.role_repo.assign(user_id, role.id, Some(admin_id)).await? {
+            tracing::info!("User {} assigned role {} to user {}", admin_id, role.name, user_id);
+            self.expire_tokens(user_id);
+        }
+        self.role_repo.grants_for_user(user_id).await
+    }
+
+    pub async fn remove_role(&self, user_id: i32, role_name: &str, admin_id: i32) -> Result<Grants> {
+        // An admin locking themselves out would leave nobody to undo it
+        if user_id == admin_id && role_name == roles::ADMIN {
+            return Err(anyhow!("Cannot remove your own admin role"));
+        }
+
+        self.ensure_user_exists(user_id).await?;
+        let role = self.role_repo.find_by_name(role_name).await?
+            .ok_or_else(|| anyhow!("Role not found"))?;
+
+        if self.role_repo.remove(user_id, role.id).await? {
+            tracing::info!("User {} removed role {} from user {}", admin_id, role.name, user_id);
+            self.expire_tokens(user_id);
+        }
+        self.role_repo.grants_for_user(user_id).await
+    }
+
+    /// Access tokens carry the old grants; make the user refresh to pick up the new ones
+    fn expire_tokens(&self, user_id: i32) {
+        if let Err(e) = self.revocation.revoke_user_tokens(user_id) {
+            tracing::error!("Failed to revoke access tokens of user {}: {}", user_id, e);
+        }
+    }
+
+    async fn ensure_user_exists(&self, user_id: i32) -> Result<()> {
+        self.user_repo.find_by_id(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        Ok(())
+    }
+}
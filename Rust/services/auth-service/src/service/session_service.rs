@@ -0,0 +1,109 @@
+use anyhow::{Result, anyhow};
+use authz::TokenRevocation;
+use chrono::Utc;
+use common::cache::RedisCache;
+
+use crate::domain::{ClientInfo, SessionView, UserSession};
+use crate::repo::{RefreshTokenRepository, SessionRepository};
+use super::auth_service::refresh_token_ttl;
+
+/// Per-user channel of chat-service's RedisListener; a `session_revoked`
+/// message there closes the WebSockets opened with that session's tokens
+fn chat_user_channel(user_id: i32) -> String {
+    format!("chat:user:{}", user_id)
+}
+
+/// Login sessions: one per login, listed to the user and revocable one by one
+#[derive(Clone)]
+pub struct SessionService {
+    repo: SessionRepository,
+    refresh_token_repo: RefreshTokenRepository,
+    revocation: TokenRevocation,
+    redis: RedisCache,
+}
+
+impl SessionService {
+    pub fn new(
+        repo: SessionRepository,
+        refresh_token_repo: RefreshTokenRepository,
+        revocation: TokenRevocation,
+        redis: RedisCache,
+    ) -> Self {
+        Self { repo, refresh_token_repo, revocation, redis }
+    }
+
+    pub async fn start(&self, user_id: i32, family_id: &str, client: &ClientInfo) -> Result<()> {
+        self.repo.create(user_id, family_id, client).await?;
+        Ok(())
+    }
+
+    pub async fn touch(&self, family_id: &str, client: &ClientInfo) -> Result<()> {
+        self.repo.touch(family_id, &client.ip).await
+    }
+
+    /// Sessions whose refresh token can still be used; `current_sid` marks the caller's
+    pub async fn list(&self, user_id: i32, current_sid: Option<&str>) -> Result<Vec<SessionView>> {
+        let sessions = self.repo.list_active(user_id, Utc::now() - refresh_token_ttl()).await?;
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionView {
+                current: current_sid == Some(session.family_id.as_str()),
+                session,
+            })
+            .collect())
+    }
+
+    pub async fn revoke(&self, user_id: i32, session_id: i64) -> Result<()> {
+        let session = self.repo.find_for_user(session_id, user_id).await?
+            .filter(|session| session.revoked_at.is_none())
+            .ok_or_else(|| anyhow!("Session not found"))?;
+        self.end(user_id, &session.family_id, "session_revoked").await?;
+        tracing::info!("User {} revoked session {} ({})", user_id, session.id, session.device);
+        Ok(())
+    }
+
+    /// Revoke every session but the current one. Returns how many were revoked.
+    pub async fn revoke_others(&self, user_id: i32, current_sid: &str) -> Result<usize> {
+        let others: Vec<UserSession> = self.list(user_id, Some(current_sid)).await?
+            .into_iter()
+            .filter(|view| !view.current)
+            .map(|view| view.session)
+            .collect();
+        for session in &others {
+            self.end(user_id, &session.family_id, "session_revoked").await?;
+        }
+        tracing::info!("User {} revoked {} other session(s)", user_id, others.len());
+        Ok(others.len())
+    }
+
+    /// End one session: its refresh tokens, its access tokens and its WebSockets
+    pub async fn end(&self, user_id: i32, family_id: &str, reason: &str) -> Result<()> {
+        self.refresh_token_repo.revoke_family(family_id, reason).await?;
+        self.repo.revoke(family_id).await?;
+        self.revocation.revoke_session(family_id)?;
+        self.notify_chat(user_id, family_id);
+        Ok(())
+    }
+
+    /// End every session of the user and deny their access tokens. The
+    /// caller's watermark only covers tokens from earlier seconds.
+    pub async fn end_all(&self, user_id: i32) -> Result<()> {
+        for session in self.repo.list_active(user_id, Utc::now() - refresh_token_ttl()).await? {
+            self.repo.revoke(&session.family_id).await?;
+            self.revocation.revoke_session(&session.family_id)?;
+            self.notify_chat(user_id, &session.family_id);
+        }
+        Ok(())
+    }
+
+    fn notify_chat(&self, user_id: i32, family_id: &str) {
+        let message = serde_json::json!({
+            "type": "session_revoked",
+            "session_id": family_id,
+            "message": "Phiên đăng nhập này đã bị đăng xuất. Vui lòng đăng nhập lại."
+        });
+        if let Err(e) = self.redis.publish(&chat_user_channel(user_id), &message) {
+            tracing::error!("Failed to notify chat-service of revoked session of user {}: {}", user_id, e);
+        }
+    }
+}
@@ -4,10 +4,14 @@ use actix_web_actors::ws;
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
+use crate::contacts::Contacts;
 use crate::domain::{CreateDirectRoomRequest, CreateRoomRequest, Room, RoomMemberResponse, RoomResponse, Message};
 use crate::repo::{MessageRepository, RoomRepository, InvitationRepository};
+use crate::sender_names::SenderNames;
 use crate::websocket::{ChatServer, WsSession, BroadcastToUsers, BroadcastToRoom, WsResponse};
 use authz::jwt::Claims;
+use authz::permissions;
+use apikeys::ApiKeyPrincipal;
 
 #[derive(Serialize)]
 pub struct HealthResponse {
@@ -27,6 +31,8 @@ pub struct AppState {
     pub room_repo: RoomRepository,
     pub invitation_repo: InvitationRepository,
     pub redis_cache: common::cache::RedisCache,
+    pub sender_names: SenderNames,
+    pub contacts: Contacts,
 }
 
 // Health check endpoint (no auth required)
@@ -71,12 +77,12 @@ fn get_claims(req: &HttpRequest) -> Result<Claims> {
 }
 
 // Helper function to extract claims from query string (for WebSocket)
-fn get_claims_from_query(req: &HttpRequest) -> Result<Claims> {
+async fn get_claims_from_query(req: &HttpRequest) -> Result<Claims> {
     use authz::jwt::JwtValidator;
     
     // Try to get from middleware first (header Authorization)
-    if let Some(claims) = req.extensions().get::<Claims>() {
-        return Ok(claims.clone());
+    if let Some(claims) = req.extensions().get::<Claims>().cloned() {
+        return Ok(claims);
     }
     
     // Fallback: Read token from query string
@@ -88,11 +94,9 @@ fn get_claims_from_query(req: &HttpRequest) -> Result<Claims> {
         .ok_or_else(|| actix_web::error::ErrorUnauthorized("No token provided"))?;
     
     // Decode JWT
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "RushTech@2025xAjxh".to_string());
-    
-    let validator = JwtValidator::new(jwt_secret);
-    validator.verify_token(token)
+    JwtValidator::from_env()
+        .verify_token(token)
+        .await
         .map_err(|e| {
             error!("Failed to decode token from query string: {}", e);
             actix_web::error::ErrorUnauthorized("Invalid token")
@@ -106,19 +110,43 @@ pub async fn ws_handler(
     state: web::Data<AppState>,
 ) -> Result<HttpResponse> {
     // Try to get claims from query string (for browser WebSocket)
-    let claims = get_claims_from_query(&req)?;
+    let claims = get_claims_from_query(&req).await?;
     info!("WebSocket connection request from user {}", claims.user_id);
 
     let session = WsSession::new(
         claims.user_id as i64,
+        claims.sid.clone(),
         state.chat_server.clone(),
         state.message_repo.clone(),
         state.room_repo.clone(),
+        state.sender_names.clone(),
+        state.contacts.clone(),
     );
 
     ws::start(session, &req, stream)
 }
 
+/// 403 when either user blocks the other. Fails closed: a block must hold
+/// while core-service is unreachable.
+async fn check_not_blocked(state: &AppState, user_id: i64, other_user_id: i64) -> Option<HttpResponse> {
+    match state.contacts.relationship(user_id, other_user_id).await {
+        Ok(relationship) if relationship.is_blocked() => Some(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Cannot start a conversation with this user"
+        }))),
+        Ok(_) => None,
+        Err(e) => {
+            error!("Failed to check blocks between users {} and {}: {}", user_id, other_user_id, e);
+            Some(contacts_unavailable())
+        }
+    }
+}
+
+fn contacts_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(serde_json::json!({
+        "error": "Could not check blocked users, try again later"
+    }))
+}
+
 // Create a new room (direct or group)
 pub async fn create_room(
     req: HttpRequest,
@@ -144,6 +172,10 @@ pub async fn create_room(
         }
 
         let other_user_id = body.member_ids[0];
+        if let Some(response) = check_not_blocked(&state, user_id, other_user_id).await {
+            return Ok(response);
+        }
+
         match state.room_repo.find_direct_room(user_id, other_user_id).await {
             Ok(Some(room)) => {
                 // Room already exists, return it
@@ -185,6 +217,23 @@ pub async fn create_room(
         }
     }
 
+    // Users blocking the creator, or blocked by them, are left out of a group
+    let mut invitees = Vec::new();
+    if body.room_type == "group" {
+        for member_id in body.member_ids.iter().filter(|id| **id != user_id) {
+            match state.contacts.relationship(user_id, *member_id).await {
+                Ok(relationship) if relationship.is_blocked() => {
+                    info!("Not inviting user {} to a group of user {}: blocked", member_id, user_id);
+                }
+                Ok(_) => invitees.push(*member_id),
+                Err(e) => {
+                    error!("Failed to check blocks between users {} and {}: {}", user_id, member_id, e);
+                    return Ok(contacts_unavailable());
+                }
+            }
+        }
+    }
+
     // Create new room
     let room = Room::new(body.name.clone(), body.room_type.clone(), user_id);
 
@@ -203,7 +252,7 @@ pub async fn create_room(
     // For direct chats, add the other user directly
     if body.room_type == "group" {
         // Create invitations for group members
-        for member_id in &body.member_ids {
+        for member_id in &invitees {
             if *member_id != user_id {
                 match state.invitation_repo.create_invitation(&room.id, *member_id, user_id).await {
                     Ok(invitation_id) => {
@@ -317,6 +366,10 @@ pub async fn create_direct_room(
     let user_id = claims.user_id as i64;
     let other_user_id = body.other_user_id;
 
+    if let Some(response) = check_not_blocked(&state, user_id, other_user_id).await {
+        return Ok(response);
+    }
+
     // Check if room already exists
     match state.room_repo.find_direct_room(user_id, other_user_id).await {
         Ok(Some(room)) => {
@@ -465,13 +518,13 @@ pub async fn get_room(
     let claims = get_claims(&req)?;
     let user_id = claims.user_id as i64;
 
-    // Check if user is member
+    // Check if user is member; moderators can read any room
     let is_member = state.room_repo.is_member(&*room_id, user_id).await.map_err(|e| {
         error!("Failed to check room membership: {}", e);
         actix_web::error::ErrorInternalServerError("Failed to check room membership")
     })?;
 
-    if !is_member {
+    if !is_member && !claims.has_permission(permissions::CHAT_MODERATE) {
         return Ok(HttpResponse::Forbidden().json(serde_json::json!({
             "error": "Not a member of this room"
         })));
@@ -515,6 +568,39 @@ pub async fn get_room(
     }
 }
 
+/// Room members for backend services calling with an API key
+pub async fn get_room_members_internal(
+    principal: ApiKeyPrincipal,
+    state: web::Data<AppState>,
+    room_id: web::Path<String>,
+) -> Result<HttpResponse> {
+    info!("API key {} ({}) reading members of room {}", principal.prefix, principal.owner, room_id);
+
+    if state.room_repo.get_room(&room_id).await.map_err(|e| {
+        error!("Failed to get room: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to get room")
+    })?.is_none() {
+        return Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Room not found"
+        })));
+    }
+
+    let members = state.room_repo.get_room_members_with_users(&room_id).await.map_err(|e| {
+        error!("Failed to get room members: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to get room members")
+    })?;
+
+    let members: Vec<RoomMemberResponse> = members.into_iter().map(|(m, user_name, user_email)| RoomMemberResponse {
+        user_id: m.user_id,
+        role: m.role,
+        joined_at: m.joined_at,
+        user_name,
+        user_email,
+    }).collect();
+
+    Ok(HttpResponse::Ok().json(members))
+}
+
 #[derive(Deserialize)]
 pub struct GetMessagesQuery {
     limit: Option<i64>,
@@ -531,13 +617,13 @@ pub async fn get_room_messages(
     let claims = get_claims(&req)?;
     let user_id = claims.user_id as i64;
 
-    // Check if user is member
+    // Check if user is member; moderators can read any room
     let is_member = state.room_repo.is_member(&*room_id, user_id).await.map_err(|e| {
         error!("Failed to check room membership: {}", e);
         actix_web::error::ErrorInternalServerError("Failed to check room membership")
     })?;
 
-    if !is_member {
+    if !is_member && !claims.has_permission(permissions::CHAT_MODERATE) {
         return Ok(HttpResponse::Forbidden().json(serde_json::json!({
             "error": "Not a member of this room"
         })));
@@ -553,8 +639,11 @@ pub async fn get_room_messages(
             actix_web::error::ErrorInternalServerError("Failed to get messages")
         })?;
 
+    // Messages from users the reader blocked stay hidden, as they are live
+    let blocked = state.contacts.blocked(user_id).await;
     let responses: Vec<_> = messages_with_users
         .into_iter()
+        .filter(|(m, _)| !blocked.contains(&m.sender_id))
         .map(|(m, sender_name)| {
             let mut response = m.to_response();
             response.sender_name = sender_name;
@@ -725,7 +814,7 @@ pub async fn accept_invitation(
     state.chat_server.do_send(BroadcastToRoom {
         room_id: room.id.clone(),
         message: system_ws_message,
-        exclude_user: None,
+        exclude_users: Vec::new(),
     });
 
     // Notify all room members about new member
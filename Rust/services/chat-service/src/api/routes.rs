@@ -1,17 +1,21 @@
 use actix_web::web;
 use authz::AuthMiddleware;
+use apikeys::{scopes, ApiKeyAuth, ApiKeyVerifier};
 use common::cache::RedisCache;
 
 use super::handlers::*;
 use crate::middleware::RateLimiter;
 
-pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    // Get JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "RushTech@2025xAjxh".to_string());
-    
+pub fn configure_routes(cfg: &mut web::ServiceConfig, api_keys: &ApiKeyVerifier) {
     // Health check outside /api scope (no auth required)
     cfg.route("/health", web::get().to(health_check));
+
+    // Server-to-server routes (API key required)
+    cfg.service(
+        web::scope("/internal")
+            .wrap(ApiKeyAuth::new(api_keys.clone(), scopes::CHAT_READ))
+            .route("/rooms/{room_id}/members", web::get().to(get_room_members_internal)),
+    );
     
     // Try to create Redis cache for rate limiting
     let redis_url = std::env::var("REDIS_URL")
@@ -28,7 +32,7 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             cfg.service(
                 web::scope("/api")
                     .wrap(rate_limiter)
-                    .wrap(AuthMiddleware::new(jwt_secret))
+                    .wrap(AuthMiddleware::from_env())
                     // Room endpoints
                     .route("/rooms", web::post().to(create_room))
                     .route("/rooms/direct", web::post().to(create_direct_room))
@@ -53,7 +57,7 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             // Configure routes without rate limiting
             cfg.service(
                 web::scope("/api")
-                    .wrap(AuthMiddleware::new(jwt_secret))
+                    .wrap(AuthMiddleware::from_env())
                     // Room endpoints
                     .route("/rooms", web::post().to(create_room))
                     .route("/rooms/direct", web::post().to(create_direct_room))
@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use common::cache::{user_cache_key, RedisCache};
+use common::http_client::HttpClient;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+// A new block reaches message delivery and history within this time
+const BLOCKS_TTL_SECONDS: u64 = 30;
+
+fn blocks_key(user_id: i64) -> String {
+    format!("{}:blocks", user_cache_key(user_id))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Relationship {
+    pub blocked: bool,
+    pub blocked_by: bool,
+}
+
+impl Relationship {
+    pub fn is_blocked(&self) -> bool {
+        self.blocked || self.blocked_by
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct BlockLists {
+    blocked: Vec<i64>,
+    blocked_by: Vec<i64>,
+}
+
+/// Contacts and blocks, kept by core-service
+#[derive(Clone)]
+pub struct Contacts {
+    base_url: String,
+    api_key: String,
+    http: HttpClient,
+    cache: RedisCache,
+}
+
+impl Contacts {
+    /// CORE_SERVICE_URL, and either the OAUTH_* client of chat-service or
+    /// CORE_SERVICE_API_KEY, both with the 'contacts:read' scope
+    pub fn from_env(cache: RedisCache) -> Self {
+        let base_url = std::env::var("CORE_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8082".to_string());
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: std::env::var("CORE_SERVICE_API_KEY").unwrap_or_default(),
+            http: HttpClient::from_env(),
+            cache,
+        }
+    }
+
+    /// A service client token goes to /service, the API key to /internal
+    async fn get(&self, path: &str) -> Result<reqwest::Response> {
+        if self.http.client_id().is_some() {
+            return self.http.get(&format!("{}/service{}", self.base_url, path)).await;
+        }
+        let request = self.http
+            .request(reqwest::Method::GET, &format!("{}/internal{}", self.base_url, path))
+            .header("X-API-Key", &self.api_key);
+        self.http.send(request).await
+    }
+
+    pub async fn relationship(&self, user_id: i64, other_id: i64) -> Result<Relationship> {
+        let response = self.get(&format!("/users/{}/relationships/{}", user_id, other_id)).await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Core service error: {}", error_text));
+        }
+
+        Ok(response.json::<Relationship>().await?)
+    }
+
+    /// Users who blocked the user and get none of their messages. Nobody
+    /// when core-service cannot be reached, messages are not held up by it.
+    pub async fn blocked_by(&self, user_id: i64) -> Vec<i64> {
+        self.block_lists(user_id).await.blocked_by
+    }
+
+    /// Users the user blocked, whose messages are left out of their history
+    pub async fn blocked(&self, user_id: i64) -> Vec<i64> {
+        self.block_lists(user_id).await.blocked
+    }
+
+    async fn block_lists(&self, user_id: i64) -> BlockLists {
+        match self.cache.get::<BlockLists>(&blocks_key(user_id)) {
+            Ok(Some(lists)) => return lists,
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read cached blocks of user {}: {}", user_id, e),
+        }
+
+        match self.fetch_block_lists(user_id).await {
+            Ok(lists) => {
+                let _ = self.cache.set(&blocks_key(user_id), &lists, BLOCKS_TTL_SECONDS);
+                lists
+            }
+            Err(e) => {
+                warn!("Failed to look up blocks of user {}, filtering nobody: {}", user_id, e);
+                BlockLists::default()
+            }
+        }
+    }
+
+    async fn fetch_block_lists(&self, user_id: i64) -> Result<BlockLists> {
+        let response = self.get(&format!("/users/{}/blocks", user_id)).await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Core service error: {}", error_text));
+        }
+
+        Ok(response.json::<BlockLists>().await?)
+    }
+}
@@ -1,9 +1,12 @@
 mod api;
+mod contacts;
 mod domain;
 mod repo;
 mod websocket;
 mod redis_listener;
 mod middleware;
+mod privacy_consumer;
+mod sender_names;
 
 use actix::Actor;
 use actix_web::{middleware::Logger, web, App, HttpServer};
@@ -12,9 +15,15 @@ use tracing::{info, Level};
 use tracing_subscriber::FmtSubscriber;
 
 use api::{configure_routes, AppState, MetricsCollector};
-use repo::{MessageRepository, RoomRepository, InvitationRepository};
+use apikeys::{ApiKeyStore, ApiKeyVerifier};
+use contacts::Contacts;
+use contracts::events::PRIVACY_REPORT_MAX_BYTES;
+use messaging::kafka_producer::KafkaProducer;
+use privacy_consumer::PrivacyConsumer;
+use repo::{MessageRepository, RoomRepository, InvitationRepository, PrivacyRepository};
 use websocket::ChatServer;
 use redis_listener::RedisListener;
+use sender_names::SenderNames;
 
 #[actix_web::main]
 async fn main() -> Result<()> {
@@ -31,6 +40,8 @@ async fn main() -> Result<()> {
         .unwrap_or_else(|_| "mysql://root@localhost:3306/rustdb".to_string());
     let redis_url = std::env::var("REDIS_URL")
         .unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let kafka_brokers = std::env::var("KAFKA_BROKERS")
+        .unwrap_or_else(|_| "localhost:9092".to_string());
     let server_port = std::env::var("SERVER_PORT")
         .unwrap_or_else(|_| "8084".to_string())
         .parse::<u16>()?;
@@ -54,6 +65,9 @@ async fn main() -> Result<()> {
     let room_repo = RoomRepository::new(db_pool.clone());
     let invitation_repo = InvitationRepository::new(db_pool.clone());
 
+    // API keys of backend services calling /internal routes
+    let api_key_verifier = ApiKeyVerifier::new(ApiKeyStore::new(db_pool.clone()));
+
     // Start chat server actor
     let chat_server = ChatServer::new(redis_conn).start();
     info!("Chat server actor started");
@@ -65,6 +79,25 @@ async fn main() -> Result<()> {
     });
     info!("Redis listener started");
 
+    // Sender names on messages, kept current from user events
+    let sender_names = SenderNames::new(redis_cache.clone(), room_repo.clone());
+    let names_consumer = sender_names.clone();
+    let names_brokers = kafka_brokers.clone();
+    tokio::spawn(async move {
+        if let Err(e) = names_consumer.start_consumer(&names_brokers).await {
+            tracing::error!("User events consumer stopped: {}", e);
+        }
+    });
+
+    // Data exports and erasures requested through auth-service
+    let report_producer = KafkaProducer::with_max_message_bytes(&kafka_brokers, PRIVACY_REPORT_MAX_BYTES)?;
+    let privacy_consumer = PrivacyConsumer::new(PrivacyRepository::new(db_pool.clone()), report_producer);
+    tokio::spawn(async move {
+        if let Err(e) = privacy_consumer.start(&kafka_brokers).await {
+            tracing::error!("Privacy requests consumer stopped: {}", e);
+        }
+    });
+
     // Create app state
     let app_state = web::Data::new(AppState {
         chat_server,
@@ -72,6 +105,8 @@ async fn main() -> Result<()> {
         room_repo,
         invitation_repo,
         redis_cache: redis_cache.clone(),
+        sender_names,
+        contacts: Contacts::from_env(redis_cache.clone()),
     });
 
     info!("Starting HTTP server on port {}", server_port);
@@ -81,7 +116,7 @@ async fn main() -> Result<()> {
         App::new()
             .app_data(app_state.clone())
             .wrap(Logger::default())
-            .configure(configure_routes)
+            .configure(|cfg| configure_routes(cfg, &api_key_verifier))
     })
     .bind(("0.0.0.0", server_port))?
     .run()
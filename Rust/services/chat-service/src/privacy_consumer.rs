@@ -0,0 +1,54 @@
+use anyhow::Result;
+use contracts::events::{
+    PrivacyReportEvent, PrivacyRequestEvent, PrivacyRequestKind, PRIVACY_REPORTS_TOPIC, PRIVACY_REQUESTS_TOPIC,
+};
+use messaging::kafka_consumer::{InvalidMessage, KafkaConsumer};
+use messaging::kafka_producer::KafkaProducer;
+use tracing::{error, info};
+
+use crate::repo::PrivacyRepository;
+
+const SERVICE_NAME: &str = "chat-service";
+
+/// Answers the data exports and erasures auth-service asks for on
+/// privacy-requests
+pub struct PrivacyConsumer {
+    privacy_repo: PrivacyRepository,
+    producer: KafkaProducer,
+}
+
+impl PrivacyConsumer {
+    /// `producer` must allow messages up to PRIVACY_REPORT_MAX_BYTES
+    pub fn new(privacy_repo: PrivacyRepository, producer: KafkaProducer) -> Self {
+        Self { privacy_repo, producer }
+    }
+
+    pub async fn start(self, brokers: &str) -> Result<()> {
+        info!("Starting privacy requests consumer");
+
+        let consumer = KafkaConsumer::new(brokers, "chat-privacy-group", &[PRIVACY_REQUESTS_TOPIC])?;
+        consumer.consume_async(|_key, payload| self.handle(payload)).await
+    }
+
+    /// Both kinds can run again on a redelivery; auth-service keeps the
+    /// first report
+    async fn handle(&self, payload: String) -> Result<()> {
+        let request: PrivacyRequestEvent = serde_json::from_str(&payload)
+            .map_err(|e| InvalidMessage(format!("Parse error: {}", e)))?;
+        let user_id = i64::from(request.user_id);
+
+        let result = match request.kind {
+            PrivacyRequestKind::Export => self.privacy_repo.export_user(user_id).await.map(Some),
+            PrivacyRequestKind::Erasure => self.privacy_repo.erase_user(user_id).await.map(|()| None),
+        };
+        match &result {
+            Ok(_) => info!("Handled {} request {} for user {}", request.kind.as_str(), request.request_id, user_id),
+            Err(e) => error!("Privacy request {} for user {} failed: {}", request.request_id, user_id, e),
+        }
+
+        let report = PrivacyReportEvent::from_result(&request, SERVICE_NAME, result.map_err(|e| e.to_string()));
+        self.producer
+            .send_message(PRIVACY_REPORTS_TOPIC, &request.request_id.to_string(), &report.to_payload()?)
+            .await
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::HashSet;
 use tokio::time::{sleep, Duration};
 use tracing::{error, info, warn};
 
-use crate::websocket::{BroadcastToRoomLocal, ChatServer, WsResponse};
+use crate::websocket::{BroadcastToRoomLocal, ChatServer, RoomBroadcast, WsResponse};
 
 pub struct RedisListener {
     redis_conn: MultiplexedConnection,
@@ -72,13 +72,21 @@ impl RedisListener {
     }
 
     async fn handle_room_message(&self, room_id: &str, payload: &str) {
-        match serde_json::from_str::<WsResponse>(payload) {
-            Ok(message) => {
+        // Instances not yet updated publish the bare message
+        let broadcast = serde_json::from_str::<RoomBroadcast>(payload).or_else(|_| {
+            serde_json::from_str::<WsResponse>(payload).map(|message| RoomBroadcast {
+                message,
+                exclude_users: Vec::new(),
+            })
+        });
+
+        match broadcast {
+            Ok(RoomBroadcast { message, exclude_users }) => {
                 // Broadcast ONLY to local connections (do not re-publish to Redis)
                 self.chat_server.do_send(BroadcastToRoomLocal {
                     room_id: room_id.to_string(),
                     message,
-                    exclude_user: None,
+                    exclude_users,
                 });
             }
             Err(e) => {
@@ -89,6 +97,17 @@ impl RedisListener {
 
     async fn handle_user_message(&self, user_id: i64, payload: &str) {
         match serde_json::from_str::<WsResponse>(payload) {
+            Ok(WsResponse::SessionRevoked { session_id, message }) => {
+                use crate::websocket::DisconnectAuthSession;
+                self.chat_server.do_send(DisconnectAuthSession {
+                    user_id,
+                    message: WsResponse::SessionRevoked {
+                        session_id: session_id.clone(),
+                        message,
+                    },
+                    auth_session_id: session_id,
+                });
+            }
             Ok(message) => {
                 // Send to specific user if connected to this instance
                 use crate::websocket::BroadcastToUsers;
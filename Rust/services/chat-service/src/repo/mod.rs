@@ -1,7 +1,9 @@
 pub mod message_repo;
 pub mod room_repo;
 pub mod invitation_repo;
+pub mod privacy_repo;
 
 pub use message_repo::MessageRepository;
 pub use room_repo::RoomRepository;
 pub use invitation_repo::InvitationRepository;
+pub use privacy_repo::PrivacyRepository;
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use sqlx::MySqlPool;
+
+/// Stands in for an erased user on messages, rooms and invitations
+const ERASED_USER_ID: i64 = 0;
+
+#[derive(Clone)]
+pub struct PrivacyRepository {
+    pub pool: MySqlPool,
+}
+
+impl PrivacyRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// The user's rooms, the messages they sent and their invitations
+    pub async fn export_user(&self, user_id: i64) -> Result<serde_json::Value> {
+        let queries = [
+            ("rooms", r#"
+                SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('room_id', r.id, 'name', r.name, 'room_type', r.room_type,
+                    'role', m.role, 'joined_at', m.joined_at, 'left_at', m.left_at)), JSON_ARRAY())
+                FROM chat_room_members m
+                JOIN chat_rooms r ON r.id = m.room_id
+                WHERE m.user_id = ?
+            "#),
+            ("messages", r#"
+                SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('id', id, 'room_id', room_id, 'content', content,
+                    'message_type', message_type, 'metadata', metadata, 'created_at', created_at)), JSON_ARRAY())
+                FROM chat_messages
+                WHERE sender_id = ?
+            "#),
+            ("invitations_received", r#"
+                SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('room_id', room_id, 'invited_by', invited_by,
+                    'status', status, 'created_at', created_at)), JSON_ARRAY())
+                FROM room_invitations
+                WHERE user_id = ?
+            "#),
+            ("invitations_sent", r#"
+                SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('room_id', room_id, 'user_id', user_id,
+                    'status', status, 'created_at', created_at)), JSON_ARRAY())
+                FROM room_invitations
+                WHERE invited_by = ?
+            "#),
+        ];
+
+        let mut export = serde_json::Map::new();
+        for (name, query) in queries {
+            let json: Option<String> = sqlx::query_scalar(query)
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .flatten();
+            let value = match json {
+                Some(json) => serde_json::from_str(&json)?,
+                None => serde_json::Value::Null,
+            };
+            export.insert(name.to_string(), value);
+        }
+
+        Ok(serde_json::Value::Object(export))
+    }
+
+    /// Detach the user from everything in chat. Messages stay in their rooms
+    /// for the other members, without a sender.
+    pub async fn erase_user(&self, user_id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let statements = [
+            "UPDATE chat_messages SET sender_id = ?, metadata = NULL WHERE sender_id = ?",
+            "UPDATE chat_rooms SET created_by = ? WHERE created_by = ?",
+            "UPDATE room_invitations SET invited_by = ? WHERE invited_by = ?",
+        ];
+        for statement in statements {
+            sqlx::query(statement)
+                .bind(ERASED_USER_ID)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for table in ["room_invitations", "chat_room_members"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE user_id = ?", table))
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use common::cache::{user_cache_key, RedisCache};
+use contracts::events::{UserEvent, UserEventMessage, USER_EVENTS_TOPIC};
+use messaging::kafka_consumer::{InvalidMessage, KafkaConsumer};
+use tracing::{info, warn};
+
+use crate::repo::RoomRepository;
+
+// Names are refreshed from user-events, the TTL only bounds a missed event
+const NAME_TTL_SECONDS: u64 = 24 * 3600;
+
+fn name_key(user_id: i64) -> String {
+    format!("{}:name", user_cache_key(user_id))
+}
+
+/// Sender names shown on chat messages, cached in Redis instead of being
+/// looked up for every message
+#[derive(Clone)]
+pub struct SenderNames {
+    cache: RedisCache,
+    room_repo: RoomRepository,
+}
+
+impl SenderNames {
+    pub fn new(cache: RedisCache, room_repo: RoomRepository) -> Self {
+        Self { cache, room_repo }
+    }
+
+    pub async fn get(&self, user_id: i64) -> Option<String> {
+        match self.cache.get::<String>(&name_key(user_id)) {
+            Ok(Some(name)) => return Some(name),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read cached name of user {}: {}", user_id, e),
+        }
+
+        let name = match self.room_repo.get_user_name(user_id).await {
+            Ok(name) => name?,
+            Err(e) => {
+                warn!("Failed to look up name of user {}: {}", user_id, e);
+                return None;
+            }
+        };
+        let _ = self.cache.set(&name_key(user_id), &name, NAME_TTL_SECONDS);
+        Some(name)
+    }
+
+    /// Keep cached names current as users are created, renamed in
+    /// core-service or deleted
+    pub async fn start_consumer(self, brokers: &str) -> Result<()> {
+        info!("Starting user events consumer");
+
+        let consumer = KafkaConsumer::new(brokers, "chat-user-group", &[USER_EVENTS_TOPIC])?;
+        consumer.consume_async(|_key, payload| self.handle(payload)).await
+    }
+
+    async fn handle(&self, payload: String) -> Result<()> {
+        let message: UserEventMessage = serde_json::from_str(&payload)
+            .map_err(|e| InvalidMessage(format!("Parse error: {}", e)))?;
+
+        match &message.event {
+            UserEvent::Created(e) => self.cache.set(&name_key(e.user_id.into()), &e.name, NAME_TTL_SECONDS)?,
+            UserEvent::Updated(e) => match &e.name {
+                Some(name) => self.cache.set(&name_key(e.user_id.into()), name, NAME_TTL_SECONDS)?,
+                None => self.cache.delete(&name_key(e.user_id.into()))?,
+            },
+            UserEvent::Deleted(e) => self.cache.delete(&name_key(e.user_id.into()))?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
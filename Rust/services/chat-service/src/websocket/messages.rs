@@ -101,6 +101,13 @@ pub enum WsResponse {
     ConnectionReplaced {
         message: String,
     },
+    // Published by auth-service when a login session is revoked; closes the
+    // connections opened with that session's tokens
+    #[serde(rename = "session_revoked")]
+    SessionRevoked {
+        session_id: String,
+        message: String,
+    },
     #[serde(rename = "rate_limit_exceeded")]
     RateLimitExceeded {
         event_type: String,
@@ -119,6 +126,7 @@ pub enum WsResponse {
 pub struct Connect {
     pub addr: Recipient<WsResponseMessage>,
     pub user_id: i64,
+    pub auth_session_id: Option<String>, // sid claim of the token
 }
 
 #[derive(Message)]
@@ -155,7 +163,7 @@ pub struct SendMessage {
 pub struct BroadcastToRoom {
     pub room_id: String,
     pub message: WsResponse,
-    pub exclude_user: Option<i64>,
+    pub exclude_users: Vec<i64>,
 }
 
 // Broadcast only to local connections (used by Redis listener to avoid loops)
@@ -164,7 +172,15 @@ pub struct BroadcastToRoom {
 pub struct BroadcastToRoomLocal {
     pub room_id: String,
     pub message: WsResponse,
-    pub exclude_user: Option<i64>,
+    pub exclude_users: Vec<i64>,
+}
+
+// Payload of the chat:room:* Redis channels, exclusions travel with the message
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoomBroadcast {
+    pub message: WsResponse,
+    #[serde(default)]
+    pub exclude_users: Vec<i64>,
 }
 
 #[derive(Message, Clone)]
@@ -184,6 +200,15 @@ pub struct BroadcastToUsers {
     pub message: WsResponse,
 }
 
+// Close the local connections of a revoked login session
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DisconnectAuthSession {
+    pub user_id: i64,
+    pub auth_session_id: String,
+    pub message: WsResponse,
+}
+
 // Track user typing
 #[derive(Message)]
 #[rtype(result = "()")]
@@ -16,6 +16,7 @@ pub struct SessionInfo {
     pub addr: Recipient<WsResponseMessage>,
     pub connected_at: Instant,
     pub session_id: String,
+    pub auth_session_id: Option<String>,
 }
 
 pub struct ChatServer {
@@ -158,6 +159,7 @@ impl Handler<Connect> for ChatServer {
             addr: msg.addr,
             connected_at: Instant::now(),
             session_id: session_id.clone(),
+            auth_session_id: msg.auth_session_id,
         });
         
         info!(
@@ -342,11 +344,14 @@ impl Handler<BroadcastToRoom> for ChatServer {
         // Let Redis listener handle broadcasting to ALL instances (including this one)
         let redis_channel = format!("chat:room:{}", msg.room_id);
         let mut redis_conn = self.redis_conn.clone();
-        let message = msg.message.clone();
+        let broadcast = RoomBroadcast {
+            message: msg.message,
+            exclude_users: msg.exclude_users,
+        };
         
         ctx.spawn(
             async move {
-                if let Ok(json) = serde_json::to_string(&message) {
+                if let Ok(json) = serde_json::to_string(&broadcast) {
                     let _: Result<(), redis::RedisError> = redis_conn.publish(&redis_channel, json).await;
                 }
             }
@@ -363,10 +368,8 @@ impl Handler<BroadcastToRoomLocal> for ChatServer {
         // Only broadcast to local connections, do NOT publish to Redis
         if let Some(users) = self.rooms.get(&msg.room_id) {
             for user_id in users {
-                if let Some(exclude) = msg.exclude_user {
-                    if *user_id == exclude {
-                        continue;
-                    }
+                if msg.exclude_users.contains(user_id) {
+                    continue;
                 }
                 self.send_message_to_user(*user_id, &msg.message);
             }
@@ -401,6 +404,22 @@ impl Handler<BroadcastToUsers> for ChatServer {
     }
 }
 
+impl Handler<DisconnectAuthSession> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: DisconnectAuthSession, _: &mut Context<Self>) {
+        // Each session closes itself on the message and then sends Disconnect
+        if let Some(sessions) = self.sessions.get(&msg.user_id) {
+            for session in sessions {
+                if session.auth_session_id.as_deref() == Some(msg.auth_session_id.as_str()) {
+                    info!("Closing session {} of user {}: login session revoked", session.session_id, msg.user_id);
+                    let _ = session.addr.do_send(WsResponseMessage(msg.message.clone()));
+                }
+            }
+        }
+    }
+}
+
 impl Handler<UserTyping> for ChatServer {
     type Result = ();
     
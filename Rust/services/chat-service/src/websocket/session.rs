@@ -2,12 +2,13 @@ use actix::prelude::*;
 use actix_web_actors::ws;
 use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
-use sqlx;
 
 use super::messages::*;
 use super::server::ChatServer;
+use crate::contacts::Contacts;
 use crate::domain::Message;
 use crate::repo::{MessageRepository, RoomRepository};
+use crate::sender_names::SenderNames;
 
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
@@ -16,27 +17,36 @@ pub struct WsSession {
     pub id: i64,
     pub user_id: i64,
     pub session_id: String,
+    pub auth_session_id: Option<String>,
     pub hb: Instant,
     pub server_addr: Addr<ChatServer>,
     pub message_repo: MessageRepository,
     pub room_repo: RoomRepository,
+    pub sender_names: SenderNames,
+    pub contacts: Contacts,
 }
 
 impl WsSession {
     pub fn new(
         user_id: i64,
+        auth_session_id: Option<String>,
         server_addr: Addr<ChatServer>,
         message_repo: MessageRepository,
         room_repo: RoomRepository,
+        sender_names: SenderNames,
+        contacts: Contacts,
     ) -> Self {
         Self {
             id: rand::random::<i64>(),
             user_id,
             session_id: uuid::Uuid::new_v4().to_string(),
+            auth_session_id,
             hb: Instant::now(),
             server_addr,
             message_repo,
             room_repo,
+            sender_names,
+            contacts,
         }
     }
 
@@ -75,6 +85,8 @@ impl WsSession {
                 
                 let message_repo = self.message_repo.clone();
                 let room_repo = self.room_repo.clone();
+                let sender_names = self.sender_names.clone();
+                let contacts = self.contacts.clone();
 
                 let fut = async move {
                     // Wait for rate limit check
@@ -123,15 +135,9 @@ impl WsSession {
                                 error!("Failed to unhide room: {}", e);
                             }
 
-                            // Get sender name from database
-                            let sender_name = sqlx::query_scalar::<_, String>(
-                                "SELECT name FROM users WHERE id = ?"
-                            )
-                            .bind(user_id)
-                            .fetch_optional(&message_repo.pool)
-                            .await
-                            .ok()
-                            .flatten();
+                            let sender_name = sender_names.get(user_id).await;
+                            // Saved, but not delivered to members who blocked the sender
+                            let blocked_by = contacts.blocked_by(user_id).await;
 
                             // Broadcast to room
                             let response = WsResponse::Message {
@@ -148,7 +154,7 @@ impl WsSession {
                             server_addr.do_send(BroadcastToRoom {
                                 room_id: room_id.clone(),
                                 message: response,
-                                exclude_user: None,
+                                exclude_users: blocked_by.clone(),
                             });
 
                             // Send room_updated notification
@@ -160,13 +166,15 @@ impl WsSession {
                             server_addr.do_send(BroadcastToRoom {
                                 room_id: room_id.clone(),
                                 message: room_updated,
-                                exclude_user: None,
+                                exclude_users: blocked_by.clone(),
                             });
 
                             // Calculate and send unread updates to all members except sender
                             if let Ok(members) = room_repo.get_room_members(&room_id).await {
                                 for member in members {
-                                    if member.user_id != user_id && member.left_at.is_none() {
+                                    if member.user_id != user_id && member.left_at.is_none()
+                                        && !blocked_by.contains(&member.user_id)
+                                    {
                                         if let Ok(unread_count) = room_repo.get_unread_count(&room_id, member.user_id).await {
                                             let unread_notification = WsResponse::UnreadUpdated {
                                                 room_id: room_id.clone(),
@@ -312,7 +320,7 @@ impl WsSession {
                             act.server_addr.do_send(BroadcastToRoom {
                                 room_id,
                                 message: response,
-                                exclude_user: Some(act.user_id),
+                                exclude_users: vec![act.user_id],
                             });
                         }
                         Err((retry_after, event_type)) => {
@@ -342,6 +350,7 @@ impl Actor for WsSession {
             .send(Connect {
                 addr: addr.recipient(),
                 user_id: self.user_id,
+                auth_session_id: self.auth_session_id.clone(),
             })
             .into_actor(self)
             .then(|_, _, _| fut::ready(()))
@@ -402,5 +411,12 @@ impl Handler<WsResponseMessage> for WsSession {
         if let Ok(json) = serde_json::to_string(&msg.0) {
             ctx.text(json);
         }
+        if matches!(msg.0, WsResponse::SessionRevoked { .. }) {
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Policy,
+                description: Some("Session revoked".to_string()),
+            }));
+            ctx.stop();
+        }
     }
 }
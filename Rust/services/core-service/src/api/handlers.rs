@@ -1,7 +1,32 @@
-use actix_web::{web, HttpResponse, Responder};
-use authz::Claims;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use apikeys::ApiKeyPrincipal;
+use authz::{permissions, Claims};
+use serde::Deserialize;
+use crate::domain::contact::{NewContactRequest, RequestDirection};
+use crate::domain::kyc::{DocumentKind, RejectRequest};
+use crate::domain::search::{UserSearch, UserSearchParams};
+use crate::domain::user::ProfileUpdate;
+use crate::service::contact_service::ContactService;
+use crate::service::kyc_service::KycService;
 use crate::service::user_service::UserService;
 
+#[derive(Deserialize)]
+pub struct EmailChangeRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct KycQueueQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ContactRequestsQuery {
+    pub direction: Option<String>,
+}
+
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok().json(serde_json::json!({
         "status": "ok",
@@ -9,33 +34,470 @@ pub async fn health_check() -> impl Responder {
     }))
 }
 
+/// Any user may search; only `users:read` lifts the privacy rules
 pub async fn get_users(
-    _claims: web::ReqData<Claims>,
+    claims: web::ReqData<Claims>,
     service: web::Data<UserService>,
+    query: web::Query<UserSearchParams>,
 ) -> impl Responder {
-    match service.get_all_users().await {
-        Ok(users) => HttpResponse::Ok().json(users),
-        Err(e) => {
-            tracing::error!("Failed to get users: {}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch users"
-            }))
-        }
+    let search = match UserSearch::parse(&query, claims.user_id, claims.has_permission(permissions::USERS_READ)) {
+        Ok(search) => search,
+        Err(e) => return profile_error("Invalid search", e),
+    };
+
+    match service.search_users(&search).await {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => profile_error("Failed to fetch users", e),
     }
 }
 
 pub async fn get_user(
-    _claims: web::ReqData<Claims>,
+    claims: web::ReqData<Claims>,
     service: web::Data<UserService>,
     user_id: web::Path<i32>,
+    query: web::Query<UserSearchParams>,
 ) -> impl Responder {
-    match service.get_user_by_id(user_id.into_inner()).await {
+    let search = match UserSearch::parse(&query, claims.user_id, claims.has_permission(permissions::USERS_READ)) {
+        Ok(search) => search,
+        Err(e) => return profile_error("Invalid fields", e),
+    };
+
+    match service.get_user(user_id.into_inner(), &search).await {
         Ok(user) => HttpResponse::Ok().json(user),
-        Err(e) => {
-            tracing::error!("Failed to get user: {}", e);
-            HttpResponse::NotFound().json(serde_json::json!({
-                "error": "User not found"
-            }))
+        Err(e) => profile_error("Failed to get user", e),
+    }
+}
+
+fn profile_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.starts_with("Invalid") || error_msg.starts_with("Unsupported image")
+        || error_msg.contains("current address") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("already registered") {
+        HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("two-factor") {
+        HttpResponse::Forbidden().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn get_me(
+    claims: web::ReqData<Claims>,
+    service: web::Data<UserService>,
+) -> impl Responder {
+    match service.get_profile(claims.user_id).await {
+        Ok(profile) => HttpResponse::Ok().json(profile),
+        Err(e) => profile_error("Failed to get profile", e),
+    }
+}
+
+pub async fn update_me(
+    claims: web::ReqData<Claims>,
+    service: web::Data<UserService>,
+    update: web::Json<ProfileUpdate>,
+) -> impl Responder {
+    match service.update_profile(claims.user_id, &update).await {
+        Ok(profile) => HttpResponse::Ok().json(profile),
+        Err(e) => profile_error("Failed to update profile", e),
+    }
+}
+
+/// Raw image bytes as the body
+pub async fn upload_avatar(
+    claims: web::ReqData<Claims>,
+    service: web::Data<UserService>,
+    body: web::Bytes,
+) -> impl Responder {
+    match service.upload_avatar(claims.user_id, body.to_vec()).await {
+        Ok(profile) => HttpResponse::Ok().json(profile),
+        Err(e) => profile_error("Failed to upload avatar", e),
+    }
+}
+
+pub async fn delete_avatar(
+    claims: web::ReqData<Claims>,
+    service: web::Data<UserService>,
+) -> impl Responder {
+    match service.delete_avatar(claims.user_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => profile_error("Failed to delete avatar", e),
+    }
+}
+
+pub async fn get_avatar(
+    _claims: web::ReqData<Claims>,
+    service: web::Data<UserService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    match service.avatar(user_id.into_inner()).await {
+        // The URL carries the version, so the picture never changes under it
+        Ok(png) => HttpResponse::Ok()
+            .content_type("image/png")
+            .insert_header(("Cache-Control", "private, max-age=31536000, immutable"))
+            .body(png),
+        Err(e) => profile_error("Failed to get avatar", e),
+    }
+}
+
+/// The user's bearer token is passed on to auth-service, which applies
+/// its own checks (recent two-factor authentication included)
+pub async fn request_email_change(
+    _claims: web::ReqData<Claims>,
+    service: web::Data<UserService>,
+    req: HttpRequest,
+    request: web::Json<EmailChangeRequest>,
+) -> impl Responder {
+    let token = req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    match service.request_email_change(token, &request.email).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => profile_error("Failed to request email change", e),
+    }
+}
+
+fn kyc_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.starts_with("Invalid") || error_msg.starts_with("Unsupported document")
+        || error_msg.starts_with("Missing documents") || error_msg.starts_with("No application") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("under review") || error_msg.contains("already verified")
+        || error_msg.starts_with("Only a pending") {
+        HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.starts_with("Verify your email") || error_msg.starts_with("Reviewers cannot") {
+        HttpResponse::Forbidden().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("not configured") {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn get_my_kyc(
+    claims: web::ReqData<Claims>,
+    kyc_service: web::Data<KycService>,
+) -> impl Responder {
+    match kyc_service.status(claims.user_id).await {
+        Ok(status) => HttpResponse::Ok().json(status),
+        Err(e) => kyc_error("Failed to get verification status", e),
+    }
+}
+
+/// Raw PNG, JPEG or PDF as the body
+pub async fn upload_kyc_document(
+    claims: web::ReqData<Claims>,
+    kyc_service: web::Data<KycService>,
+    kind: web::Path<String>,
+    body: web::Bytes,
+) -> impl Responder {
+    let kind = match DocumentKind::parse(&kind) {
+        Ok(kind) => kind,
+        Err(e) => return kyc_error("Failed to upload document", e),
+    };
+    match kyc_service.upload_document(claims.user_id, kind, body.to_vec()).await {
+        Ok(status) => HttpResponse::Ok().json(status),
+        Err(e) => kyc_error("Failed to upload document", e),
+    }
+}
+
+pub async fn submit_kyc(
+    claims: web::ReqData<Claims>,
+    kyc_service: web::Data<KycService>,
+) -> impl Responder {
+    match kyc_service.submit(claims.user_id).await {
+        Ok(status) => HttpResponse::Accepted().json(status),
+        Err(e) => kyc_error("Failed to submit application", e),
+    }
+}
+
+/// Pending applications by default, oldest first
+pub async fn get_kyc_queue(
+    kyc_service: web::Data<KycService>,
+    query: web::Query<KycQueueQuery>,
+) -> impl Responder {
+    let status = query.status.as_deref().unwrap_or("pending");
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match kyc_service.queue(status, limit, offset).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => kyc_error("Failed to fetch review queue", e),
+    }
+}
+
+pub async fn get_kyc_application(
+    kyc_service: web::Data<KycService>,
+    application_id: web::Path<i64>,
+) -> impl Responder {
+    match kyc_service.review(application_id.into_inner()).await {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(e) => kyc_error("Failed to get application", e),
+    }
+}
+
+pub async fn get_kyc_document(
+    claims: web::ReqData<Claims>,
+    kyc_service: web::Data<KycService>,
+    path: web::Path<(i64, String)>,
+) -> impl Responder {
+    let (application_id, kind) = path.into_inner();
+    let kind = match DocumentKind::parse(&kind) {
+        Ok(kind) => kind,
+        Err(e) => return kyc_error("Failed to get document", e),
+    };
+
+    match kyc_service.document(application_id, kind).await {
+        Ok((content_type, document)) => {
+            tracing::info!("User {} viewed {} of KYC application {}", claims.user_id, kind.as_str(), application_id);
+            HttpResponse::Ok()
+                .content_type(content_type)
+                .insert_header(("Cache-Control", "no-store"))
+                .body(document)
         }
+        Err(e) => kyc_error("Failed to get document", e),
+    }
+}
+
+pub async fn approve_kyc_application(
+    claims: web::ReqData<Claims>,
+    kyc_service: web::Data<KycService>,
+    application_id: web::Path<i64>,
+) -> impl Responder {
+    match kyc_service.approve(application_id.into_inner(), claims.user_id).await {
+        Ok(application) => HttpResponse::Ok().json(application),
+        Err(e) => kyc_error("Failed to approve application", e),
+    }
+}
+
+pub async fn reject_kyc_application(
+    claims: web::ReqData<Claims>,
+    kyc_service: web::Data<KycService>,
+    application_id: web::Path<i64>,
+    request: web::Json<RejectRequest>,
+) -> impl Responder {
+    match kyc_service.reject(application_id.into_inner(), claims.user_id, &request.reason).await {
+        Ok(application) => HttpResponse::Ok().json(application),
+        Err(e) => kyc_error("Failed to reject application", e),
+    }
+}
+
+/// Who called a server-to-server route: an API key under /internal, or a
+/// service client token under /service
+fn internal_caller(req: &HttpRequest) -> String {
+    let extensions = req.extensions();
+    if let Some(principal) = extensions.get::<ApiKeyPrincipal>() {
+        format!("API key {} ({})", principal.prefix, principal.owner)
+    } else if let Some(client_id) = extensions.get::<Claims>().and_then(|claims| claims.client_id.as_deref()) {
+        format!("Service client {}", client_id)
+    } else {
+        "Unknown caller".to_string()
+    }
+}
+
+/// Tier lookup for the gateway's payment limits
+pub async fn get_kyc_internal(
+    req: HttpRequest,
+    kyc_service: web::Data<KycService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    let user_id = user_id.into_inner();
+    tracing::debug!("{} reading KYC tier of user {}", internal_caller(&req), user_id);
+
+    match kyc_service.tier(user_id).await {
+        Ok((tier, verified_at)) => HttpResponse::Ok().json(serde_json::json!({
+            "user_id": user_id,
+            "tier": tier,
+            "level": tier.level(),
+            "verified_at": verified_at,
+        })),
+        Err(e) => kyc_error("Failed to get verification status", e),
+    }
+}
+
+fn contact_error(context: &str, e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.starts_with("Invalid") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.starts_with("Already in your contacts") || error_msg.starts_with("Request already sent")
+        || error_msg.contains("declined recently") || error_msg.contains("no longer pending") {
+        HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.starts_with("Cannot send a request") {
+        HttpResponse::Forbidden().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        tracing::error!("{}: {}", context, e);
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": context
+        }))
+    }
+}
+
+pub async fn get_contacts(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+) -> impl Responder {
+    match contact_service.contacts(claims.user_id).await {
+        Ok(contacts) => HttpResponse::Ok().json(serde_json::json!({ "data": contacts })),
+        Err(e) => contact_error("Failed to list contacts", e),
+    }
+}
+
+pub async fn remove_contact(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    contact_id: web::Path<i32>,
+) -> impl Responder {
+    match contact_service.remove_contact(claims.user_id, contact_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => contact_error("Failed to remove contact", e),
+    }
+}
+
+/// Pending requests, `?direction=incoming` (default) or `outgoing`
+pub async fn get_contact_requests(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    query: web::Query<ContactRequestsQuery>,
+) -> impl Responder {
+    let direction = match RequestDirection::parse(query.direction.as_deref()) {
+        Ok(direction) => direction,
+        Err(e) => return contact_error("Invalid direction", e),
+    };
+
+    match contact_service.requests(claims.user_id, direction).await {
+        Ok(requests) => HttpResponse::Ok().json(serde_json::json!({ "data": requests })),
+        Err(e) => contact_error("Failed to list contact requests", e),
+    }
+}
+
+pub async fn send_contact_request(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    request: web::Json<NewContactRequest>,
+) -> impl Responder {
+    match contact_service.send_request(claims.user_id, request.user_id).await {
+        Ok(request) => HttpResponse::Created().json(request),
+        Err(e) => contact_error("Failed to send contact request", e),
+    }
+}
+
+pub async fn accept_contact_request(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    request_id: web::Path<i64>,
+) -> impl Responder {
+    match contact_service.accept(claims.user_id, request_id.into_inner()).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(e) => contact_error("Failed to accept contact request", e),
+    }
+}
+
+pub async fn decline_contact_request(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    request_id: web::Path<i64>,
+) -> impl Responder {
+    match contact_service.decline(claims.user_id, request_id.into_inner()).await {
+        Ok(request) => HttpResponse::Ok().json(request),
+        Err(e) => contact_error("Failed to decline contact request", e),
+    }
+}
+
+pub async fn cancel_contact_request(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    request_id: web::Path<i64>,
+) -> impl Responder {
+    match contact_service.cancel(claims.user_id, request_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => contact_error("Failed to cancel contact request", e),
+    }
+}
+
+pub async fn get_blocks(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+) -> impl Responder {
+    match contact_service.blocks(claims.user_id).await {
+        Ok(blocks) => HttpResponse::Ok().json(serde_json::json!({ "data": blocks })),
+        Err(e) => contact_error("Failed to list blocked users", e),
+    }
+}
+
+pub async fn block_user(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    match contact_service.block(claims.user_id, user_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => contact_error("Failed to block user", e),
+    }
+}
+
+pub async fn unblock_user(
+    claims: web::ReqData<Claims>,
+    contact_service: web::Data<ContactService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    match contact_service.unblock(claims.user_id, user_id.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => contact_error("Failed to unblock user", e),
+    }
+}
+
+/// How one user stands with another, for chat-service and the gateway
+pub async fn get_relationship_internal(
+    req: HttpRequest,
+    contact_service: web::Data<ContactService>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let (user_id, other_id) = path.into_inner();
+    tracing::debug!("{} reading relationship of users {} and {}", internal_caller(&req), user_id, other_id);
+
+    match contact_service.relationship(user_id, other_id).await {
+        Ok(relationship) => HttpResponse::Ok().json(serde_json::json!({
+            "user_id": user_id,
+            "other_id": other_id,
+            "contact": relationship.contact,
+            "blocked": relationship.blocked,
+            "blocked_by": relationship.blocked_by,
+        })),
+        Err(e) => contact_error("Failed to get relationship", e),
+    }
+}
+
+/// Block lists of a user, for filtering chat message delivery
+pub async fn get_blocks_internal(
+    req: HttpRequest,
+    contact_service: web::Data<ContactService>,
+    user_id: web::Path<i32>,
+) -> impl Responder {
+    let user_id = user_id.into_inner();
+    tracing::debug!("{} reading blocks of user {}", internal_caller(&req), user_id);
+
+    match contact_service.block_lists(user_id).await {
+        Ok((blocked, blocked_by)) => HttpResponse::Ok().json(serde_json::json!({
+            "user_id": user_id,
+            "blocked": blocked,
+            "blocked_by": blocked_by,
+        })),
+        Err(e) => contact_error("Failed to get blocks", e),
     }
 }
@@ -1,21 +1,104 @@
 use actix_web::web;
 use super::handlers;
-use authz::AuthMiddleware;
-
-pub fn configure(cfg: &mut web::ServiceConfig) {
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "RushTech@2025xAjxh".to_string());
+use apikeys::{scopes, ApiKeyAuth, ApiKeyVerifier};
+use authz::{permissions, AuthMiddleware, Require};
+use crate::domain::avatar::AVATAR_UPLOAD_LIMIT;
+use crate::domain::kyc::DOCUMENT_UPLOAD_LIMIT;
 
+pub fn configure(cfg: &mut web::ServiceConfig, api_keys: &ApiKeyVerifier) {
     cfg
         // Public route
         .route("/health", web::get().to(handlers::health_check))
+        // Server-to-server routes (API key required)
+        .service(
+            web::scope("/internal")
+                .service(
+                    web::resource("/users/{id}/kyc")
+                        .wrap(ApiKeyAuth::new(api_keys.clone(), scopes::KYC_READ))
+                        .route(web::get().to(handlers::get_kyc_internal))
+                )
+                .service(
+                    web::resource("/users/{id}/blocks")
+                        .wrap(ApiKeyAuth::new(api_keys.clone(), scopes::CONTACTS_READ))
+                        .route(web::get().to(handlers::get_blocks_internal))
+                )
+                .service(
+                    web::resource("/users/{id}/relationships/{other_id}")
+                        .wrap(ApiKeyAuth::new(api_keys.clone(), scopes::CONTACTS_READ))
+                        .route(web::get().to(handlers::get_relationship_internal))
+                )
+        )
+        // The same routes for service clients (client_credentials token with the scope)
+        .service(
+            web::scope("/service")
+                .wrap(Require::service())
+                .wrap(AuthMiddleware::from_env().accept_service_clients())
+                .service(
+                    web::resource("/users/{id}/kyc")
+                        .wrap(Require::permission(scopes::KYC_READ))
+                        .route(web::get().to(handlers::get_kyc_internal))
+                )
+                .service(
+                    web::resource("/users/{id}/blocks")
+                        .wrap(Require::permission(scopes::CONTACTS_READ))
+                        .route(web::get().to(handlers::get_blocks_internal))
+                )
+                .service(
+                    web::resource("/users/{id}/relationships/{other_id}")
+                        .wrap(Require::permission(scopes::CONTACTS_READ))
+                        .route(web::get().to(handlers::get_relationship_internal))
+                )
+        )
         // Protected routes
         .service(
             web::scope("/api/v1")
-                .wrap(AuthMiddleware::new(jwt_secret))
+                .wrap(AuthMiddleware::from_env())
                 .service(
                     web::scope("/users")
                         .route("", web::get().to(handlers::get_users))
+                        // Before /{id}, which would take "me" as an id
+                        .route("/me", web::get().to(handlers::get_me))
+                        .route("/me", web::patch().to(handlers::update_me))
+                        .service(
+                            web::resource("/me/avatar")
+                                .app_data(web::PayloadConfig::new(AVATAR_UPLOAD_LIMIT))
+                                .route(web::put().to(handlers::upload_avatar))
+                                .route(web::delete().to(handlers::delete_avatar))
+                        )
+                        .route("/me/email", web::post().to(handlers::request_email_change))
+                        .route("/me/kyc", web::get().to(handlers::get_my_kyc))
+                        .service(
+                            web::resource("/me/kyc/documents/{kind}")
+                                .app_data(web::PayloadConfig::new(DOCUMENT_UPLOAD_LIMIT))
+                                .route(web::put().to(handlers::upload_kyc_document))
+                        )
+                        .route("/me/kyc/submit", web::post().to(handlers::submit_kyc))
                         .route("/{id}", web::get().to(handlers::get_user))
+                        .route("/{id}/avatar", web::get().to(handlers::get_avatar))
+                )
+                .service(
+                    web::scope("/contacts")
+                        .route("", web::get().to(handlers::get_contacts))
+                        // Before /{user_id}
+                        .route("/requests", web::get().to(handlers::get_contact_requests))
+                        .route("/requests", web::post().to(handlers::send_contact_request))
+                        .route("/requests/{id}", web::delete().to(handlers::cancel_contact_request))
+                        .route("/requests/{id}/accept", web::post().to(handlers::accept_contact_request))
+                        .route("/requests/{id}/decline", web::post().to(handlers::decline_contact_request))
+                        .route("/blocks", web::get().to(handlers::get_blocks))
+                        .route("/blocks/{user_id}", web::put().to(handlers::block_user))
+                        .route("/blocks/{user_id}", web::delete().to(handlers::unblock_user))
+                        .route("/{user_id}", web::delete().to(handlers::remove_contact))
+                )
+                // Identity verification review
+                .service(
+                    web::scope("/admin/kyc")
+                        .wrap(Require::permission(permissions::KYC_REVIEW))
+                        .route("/applications", web::get().to(handlers::get_kyc_queue))
+                        .route("/applications/{id}", web::get().to(handlers::get_kyc_application))
+                        .route("/applications/{id}/documents/{kind}", web::get().to(handlers::get_kyc_document))
+                        .route("/applications/{id}/approve", web::post().to(handlers::approve_kyc_application))
+                        .route("/applications/{id}/reject", web::post().to(handlers::reject_kyc_application))
                 )
         );
 }
@@ -0,0 +1,57 @@
+// auth-service client, for the account changes auth-service owns
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+#[derive(Clone)]
+pub struct AuthClient {
+    base_url: String,
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl AuthClient {
+    pub fn new(base_url: String, api_key: String) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// AUTH_SERVICE_URL and AUTH_SERVICE_API_KEY (a key with the 'auth' scope)
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("AUTH_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8081".to_string()),
+            std::env::var("AUTH_SERVICE_API_KEY").unwrap_or_default(),
+        )
+    }
+
+    /// Ask auth-service to mail a confirmation link to `new_email`, on
+    /// behalf of the user whose access token is forwarded. The address
+    /// changes once the link is followed.
+    pub async fn request_email_change(&self, access_token: &str, new_email: &str) -> Result<()> {
+        let response = self.client
+            .post(format!("{}/api/v1/auth/email-change/request", self.base_url))
+            .header("X-API-Key", &self.api_key)
+            .bearer_auth(access_token)
+            .json(&serde_json::json!({ "email": new_email }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            return Ok(());
+        }
+        let status = response.status();
+        // auth-service messages are meant for the user, pass them on
+        match response.json::<ErrorBody>().await {
+            Ok(body) if status.is_client_error() => Err(anyhow!("{}", body.error)),
+            Ok(body) => Err(anyhow!("Auth service error {}: {}", status, body.error)),
+            Err(_) => Err(anyhow!("Auth service error {}", status)),
+        }
+    }
+}
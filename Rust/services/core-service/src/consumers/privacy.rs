@@ -0,0 +1,39 @@
+use anyhow::Result;
+use contracts::events::{
+    PrivacyReportEvent, PrivacyRequestEvent, PrivacyRequestKind, PRIVACY_REPORTS_TOPIC, PRIVACY_REQUESTS_TOPIC,
+};
+use messaging::kafka_consumer::{InvalidMessage, KafkaConsumer};
+use messaging::kafka_producer::KafkaProducer;
+use crate::service::user_service::UserService;
+
+const SERVICE_NAME: &str = "core-service";
+
+/// Answers the data exports and erasures auth-service asks for. `producer`
+/// must allow messages up to PRIVACY_REPORT_MAX_BYTES.
+pub async fn start(brokers: &str, service: UserService, producer: KafkaProducer) -> Result<()> {
+    tracing::info!("🔒 Privacy requests consumer starting...");
+
+    let consumer = KafkaConsumer::new(brokers, "core-privacy-group", &[PRIVACY_REQUESTS_TOPIC])?;
+
+    consumer.consume_async(|_key, payload| {
+        let service = service.clone();
+        let producer = producer.clone();
+        async move {
+            let request = serde_json::from_str::<PrivacyRequestEvent>(&payload)
+                .map_err(|e| InvalidMessage(format!("Parse error: {}", e)))?;
+
+            let result = match request.kind {
+                PrivacyRequestKind::Export => service.export_user(request.user_id).await.map(Some),
+                PrivacyRequestKind::Erasure => service.erase_user(request.user_id, request.timestamp).await.map(|()| None),
+            };
+            if let Err(e) = &result {
+                tracing::error!("Privacy request {} for user {} failed: {}", request.request_id, request.user_id, e);
+            }
+
+            let report = PrivacyReportEvent::from_result(&request, SERVICE_NAME, result.map_err(|e| e.to_string()));
+            producer
+                .send_message(PRIVACY_REPORTS_TOPIC, &request.request_id.to_string(), &report.to_payload()?)
+                .await
+        }
+    }).await
+}
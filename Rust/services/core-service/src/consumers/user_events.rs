@@ -0,0 +1,24 @@
+use anyhow::Result;
+use contracts::events::{UserEventMessage, USER_EVENTS_TOPIC};
+use messaging::kafka_consumer::{InvalidMessage, KafkaConsumer};
+use crate::service::user_service::UserService;
+
+/// Keeps user_profiles current from the user-events topic. A failed write is
+/// retried by the consumer until it goes through, so no event is lost.
+pub async fn start(brokers: &str, service: UserService) -> Result<()> {
+    tracing::info!("👤 User events consumer starting...");
+
+    let consumer = KafkaConsumer::new(brokers, "core-user-group", &[USER_EVENTS_TOPIC])?;
+
+    consumer.consume_async(|key, payload| {
+        let service = service.clone();
+        async move {
+            let message = serde_json::from_str::<UserEventMessage>(&payload)
+                .map_err(|e| InvalidMessage(format!("Parse error for user {}: {}", key, e)))?;
+
+            service.apply_event(&message.event).await.map_err(|e| {
+                anyhow::anyhow!("Failed to apply {} event {}: {}", message.event.event_type(), message.event_id, e)
+            })
+        }
+    }).await
+}
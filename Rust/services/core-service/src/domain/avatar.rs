@@ -0,0 +1,62 @@
+// Avatars are stored as square PNGs, whatever was uploaded
+use anyhow::{anyhow, Result};
+use image::{ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+/// Side of the stored picture, in pixels
+pub const AVATAR_SIZE: u32 = 256;
+/// Largest upload accepted
+pub const AVATAR_UPLOAD_LIMIT: usize = 5 * 1024 * 1024;
+
+const MAX_SOURCE_SIDE: u32 = 8192;
+
+/// Decode a PNG, JPEG or WebP upload, crop it to a centered square and
+/// scale it to AVATAR_SIZE. CPU bound, run it off the executor.
+pub fn resize_avatar(upload: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = ImageReader::new(Cursor::new(upload))
+        .with_guessed_format()
+        .map_err(|_| anyhow!("Unsupported image"))?;
+    match reader.format() {
+        Some(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP) => {}
+        _ => return Err(anyhow!("Unsupported image: use PNG, JPEG or WebP")),
+    }
+
+    // A small file can still claim huge dimensions
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_SIDE);
+    limits.max_image_height = Some(MAX_SOURCE_SIDE);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|e| anyhow!("Unsupported image: {}", e))?;
+
+    let avatar = image.resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, image::imageops::FilterType::Lanczos3);
+    let mut png = Vec::new();
+    avatar.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, GenericImageView, RgbImage};
+
+    fn encoded(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40])));
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_avatar_is_a_square_png() {
+        let avatar = resize_avatar(&encoded(640, 480, ImageFormat::Jpeg)).unwrap();
+        let image = image::load_from_memory_with_format(&avatar, ImageFormat::Png).unwrap();
+        assert_eq!(image.dimensions(), (AVATAR_SIZE, AVATAR_SIZE));
+    }
+
+    #[test]
+    fn test_other_files_are_rejected() {
+        assert!(resize_avatar(b"GIF89a not really").is_err());
+        assert!(resize_avatar(b"hello").is_err());
+        assert!(resize_avatar(&encoded(MAX_SOURCE_SIDE + 1, 1, ImageFormat::Png)).is_err());
+    }
+}
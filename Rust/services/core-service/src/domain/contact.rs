@@ -0,0 +1,194 @@
+// Contacts: friend requests, contact lists and blocks
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// A declined request can be sent again after this many days
+pub const REQUEST_RESEND_COOLDOWN_DAYS: i64 = 7;
+
+/// Status of a request: 'pending' until the addressee answers, then
+/// 'accepted' or 'declined'; 'canceled' by the sender or by a block
+pub mod request_status {
+    pub const PENDING: &str = "pending";
+    pub const ACCEPTED: &str = "accepted";
+    pub const DECLINED: &str = "declined";
+    pub const CANCELED: &str = "canceled";
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ContactRequest {
+    pub id: i64,
+    pub requester_id: i32,
+    pub requester_name: String,
+    pub addressee_id: i32,
+    pub addressee_name: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Contact {
+    pub user_id: i32,
+    pub name: String,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct BlockedUser {
+    pub user_id: i32,
+    pub name: String,
+    pub blocked_at: DateTime<Utc>,
+}
+
+/// How one user stands with another
+#[derive(Debug, Clone, Copy, Default, Serialize, FromRow)]
+pub struct Relationship {
+    pub contact: bool,
+    /// The user blocked the other
+    pub blocked: bool,
+    /// The other blocked the user
+    pub blocked_by: bool,
+}
+
+impl Relationship {
+    /// A block either way keeps the two apart
+    pub fn is_blocked(&self) -> bool {
+        self.blocked || self.blocked_by
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewContactRequest {
+    pub user_id: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequestDirection {
+    Incoming,
+    Outgoing,
+}
+
+impl RequestDirection {
+    /// Incoming unless asked otherwise
+    pub fn parse(direction: Option<&str>) -> Result<Self> {
+        match direction {
+            None | Some("incoming") => Ok(RequestDirection::Incoming),
+            Some("outgoing") => Ok(RequestDirection::Outgoing),
+            Some(_) => Err(anyhow!("Invalid direction: use incoming or outgoing")),
+        }
+    }
+}
+
+/// What sending a request to a user comes down to
+#[derive(Debug, PartialEq)]
+pub enum RequestOutcome {
+    Send,
+    /// The user already asked the sender, so both want it
+    AcceptTheirs(i64),
+}
+
+/// `previous` is the sender's last request to the user, `theirs` a pending
+/// request the other way
+pub fn plan_request(
+    from: i32,
+    to: i32,
+    relationship: &Relationship,
+    previous: Option<&ContactRequest>,
+    theirs: Option<&ContactRequest>,
+    now: DateTime<Utc>,
+) -> Result<RequestOutcome> {
+    if from == to {
+        return Err(anyhow!("Invalid request: you cannot add yourself"));
+    }
+    // Same answer for both directions, a block is not revealed
+    if relationship.is_blocked() {
+        return Err(anyhow!("Cannot send a request to this user"));
+    }
+    if relationship.contact {
+        return Err(anyhow!("Already in your contacts"));
+    }
+    if let Some(theirs) = theirs {
+        return Ok(RequestOutcome::AcceptTheirs(theirs.id));
+    }
+
+    match previous {
+        Some(p) if p.status == request_status::PENDING => Err(anyhow!("Request already sent")),
+        Some(p) if p.status == request_status::DECLINED
+            && p.responded_at.is_some_and(|at| at > now - Duration::days(REQUEST_RESEND_COOLDOWN_DAYS)) =>
+        {
+            Err(anyhow!("Request was declined recently, try again later"))
+        }
+        _ => Ok(RequestOutcome::Send),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: i64, status: &str, responded_days_ago: Option<i64>) -> ContactRequest {
+        ContactRequest {
+            id,
+            requester_id: 1,
+            requester_name: "Alice".to_string(),
+            addressee_id: 2,
+            addressee_name: "Bob".to_string(),
+            status: status.to_string(),
+            created_at: Utc::now() - Duration::days(30),
+            responded_at: responded_days_ago.map(|days| Utc::now() - Duration::days(days)),
+        }
+    }
+
+    #[test]
+    fn plans_a_new_request() {
+        let outcome = plan_request(1, 2, &Relationship::default(), None, None, Utc::now()).unwrap();
+        assert_eq!(outcome, RequestOutcome::Send);
+    }
+
+    #[test]
+    fn rejects_self_contacts_and_blocks() {
+        let none = Relationship::default();
+        assert!(plan_request(1, 1, &none, None, None, Utc::now()).is_err());
+
+        let contact = Relationship { contact: true, ..none };
+        assert!(plan_request(1, 2, &contact, None, None, Utc::now()).is_err());
+
+        let blocked = Relationship { blocked: true, ..none };
+        let blocked_by = Relationship { blocked_by: true, ..none };
+        let e1 = plan_request(1, 2, &blocked, None, None, Utc::now()).unwrap_err();
+        let e2 = plan_request(1, 2, &blocked_by, None, None, Utc::now()).unwrap_err();
+        assert_eq!(e1.to_string(), e2.to_string());
+    }
+
+    #[test]
+    fn accepts_a_pending_request_the_other_way() {
+        let theirs = request(7, request_status::PENDING, None);
+        let outcome = plan_request(2, 1, &Relationship::default(), None, Some(&theirs), Utc::now()).unwrap();
+        assert_eq!(outcome, RequestOutcome::AcceptTheirs(7));
+    }
+
+    #[test]
+    fn holds_back_repeated_requests() {
+        let none = Relationship::default();
+        let pending = request(1, request_status::PENDING, None);
+        assert!(plan_request(1, 2, &none, Some(&pending), None, Utc::now()).is_err());
+
+        let declined = request(1, request_status::DECLINED, Some(1));
+        assert!(plan_request(1, 2, &none, Some(&declined), None, Utc::now()).is_err());
+
+        let declined_long_ago = request(1, request_status::DECLINED, Some(REQUEST_RESEND_COOLDOWN_DAYS + 1));
+        assert!(plan_request(1, 2, &none, Some(&declined_long_ago), None, Utc::now()).is_ok());
+
+        let canceled = request(1, request_status::CANCELED, Some(0));
+        assert!(plan_request(1, 2, &none, Some(&canceled), None, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn parses_direction() {
+        assert_eq!(RequestDirection::parse(None).unwrap(), RequestDirection::Incoming);
+        assert_eq!(RequestDirection::parse(Some("outgoing")).unwrap(), RequestDirection::Outgoing);
+        assert!(RequestDirection::parse(Some("both")).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+// Identity verification (KYC): tiers, applications and document storage
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Largest document upload accepted
+pub const DOCUMENT_UPLOAD_LIMIT: usize = 10 * 1024 * 1024;
+
+const NONCE_LEN: usize = 12;
+
+/// Verification level, the gateway sets payment limits by it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KycTier {
+    /// Nothing verified yet
+    Unverified = 0,
+    /// Verified email and a phone number on the profile
+    Basic = 1,
+    /// Identity documents approved by a reviewer
+    Verified = 2,
+}
+
+impl KycTier {
+    pub fn for_user(email_verified: bool, has_phone: bool, identity_verified: bool) -> Self {
+        if identity_verified {
+            KycTier::Verified
+        } else if email_verified && has_phone {
+            KycTier::Basic
+        } else {
+            KycTier::Unverified
+        }
+    }
+
+    pub fn level(&self) -> u8 {
+        *self as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    IdFront,
+    IdBack,
+    Selfie,
+}
+
+impl DocumentKind {
+    pub const ALL: [DocumentKind; 3] = [DocumentKind::IdFront, DocumentKind::IdBack, DocumentKind::Selfie];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentKind::IdFront => "id_front",
+            DocumentKind::IdBack => "id_back",
+            DocumentKind::Selfie => "selfie",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|k| k.as_str() == kind)
+            .ok_or_else(|| anyhow!("Invalid document kind: use id_front, id_back or selfie"))
+    }
+}
+
+/// Status of an application: 'draft' while documents are uploaded, then
+/// 'pending' review, 'approved' or 'rejected'
+pub mod status {
+    pub const DRAFT: &str = "draft";
+    pub const PENDING: &str = "pending";
+    pub const APPROVED: &str = "approved";
+    pub const REJECTED: &str = "rejected";
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct KycApplication {
+    pub id: i64,
+    pub user_id: i32,
+    pub status: String,
+    /// Given to the user on rejection
+    pub reason: Option<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct KycDocument {
+    pub kind: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    #[serde(skip_serializing)]
+    pub file_path: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// What a user sees of their verification
+#[derive(Debug, Serialize)]
+pub struct KycStatus {
+    pub tier: KycTier,
+    pub level: u8,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub application: Option<KycApplication>,
+    pub documents: Vec<KycDocument>,
+}
+
+/// An application with its documents, for reviewers
+#[derive(Debug, Serialize)]
+pub struct KycReview {
+    #[serde(flatten)]
+    pub application: KycApplication,
+    pub user_name: String,
+    pub user_email: String,
+    pub documents: Vec<KycDocument>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectRequest {
+    pub reason: String,
+}
+
+/// Content type of an uploaded document: a photo or a scan as PDF
+pub fn document_content_type(upload: &[u8]) -> Result<&'static str> {
+    if upload.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Ok("image/png")
+    } else if upload.starts_with(b"\xff\xd8\xff") {
+        Ok("image/jpeg")
+    } else if upload.starts_with(b"%PDF-") {
+        Ok("application/pdf")
+    } else {
+        Err(anyhow!("Unsupported document: use PNG, JPEG or PDF"))
+    }
+}
+
+/// AES-256-GCM for documents at rest; a file is the nonce followed by the
+/// ciphertext
+#[derive(Clone)]
+pub struct DocumentCipher {
+    cipher: Aes256Gcm,
+}
+
+impl DocumentCipher {
+    /// `key` is 32 bytes, base64 encoded
+    pub fn from_base64(key: &str) -> Result<Self> {
+        let key = STANDARD.decode(key.trim()).map_err(|_| anyhow!("KYC encryption key is not valid base64"))?;
+        if key.len() != 32 {
+            return Err(anyhow!("KYC encryption key must be 32 bytes"));
+        }
+        Ok(Self { cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)) })
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher.encrypt(&nonce, plaintext).map_err(|_| anyhow!("Failed to encrypt document"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("Failed to decrypt document"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt document"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+
+    #[test]
+    fn test_tier_for_user() {
+        assert_eq!(KycTier::for_user(false, true, false), KycTier::Unverified);
+        assert_eq!(KycTier::for_user(true, false, false), KycTier::Unverified);
+        assert_eq!(KycTier::for_user(true, true, false), KycTier::Basic);
+        assert_eq!(KycTier::for_user(true, true, true).level(), 2);
+    }
+
+    #[test]
+    fn test_document_kind_and_type() {
+        assert_eq!(DocumentKind::parse("id_back").unwrap(), DocumentKind::IdBack);
+        assert!(DocumentKind::parse("passport").is_err());
+        assert_eq!(document_content_type(b"%PDF-1.7 ...").unwrap(), "application/pdf");
+        assert!(document_content_type(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_cipher_round_trip() {
+        let cipher = DocumentCipher::from_base64(KEY).unwrap();
+        let sealed = cipher.encrypt(b"scan of an ID card").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"scan of an ID card");
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), b"scan of an ID card");
+
+        // Same content, fresh nonce
+        assert_ne!(cipher.encrypt(b"scan of an ID card").unwrap(), sealed);
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt(&tampered).is_err());
+        assert!(DocumentCipher::from_base64("c2hvcnQ=").is_err());
+    }
+}
@@ -1 +1,5 @@
+pub mod avatar;
+pub mod contact;
+pub mod kyc;
+pub mod search;
 pub mod user;
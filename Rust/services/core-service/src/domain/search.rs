@@ -0,0 +1,349 @@
+// User search: query parsing, keyset cursors and what each caller may see
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const DEFAULT_LIMIT: u32 = 20;
+pub const MAX_LIMIT: u32 = 100;
+
+/// Fields anyone may ask for
+const PUBLIC_FIELDS: &[&str] = &["id", "name", "email", "avatar_url"];
+/// Fields only callers with `users:read` may ask for
+const ADMIN_FIELDS: &[&str] = &["email_verified", "phone", "locale", "timezone", "discoverable", "created_at"];
+
+/// Query string of `GET /users`
+#[derive(Debug, Default, Deserialize)]
+pub struct UserSearchParams {
+    pub q: Option<String>,
+    /// 'prefix' (default) or 'fulltext'
+    pub mode: Option<String>,
+    /// 'name' (default), 'created_at' or 'id', '-' in front for descending
+    pub sort: Option<String>,
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    /// Comma separated, all allowed fields by default
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchMode {
+    Prefix,
+    FullText,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Name,
+    CreatedAt,
+    Id,
+}
+
+impl SortKey {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::CreatedAt => "created_at",
+            SortKey::Id => "id",
+        }
+    }
+}
+
+/// Position after the last row of a page: its sort value and id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(rename = "s")]
+    pub sort: String,
+    #[serde(rename = "v")]
+    pub value: Option<String>,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| anyhow!("Invalid cursor"))
+    }
+
+    pub fn created_at(&self) -> Result<DateTime<Utc>> {
+        self.value
+            .as_deref()
+            .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+            .map(|v| v.with_timezone(&Utc))
+            .ok_or_else(|| anyhow!("Invalid cursor"))
+    }
+}
+
+/// A validated search, scoped to what the caller may see
+#[derive(Debug)]
+pub struct UserSearch {
+    pub query: Option<String>,
+    pub mode: SearchMode,
+    pub sort: SortKey,
+    pub descending: bool,
+    pub limit: u32,
+    pub cursor: Option<Cursor>,
+    pub fields: Vec<&'static str>,
+    /// Callers with `users:read` see every profile and full emails
+    pub full_access: bool,
+    pub caller_id: i32,
+}
+
+impl UserSearch {
+    pub fn parse(params: &UserSearchParams, caller_id: i32, full_access: bool) -> Result<Self> {
+        let query = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()).map(str::to_string);
+        let mode = match params.mode.as_deref() {
+            None | Some("prefix") => SearchMode::Prefix,
+            Some("fulltext") => SearchMode::FullText,
+            Some(_) => return Err(anyhow!("Invalid mode: use prefix or fulltext")),
+        };
+
+        let sort = params.sort.as_deref().unwrap_or("name");
+        let (descending, sort) = match sort.strip_prefix('-') {
+            Some(sort) => (true, sort),
+            None => (false, sort),
+        };
+        let sort = match sort {
+            "name" => SortKey::Name,
+            "created_at" => SortKey::CreatedAt,
+            "id" => SortKey::Id,
+            _ => return Err(anyhow!("Invalid sort: use name, created_at or id")),
+        };
+
+        // A cursor only makes sense for the order it was made for
+        let cursor = params.cursor.as_deref().map(Cursor::decode).transpose()?;
+        if let Some(cursor) = &cursor {
+            let expected = format!("{}{}", if descending { "-" } else { "" }, sort.as_str());
+            if cursor.sort != expected || (sort == SortKey::CreatedAt && cursor.created_at().is_err()) {
+                return Err(anyhow!("Invalid cursor: made for another sort"));
+            }
+        }
+
+        let allowed = |field: &str| PUBLIC_FIELDS.contains(&field) || (full_access && ADMIN_FIELDS.contains(&field));
+        let fields = match params.fields.as_deref() {
+            Some(fields) => {
+                let mut selected = vec!["id"];
+                for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+                    let field = PUBLIC_FIELDS.iter().chain(ADMIN_FIELDS)
+                        .find(|f| **f == field && allowed(f))
+                        .ok_or_else(|| anyhow!("Invalid field: {}", field))?;
+                    if !selected.contains(field) {
+                        selected.push(field);
+                    }
+                }
+                selected
+            }
+            None => PUBLIC_FIELDS.iter().chain(ADMIN_FIELDS).copied().filter(|f| allowed(f)).collect(),
+        };
+
+        Ok(Self {
+            query,
+            mode,
+            sort,
+            descending,
+            limit: params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+            cursor,
+            fields,
+            full_access,
+            caller_id,
+        })
+    }
+
+    /// Cursor following `row`, the last of a full page
+    pub fn cursor_after(&self, row: &UserSearchRow) -> Cursor {
+        let value = match self.sort {
+            SortKey::Name => Some(row.name.clone()),
+            SortKey::CreatedAt => Some(row.created_at.to_rfc3339()),
+            SortKey::Id => None,
+        };
+        Cursor {
+            sort: format!("{}{}", if self.descending { "-" } else { "" }, self.sort.as_str()),
+            value,
+            id: row.id,
+        }
+    }
+
+    /// The row as the caller may see it, limited to the selected fields
+    pub fn project(&self, row: &UserSearchRow) -> serde_json::Value {
+        let email = if self.full_access || row.id == self.caller_id {
+            row.email.clone()
+        } else {
+            mask_email(&row.email)
+        };
+        let avatar_url = row.avatar_updated_at
+            .map(|at| format!("/api/v1/users/{}/avatar?v={}", row.id, at.timestamp()));
+
+        let mut object = serde_json::Map::new();
+        for field in &self.fields {
+            let value = match *field {
+                "id" => serde_json::json!(row.id),
+                "name" => serde_json::json!(row.name),
+                "email" => serde_json::json!(email),
+                "avatar_url" => serde_json::json!(avatar_url),
+                "email_verified" => serde_json::json!(row.email_verified),
+                "phone" => serde_json::json!(row.phone),
+                "locale" => serde_json::json!(row.locale),
+                "timezone" => serde_json::json!(row.timezone),
+                "discoverable" => serde_json::json!(row.discoverable),
+                "created_at" => serde_json::json!(row.created_at),
+                _ => continue,
+            };
+            object.insert(field.to_string(), value);
+        }
+        serde_json::Value::Object(object)
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UserSearchRow {
+    pub id: i32,
+    pub name: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub phone: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub discoverable: bool,
+    pub avatar_updated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One page of results
+#[derive(Debug, Serialize)]
+pub struct UserPage {
+    pub data: Vec<serde_json::Value>,
+    pub next_cursor: Option<String>,
+}
+
+/// 'an.nguyen@example.com' becomes 'a***@example.com'
+pub fn mask_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let first: String = local.chars().take(1).collect();
+            format!("{}***@{}", first, domain)
+        }
+        None => "***".to_string(),
+    }
+}
+
+/// LIKE pattern matching values starting with `query`
+pub fn like_prefix(query: &str) -> String {
+    let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("{}%", escaped)
+}
+
+/// Boolean mode AGAINST expression requiring every word, each as a prefix.
+/// Operators typed by the user are dropped.
+pub fn fulltext_terms(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("+{}*", word))
+        .collect();
+    if terms.is_empty() { None } else { Some(terms.join(" ")) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> UserSearchParams {
+        let get = |key: &str| pairs.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string());
+        UserSearchParams {
+            q: get("q"),
+            mode: get("mode"),
+            sort: get("sort"),
+            limit: get("limit").map(|l| l.parse().unwrap()),
+            cursor: get("cursor"),
+            fields: get("fields"),
+        }
+    }
+
+    fn row() -> UserSearchRow {
+        UserSearchRow {
+            id: 42,
+            name: "Nguyễn An".to_string(),
+            email: "an.nguyen@example.com".to_string(),
+            email_verified: true,
+            phone: Some("+84901234567".to_string()),
+            locale: None,
+            timezone: None,
+            discoverable: true,
+            avatar_updated_at: None,
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_defaults_and_limits() {
+        let search = UserSearch::parse(&params(&[("q", "  "), ("limit", "1000")]), 1, false).unwrap();
+        assert_eq!(search.query, None);
+        assert_eq!(search.mode, SearchMode::Prefix);
+        assert_eq!((search.sort, search.descending), (SortKey::Name, false));
+        assert_eq!(search.limit, MAX_LIMIT);
+        assert_eq!(search.fields, vec!["id", "name", "email", "avatar_url"]);
+
+        let search = UserSearch::parse(&params(&[("sort", "-created_at"), ("mode", "fulltext")]), 1, true).unwrap();
+        assert_eq!((search.sort, search.descending), (SortKey::CreatedAt, true));
+        assert_eq!(search.mode, SearchMode::FullText);
+        assert!(search.fields.contains(&"phone"));
+
+        assert!(UserSearch::parse(&params(&[("sort", "email")]), 1, true).is_err());
+        assert!(UserSearch::parse(&params(&[("mode", "regex")]), 1, true).is_err());
+    }
+
+    #[test]
+    fn test_fields_are_limited_by_access() {
+        let search = UserSearch::parse(&params(&[("fields", "name,name")]), 1, false).unwrap();
+        assert_eq!(search.fields, vec!["id", "name"]);
+        assert!(UserSearch::parse(&params(&[("fields", "name,phone")]), 1, false).is_err());
+        assert!(UserSearch::parse(&params(&[("fields", "name,phone")]), 1, true).is_ok());
+        assert!(UserSearch::parse(&params(&[("fields", "password")]), 1, true).is_err());
+    }
+
+    #[test]
+    fn test_cursor_round_trip_and_sort_check() {
+        let search = UserSearch::parse(&params(&[("sort", "-created_at")]), 1, false).unwrap();
+        let cursor = search.cursor_after(&row()).encode();
+
+        let next = UserSearch::parse(&params(&[("sort", "-created_at"), ("cursor", &cursor)]), 1, false).unwrap();
+        let decoded = next.cursor.unwrap();
+        assert_eq!(decoded.id, 42);
+        assert_eq!(decoded.created_at().unwrap(), row().created_at);
+
+        assert!(UserSearch::parse(&params(&[("sort", "created_at"), ("cursor", &cursor)]), 1, false).is_err());
+        assert!(UserSearch::parse(&params(&[("cursor", "not-a-cursor")]), 1, false).is_err());
+    }
+
+    #[test]
+    fn test_projection_masks_email_for_others() {
+        let search = UserSearch::parse(&params(&[]), 1, false).unwrap();
+        let value = search.project(&row());
+        assert_eq!(value["email"], "a***@example.com");
+        assert!(value.get("phone").is_none());
+
+        let own = UserSearch::parse(&params(&[]), 42, false).unwrap();
+        assert_eq!(own.project(&row())["email"], "an.nguyen@example.com");
+
+        let admin = UserSearch::parse(&params(&[("fields", "email,phone")]), 1, true).unwrap();
+        assert_eq!(admin.project(&row()), serde_json::json!({
+            "id": 42, "email": "an.nguyen@example.com", "phone": "+84901234567"
+        }));
+    }
+
+    #[test]
+    fn test_query_helpers() {
+        assert_eq!(like_prefix("50%_a\\"), "50\\%\\_a\\\\%");
+        assert_eq!(fulltext_terms("an +nguyen* example.com").as_deref(), Some("+an* +nguyen* +example* +com*"));
+        assert_eq!(fulltext_terms("+-*\"()"), None);
+        assert_eq!(mask_email("invalid"), "***");
+    }
+}
@@ -1,9 +1,184 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
-pub struct User {
+const NAME_MAX_CHARS: usize = 100;
+
+/// The user's own view of their account, from `GET /users/me`
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct UserProfile {
     pub id: i32,
     pub name: String,
     pub email: String,
+    pub email_verified: bool,
+    pub phone: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    /// Listed in other users' searches
+    pub discoverable: bool,
+    #[serde(skip_serializing)]
+    pub avatar_updated_at: Option<DateTime<Utc>>,
+    #[sqlx(skip)]
+    pub avatar_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserProfile {
+    /// Fill in `avatar_url`, versioned so clients drop a cached old picture
+    pub fn with_avatar_url(mut self) -> Self {
+        self.avatar_url = self.avatar_updated_at
+            .map(|at| format!("/api/v1/users/{}/avatar?v={}", self.id, at.timestamp()));
+        self
+    }
+
+    /// Apply the fields present in `update`; an empty string clears an
+    /// optional field. Returns whether the name changed.
+    pub fn apply(&mut self, update: &ProfileUpdate) -> Result<bool> {
+        let mut name_changed = false;
+        if let Some(name) = &update.name {
+            let name = normalize_name(name)?;
+            name_changed = name != self.name;
+            self.name = name;
+        }
+        if let Some(phone) = &update.phone {
+            self.phone = optional(phone).map(normalize_phone).transpose()?;
+        }
+        if let Some(locale) = &update.locale {
+            self.locale = optional(locale).map(normalize_locale).transpose()?;
+        }
+        if let Some(timezone) = &update.timezone {
+            self.timezone = optional(timezone).map(normalize_timezone).transpose()?;
+        }
+        if let Some(discoverable) = update.discoverable {
+            self.discoverable = discoverable;
+        }
+        Ok(name_changed)
+    }
+}
+
+/// Body of `PATCH /users/me`, absent fields are left as they are
+#[derive(Debug, Default, Deserialize)]
+pub struct ProfileUpdate {
+    pub name: Option<String>,
+    pub phone: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub discoverable: Option<bool>,
+}
+
+fn optional(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|v| !v.is_empty())
+}
+
+fn normalize_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > NAME_MAX_CHARS || name.chars().any(char::is_control) {
+        return Err(anyhow!("Invalid name: 1 to {} characters", NAME_MAX_CHARS));
+    }
+    Ok(name.to_string())
+}
+
+/// E.164: '+', country code and number, 8 to 15 digits. Spaces, dashes,
+/// dots and parentheses are dropped.
+fn normalize_phone(phone: &str) -> Result<String> {
+    let phone: String = phone.chars().filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')')).collect();
+    let digits = phone.strip_prefix('+').unwrap_or("");
+    if !(8..=15).contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_digit()) || digits.starts_with('0') {
+        return Err(anyhow!("Invalid phone number: use the international format, e.g. +84901234567"));
+    }
+    Ok(phone)
+}
+
+/// Language and optional region, e.g. 'vi' or 'vi-VN'
+fn normalize_locale(locale: &str) -> Result<String> {
+    let invalid = || anyhow!("Invalid locale: use a language and optional region, e.g. vi-VN");
+    let (language, region) = match locale.replace('_', "-").split_once('-') {
+        Some((language, region)) => (language.to_lowercase(), Some(region.to_uppercase())),
+        None => (locale.to_lowercase(), None),
+    };
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_lowercase()) {
+        return Err(invalid());
+    }
+    match region {
+        Some(region) if region.len() == 2 && region.chars().all(|c| c.is_ascii_uppercase()) => {
+            Ok(format!("{}-{}", language, region))
+        }
+        Some(_) => Err(invalid()),
+        None => Ok(language),
+    }
+}
+
+/// An IANA time zone name, e.g. 'Asia/Ho_Chi_Minh'
+fn normalize_timezone(timezone: &str) -> Result<String> {
+    timezone
+        .parse::<chrono_tz::Tz>()
+        .map(|tz| tz.name().to_string())
+        .map_err(|_| anyhow!("Invalid timezone: use an IANA name, e.g. Asia/Ho_Chi_Minh"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> UserProfile {
+        UserProfile {
+            id: 7,
+            name: "An".to_string(),
+            email: "an@example.com".to_string(),
+            email_verified: true,
+            phone: Some("+84901234567".to_string()),
+            locale: None,
+            timezone: None,
+            discoverable: true,
+            avatar_updated_at: None,
+            avatar_url: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_apply_normalizes_and_clears() {
+        let mut profile = profile();
+        let update = ProfileUpdate {
+            name: Some("  Nguyễn An ".to_string()),
+            phone: Some("".to_string()),
+            locale: Some("vi_vn".to_string()),
+            timezone: Some("Asia/Ho_Chi_Minh".to_string()),
+            discoverable: Some(false),
+        };
+        assert!(profile.apply(&update).unwrap());
+        assert_eq!(profile.name, "Nguyễn An");
+        assert_eq!(profile.phone, None);
+        assert_eq!(profile.locale.as_deref(), Some("vi-VN"));
+        assert_eq!(profile.timezone.as_deref(), Some("Asia/Ho_Chi_Minh"));
+        assert!(!profile.discoverable);
+
+        // Absent fields stay, the same name is no change
+        assert!(!profile.apply(&ProfileUpdate { name: Some("Nguyễn An".to_string()), ..Default::default() }).unwrap());
+        assert_eq!(profile.locale.as_deref(), Some("vi-VN"));
+    }
+
+    #[test]
+    fn test_invalid_fields_are_rejected() {
+        let invalid = [
+            ProfileUpdate { name: Some(" ".to_string()), ..Default::default() },
+            ProfileUpdate { phone: Some("0901234567".to_string()), ..Default::default() },
+            ProfileUpdate { phone: Some("+84 90x".to_string()), ..Default::default() },
+            ProfileUpdate { locale: Some("vietnamese".to_string()), ..Default::default() },
+            ProfileUpdate { timezone: Some("Mars/Olympus".to_string()), ..Default::default() },
+        ];
+        for update in invalid {
+            assert!(profile().apply(&update).is_err(), "{:?}", update);
+        }
+        assert_eq!(normalize_phone("+84 (90) 123-4567").unwrap(), "+84901234567");
+    }
+
+    #[test]
+    fn test_avatar_url_is_versioned() {
+        let mut profile = profile();
+        assert!(profile.clone().with_avatar_url().avatar_url.is_none());
+        profile.avatar_updated_at = DateTime::from_timestamp(1_700_000_000, 0);
+        assert_eq!(profile.with_avatar_url().avatar_url.as_deref(), Some("/api/v1/users/7/avatar?v=1700000000"));
+    }
 }
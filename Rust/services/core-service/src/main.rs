@@ -1,12 +1,23 @@
 mod api;
+mod clients;
+mod consumers;
 mod domain;
 mod repo;
 mod service;
 
 use actix_web::{web, App, HttpServer};
 use actix_cors::Cors;
+use apikeys::{ApiKeyStore, ApiKeyVerifier};
+use clients::AuthClient;
 use common::config::AppConfig;
+use contracts::events::PRIVACY_REPORT_MAX_BYTES;
+use messaging::kafka_producer::KafkaProducer;
+use domain::kyc::DocumentCipher;
+use repo::contact_repo::ContactRepository;
+use repo::kyc_repo::KycRepository;
 use repo::user_repo::UserRepository;
+use service::contact_service::ContactService;
+use service::kyc_service::KycService;
 use service::user_service::UserService;
 
 #[actix_web::main]
@@ -18,17 +29,64 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to create database pool");
     
+    // Profile changes are published as user events
+    let kafka_brokers = std::env::var("KAFKA_BROKERS")
+        .unwrap_or_else(|_| "localhost:9092".to_string());
+    let producer = KafkaProducer::new(&kafka_brokers)
+        .expect("Failed to create Kafka producer");
+
+    // KYC documents are encrypted with KYC_ENCRYPTION_KEY (32 bytes, base64)
+    let kyc_cipher = match std::env::var("KYC_ENCRYPTION_KEY") {
+        Ok(key) => Some(DocumentCipher::from_base64(&key).expect("Invalid KYC_ENCRYPTION_KEY")),
+        Err(_) => {
+            tracing::warn!("KYC_ENCRYPTION_KEY is not set, identity documents cannot be uploaded or read");
+            None
+        }
+    };
+    let kyc_service = KycService::new(KycRepository::new(pool.clone()), kyc_cipher);
+    let contact_service = ContactService::new(ContactRepository::new(pool.clone()));
+
     // Initialize repository and service
-    let user_repository = UserRepository::new(pool);
-    let user_service = UserService::new(user_repository);
+    let user_repository = UserRepository::new(pool.clone());
+    let user_service = UserService::new(
+        user_repository,
+        producer,
+        AuthClient::from_env(),
+        kyc_service.clone(),
+        contact_service.clone(),
+    );
+
+    // Keep the user projection current from auth-service events
+    let consumer_service = user_service.clone();
+    let consumer_brokers = kafka_brokers.clone();
+    tokio::spawn(async move {
+        if let Err(e) = consumers::user_events::start(&consumer_brokers, consumer_service).await {
+            tracing::error!("User events consumer stopped: {}", e);
+        }
+    });
+
+    // Data exports and erasures requested through auth-service
+    let report_producer = KafkaProducer::with_max_message_bytes(&kafka_brokers, PRIVACY_REPORT_MAX_BYTES)
+        .expect("Failed to create Kafka producer");
+    let privacy_service = user_service.clone();
+    tokio::spawn(async move {
+        if let Err(e) = consumers::privacy::start(&kafka_brokers, privacy_service, report_producer).await {
+            tracing::error!("Privacy requests consumer stopped: {}", e);
+        }
+    });
     
+    // API keys of backend services calling /internal routes
+    let api_key_verifier = ApiKeyVerifier::new(ApiKeyStore::new(pool));
+
     let server_address = config.server_address();
     tracing::info!("⚙️  Core Service starting on http://{}", server_address);
     
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(user_service.clone()))
-            .configure(api::routes::configure)
+            .app_data(web::Data::new(kyc_service.clone()))
+            .app_data(web::Data::new(contact_service.clone()))
+            .configure(|cfg| api::routes::configure(cfg, &api_key_verifier))
     })
     .bind(&server_address)?
     .run()
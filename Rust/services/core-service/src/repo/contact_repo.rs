@@ -0,0 +1,265 @@
+use anyhow::Result;
+use sqlx::MySqlPool;
+use crate::domain::contact::{request_status, BlockedUser, Contact, ContactRequest, RequestDirection, Relationship};
+
+const REQUEST_SELECT: &str = "SELECT r.id, r.requester_id, rp.name AS requester_name, r.addressee_id, ap.name AS addressee_name, \
+     r.status, r.created_at, r.responded_at \
+     FROM contact_requests r \
+     JOIN user_profiles rp ON rp.user_id = r.requester_id \
+     JOIN user_profiles ap ON ap.user_id = r.addressee_id";
+
+#[derive(Clone)]
+pub struct ContactRepository {
+    pool: MySqlPool,
+}
+
+impl ContactRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn user_exists(&self, user_id: i32) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM user_profiles WHERE user_id = ? AND deleted_at IS NULL)"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(exists)
+    }
+
+    pub async fn relationship(&self, user_id: i32, other_id: i32) -> Result<Relationship> {
+        let relationship = sqlx::query_as::<_, Relationship>(
+            "SELECT EXISTS(SELECT 1 FROM contacts WHERE user_id = ? AND contact_id = ?) AS contact, \
+             EXISTS(SELECT 1 FROM user_blocks WHERE blocker_id = ? AND blocked_id = ?) AS blocked, \
+             EXISTS(SELECT 1 FROM user_blocks WHERE blocker_id = ? AND blocked_id = ?) AS blocked_by"
+        )
+        .bind(user_id)
+        .bind(other_id)
+        .bind(user_id)
+        .bind(other_id)
+        .bind(other_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(relationship)
+    }
+
+    pub async fn find_request(&self, id: i64) -> Result<Option<ContactRequest>> {
+        let request = sqlx::query_as::<_, ContactRequest>(&format!("{} WHERE r.id = ?", REQUEST_SELECT))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(request)
+    }
+
+    /// The request from one user to another, whatever its status
+    pub async fn request_between(&self, requester_id: i32, addressee_id: i32) -> Result<Option<ContactRequest>> {
+        let request = sqlx::query_as::<_, ContactRequest>(&format!(
+            "{} WHERE r.requester_id = ? AND r.addressee_id = ?",
+            REQUEST_SELECT
+        ))
+        .bind(requester_id)
+        .bind(addressee_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(request)
+    }
+
+    /// Pending requests to or from the user, newest first
+    pub async fn pending_requests(&self, user_id: i32, direction: RequestDirection) -> Result<Vec<ContactRequest>> {
+        let column = match direction {
+            RequestDirection::Incoming => "r.addressee_id",
+            RequestDirection::Outgoing => "r.requester_id",
+        };
+        let requests = sqlx::query_as::<_, ContactRequest>(&format!(
+            "{} WHERE {} = ? AND r.status = ? AND rp.deleted_at IS NULL AND ap.deleted_at IS NULL ORDER BY r.created_at DESC",
+            REQUEST_SELECT, column
+        ))
+        .bind(user_id)
+        .bind(request_status::PENDING)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(requests)
+    }
+
+    /// Sending again after a decline or cancel reopens the same row
+    pub async fn send_request(&self, requester_id: i32, addressee_id: i32) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO contact_requests (requester_id, addressee_id, status) VALUES (?, ?, ?) \
+             ON DUPLICATE KEY UPDATE id = LAST_INSERT_ID(id), status = VALUES(status), \
+             created_at = CURRENT_TIMESTAMP, responded_at = NULL"
+        )
+        .bind(requester_id)
+        .bind(addressee_id)
+        .bind(request_status::PENDING)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_id() as i64)
+    }
+
+    /// Returns false unless the request was still pending
+    pub async fn accept_request(&self, request: &ContactRequest) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // A pending request the other way is settled along with it
+        let result = sqlx::query(
+            "UPDATE contact_requests SET status = ?, responded_at = NOW() \
+             WHERE status = ? AND (id = ? OR (requester_id = ? AND addressee_id = ?))"
+        )
+        .bind(request_status::ACCEPTED)
+        .bind(request_status::PENDING)
+        .bind(request.id)
+        .bind(request.addressee_id)
+        .bind(request.requester_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("INSERT IGNORE INTO contacts (user_id, contact_id) VALUES (?, ?), (?, ?)")
+            .bind(request.requester_id)
+            .bind(request.addressee_id)
+            .bind(request.addressee_id)
+            .bind(request.requester_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Decline or cancel; returns false unless the request was still pending
+    pub async fn close_request(&self, id: i64, status: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE contact_requests SET status = ?, responded_at = NOW() WHERE id = ? AND status = ?"
+        )
+        .bind(status)
+        .bind(id)
+        .bind(request_status::PENDING)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn contacts(&self, user_id: i32) -> Result<Vec<Contact>> {
+        let contacts = sqlx::query_as::<_, Contact>(
+            "SELECT c.contact_id AS user_id, p.name, c.created_at AS since \
+             FROM contacts c JOIN user_profiles p ON p.user_id = c.contact_id \
+             WHERE c.user_id = ? AND p.deleted_at IS NULL ORDER BY p.name, c.contact_id"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(contacts)
+    }
+
+    /// Returns false if they were not contacts
+    pub async fn remove_contact(&self, user_id: i32, contact_id: i32) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM contacts WHERE (user_id = ? AND contact_id = ?) OR (user_id = ? AND contact_id = ?)"
+        )
+        .bind(user_id)
+        .bind(contact_id)
+        .bind(contact_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Blocking also ends the contact and cancels pending requests either way
+    pub async fn block(&self, blocker_id: i32, blocked_id: i32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("INSERT IGNORE INTO user_blocks (blocker_id, blocked_id) VALUES (?, ?)")
+            .bind(blocker_id)
+            .bind(blocked_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "DELETE FROM contacts WHERE (user_id = ? AND contact_id = ?) OR (user_id = ? AND contact_id = ?)"
+        )
+        .bind(blocker_id)
+        .bind(blocked_id)
+        .bind(blocked_id)
+        .bind(blocker_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE contact_requests SET status = ?, responded_at = NOW() WHERE status = ? \
+             AND ((requester_id = ? AND addressee_id = ?) OR (requester_id = ? AND addressee_id = ?))"
+        )
+        .bind(request_status::CANCELED)
+        .bind(request_status::PENDING)
+        .bind(blocker_id)
+        .bind(blocked_id)
+        .bind(blocked_id)
+        .bind(blocker_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Returns false if the user was not blocked
+    pub async fn unblock(&self, blocker_id: i32, blocked_id: i32) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM user_blocks WHERE blocker_id = ? AND blocked_id = ?")
+            .bind(blocker_id)
+            .bind(blocked_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn blocks(&self, user_id: i32) -> Result<Vec<BlockedUser>> {
+        let blocks = sqlx::query_as::<_, BlockedUser>(
+            "SELECT b.blocked_id AS user_id, p.name, b.created_at AS blocked_at \
+             FROM user_blocks b JOIN user_profiles p ON p.user_id = b.blocked_id \
+             WHERE b.blocker_id = ? ORDER BY b.created_at DESC"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(blocks)
+    }
+
+    /// Ids of the users the user blocked, and of those who blocked the user
+    pub async fn block_lists(&self, user_id: i32) -> Result<(Vec<i32>, Vec<i32>)> {
+        let blocked = sqlx::query_scalar("SELECT blocked_id FROM user_blocks WHERE blocker_id = ?")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+        let blocked_by = sqlx::query_scalar("SELECT blocker_id FROM user_blocks WHERE blocked_id = ?")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok((blocked, blocked_by))
+    }
+
+    /// Drop the user's contacts, requests and blocks, both sides
+    pub async fn erase_user(&self, user_id: i32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM contacts WHERE user_id = ? OR contact_id = ?")
+            .bind(user_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM contact_requests WHERE requester_id = ? OR addressee_id = ?")
+            .bind(user_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM user_blocks WHERE blocker_id = ? OR blocked_id = ?")
+            .bind(user_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
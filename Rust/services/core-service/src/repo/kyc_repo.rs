@@ -0,0 +1,211 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, MySqlPool};
+use crate::domain::kyc::{status, KycApplication, KycDocument};
+
+/// A user's verification inputs, from their profile
+#[derive(Debug, FromRow)]
+pub struct KycFacts {
+    pub email_verified: bool,
+    pub has_phone: bool,
+    pub kyc_verified_at: Option<DateTime<Utc>>,
+}
+
+/// Row of the review queue
+#[derive(Debug, Serialize, FromRow)]
+pub struct KycQueueEntry {
+    pub id: i64,
+    pub user_id: i32,
+    pub user_name: String,
+    pub user_email: String,
+    pub status: String,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+const APPLICATION_COLUMNS: &str = "id, user_id, status, reason, submitted_at, reviewed_by, reviewed_at, created_at";
+
+#[derive(Clone)]
+pub struct KycRepository {
+    pool: MySqlPool,
+}
+
+impl KycRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn facts(&self, user_id: i32) -> Result<Option<KycFacts>> {
+        let facts = sqlx::query_as::<_, KycFacts>(
+            "SELECT email_verified, phone IS NOT NULL AS has_phone, kyc_verified_at \
+             FROM user_profiles WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(facts)
+    }
+
+    /// The user's most recent application
+    pub async fn latest_application(&self, user_id: i32) -> Result<Option<KycApplication>> {
+        let application = sqlx::query_as::<_, KycApplication>(&format!(
+            "SELECT {} FROM kyc_applications WHERE user_id = ? ORDER BY id DESC LIMIT 1",
+            APPLICATION_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(application)
+    }
+
+    pub async fn find_application(&self, id: i64) -> Result<Option<KycApplication>> {
+        let application = sqlx::query_as::<_, KycApplication>(&format!(
+            "SELECT {} FROM kyc_applications WHERE id = ?",
+            APPLICATION_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(application)
+    }
+
+    pub async fn create_application(&self, user_id: i32) -> Result<i64> {
+        let result = sqlx::query("INSERT INTO kyc_applications (user_id, status) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(status::DRAFT)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_id() as i64)
+    }
+
+    pub async fn documents(&self, application_id: i64) -> Result<Vec<KycDocument>> {
+        let documents = sqlx::query_as::<_, KycDocument>(
+            "SELECT kind, content_type, size_bytes, file_path, uploaded_at \
+             FROM kyc_documents WHERE application_id = ? ORDER BY kind"
+        )
+        .bind(application_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(documents)
+    }
+
+    /// A new upload of the same kind replaces the previous one
+    pub async fn save_document(
+        &self,
+        application_id: i64,
+        kind: &str,
+        content_type: &str,
+        size_bytes: i64,
+        file_path: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO kyc_documents (application_id, kind, content_type, size_bytes, file_path) VALUES (?, ?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE content_type = VALUES(content_type), size_bytes = VALUES(size_bytes), \
+             file_path = VALUES(file_path)"
+        )
+        .bind(application_id)
+        .bind(kind)
+        .bind(content_type)
+        .bind(size_bytes)
+        .bind(file_path)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns false unless the application was still a draft
+    pub async fn submit(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE kyc_applications SET status = ?, submitted_at = NOW() WHERE id = ? AND status = ?"
+        )
+        .bind(status::PENDING)
+        .bind(id)
+        .bind(status::DRAFT)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Applications in `status`, oldest submission first
+    pub async fn queue(&self, status: &str, limit: i64, offset: i64) -> Result<Vec<KycQueueEntry>> {
+        let entries = sqlx::query_as::<_, KycQueueEntry>(
+            "SELECT a.id, a.user_id, p.name AS user_name, p.email AS user_email, a.status, a.submitted_at, a.reviewed_at \
+             FROM kyc_applications a JOIN user_profiles p ON p.user_id = a.user_id \
+             WHERE a.status = ? ORDER BY a.submitted_at, a.id LIMIT ? OFFSET ?"
+        )
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(entries)
+    }
+
+    /// Name and email of the applicant
+    pub async fn applicant(&self, id: i64) -> Result<(String, String)> {
+        let applicant = sqlx::query_as::<_, (String, String)>(
+            "SELECT p.name, p.email FROM kyc_applications a JOIN user_profiles p ON p.user_id = a.user_id WHERE a.id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(applicant.unwrap_or_default())
+    }
+
+    /// Decide a pending application; an approval marks the user verified.
+    /// Returns false if the application was not pending anymore.
+    pub async fn review(&self, application: &KycApplication, approved: bool, reviewer_id: i32, reason: Option<&str>) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE kyc_applications SET status = ?, reason = ?, reviewed_by = ?, reviewed_at = NOW() \
+             WHERE id = ? AND status = ?"
+        )
+        .bind(if approved { status::APPROVED } else { status::REJECTED })
+        .bind(reason)
+        .bind(reviewer_id)
+        .bind(application.id)
+        .bind(status::PENDING)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        if approved {
+            sqlx::query("UPDATE user_profiles SET kyc_verified_at = NOW() WHERE user_id = ?")
+                .bind(application.user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Applications for a data export, without the documents themselves
+    pub async fn export(&self, user_id: i32) -> Result<serde_json::Value> {
+        let json: Option<String> = sqlx::query_scalar(
+            "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('status', status, 'reason', reason, \
+             'submitted_at', submitted_at, 'reviewed_at', reviewed_at, 'created_at', created_at)), JSON_ARRAY()) \
+             FROM kyc_applications WHERE user_id = ?"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .flatten();
+
+        Ok(match json {
+            Some(json) => serde_json::from_str(&json)?,
+            None => serde_json::Value::Null,
+        })
+    }
+
+    /// Drop the user's applications and documents
+    pub async fn erase_user(&self, user_id: i32) -> Result<()> {
+        sqlx::query("DELETE FROM kyc_applications WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
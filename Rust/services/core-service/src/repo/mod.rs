@@ -1 +1,3 @@
+pub mod contact_repo;
+pub mod kyc_repo;
 pub mod user_repo;
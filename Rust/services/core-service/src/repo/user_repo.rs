@@ -1,7 +1,11 @@
 use anyhow::Result;
-use sqlx::MySqlPool;
-use crate::domain::user::User;
+use chrono::{DateTime, Utc};
+use sqlx::{MySql, MySqlPool, QueryBuilder};
+use crate::domain::search::{fulltext_terms, like_prefix, SearchMode, SortKey, UserSearch, UserSearchRow};
+use crate::domain::user::UserProfile;
 
+/// Users as projected from the user-events topic. Each write is skipped
+/// when the row already holds a newer event, so redeliveries are harmless.
 #[derive(Clone)]
 pub struct UserRepository {
     pool: MySqlPool,
@@ -12,18 +16,202 @@ impl UserRepository {
         Self { pool }
     }
 
-    pub async fn find_all(&self) -> Result<Vec<User>> {
-        let users = sqlx::query_as::<_, User>("SELECT id, name, email FROM users")
-            .fetch_all(&self.pool)
-            .await?;
-        Ok(users)
+    /// One page of `search` plus one row, to tell whether another page follows
+    pub async fn search(&self, search: &UserSearch) -> Result<Vec<UserSearchRow>> {
+        let mut query = QueryBuilder::<MySql>::new(
+            "SELECT user_id AS id, name, email, email_verified, phone, locale, timezone, discoverable, \
+             avatar_updated_at, created_at FROM user_profiles WHERE deleted_at IS NULL"
+        );
+
+        // Other users only see discoverable profiles, and find an email by its full address
+        if !search.full_access {
+            query.push(" AND (discoverable = TRUE OR user_id = ").push_bind(search.caller_id).push(")");
+        }
+
+        if let Some(q) = &search.query {
+            match search.mode {
+                SearchMode::Prefix => {
+                    let pattern = like_prefix(q);
+                    query.push(" AND (name LIKE ").push_bind(pattern.clone())
+                        .push(" OR name LIKE ").push_bind(format!("% {}", pattern));
+                    if search.full_access {
+                        query.push(" OR email LIKE ").push_bind(pattern);
+                    } else {
+                        query.push(" OR email = ").push_bind(q.clone());
+                    }
+                    query.push(")");
+                }
+                SearchMode::FullText => {
+                    // Nothing left to search for once operators are dropped
+                    let Some(terms) = fulltext_terms(q) else { return Ok(Vec::new()) };
+                    let columns = if search.full_access { "name, email" } else { "name" };
+                    query.push(format!(" AND MATCH({}) AGAINST (", columns))
+                        .push_bind(terms)
+                        .push(" IN BOOLEAN MODE)");
+                }
+            }
+        }
+
+        // Keyset pagination, user_id breaking ties
+        let (column, cmp, direction) = match (search.sort, search.descending) {
+            (SortKey::Name, false) => ("name", ">", "ASC"),
+            (SortKey::Name, true) => ("name", "<", "DESC"),
+            (SortKey::CreatedAt, false) => ("created_at", ">", "ASC"),
+            (SortKey::CreatedAt, true) => ("created_at", "<", "DESC"),
+            (SortKey::Id, false) => ("user_id", ">", "ASC"),
+            (SortKey::Id, true) => ("user_id", "<", "DESC"),
+        };
+        if let Some(cursor) = &search.cursor {
+            match search.sort {
+                SortKey::Id => {
+                    query.push(format!(" AND user_id {} ", cmp)).push_bind(cursor.id);
+                }
+                SortKey::Name | SortKey::CreatedAt => {
+                    query.push(format!(" AND ({} {} ", column, cmp));
+                    if search.sort == SortKey::Name {
+                        let name = cursor.value.clone().unwrap_or_default();
+                        query.push_bind(name.clone())
+                            .push(format!(" OR ({} = ", column)).push_bind(name);
+                    } else {
+                        let created_at = cursor.created_at()?;
+                        query.push_bind(created_at)
+                            .push(format!(" OR ({} = ", column)).push_bind(created_at);
+                    }
+                    query.push(format!(" AND user_id {} ", cmp)).push_bind(cursor.id).push("))");
+                }
+            }
+        }
+
+        query.push(format!(" ORDER BY {} {}", column, direction));
+        if search.sort != SortKey::Id {
+            query.push(format!(", user_id {}", direction));
+        }
+        query.push(" LIMIT ").push_bind(search.limit + 1);
+
+        let rows = query.build_query_as::<UserSearchRow>().fetch_all(&self.pool).await?;
+        Ok(rows)
+    }
+
+    pub async fn find_row(&self, id: i32) -> Result<Option<UserSearchRow>> {
+        let row = sqlx::query_as::<_, UserSearchRow>(
+            "SELECT user_id AS id, name, email, email_verified, phone, locale, timezone, discoverable, \
+             avatar_updated_at, created_at FROM user_profiles WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row)
+    }
+
+    pub async fn find_profile(&self, id: i32) -> Result<Option<UserProfile>> {
+        let profile = sqlx::query_as::<_, UserProfile>(
+            "SELECT user_id AS id, name, email, email_verified, phone, locale, timezone, discoverable, \
+             avatar_updated_at, created_at FROM user_profiles WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(profile)
+    }
+
+    /// Written by core-service itself, so event_at is left alone
+    pub async fn update_profile(&self, profile: &UserProfile) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_profiles SET name = ?, phone = ?, locale = ?, timezone = ?, discoverable = ? \
+             WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(&profile.name)
+        .bind(&profile.phone)
+        .bind(&profile.locale)
+        .bind(&profile.timezone)
+        .bind(profile.discoverable)
+        .bind(profile.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn set_avatar_updated_at(&self, id: i32, updated_at: Option<DateTime<Utc>>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_profiles SET avatar_updated_at = ? WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(updated_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Everything kept on the user, for a data export
+    pub async fn export(&self, id: i32) -> Result<Option<serde_json::Value>> {
+        let json: Option<String> = sqlx::query_scalar(
+            "SELECT JSON_OBJECT('user_id', user_id, 'name', name, 'email', email, 'email_verified', email_verified IS TRUE, \
+             'phone', phone, 'locale', locale, 'timezone', timezone, 'discoverable', discoverable IS TRUE, 'locked_until', locked_until, 'created_at', created_at, 'updated_at', updated_at) \
+             FROM user_profiles WHERE user_id = ? AND deleted_at IS NULL"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    /// Returns false if the user was already there
+    pub async fn insert(&self, id: i32, name: &str, email: &str, email_verified: bool, event_at: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT IGNORE INTO user_profiles (user_id, name, email, email_verified, event_at) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(id)
+        .bind(name)
+        .bind(email)
+        .bind(email_verified)
+        .bind(event_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn mark_email_verified(&self, id: i32, email: Option<&str>, event_at: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_profiles SET email_verified = TRUE, email = COALESCE(?, email), event_at = ? \
+             WHERE user_id = ? AND event_at <= ? AND deleted_at IS NULL"
+        )
+        .bind(email)
+        .bind(event_at)
+        .bind(id)
+        .bind(event_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn set_locked_until(&self, id: i32, locked_until: DateTime<Utc>, event_at: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE user_profiles SET locked_until = ?, event_at = ? \
+             WHERE user_id = ? AND event_at <= ? AND deleted_at IS NULL"
+        )
+        .bind(locked_until)
+        .bind(event_at)
+        .bind(id)
+        .bind(event_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<User> {
-        let user = sqlx::query_as::<_, User>("SELECT id, name, email FROM users WHERE id = ?")
-            .bind(id)
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(user)
+    /// Keep a tombstone without the personal data
+    pub async fn mark_deleted(&self, id: i32, event_at: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO user_profiles (user_id, name, email, deleted_at, event_at) VALUES (?, '', '', ?, ?) \
+             ON DUPLICATE KEY UPDATE name = '', email = '', phone = NULL, locale = NULL, timezone = NULL, \
+             avatar_updated_at = NULL, kyc_verified_at = NULL, locked_until = NULL, \
+             deleted_at = VALUES(deleted_at), event_at = VALUES(event_at)"
+        )
+        .bind(id)
+        .bind(event_at)
+        .bind(event_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
     }
 }
@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use crate::domain::contact::{
+    plan_request, request_status, BlockedUser, Contact, ContactRequest, Relationship, RequestDirection, RequestOutcome,
+};
+use crate::repo::contact_repo::ContactRepository;
+
+#[derive(Clone)]
+pub struct ContactService {
+    repository: ContactRepository,
+}
+
+impl ContactService {
+    pub fn new(repository: ContactRepository) -> Self {
+        Self { repository }
+    }
+
+    pub async fn contacts(&self, user_id: i32) -> Result<Vec<Contact>> {
+        self.repository.contacts(user_id).await
+    }
+
+    pub async fn remove_contact(&self, user_id: i32, contact_id: i32) -> Result<()> {
+        if !self.repository.remove_contact(user_id, contact_id).await? {
+            return Err(anyhow!("Contact not found"));
+        }
+        Ok(())
+    }
+
+    pub async fn requests(&self, user_id: i32, direction: RequestDirection) -> Result<Vec<ContactRequest>> {
+        self.repository.pending_requests(user_id, direction).await
+    }
+
+    /// Asking someone who already asked the sender makes them contacts
+    pub async fn send_request(&self, from: i32, to: i32) -> Result<ContactRequest> {
+        if from != to && !self.repository.user_exists(to).await? {
+            return Err(anyhow!("User not found"));
+        }
+
+        let relationship = self.repository.relationship(from, to).await?;
+        let previous = self.repository.request_between(from, to).await?;
+        let theirs = self.repository.request_between(to, from).await?
+            .filter(|r| r.status == request_status::PENDING);
+
+        let id = match plan_request(from, to, &relationship, previous.as_ref(), theirs.as_ref(), Utc::now())? {
+            RequestOutcome::Send => self.repository.send_request(from, to).await?,
+            RequestOutcome::AcceptTheirs(id) => {
+                let theirs = theirs.ok_or_else(|| anyhow!("Request not found"))?;
+                self.repository.accept_request(&theirs).await?;
+                id
+            }
+        };
+        self.find_request(id).await
+    }
+
+    /// Only the addressee accepts or declines
+    pub async fn accept(&self, user_id: i32, request_id: i64) -> Result<ContactRequest> {
+        // A block cancels pending requests, so a pending one is between non-blocked users
+        let request = self.pending_request(request_id, |r| r.addressee_id == user_id).await?;
+        if !self.repository.accept_request(&request).await? {
+            return Err(anyhow!("Request is no longer pending"));
+        }
+        self.find_request(request_id).await
+    }
+
+    pub async fn decline(&self, user_id: i32, request_id: i64) -> Result<ContactRequest> {
+        self.pending_request(request_id, |r| r.addressee_id == user_id).await?;
+        if !self.repository.close_request(request_id, request_status::DECLINED).await? {
+            return Err(anyhow!("Request is no longer pending"));
+        }
+        self.find_request(request_id).await
+    }
+
+    /// Only the sender cancels
+    pub async fn cancel(&self, user_id: i32, request_id: i64) -> Result<()> {
+        self.pending_request(request_id, |r| r.requester_id == user_id).await?;
+        if !self.repository.close_request(request_id, request_status::CANCELED).await? {
+            return Err(anyhow!("Request is no longer pending"));
+        }
+        Ok(())
+    }
+
+    pub async fn blocks(&self, user_id: i32) -> Result<Vec<BlockedUser>> {
+        self.repository.blocks(user_id).await
+    }
+
+    pub async fn block(&self, user_id: i32, other_id: i32) -> Result<()> {
+        if user_id == other_id {
+            return Err(anyhow!("Invalid block: you cannot block yourself"));
+        }
+        if !self.repository.user_exists(other_id).await? {
+            return Err(anyhow!("User not found"));
+        }
+        self.repository.block(user_id, other_id).await
+    }
+
+    pub async fn unblock(&self, user_id: i32, other_id: i32) -> Result<()> {
+        if !self.repository.unblock(user_id, other_id).await? {
+            return Err(anyhow!("Block not found"));
+        }
+        Ok(())
+    }
+
+    pub async fn relationship(&self, user_id: i32, other_id: i32) -> Result<Relationship> {
+        self.repository.relationship(user_id, other_id).await
+    }
+
+    /// Users the user blocked, and users who blocked the user
+    pub async fn block_lists(&self, user_id: i32) -> Result<(Vec<i32>, Vec<i32>)> {
+        self.repository.block_lists(user_id).await
+    }
+
+    pub async fn export_user(&self, user_id: i32) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({
+            "contacts": self.repository.contacts(user_id).await?,
+            "blocked_users": self.repository.blocks(user_id).await?,
+            "incoming_requests": self.repository.pending_requests(user_id, RequestDirection::Incoming).await?,
+            "outgoing_requests": self.repository.pending_requests(user_id, RequestDirection::Outgoing).await?,
+        }))
+    }
+
+    pub async fn erase_user(&self, user_id: i32) -> Result<()> {
+        self.repository.erase_user(user_id).await
+    }
+
+    async fn find_request(&self, id: i64) -> Result<ContactRequest> {
+        self.repository.find_request(id).await?
+            .ok_or_else(|| anyhow!("Request not found"))
+    }
+
+    /// Requests of other users read as not found
+    async fn pending_request(&self, id: i64, party: impl Fn(&ContactRequest) -> bool) -> Result<ContactRequest> {
+        let request = self.repository.find_request(id).await?
+            .filter(party)
+            .ok_or_else(|| anyhow!("Request not found"))?;
+        if request.status != request_status::PENDING {
+            return Err(anyhow!("Request is no longer pending"));
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use crate::domain::kyc::{
+    document_content_type, status, DocumentCipher, DocumentKind, KycApplication, KycReview, KycStatus, KycTier,
+};
+use crate::repo::kyc_repo::{KycQueueEntry, KycRepository};
+
+const REASON_MAX_CHARS: usize = 500;
+
+#[derive(Clone)]
+pub struct KycService {
+    repository: KycRepository,
+    cipher: Option<DocumentCipher>,
+    storage_dir: PathBuf,
+}
+
+impl KycService {
+    /// Documents are kept under KYC_STORAGE_DIR (default ./kyc-documents),
+    /// one directory per user. Without a cipher, uploads and reads fail.
+    pub fn new(repository: KycRepository, cipher: Option<DocumentCipher>) -> Self {
+        let storage_dir = std::env::var("KYC_STORAGE_DIR").unwrap_or_else(|_| "./kyc-documents".to_string());
+        Self { repository, cipher, storage_dir: PathBuf::from(storage_dir) }
+    }
+
+    pub async fn tier(&self, user_id: i32) -> Result<(KycTier, Option<DateTime<Utc>>)> {
+        let facts = self.repository.facts(user_id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        let tier = KycTier::for_user(facts.email_verified, facts.has_phone, facts.kyc_verified_at.is_some());
+        Ok((tier, facts.kyc_verified_at))
+    }
+
+    pub async fn status(&self, user_id: i32) -> Result<KycStatus> {
+        let (tier, verified_at) = self.tier(user_id).await?;
+        let application = self.repository.latest_application(user_id).await?;
+        let documents = match &application {
+            Some(application) => self.repository.documents(application.id).await?,
+            None => Vec::new(),
+        };
+        Ok(KycStatus { tier, level: tier.level(), verified_at, application, documents })
+    }
+
+    /// Add a document to the user's draft application, starting a new one
+    /// after a rejection
+    pub async fn upload_document(&self, user_id: i32, kind: DocumentKind, upload: Vec<u8>) -> Result<KycStatus> {
+        let cipher = self.cipher()?.clone();
+        let content_type = document_content_type(&upload)?;
+
+        let application_id = match self.repository.latest_application(user_id).await? {
+            Some(a) if a.status == status::DRAFT => a.id,
+            Some(a) if a.status == status::PENDING => return Err(anyhow!("Application is under review")),
+            Some(a) if a.status == status::APPROVED => return Err(anyhow!("Identity is already verified")),
+            _ => {
+                // Unknown and deleted users fail here
+                self.tier(user_id).await?;
+                self.repository.create_application(user_id).await?
+            }
+        };
+
+        let size = upload.len() as i64;
+        let sealed = tokio::task::spawn_blocking(move || cipher.encrypt(&upload)).await??;
+
+        // Written aside and renamed, a reviewer never reads half a file
+        let dir = self.user_dir(user_id);
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = dir.join(format!("{}-{}.bin", application_id, kind.as_str()));
+        let part = path.with_extension("bin.part");
+        tokio::fs::write(&part, &sealed).await?;
+        tokio::fs::rename(&part, &path).await?;
+
+        self.repository
+            .save_document(application_id, kind.as_str(), content_type, size, &path.to_string_lossy())
+            .await?;
+        self.status(user_id).await
+    }
+
+    /// Send the draft for review once every document is in
+    pub async fn submit(&self, user_id: i32) -> Result<KycStatus> {
+        let application = self.repository.latest_application(user_id).await?
+            .filter(|a| a.status == status::DRAFT)
+            .ok_or_else(|| anyhow!("No application to submit, upload your documents first"))?;
+
+        let uploaded = self.repository.documents(application.id).await?;
+        let missing: Vec<&str> = DocumentKind::ALL
+            .iter()
+            .map(|k| k.as_str())
+            .filter(|k| !uploaded.iter().any(|d| d.kind == *k))
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow!("Missing documents: {}", missing.join(", ")));
+        }
+
+        let (tier, _) = self.tier(user_id).await?;
+        if tier < KycTier::Basic {
+            return Err(anyhow!("Verify your email and add a phone number before submitting"));
+        }
+
+        if !self.repository.submit(application.id).await? {
+            return Err(anyhow!("Application is under review"));
+        }
+        tracing::info!("KYC application {} of user {} submitted", application.id, user_id);
+        self.status(user_id).await
+    }
+
+    pub async fn queue(&self, status: &str, limit: i64, offset: i64) -> Result<Vec<KycQueueEntry>> {
+        if ![status::PENDING, status::APPROVED, status::REJECTED].contains(&status) {
+            return Err(anyhow!("Invalid status: use pending, approved or rejected"));
+        }
+        self.repository.queue(status, limit, offset).await
+    }
+
+    pub async fn review(&self, application_id: i64) -> Result<KycReview> {
+        let application = self.application(application_id).await?;
+        let documents = self.repository.documents(application.id).await?;
+        let (user_name, user_email) = self.repository.applicant(application.id).await?;
+        Ok(KycReview { application, user_name, user_email, documents })
+    }
+
+    /// A document decrypted, with its content type
+    pub async fn document(&self, application_id: i64, kind: DocumentKind) -> Result<(String, Vec<u8>)> {
+        let cipher = self.cipher()?.clone();
+        let application = self.application(application_id).await?;
+        let document = self.repository.documents(application.id).await?
+            .into_iter()
+            .find(|d| d.kind == kind.as_str())
+            .ok_or_else(|| anyhow!("Document not found"))?;
+
+        let sealed = tokio::fs::read(&document.file_path).await
+            .map_err(|e| anyhow!("Document not found: {}", e))?;
+        let plaintext = tokio::task::spawn_blocking(move || cipher.decrypt(&sealed)).await??;
+        Ok((document.content_type, plaintext))
+    }
+
+    pub async fn approve(&self, application_id: i64, reviewer_id: i32) -> Result<KycApplication> {
+        self.decide(application_id, true, reviewer_id, None).await
+    }
+
+    pub async fn reject(&self, application_id: i64, reviewer_id: i32, reason: &str) -> Result<KycApplication> {
+        let reason = reason.trim();
+        if reason.is_empty() || reason.chars().count() > REASON_MAX_CHARS {
+            return Err(anyhow!("Invalid reason: 1 to {} characters", REASON_MAX_CHARS));
+        }
+        self.decide(application_id, false, reviewer_id, Some(reason)).await
+    }
+
+    pub async fn export_user(&self, user_id: i32) -> Result<serde_json::Value> {
+        self.repository.export(user_id).await
+    }
+
+    /// Applications and document files of an erased or deleted user
+    pub async fn erase_user(&self, user_id: i32) -> Result<()> {
+        self.repository.erase_user(user_id).await?;
+        match tokio::fs::remove_dir_all(self.user_dir(user_id)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn decide(&self, application_id: i64, approved: bool, reviewer_id: i32, reason: Option<&str>) -> Result<KycApplication> {
+        let application = self.application(application_id).await?;
+        if application.user_id == reviewer_id {
+            return Err(anyhow!("Reviewers cannot decide their own application"));
+        }
+        if !self.repository.review(&application, approved, reviewer_id, reason).await? {
+            return Err(anyhow!("Only a pending application can be reviewed"));
+        }
+        tracing::info!(
+            "KYC application {} of user {} {} by user {}",
+            application.id, application.user_id, if approved { "approved" } else { "rejected" }, reviewer_id
+        );
+        self.application(application_id).await
+    }
+
+    async fn application(&self, application_id: i64) -> Result<KycApplication> {
+        self.repository.find_application(application_id).await?
+            .ok_or_else(|| anyhow!("Application not found"))
+    }
+
+    fn cipher(&self) -> Result<&DocumentCipher> {
+        self.cipher.as_ref().ok_or_else(|| anyhow!("KYC document storage is not configured"))
+    }
+
+    fn user_dir(&self, user_id: i32) -> PathBuf {
+        self.storage_dir.join(user_id.to_string())
+    }
+}
@@ -1 +1,3 @@
+pub mod contact_service;
+pub mod kyc_service;
 pub mod user_service;
@@ -1,22 +1,200 @@
-use anyhow::Result;
-use crate::domain::user::User;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use contracts::events::{UserEvent, UserEventMessage, UserUpdatedEvent, USER_EVENTS_TOPIC};
+use messaging::kafka_producer::KafkaProducer;
+use std::path::PathBuf;
+use crate::clients::AuthClient;
+use crate::domain::avatar::resize_avatar;
+use crate::domain::search::{UserPage, UserSearch};
+use crate::domain::user::{ProfileUpdate, UserProfile};
 use crate::repo::user_repo::UserRepository;
+use crate::service::contact_service::ContactService;
+use crate::service::kyc_service::KycService;
 
 #[derive(Clone)]
 pub struct UserService {
     repository: UserRepository,
+    producer: KafkaProducer,
+    auth_client: AuthClient,
+    kyc_service: KycService,
+    contact_service: ContactService,
+    avatar_dir: PathBuf,
 }
 
 impl UserService {
-    pub fn new(repository: UserRepository) -> Self {
-        Self { repository }
+    /// Avatars are kept under AVATAR_STORAGE_DIR (default ./avatars)
+    pub fn new(
+        repository: UserRepository,
+        producer: KafkaProducer,
+        auth_client: AuthClient,
+        kyc_service: KycService,
+        contact_service: ContactService,
+    ) -> Self {
+        let avatar_dir = std::env::var("AVATAR_STORAGE_DIR").unwrap_or_else(|_| "./avatars".to_string());
+        Self { repository, producer, auth_client, kyc_service, contact_service, avatar_dir: PathBuf::from(avatar_dir) }
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<User>> {
-        Ok(self.repository.find_all().await?)
+    pub async fn search_users(&self, search: &UserSearch) -> Result<UserPage> {
+        let mut rows = self.repository.search(search).await?;
+        let next_cursor = if rows.len() > search.limit as usize {
+            rows.truncate(search.limit as usize);
+            rows.last().map(|row| search.cursor_after(row).encode())
+        } else {
+            None
+        };
+        Ok(UserPage {
+            data: rows.iter().map(|row| search.project(row)).collect(),
+            next_cursor,
+        })
     }
 
-    pub async fn get_user_by_id(&self, id: i32) -> Result<User> {
-        Ok(self.repository.find_by_id(id).await?)
+    /// A single user, as `search` would show it
+    pub async fn get_user(&self, id: i32, search: &UserSearch) -> Result<serde_json::Value> {
+        match self.repository.find_row(id).await? {
+            Some(row) if search.full_access || row.discoverable || row.id == search.caller_id => Ok(search.project(&row)),
+            _ => Err(anyhow!("User not found")),
+        }
+    }
+
+    pub async fn get_profile(&self, id: i32) -> Result<UserProfile> {
+        let profile = self.repository.find_profile(id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        Ok(profile.with_avatar_url())
+    }
+
+    /// A name change is published as user.updated for the other services
+    pub async fn update_profile(&self, id: i32, update: &ProfileUpdate) -> Result<UserProfile> {
+        let mut profile = self.repository.find_profile(id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        let name_changed = profile.apply(update)?;
+        if !self.repository.update_profile(&profile).await? {
+            return Err(anyhow!("User not found"));
+        }
+        if name_changed {
+            self.publish_update(id, &profile.name).await;
+        }
+        Ok(profile.with_avatar_url())
+    }
+
+    /// Replace the user's avatar with `upload`, resized to a square PNG
+    pub async fn upload_avatar(&self, id: i32, upload: Vec<u8>) -> Result<UserProfile> {
+        let avatar = tokio::task::spawn_blocking(move || resize_avatar(&upload)).await??;
+
+        // Written aside and renamed, readers never see half a file
+        tokio::fs::create_dir_all(&self.avatar_dir).await?;
+        let path = self.avatar_path(id);
+        let part = path.with_extension("png.part");
+        tokio::fs::write(&part, &avatar).await?;
+        tokio::fs::rename(&part, &path).await?;
+
+        if !self.repository.set_avatar_updated_at(id, Some(Utc::now())).await? {
+            self.remove_avatar_file(id).await;
+            return Err(anyhow!("User not found"));
+        }
+        self.get_profile(id).await
+    }
+
+    pub async fn delete_avatar(&self, id: i32) -> Result<()> {
+        if !self.repository.set_avatar_updated_at(id, None).await? {
+            return Err(anyhow!("User not found"));
+        }
+        self.remove_avatar_file(id).await;
+        Ok(())
+    }
+
+    /// The PNG of a user who has an avatar
+    pub async fn avatar(&self, id: i32) -> Result<Vec<u8>> {
+        let profile = self.repository.find_profile(id).await?
+            .ok_or_else(|| anyhow!("User not found"))?;
+        if profile.avatar_updated_at.is_none() {
+            return Err(anyhow!("Avatar not found"));
+        }
+        tokio::fs::read(self.avatar_path(id)).await
+            .map_err(|e| anyhow!("Avatar not found: {}", e))
+    }
+
+    /// auth-service owns the address: it mails a link to the new one and
+    /// sends user.email_verified once the link is followed
+    pub async fn request_email_change(&self, access_token: &str, new_email: &str) -> Result<()> {
+        self.auth_client.request_email_change(access_token, new_email).await
+    }
+
+    pub async fn export_user(&self, id: i32) -> Result<serde_json::Value> {
+        let mut export = self.repository.export(id).await?.unwrap_or(serde_json::Value::Null);
+        if let Some(object) = export.as_object_mut() {
+            object.insert("kyc_applications".to_string(), self.kyc_service.export_user(id).await?);
+            object.insert("contacts".to_string(), self.contact_service.export_user(id).await?);
+        }
+        Ok(export)
+    }
+
+    /// Erasure ahead of the user.deleted event that ends it
+    pub async fn erase_user(&self, id: i32, requested_at: DateTime<Utc>) -> Result<()> {
+        self.repository.mark_deleted(id, requested_at).await?;
+        self.kyc_service.erase_user(id).await?;
+        self.contact_service.erase_user(id).await?;
+        self.remove_avatar_file(id).await;
+        Ok(())
+    }
+
+    /// Bring the projection up to date with an event from auth-service
+    pub async fn apply_event(&self, event: &UserEvent) -> Result<()> {
+        let applied = match event {
+            UserEvent::Created(e) => {
+                self.repository.insert(e.user_id, &e.name, &e.email, e.email_verified, e.timestamp).await?
+            }
+            UserEvent::EmailVerified(e) => {
+                self.repository.mark_email_verified(e.user_id, e.email.as_deref(), e.timestamp).await?
+            }
+            UserEvent::Locked(e) => {
+                self.repository.set_locked_until(e.user_id, e.locked_until, e.timestamp).await?
+            }
+            UserEvent::Deleted(e) => {
+                let applied = self.repository.mark_deleted(e.user_id, e.timestamp).await?;
+                self.kyc_service.erase_user(e.user_id).await?;
+                self.contact_service.erase_user(e.user_id).await?;
+                self.remove_avatar_file(e.user_id).await;
+                applied
+            }
+            // Nothing core-service keeps, or made here in the first place
+            UserEvent::PasswordChanged(_) | UserEvent::Updated(_) => return Ok(()),
+        };
+        if !applied {
+            tracing::debug!("Skipped {} for user {}, already applied or unknown user", event.event_type(), event.user_id());
+        }
+        Ok(())
+    }
+
+    async fn publish_update(&self, id: i32, name: &str) {
+        let message = UserEventMessage {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            event: UserEvent::Updated(UserUpdatedEvent {
+                user_id: id,
+                email: None,
+                name: Some(name.to_string()),
+                timestamp: Utc::now(),
+            }),
+        };
+        let result = match serde_json::to_string(&message) {
+            Ok(payload) => self.producer.send_message(USER_EVENTS_TOPIC, &id.to_string(), &payload).await,
+            Err(e) => Err(e.into()),
+        };
+        // The profile is saved already, other services catch up on the next change
+        if let Err(e) = result {
+            tracing::error!("Failed to publish user.updated for user {}: {}", id, e);
+        }
+    }
+
+    fn avatar_path(&self, id: i32) -> PathBuf {
+        self.avatar_dir.join(format!("{}.png", id))
+    }
+
+    async fn remove_avatar_file(&self, id: i32) {
+        match tokio::fs::remove_file(self.avatar_path(id)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                tracing::warn!("Failed to remove avatar of user {}: {}", id, e);
+            }
+            _ => {}
+        }
     }
 }
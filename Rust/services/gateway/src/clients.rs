@@ -1,6 +1,7 @@
 // Stripe API client
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use common::http_client::HttpClient;
 
 #[derive(Serialize)]
 pub struct CreatePaymentIntentRequest {
@@ -17,6 +18,54 @@ pub struct PaymentIntent {
     pub status: String,
 }
 
+/// Destination charge: the platform keeps the application fee and the
+/// remainder is transferred to the connected account
+#[derive(Debug, Clone)]
+pub struct PaymentSplit {
+    pub destination: String, // acct_...
+    pub application_fee_cents: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StripePayout {
+    pub id: String,
+    pub status: String,
+    pub arrival_date: Option<i64>,
+    #[serde(default)]
+    pub failure_message: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StripeAccount {
+    pub id: String,
+}
+
+/// Single-use link to Stripe's hosted onboarding of a connected account
+#[derive(Deserialize, Debug)]
+pub struct AccountLink {
+    pub url: String,
+    pub expires_at: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StripeRefund {
+    pub id: String,
+    pub status: String,
+}
+
+/// Stripe answered with a 4xx: nothing was created. Any other error (timeout,
+/// 5xx, unreadable answer) leaves the outcome unknown.
+#[derive(Debug)]
+pub struct StripeRejection(pub String);
+
+impl std::fmt::Display for StripeRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stripe API error: {}", self.0)
+    }
+}
+
+impl std::error::Error for StripeRejection {}
+
 pub struct StripeClient {
     api_key: String,
     client: reqwest::Client,
@@ -39,15 +88,32 @@ impl StripeClient {
         }
     }
 
-    pub async fn create_payment_intent(&self, amount: i64, currency: &str) -> Result<PaymentIntent> {
+    pub async fn create_payment_intent(
+        &self,
+        amount: i64,
+        currency: &str,
+        split: Option<&PaymentSplit>,
+        manual_capture: bool,
+    ) -> Result<PaymentIntent> {
+        let mut form = vec![
+            ("amount", amount.to_string()),
+            ("currency", currency.to_string()),
+        ];
+
+        if manual_capture {
+            form.push(("capture_method", "manual".to_string()));
+        }
+
+        if let Some(split) = split {
+            form.push(("application_fee_amount", split.application_fee_cents.to_string()));
+            form.push(("transfer_data[destination]", split.destination.clone()));
+        }
+
         let response = self.client
             .post("https://api.stripe.com/v1/payment_intents")
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&[
-                ("amount", amount.to_string()),
-                ("currency", currency.to_string()),
-            ])
+            .form(&form)
             .send()
             .await?;
 
@@ -77,5 +143,253 @@ impl StripeClient {
         let payment_intent = response.json::<PaymentIntent>().await?;
         Ok(payment_intent)
     }
+
+    /// Capture the authorized amount of a manual-capture PaymentIntent
+    pub async fn capture_payment_intent(&self, intent_id: &str) -> Result<PaymentIntent> {
+        let url = format!("https://api.stripe.com/v1/payment_intents/{}/capture", intent_id);
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Stripe API error: {}", error_text));
+        }
+
+        let payment_intent = response.json::<PaymentIntent>().await?;
+        Ok(payment_intent)
+    }
+
+    pub async fn cancel_payment_intent(&self, intent_id: &str, reason: Option<&str>) -> Result<PaymentIntent> {
+        let url = format!("https://api.stripe.com/v1/payment_intents/{}/cancel", intent_id);
+        let form: Vec<(&str, &str)> = reason.map(|r| vec![("cancellation_reason", r)]).unwrap_or_default();
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Stripe API error: {}", error_text));
+        }
+
+        let payment_intent = response.json::<PaymentIntent>().await?;
+        Ok(payment_intent)
+    }
+
+    /// Express account for a seller; Stripe collects and verifies the bank
+    /// details during onboarding
+    pub async fn create_express_account(&self, user_id: i32, country: Option<&str>, default_currency: &str) -> Result<StripeAccount> {
+        let mut form = vec![
+            ("type", "express".to_string()),
+            ("capabilities[transfers][requested]", "true".to_string()),
+            ("default_currency", default_currency.to_lowercase()),
+            ("metadata[user_id]", user_id.to_string()),
+        ];
+        if let Some(country) = country {
+            form.push(("country", country.to_uppercase()));
+        }
+
+        let response = self.client
+            .post("https://api.stripe.com/v1/accounts")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Stripe API error: {}", error_text));
+        }
+
+        let account = response.json::<StripeAccount>().await?;
+        Ok(account)
+    }
+
+    pub async fn create_account_link(&self, connected_account: &str, refresh_url: &str, return_url: &str) -> Result<AccountLink> {
+        let response = self.client
+            .post("https://api.stripe.com/v1/account_links")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&[
+                ("account", connected_account),
+                ("refresh_url", refresh_url),
+                ("return_url", return_url),
+                ("type", "account_onboarding"),
+            ])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Stripe API error: {}", error_text));
+        }
+
+        let link = response.json::<AccountLink>().await?;
+        Ok(link)
+    }
+
+    /// Pay out a connected account's Stripe balance to its bank account.
+    /// Sending the same `idempotency_key` again returns the first payout.
+    pub async fn create_payout(&self, connected_account: &str, amount: i64, currency: &str, idempotency_key: &str) -> Result<StripePayout> {
+        let response = self.client
+            .post("https://api.stripe.com/v1/payouts")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Stripe-Account", connected_account)
+            .header("Idempotency-Key", idempotency_key)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&[
+                ("amount", amount.to_string()),
+                ("currency", currency.to_lowercase()),
+            ])
+            .send()
+            .await?;
+
+        if response.status().is_client_error() {
+            return Err(StripeRejection(response.text().await?).into());
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Stripe API error: {}", error_text));
+        }
+
+        let payout = response.json::<StripePayout>().await?;
+        Ok(payout)
+    }
+
+    pub async fn retrieve_payout(&self, connected_account: &str, payout_id: &str) -> Result<StripePayout> {
+        let url = format!("https://api.stripe.com/v1/payouts/{}", payout_id);
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Stripe-Account", connected_account)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Stripe API error: {}", error_text));
+        }
+
+        let payout = response.json::<StripePayout>().await?;
+        Ok(payout)
+    }
+
+    /// Refund a payment intent. For destination charges the transfer to the
+    /// seller and the platform fee are reversed in proportion to the amount.
+    /// Sending the same `idempotency_key` again returns the first refund.
+    pub async fn create_refund(
+        &self,
+        payment_intent: &str,
+        amount: i64,
+        reason: Option<&str>,
+        destination_charge: bool,
+        idempotency_key: &str,
+    ) -> Result<StripeRefund> {
+        let mut form = vec![
+            ("payment_intent", payment_intent.to_string()),
+            ("amount", amount.to_string()),
+        ];
+        if let Some(reason) = reason {
+            form.push(("reason", reason.to_string()));
+        }
+        if destination_charge {
+            form.push(("reverse_transfer", "true".to_string()));
+            form.push(("refund_application_fee", "true".to_string()));
+        }
+
+        let response = self.client
+            .post("https://api.stripe.com/v1/refunds")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Idempotency-Key", idempotency_key)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&form)
+            .send()
+            .await?;
+
+        if response.status().is_client_error() {
+            return Err(StripeRejection(response.text().await?).into());
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Stripe API error: {}", error_text));
+        }
+
+        let refund = response.json::<StripeRefund>().await?;
+        Ok(refund)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct KycStatus {
+    pub level: u8,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Relationship {
+    pub blocked: bool,
+    pub blocked_by: bool,
+}
+
+/// core-service client, for the KYC tier of a user and blocks between users
+#[derive(Clone)]
+pub struct CoreClient {
+    base_url: String,
+    api_key: String,
+    http: HttpClient,
 }
 
+impl CoreClient {
+    /// CORE_SERVICE_URL, and either the OAUTH_* client of the gateway or
+    /// CORE_SERVICE_API_KEY, both with the 'kyc:read' and 'contacts:read' scopes
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("CORE_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8082".to_string());
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: std::env::var("CORE_SERVICE_API_KEY").unwrap_or_default(),
+            http: HttpClient::from_env(),
+        }
+    }
+
+    /// A service client token goes to /service, the API key to /internal
+    async fn get(&self, path: &str) -> Result<reqwest::Response> {
+        if self.http.client_id().is_some() {
+            return self.http.get(&format!("{}/service{}", self.base_url, path)).await;
+        }
+        let request = self.http
+            .request(reqwest::Method::GET, &format!("{}/internal{}", self.base_url, path))
+            .header("X-API-Key", &self.api_key);
+        self.http.send(request).await
+    }
+
+    pub async fn kyc_level(&self, user_id: i32) -> Result<u8> {
+        let response = self.get(&format!("/users/{}/kyc", user_id)).await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Core service error: {}", error_text));
+        }
+
+        Ok(response.json::<KycStatus>().await?.level)
+    }
+
+    pub async fn relationship(&self, user_id: i32, other_id: i32) -> Result<Relationship> {
+        let response = self.get(&format!("/users/{}/relationships/{}", user_id, other_id)).await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Core service error: {}", error_text));
+        }
+
+        Ok(response.json::<Relationship>().await?)
+    }
+}
@@ -0,0 +1,333 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+
+use super::Pseudonymizer;
+
+/// Hash the first entry of the chain links to
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub payment_id: i32,
+    pub action: String,
+    pub actor_type: String,
+    pub actor_id: Option<String>, // cleared by an erasure, the reference stays
+    pub actor_ref: Option<String>,
+    pub source_ip: Option<String>,
+    pub source_ip_ref: Option<String>,
+    pub request_id: String,
+    #[serde(serialize_with = "raw_json")]
+    pub before_state: Option<String>, // JSON snapshot, stored verbatim so the hash stays reproducible
+    #[serde(serialize_with = "raw_json")]
+    pub after_state: Option<String>,
+    pub prev_hash: String,
+    pub entry_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    /// True when the stored hash still matches the entry contents, and the
+    /// actor id and source IP, unless erased, still match their references
+    pub fn verify(&self, pseudonymizer: &Pseudonymizer) -> bool {
+        let record = AuditRecord {
+            payment_id: self.payment_id,
+            action: &self.action,
+            actor_type: &self.actor_type,
+            actor_id: self.actor_id.as_deref(),
+            actor_ref: self.actor_ref.as_deref(),
+            source_ip: self.source_ip.as_deref(),
+            source_ip_ref: self.source_ip_ref.as_deref(),
+            request_id: &self.request_id,
+            before_state: self.before_state.as_deref(),
+            after_state: self.after_state.as_deref(),
+            created_at: self.created_at,
+        };
+        let maps = |value: Option<&str>, reference: Option<&str>| match value {
+            Some(value) => pseudonymizer.pseudonym_of(value).ok().as_deref() == reference,
+            None => true,
+        };
+        record.hash(&self.prev_hash) == self.entry_hash
+            && maps(self.actor_id.as_deref(), self.actor_ref.as_deref())
+            && maps(self.source_ip.as_deref(), self.source_ip_ref.as_deref())
+    }
+}
+
+/// The hashed fields of an entry that is about to be appended
+#[derive(Debug, Clone)]
+pub struct AuditRecord<'a> {
+    pub payment_id: i32,
+    pub action: &'a str,
+    pub actor_type: &'a str,
+    pub actor_id: Option<&'a str>,
+    pub actor_ref: Option<&'a str>, // keyed pseudonym of actor_id
+    pub source_ip: Option<&'a str>,
+    pub source_ip_ref: Option<&'a str>, // keyed pseudonym of source_ip
+    pub request_id: &'a str,
+    pub before_state: Option<&'a str>,
+    pub after_state: Option<&'a str>,
+    pub created_at: DateTime<Utc>, // whole seconds, matching the TIMESTAMP column
+}
+
+impl AuditRecord<'_> {
+    /// SHA-256 over the previous hash and every field, separated by the ASCII
+    /// unit separator so adjacent fields cannot be shifted into each other.
+    /// The actor and source IP are hashed through their keyed pseudonyms: an
+    /// account erasure clears the id and the address, which only drops the
+    /// link to the person, and the chain still verifies.
+    pub fn hash(&self, prev_hash: &str) -> String {
+        let payment_id = self.payment_id.to_string();
+        let created_at = self.created_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let fields = [
+            prev_hash,
+            &payment_id,
+            self.action,
+            self.actor_type,
+            self.actor_ref.unwrap_or(""),
+            self.source_ip_ref.unwrap_or(""),
+            self.request_id,
+            self.before_state.unwrap_or(""),
+            self.after_state.unwrap_or(""),
+            &created_at,
+        ];
+
+        let mut hasher = Sha256::new();
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                hasher.update([0x1f]);
+            }
+            hasher.update(field.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// An entry waiting in the audit queue, owned so it can outlive the request
+#[derive(Debug, Clone)]
+pub struct PendingRecord {
+    pub payment_id: i32,
+    pub action: AuditAction,
+    pub actor_type: &'static str,
+    pub actor_id: Option<String>,
+    pub actor_ref: Option<String>,
+    pub source_ip: Option<String>,
+    pub source_ip_ref: Option<String>,
+    pub request_id: String,
+    pub before_state: Option<String>,
+    pub after_state: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PendingRecord {
+    pub fn as_record(&self) -> AuditRecord<'_> {
+        AuditRecord {
+            payment_id: self.payment_id,
+            action: self.action.as_str(),
+            actor_type: self.actor_type,
+            actor_id: self.actor_id.as_deref(),
+            actor_ref: self.actor_ref.as_deref(),
+            source_ip: self.source_ip.as_deref(),
+            source_ip_ref: self.source_ip_ref.as_deref(),
+            request_id: &self.request_id,
+            before_state: self.before_state.as_deref(),
+            after_state: self.after_state.as_deref(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Retrieve,
+    Refund,
+    Capture,
+    Cancel,
+    Webhook,
+    AdminOverride, // an admin acting on a payment they do not own
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Retrieve => "retrieve",
+            AuditAction::Refund => "refund",
+            AuditAction::Capture => "capture",
+            AuditAction::Cancel => "cancel",
+            AuditAction::Webhook => "webhook",
+            AuditAction::AdminOverride => "admin_override",
+        }
+    }
+
+    /// Reads change nothing, so they are written behind the request in
+    /// batches instead of taking the chain head lock on every GET
+    pub fn is_read(&self) -> bool {
+        matches!(self, AuditAction::Retrieve)
+    }
+}
+
+/// Who performed an action: a user, a backend service holding an API key,
+/// Stripe (webhooks) or the gateway itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditActor {
+    pub actor_type: &'static str,
+    pub actor_id: Option<String>,
+}
+
+impl AuditActor {
+    pub fn user(user_id: i32) -> Self {
+        Self {
+            actor_type: "user",
+            actor_id: Some(user_id.to_string()),
+        }
+    }
+
+    /// Identified by the visible prefix of the key
+    pub fn api_key(prefix: &str) -> Self {
+        Self {
+            actor_type: "api_key",
+            actor_id: Some(prefix.to_string()),
+        }
+    }
+
+    pub fn stripe() -> Self {
+        Self {
+            actor_type: "stripe",
+            actor_id: None,
+        }
+    }
+}
+
+/// Request metadata recorded with every audit entry
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub actor: AuditActor,
+    pub source_ip: Option<String>,
+    pub request_id: String,
+}
+
+/// Result of walking the whole chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainVerification {
+    pub entries_checked: u64,
+    pub valid: bool,
+    pub first_invalid_id: Option<i64>,
+}
+
+fn raw_json<S: Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    let parsed = value.as_deref().and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok());
+    parsed.serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn pseudonymizer() -> Pseudonymizer {
+        Pseudonymizer::new(Some("key".to_string()))
+    }
+
+    fn entry(prev_hash: &str) -> AuditEntry {
+        let created_at = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        let actor_ref = pseudonymizer().pseudonym_of("42").unwrap();
+        let source_ip_ref = pseudonymizer().pseudonym_of("10.0.0.1").unwrap();
+        let record = AuditRecord {
+            payment_id: 7,
+            action: "refund",
+            actor_type: "user",
+            actor_id: Some("42"),
+            actor_ref: Some(&actor_ref),
+            source_ip: Some("10.0.0.1"),
+            source_ip_ref: Some(&source_ip_ref),
+            request_id: "req-1",
+            before_state: Some(r#"{"refunded":0.0}"#),
+            after_state: Some(r#"{"refunded":5.0}"#),
+            created_at,
+        };
+
+        AuditEntry {
+            id: 1,
+            payment_id: 7,
+            action: "refund".to_string(),
+            actor_type: "user".to_string(),
+            actor_id: Some("42".to_string()),
+            actor_ref: Some(actor_ref.clone()),
+            source_ip: Some("10.0.0.1".to_string()),
+            source_ip_ref: Some(source_ip_ref.clone()),
+            request_id: "req-1".to_string(),
+            before_state: Some(r#"{"refunded":0.0}"#.to_string()),
+            after_state: Some(r#"{"refunded":5.0}"#.to_string()),
+            prev_hash: prev_hash.to_string(),
+            entry_hash: record.hash(prev_hash),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let mut e = entry(GENESIS_HASH);
+        assert!(e.verify(&pseudonymizer()));
+
+        e.after_state = Some(r#"{"refunded":50.0}"#.to_string());
+        assert!(!e.verify(&pseudonymizer()));
+    }
+
+    #[test]
+    fn test_hash_depends_on_previous_entry() {
+        let first = entry(GENESIS_HASH);
+        let second = entry(&first.entry_hash);
+        assert_ne!(first.entry_hash, second.entry_hash);
+
+        let mut relinked = second.clone();
+        relinked.prev_hash = GENESIS_HASH.to_string();
+        assert!(!relinked.verify(&pseudonymizer()));
+    }
+
+    #[test]
+    fn test_chain_verifies_after_erasure() {
+        let first = entry(GENESIS_HASH);
+        let mut second = entry(&first.entry_hash);
+
+        // What an erasure leaves behind: the references without the id and address
+        second.actor_id = None;
+        second.source_ip = None;
+        assert!(second.verify(&pseudonymizer()));
+        assert_eq!(second.prev_hash, first.entry_hash);
+    }
+
+    #[test]
+    fn test_verify_detects_a_rewritten_actor() {
+        let mut e = entry(GENESIS_HASH);
+        e.actor_id = Some("43".to_string());
+        assert!(!e.verify(&pseudonymizer()));
+
+        let mut e = entry(GENESIS_HASH);
+        e.actor_id = None;
+        e.actor_ref = Some(pseudonymizer().pseudonym_of("43").unwrap());
+        assert!(!e.verify(&pseudonymizer()));
+
+        let mut e = entry(GENESIS_HASH);
+        e.source_ip = Some("10.0.0.2".to_string());
+        assert!(!e.verify(&pseudonymizer()));
+    }
+
+    #[test]
+    fn test_only_reads_are_queued() {
+        assert!(AuditAction::Retrieve.is_read());
+        let mutating = [
+            AuditAction::Create,
+            AuditAction::Refund,
+            AuditAction::Capture,
+            AuditAction::Cancel,
+            AuditAction::Webhook,
+            AuditAction::AdminOverride,
+        ];
+        for action in mutating {
+            assert!(!action.is_read(), "{} must be chained before the response", action.as_str());
+        }
+    }
+}
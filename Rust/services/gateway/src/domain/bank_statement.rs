@@ -0,0 +1,84 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct BankStatement {
+    pub id: i64,
+    pub format: String,
+    pub account: String,
+    pub statement_ref: String,
+    pub currency: String,
+    pub statement_date: Option<NaiveDate>,
+    pub imported_by: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct StatementLine {
+    pub id: i64,
+    pub statement_id: i64,
+    pub line_no: i32,
+    pub booking_date: Option<NaiveDate>,
+    pub value_date: Option<NaiveDate>,
+    pub amount: f64,
+    pub currency: String,
+    pub direction: String,
+    pub reference: Option<String>,
+    pub bank_reference: Option<String>,
+    pub description: Option<String>,
+    pub match_status: String,
+    pub matched_type: Option<String>,
+    pub matched_id: Option<i64>,
+    pub matched_by: Option<String>,
+    pub match_candidates: Option<String>, // JSON list of candidates for ambiguous lines
+    pub match_note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchStatus {
+    Matched,
+    Unmatched,
+    Ambiguous,
+    Ignored,
+}
+
+impl MatchStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MatchStatus::Matched => "matched",
+            MatchStatus::Unmatched => "unmatched",
+            MatchStatus::Ambiguous => "ambiguous",
+            MatchStatus::Ignored => "ignored",
+        }
+    }
+}
+
+impl From<String> for MatchStatus {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "matched" => MatchStatus::Matched,
+            "ambiguous" => MatchStatus::Ambiguous,
+            "ignored" => MatchStatus::Ignored,
+            _ => MatchStatus::Unmatched,
+        }
+    }
+}
+
+/// How an imported line reconciled, saved along with the line
+#[derive(Debug, Clone)]
+pub struct LineMatch {
+    pub status: MatchStatus,
+    pub matched: Option<MatchCandidate>,
+    pub candidates: Option<String>, // JSON list of candidates for ambiguous lines
+}
+
+/// A gateway record a statement line can be reconciled against
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct MatchCandidate {
+    pub kind: String, // payment, payout
+    pub id: i64,
+    pub reference: String,
+    pub amount_cents: i64,
+    pub currency: String,
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Invoice {
+    pub id: i64,
+    pub number: Option<String>, // assigned when the invoice is finalized
+    pub user_id: i32,           // customer billed by the invoice
+    pub created_by: i32,
+    pub customer_name: String,
+    pub customer_email: Option<String>,
+    pub currency: String,
+    pub status: String,
+    pub discount_percent: f64,
+    pub subtotal: f64,
+    pub discount_amount: f64,
+    pub tax_amount: f64,
+    pub total: f64,
+    pub due_date: NaiveDate,
+    pub memo: Option<String>,
+    pub payment_id: Option<i32>,
+    pub issued_at: Option<DateTime<Utc>>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub voided_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct InvoiceLineItem {
+    pub id: i64,
+    pub invoice_id: i64,
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+    pub tax_rate: f64, // percent
+    pub amount: f64,   // quantity * unit_price, before discount and tax
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum InvoiceStatus {
+    Draft,
+    Open,
+    Paid,
+    Void,
+}
+
+impl InvoiceStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            InvoiceStatus::Draft => "draft",
+            InvoiceStatus::Open => "open",
+            InvoiceStatus::Paid => "paid",
+            InvoiceStatus::Void => "void",
+        }
+    }
+
+    /// draft -> open -> paid, and draft/open -> void
+    pub fn can_transition_to(&self, next: InvoiceStatus) -> bool {
+        matches!(
+            (self, next),
+            (InvoiceStatus::Draft, InvoiceStatus::Open)
+                | (InvoiceStatus::Open, InvoiceStatus::Paid)
+                | (InvoiceStatus::Draft, InvoiceStatus::Void)
+                | (InvoiceStatus::Open, InvoiceStatus::Void)
+        )
+    }
+}
+
+impl From<String> for InvoiceStatus {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "open" => InvoiceStatus::Open,
+            "paid" => InvoiceStatus::Paid,
+            "void" => InvoiceStatus::Void,
+            _ => InvoiceStatus::Draft,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// Payment limits by KYC tier, the tier itself is looked up in core-service
+use anyhow::{anyhow, Result};
+
+/// Amount a user may pay within 24 hours at each tier, in the payment's
+/// currency units; index 0 is unverified, 1 basic, 2 identity verified
+#[derive(Debug, Clone)]
+pub struct PaymentLimits {
+    daily: Vec<f64>,
+}
+
+impl PaymentLimits {
+    pub fn new(daily: Vec<f64>) -> Self {
+        Self { daily }
+    }
+
+    /// KYC_DAILY_PAYMENT_LIMITS, comma separated by tier (default 100,1000,10000)
+    pub fn from_env() -> Self {
+        let limits = std::env::var("KYC_DAILY_PAYMENT_LIMITS").unwrap_or_else(|_| "100,1000,10000".to_string());
+        Self::parse(&limits).expect("Invalid KYC_DAILY_PAYMENT_LIMITS")
+    }
+
+    pub fn parse(limits: &str) -> Result<Self> {
+        let daily = limits
+            .split(',')
+            .map(|l| l.trim().parse::<f64>().ok().filter(|l| *l >= 0.0))
+            .collect::<Option<Vec<f64>>>()
+            .filter(|daily| !daily.is_empty())
+            .ok_or_else(|| anyhow!("Invalid payment limits: {}", limits))?;
+        Ok(Self::new(daily))
+    }
+
+    /// Tiers above the last configured one share its limit
+    pub fn daily_limit(&self, level: u8) -> f64 {
+        let index = (level as usize).min(self.daily.len() - 1);
+        self.daily[index]
+    }
+
+    /// Err when `amount` on top of what was paid in the last 24 hours goes
+    /// over the tier's limit
+    pub fn check(&self, level: u8, paid_last_24h: f64, amount: f64) -> Result<()> {
+        let limit = self.daily_limit(level);
+        if paid_last_24h + amount <= limit {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Payment limit exceeded: KYC tier {} allows {:.2} per 24 hours, {:.2} left; verify your identity to raise it",
+            level, limit, (limit - paid_last_24h).max(0.0)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = PaymentLimits::parse("100, 1000,10000").unwrap();
+        assert_eq!(limits.daily_limit(0), 100.0);
+        assert_eq!(limits.daily_limit(2), 10000.0);
+        assert_eq!(limits.daily_limit(7), 10000.0);
+        assert!(PaymentLimits::parse("100,lots").is_err());
+        assert!(PaymentLimits::parse("-1").is_err());
+    }
+
+    #[test]
+    fn test_check_counts_the_last_24_hours() {
+        let limits = PaymentLimits::parse("100,1000").unwrap();
+        assert!(limits.check(0, 60.0, 40.0).is_ok());
+        let err = limits.check(0, 60.0, 40.01).unwrap_err().to_string();
+        assert!(err.starts_with("Payment limit exceeded: KYC tier 0 allows 100.00 per 24 hours, 40.00 left"));
+        assert!(limits.check(1, 60.0, 900.0).is_ok());
+    }
+}
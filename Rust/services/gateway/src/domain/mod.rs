@@ -1,3 +1,21 @@
 pub mod payment;
+pub mod bank_statement;
+pub mod payout;
+pub mod invoice;
+pub mod refund;
+pub mod report;
+pub mod audit;
+pub mod kyc;
+pub mod webhook;
+pub mod privacy;
 
 pub use payment::{Payment, PaymentStatus};
+pub use bank_statement::{BankStatement, StatementLine, MatchStatus, MatchCandidate, LineMatch};
+pub use payout::{ConnectedAccount, Payout, PayoutStatus, PayoutSchedule, SellerBalance};
+pub use invoice::{Invoice, InvoiceLineItem, InvoiceStatus};
+pub use refund::{Refund, RefundStatus, Dispute};
+pub use report::{PaymentReportRow, RefundDisputeReportRow, ExportFilters, ExportJob, ExportStatus, PaymentExportRow, RefundExportRow};
+pub use audit::{AuditEntry, AuditRecord, AuditAction, AuditActor, AuditContext, ChainVerification, PendingRecord, GENESIS_HASH};
+pub use kyc::PaymentLimits;
+pub use webhook::WebhookVerifier;
+pub use privacy::Pseudonymizer;
@@ -11,6 +11,8 @@ pub struct Payment {
     pub payment_method: Option<String>,
     pub stripe_payment_intent_id: Option<String>,
     pub stripe_client_secret: Option<String>,
+    pub destination_account_id: Option<i32>,
+    pub application_fee_amount: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,398 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+
+/// Balances below this are carried over to the next scheduled payout
+pub const MIN_PAYOUT_AMOUNT: f64 = 1.0;
+
+/// A seller on the marketplace that receives part of a payment
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ConnectedAccount {
+    pub id: i32,
+    pub user_id: i32,
+    pub stripe_account_id: String,
+    pub display_name: String,
+    pub default_currency: String,
+    pub payout_schedule: String, // daily, weekly, manual
+    pub status: String,          // onboarding, active, disabled
+    pub created_at: DateTime<Utc>,
+}
+
+/// Status of a connected account once Stripe reports whether it can receive
+/// payouts; an account that never finished onboarding stays in onboarding
+pub fn account_status(current: &str, payouts_enabled: bool) -> &'static str {
+    match (current, payouts_enabled) {
+        (_, true) => "active",
+        ("onboarding", false) => "onboarding",
+        (_, false) => "disabled",
+    }
+}
+
+/// Where Stripe sends the seller after onboarding, or when the link expired
+#[derive(Debug, Clone)]
+pub struct OnboardingUrls {
+    pub refresh_url: String,
+    pub return_url: String,
+}
+
+impl OnboardingUrls {
+    /// CONNECT_REFRESH_URL and CONNECT_RETURN_URL, by default pages under APP_BASE_URL
+    pub fn from_env() -> Self {
+        let base = std::env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let base = base.trim_end_matches('/');
+        Self {
+            refresh_url: std::env::var("CONNECT_REFRESH_URL")
+                .ok()
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| format!("{}/seller/onboarding/refresh", base)),
+            return_url: std::env::var("CONNECT_RETURN_URL")
+                .ok()
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| format!("{}/seller/onboarding/complete", base)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Payout {
+    pub id: i64,
+    pub connected_account_id: i32,
+    pub amount: f64,
+    pub currency: String,
+    pub status: String,
+    pub stripe_payout_id: Option<String>,
+    pub failure_reason: Option<String>,
+    pub arrival_date: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PayoutStatus {
+    Pending,
+    InTransit,
+    Paid,
+    Failed,
+    Canceled,
+}
+
+impl PayoutStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PayoutStatus::Pending => "pending",
+            PayoutStatus::InTransit => "in_transit",
+            PayoutStatus::Paid => "paid",
+            PayoutStatus::Failed => "failed",
+            PayoutStatus::Canceled => "canceled",
+        }
+    }
+
+    /// Payouts only move forward; a paid payout can still fail when the bank
+    /// sends it back
+    pub fn can_become(&self, next: &PayoutStatus) -> bool {
+        use PayoutStatus::*;
+        match (self, next) {
+            (current, next) if current == next => false,
+            (Pending, _) => true,
+            (InTransit, Paid | Failed | Canceled) => true,
+            (Paid, Failed) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<String> for PayoutStatus {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "in_transit" => PayoutStatus::InTransit,
+            "paid" => PayoutStatus::Paid,
+            "failed" => PayoutStatus::Failed,
+            "canceled" => PayoutStatus::Canceled,
+            _ => PayoutStatus::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayoutSchedule {
+    Daily,
+    Weekly,
+    Manual,
+}
+
+impl PayoutSchedule {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "daily" => Some(PayoutSchedule::Daily),
+            "weekly" => Some(PayoutSchedule::Weekly),
+            "manual" => Some(PayoutSchedule::Manual),
+            _ => None,
+        }
+    }
+
+    /// Minimum time between two scheduled payouts
+    pub fn interval(&self) -> Option<chrono::Duration> {
+        match self {
+            PayoutSchedule::Daily => Some(chrono::Duration::days(1)),
+            PayoutSchedule::Weekly => Some(chrono::Duration::weeks(1)),
+            PayoutSchedule::Manual => None,
+        }
+    }
+
+    /// Due when the interval has passed since the last payout; manual never is
+    pub fn is_due(&self, last_payout: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        match self.interval() {
+            Some(interval) => last_payout.map(|last| now - last >= interval).unwrap_or(true),
+            None => false,
+        }
+    }
+}
+
+/// Per-currency balance of a connected account
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SellerBalance {
+    pub currency: String,
+    /// Succeeded payments net of application fees
+    pub earned: f64,
+    /// Net amount of payments still pending
+    pub incoming: f64,
+    /// Seller's share of refunds (pending or succeeded) and lost disputes
+    pub refunded: f64,
+    /// Seller's share of disputes still open, held until they are decided
+    pub disputed: f64,
+    /// Payouts already paid
+    pub paid_out: f64,
+    /// Payouts pending or in transit
+    pub in_payout: f64,
+    /// What can still be paid out
+    pub available: f64,
+}
+
+pub mod ledger_source {
+    pub const PAYMENT: &str = "payment";
+    pub const PAYOUT: &str = "payout";
+    pub const REFUND: &str = "refund";
+    pub const DISPUTE: &str = "dispute";
+}
+
+/// Sum of a connected account's payments (net of application fees),
+/// payouts, or refunds and disputes (the seller's share) in one currency and
+/// status
+#[derive(Debug, Clone, FromRow)]
+pub struct LedgerTotal {
+    pub currency: String,
+    pub source: String,
+    pub status: String,
+    pub amount: f64,
+}
+
+/// Balances by currency: earned minus refunds and disputes, paid out and
+/// payouts in flight. Failed and canceled payouts count for nothing, their
+/// money is available again; open disputes are held until decided.
+pub fn seller_balances(totals: &[LedgerTotal]) -> Vec<SellerBalance> {
+    let mut balances: BTreeMap<String, SellerBalance> = BTreeMap::new();
+    for total in totals {
+        let currency = total.currency.to_uppercase();
+        let balance = balances.entry(currency.clone()).or_insert_with(|| SellerBalance { currency, ..Default::default() });
+        match (total.source.as_str(), total.status.as_str()) {
+            (ledger_source::PAYMENT, "succeeded") => balance.earned += total.amount,
+            (ledger_source::PAYMENT, "pending") => balance.incoming += total.amount,
+            (ledger_source::PAYOUT, "paid") => balance.paid_out += total.amount,
+            (ledger_source::PAYOUT, "pending" | "in_transit") => balance.in_payout += total.amount,
+            (ledger_source::REFUND, "pending" | "succeeded") => balance.refunded += total.amount,
+            (ledger_source::DISPUTE, "lost") => balance.refunded += total.amount,
+            (ledger_source::DISPUTE, "won" | "warning_closed") => {}
+            (ledger_source::DISPUTE, _) => balance.disputed += total.amount,
+            _ => {}
+        }
+    }
+
+    balances
+        .into_values()
+        .map(|mut balance| {
+            balance.available = balance.earned - balance.refunded - balance.disputed - balance.paid_out - balance.in_payout;
+            balance
+        })
+        .collect()
+}
+
+/// Cents of a destination charge: the platform keeps the application fee
+/// and the seller receives the rest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitAmounts {
+    pub amount_cents: i64,
+    pub application_fee_cents: i64,
+    pub seller_cents: i64,
+}
+
+pub fn split_amounts(amount: f64, application_fee: Option<f64>) -> Result<SplitAmounts> {
+    let fee = application_fee.unwrap_or(0.0);
+    if fee < 0.0 || fee > amount {
+        return Err(anyhow!("Invalid application fee: must be between 0 and the payment amount"));
+    }
+    let amount_cents = to_cents(amount);
+    let application_fee_cents = to_cents(fee);
+    Ok(SplitAmounts {
+        amount_cents,
+        application_fee_cents,
+        seller_cents: amount_cents - application_fee_cents,
+    })
+}
+
+pub fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+/// Amount to pay out of the available balance: all of it unless the seller
+/// asked for part
+pub fn plan_payout(available: f64, requested: Option<f64>, currency: &str) -> Result<f64> {
+    let amount = requested.unwrap_or(available);
+    if amount < MIN_PAYOUT_AMOUNT {
+        return Err(anyhow!("Payout amount must be at least {:.2} {}", MIN_PAYOUT_AMOUNT, currency));
+    }
+    if amount > available {
+        return Err(anyhow!("Insufficient balance: {:.2} {} available", available, currency));
+    }
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn total(currency: &str, source: &str, status: &str, amount: f64) -> LedgerTotal {
+        LedgerTotal {
+            currency: currency.to_string(),
+            source: source.to_string(),
+            status: status.to_string(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_split_amounts() {
+        let split = split_amounts(19.99, Some(2.5)).unwrap();
+        assert_eq!(split, SplitAmounts { amount_cents: 1999, application_fee_cents: 250, seller_cents: 1749 });
+
+        let no_fee = split_amounts(10.0, None).unwrap();
+        assert_eq!((no_fee.application_fee_cents, no_fee.seller_cents), (0, 1000));
+
+        let all_fee = split_amounts(10.0, Some(10.0)).unwrap();
+        assert_eq!(all_fee.seller_cents, 0);
+    }
+
+    #[test]
+    fn test_split_rejects_fee_out_of_range() {
+        assert!(split_amounts(10.0, Some(-0.01)).is_err());
+        assert!(split_amounts(10.0, Some(10.01)).is_err());
+    }
+
+    #[test]
+    fn test_balance_is_earned_minus_paid_minus_in_flight() {
+        let balances = seller_balances(&[
+            total("usd", ledger_source::PAYMENT, "succeeded", 100.0),
+            total("USD", ledger_source::PAYMENT, "pending", 40.0),
+            total("USD", ledger_source::PAYMENT, "failed", 25.0),
+            total("USD", ledger_source::PAYOUT, "paid", 30.0),
+            total("USD", ledger_source::PAYOUT, "pending", 10.0),
+            total("USD", ledger_source::PAYOUT, "in_transit", 5.0),
+        ]);
+
+        assert_eq!(balances, vec![SellerBalance {
+            currency: "USD".to_string(),
+            earned: 100.0,
+            incoming: 40.0,
+            refunded: 0.0,
+            disputed: 0.0,
+            paid_out: 30.0,
+            in_payout: 15.0,
+            available: 55.0,
+        }]);
+    }
+
+    #[test]
+    fn test_failed_payouts_are_available_again() {
+        let balances = seller_balances(&[
+            total("EUR", ledger_source::PAYMENT, "succeeded", 80.0),
+            total("EUR", ledger_source::PAYOUT, "failed", 50.0),
+            total("EUR", ledger_source::PAYOUT, "canceled", 20.0),
+            total("USD", ledger_source::PAYMENT, "succeeded", 10.0),
+        ]);
+
+        assert_eq!(balances.len(), 2);
+        assert_eq!(balances[0].currency, "EUR");
+        assert_eq!(balances[0].available, 80.0);
+        assert_eq!(balances[1].currency, "USD");
+        assert_eq!(balances[1].available, 10.0);
+    }
+
+    #[test]
+    fn test_refunds_and_disputes_reduce_the_balance() {
+        let balances = seller_balances(&[
+            total("USD", ledger_source::PAYMENT, "succeeded", 100.0),
+            total("USD", ledger_source::REFUND, "succeeded", 20.0),
+            total("USD", ledger_source::REFUND, "pending", 5.0),
+            total("USD", ledger_source::REFUND, "failed", 50.0),
+            total("USD", ledger_source::DISPUTE, "lost", 10.0),
+            total("USD", ledger_source::DISPUTE, "needs_response", 15.0),
+            total("USD", ledger_source::DISPUTE, "won", 30.0),
+            total("USD", ledger_source::PAYOUT, "paid", 40.0),
+        ]);
+
+        assert_eq!(balances[0].refunded, 35.0);
+        assert_eq!(balances[0].disputed, 15.0);
+        assert_eq!(balances[0].available, 10.0);
+    }
+
+    #[test]
+    fn test_schedule_is_due() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+
+        assert!(PayoutSchedule::Daily.is_due(None, now));
+        assert!(PayoutSchedule::Daily.is_due(Some(now - Duration::days(1)), now));
+        assert!(!PayoutSchedule::Daily.is_due(Some(now - Duration::hours(23)), now));
+        assert!(!PayoutSchedule::Weekly.is_due(Some(now - Duration::days(6)), now));
+        assert!(PayoutSchedule::Weekly.is_due(Some(now - Duration::weeks(1)), now));
+        assert!(!PayoutSchedule::Manual.is_due(None, now));
+    }
+
+    #[test]
+    fn test_account_status_after_update() {
+        assert_eq!(account_status("onboarding", false), "onboarding");
+        assert_eq!(account_status("onboarding", true), "active");
+        assert_eq!(account_status("active", false), "disabled");
+        assert_eq!(account_status("disabled", true), "active");
+    }
+
+    #[test]
+    fn test_payout_status_transitions() {
+        use PayoutStatus::*;
+
+        assert!(Pending.can_become(&InTransit));
+        assert!(Pending.can_become(&Failed));
+        assert!(InTransit.can_become(&Paid));
+        assert!(Paid.can_become(&Failed));
+
+        assert!(!InTransit.can_become(&Pending));
+        assert!(!Paid.can_become(&InTransit));
+        assert!(!Paid.can_become(&Paid));
+        assert!(!Failed.can_become(&Paid));
+        assert!(!Canceled.can_become(&Pending));
+    }
+
+    #[test]
+    fn test_plan_payout_defaults_to_available() {
+        assert_eq!(plan_payout(42.5, None, "USD").unwrap(), 42.5);
+        assert_eq!(plan_payout(42.5, Some(10.0), "USD").unwrap(), 10.0);
+    }
+
+    #[test]
+    fn test_plan_payout_rejects_overdraw_and_dust() {
+        assert!(plan_payout(42.5, Some(42.51), "USD").unwrap_err().to_string().starts_with("Insufficient balance"));
+        assert!(plan_payout(0.5, None, "USD").is_err());
+        assert!(plan_payout(42.5, Some(0.0), "USD").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+// Keyed pseudonyms: payments of erased users are kept for accounting, linked
+// to an HMAC-SHA256 of the user id instead of the id itself, and audit entries
+// hash the pseudonyms of their actor and IP so an erasure leaves them intact
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+#[derive(Clone)]
+pub struct Pseudonymizer {
+    key: Option<String>,
+}
+
+impl Pseudonymizer {
+    pub fn new(key: Option<String>) -> Self {
+        Self { key: key.filter(|k| !k.is_empty()) }
+    }
+
+    /// PRIVACY_PSEUDONYM_KEY, required: audit entries are hashed through
+    /// pseudonyms. The key must not change, or the records of one user stop
+    /// sharing a pseudonym and older audit entries no longer match their ids.
+    pub fn from_env() -> Self {
+        let key = std::env::var("PRIVACY_PSEUDONYM_KEY").expect("PRIVACY_PSEUDONYM_KEY must be set");
+        Self::new(Some(key))
+    }
+
+    /// 64 hex characters, the same for every record of the user
+    pub fn pseudonym(&self, user_id: i32) -> Result<String> {
+        self.pseudonym_of(&user_id.to_string())
+    }
+
+    /// Keyed pseudonym of any identifier, such as an API key prefix or an IP
+    pub fn pseudonym_of(&self, value: &str) -> Result<String> {
+        let key = self.key.as_deref()
+            .ok_or_else(|| anyhow!("PRIVACY_PSEUDONYM_KEY is not set"))?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(value.as_bytes());
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudonym_is_stable_per_user() {
+        let pseudonymizer = Pseudonymizer::new(Some("key".to_string()));
+        let pseudonym = pseudonymizer.pseudonym(42).unwrap();
+        assert_eq!(pseudonym.len(), 64);
+        assert_eq!(pseudonym, pseudonymizer.pseudonym(42).unwrap());
+        assert_ne!(pseudonym, pseudonymizer.pseudonym(43).unwrap());
+    }
+
+    #[test]
+    fn test_pseudonym_depends_on_the_key() {
+        let a = Pseudonymizer::new(Some("key-a".to_string())).pseudonym(42).unwrap();
+        let b = Pseudonymizer::new(Some("key-b".to_string())).pseudonym(42).unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_missing_key_is_an_error() {
+        assert!(Pseudonymizer::new(None).pseudonym(42).is_err());
+        assert!(Pseudonymizer::new(Some(String::new())).pseudonym(42).is_err());
+    }
+}
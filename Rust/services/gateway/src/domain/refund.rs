@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Refund {
+    pub id: i64,
+    pub payment_id: i32,
+    pub amount: f64,
+    pub currency: String,
+    pub status: String, // pending, succeeded, failed, canceled
+    pub reason: Option<String>,
+    pub stripe_refund_id: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_by: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RefundStatus {
+    Pending,
+    Succeeded,
+    Failed,
+    Canceled,
+}
+
+impl RefundStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RefundStatus::Pending => "pending",
+            RefundStatus::Succeeded => "succeeded",
+            RefundStatus::Failed => "failed",
+            RefundStatus::Canceled => "canceled",
+        }
+    }
+}
+
+impl From<String> for RefundStatus {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "succeeded" => RefundStatus::Succeeded,
+            "failed" => RefundStatus::Failed,
+            "canceled" => RefundStatus::Canceled,
+            _ => RefundStatus::Pending,
+        }
+    }
+}
+
+/// A chargeback opened by the card holder, recorded from Stripe webhooks
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Dispute {
+    pub id: i64,
+    pub payment_id: i32,
+    pub stripe_dispute_id: String,
+    pub amount: f64,
+    pub currency: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
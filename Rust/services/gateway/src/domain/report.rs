@@ -0,0 +1,131 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::exports::ExportValue;
+
+/// Payment volume for one bucket (day, currency or payment method) and currency
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PaymentReportRow {
+    pub bucket: String,
+    pub currency: String,
+    pub payment_count: i64,
+    pub succeeded_count: i64,
+    pub failed_count: i64,
+    pub volume: f64, // succeeded amount
+}
+
+/// Refund and dispute totals for succeeded payments, per currency
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct RefundDisputeReportRow {
+    pub currency: String,
+    pub payment_count: i64,
+    pub volume: f64,
+    pub refunded_count: i64,
+    pub refunded_amount: f64,
+    pub disputed_count: i64,
+    pub disputed_amount: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportFilters {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>, // inclusive
+    pub status: Option<String>,
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ExportJob {
+    pub id: i64,
+    pub requested_by: i32,
+    pub kind: String,   // payments, refunds
+    pub format: String, // csv, parquet
+    pub filters: String, // JSON encoded ExportFilters
+    pub status: String, // queued, running, completed, failed
+    pub row_count: i64,
+    #[serde(skip_serializing)]
+    pub file_path: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ExportStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl ExportStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ExportStatus::Queued => "queued",
+            ExportStatus::Running => "running",
+            ExportStatus::Completed => "completed",
+            ExportStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct PaymentExportRow {
+    pub id: i32,
+    pub user_id: i32,
+    pub amount: f64,
+    pub currency: String,
+    pub status: String,
+    pub payment_method: Option<String>,
+    pub stripe_payment_intent_id: Option<String>,
+    pub destination_account_id: Option<i32>,
+    pub application_fee_amount: Option<f64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PaymentExportRow {
+    /// Cells in the order of exports::PAYMENT_COLUMNS
+    pub fn into_values(self) -> Vec<ExportValue> {
+        vec![
+            ExportValue::Int(Some(self.id as i64)),
+            ExportValue::Int(Some(self.user_id as i64)),
+            ExportValue::Float(Some(self.amount)),
+            ExportValue::Text(Some(self.currency)),
+            ExportValue::Text(Some(self.status)),
+            ExportValue::Text(self.payment_method),
+            ExportValue::Text(self.stripe_payment_intent_id),
+            ExportValue::Int(self.destination_account_id.map(i64::from)),
+            ExportValue::Float(self.application_fee_amount),
+            ExportValue::Timestamp(Some(self.created_at)),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RefundExportRow {
+    pub id: i64,
+    pub payment_id: i32,
+    pub amount: f64,
+    pub currency: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub stripe_refund_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RefundExportRow {
+    /// Cells in the order of exports::REFUND_COLUMNS
+    pub fn into_values(self) -> Vec<ExportValue> {
+        vec![
+            ExportValue::Int(Some(self.id)),
+            ExportValue::Int(Some(self.payment_id as i64)),
+            ExportValue::Float(Some(self.amount)),
+            ExportValue::Text(Some(self.currency)),
+            ExportValue::Text(Some(self.status)),
+            ExportValue::Text(self.reason),
+            ExportValue::Text(self.stripe_refund_id),
+            ExportValue::Timestamp(Some(self.created_at)),
+        ]
+    }
+}
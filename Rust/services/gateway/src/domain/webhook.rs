@@ -0,0 +1,123 @@
+// Stripe webhook signatures: the Stripe-Signature header carries the send
+// time and an HMAC-SHA256 of "{timestamp}.{payload}" keyed with the
+// endpoint's signing secret
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+/// Events signed longer ago than this are treated as replays
+pub const DEFAULT_TOLERANCE_SECS: i64 = 300;
+
+#[derive(Clone)]
+pub struct WebhookVerifier {
+    secret: Option<String>,
+    tolerance_secs: i64,
+}
+
+impl WebhookVerifier {
+    pub fn new(secret: Option<String>, tolerance_secs: i64) -> Self {
+        Self { secret: secret.filter(|s| !s.is_empty()), tolerance_secs }
+    }
+
+    /// STRIPE_WEBHOOK_SECRET (whsec_...); without it every webhook is rejected
+    pub fn from_env() -> Self {
+        let secret = std::env::var("STRIPE_WEBHOOK_SECRET").ok();
+        if secret.as_deref().unwrap_or("").is_empty() {
+            tracing::warn!("STRIPE_WEBHOOK_SECRET is not set, Stripe webhooks will be rejected");
+        }
+        Self::new(secret, DEFAULT_TOLERANCE_SECS)
+    }
+
+    /// Ok when one of the header's v1 signatures matches the raw body and
+    /// its timestamp is within the tolerance of `now` (unix seconds)
+    pub fn verify(&self, header: Option<&str>, payload: &[u8], now: i64) -> Result<()> {
+        let secret = self.secret.as_deref()
+            .ok_or_else(|| anyhow!("Invalid webhook signature: no signing secret configured"))?;
+        let header = header.ok_or_else(|| anyhow!("Invalid webhook signature: missing Stripe-Signature header"))?;
+
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+                Some(("v1", sig)) => signatures.extend(hex::decode(sig).ok()),
+                _ => {}
+            }
+        }
+
+        let timestamp = timestamp.ok_or_else(|| anyhow!("Invalid webhook signature: missing timestamp"))?;
+        if (now - timestamp).abs() > self.tolerance_secs {
+            return Err(anyhow!("Invalid webhook signature: timestamp outside the tolerance"));
+        }
+
+        let expected = sign(secret, timestamp, payload);
+        if signatures.iter().any(|sig| bool::from(sig.as_slice().ct_eq(&expected))) {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid webhook signature: no matching signature"))
+        }
+    }
+}
+
+fn sign(secret: &str, timestamp: i64, payload: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "whsec_test";
+    const PAYLOAD: &[u8] = br#"{"type":"payout.failed"}"#;
+
+    fn header(timestamp: i64, payload: &[u8]) -> String {
+        format!("t={},v1={}", timestamp, hex::encode(sign(SECRET, timestamp, payload)))
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        let verifier = WebhookVerifier::new(Some(SECRET.to_string()), DEFAULT_TOLERANCE_SECS);
+        assert!(verifier.verify(Some(&header(1_000, PAYLOAD)), PAYLOAD, 1_100).is_ok());
+    }
+
+    #[test]
+    fn test_any_v1_signature_matches() {
+        let verifier = WebhookVerifier::new(Some(SECRET.to_string()), DEFAULT_TOLERANCE_SECS);
+        let header = format!("t=1000,v1={},{}", "00".repeat(32), header(1_000, PAYLOAD).split_once(',').unwrap().1);
+        assert!(verifier.verify(Some(&header), PAYLOAD, 1_000).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_tampered_payload() {
+        let verifier = WebhookVerifier::new(Some(SECRET.to_string()), DEFAULT_TOLERANCE_SECS);
+        let header = header(1_000, PAYLOAD);
+        assert!(verifier.verify(Some(&header), br#"{"type":"payout.paid"}"#, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_secret() {
+        let verifier = WebhookVerifier::new(Some("whsec_other".to_string()), DEFAULT_TOLERANCE_SECS);
+        assert!(verifier.verify(Some(&header(1_000, PAYLOAD)), PAYLOAD, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_rejects_old_timestamp() {
+        let verifier = WebhookVerifier::new(Some(SECRET.to_string()), DEFAULT_TOLERANCE_SECS);
+        assert!(verifier.verify(Some(&header(1_000, PAYLOAD)), PAYLOAD, 1_000 + DEFAULT_TOLERANCE_SECS + 1).is_err());
+    }
+
+    #[test]
+    fn test_rejects_missing_header_or_secret() {
+        let verifier = WebhookVerifier::new(Some(SECRET.to_string()), DEFAULT_TOLERANCE_SECS);
+        assert!(verifier.verify(None, PAYLOAD, 1_000).is_err());
+        assert!(verifier.verify(Some("v1=abcd"), PAYLOAD, 1_000).is_err());
+
+        let unconfigured = WebhookVerifier::new(None, DEFAULT_TOLERANCE_SECS);
+        assert!(unconfigured.verify(Some(&header(1_000, PAYLOAD)), PAYLOAD, 1_000).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use std::fs::File;
+use std::path::Path;
+
+use super::{ExportColumn, ExportValue, ExportWriter};
+
+pub struct CsvExportWriter {
+    writer: csv::Writer<File>,
+}
+
+impl CsvExportWriter {
+    pub fn create(path: &Path, columns: &[ExportColumn]) -> Result<Self> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(columns.iter().map(|c| c.name))?;
+        Ok(Self { writer })
+    }
+}
+
+impl ExportWriter for CsvExportWriter {
+    fn write_rows(&mut self, rows: &[Vec<ExportValue>]) -> Result<()> {
+        for row in rows {
+            self.writer.write_record(row.iter().map(to_field))?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn to_field(value: &ExportValue) -> String {
+    match value {
+        ExportValue::Int(v) => v.map(|v| v.to_string()).unwrap_or_default(),
+        ExportValue::Float(v) => v.map(|v| v.to_string()).unwrap_or_default(),
+        ExportValue::Text(v) => v.clone().unwrap_or_default(),
+        ExportValue::Timestamp(v) => v.map(|v| v.to_rfc3339()).unwrap_or_default(),
+    }
+}
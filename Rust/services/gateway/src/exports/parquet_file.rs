@@ -0,0 +1,109 @@
+use anyhow::{Result, anyhow};
+use parquet::basic::Compression;
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{ColumnType, ExportColumn, ExportValue, ExportWriter};
+
+/// Every batch of rows becomes one row group; all columns are nullable
+pub struct ParquetExportWriter {
+    writer: SerializedFileWriter<File>,
+    columns: &'static [ExportColumn],
+}
+
+impl ParquetExportWriter {
+    pub fn create(path: &Path, columns: &'static [ExportColumn]) -> Result<Self> {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|c| match c.column_type {
+                ColumnType::Int => format!("OPTIONAL INT64 {};", c.name),
+                ColumnType::Float => format!("OPTIONAL DOUBLE {};", c.name),
+                ColumnType::Text => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", c.name),
+                ColumnType::Timestamp => format!("OPTIONAL INT64 {} (TIMESTAMP(MILLIS,true));", c.name),
+            })
+            .collect();
+        let schema = parse_message_type(&format!("message export {{ {} }}", fields.join(" ")))?;
+
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        let writer = SerializedFileWriter::new(File::create(path)?, Arc::new(schema), Arc::new(props))?;
+        Ok(Self { writer, columns })
+    }
+}
+
+impl ExportWriter for ParquetExportWriter {
+    fn write_rows(&mut self, rows: &[Vec<ExportValue>]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut row_group = self.writer.next_row_group()?;
+        let mut index = 0;
+
+        while let Some(mut column) = row_group.next_column()? {
+            let name = self.columns.get(index).map(|c| c.name).unwrap_or("?");
+            let cells = rows.iter().map(|row| row.get(index));
+            let mut def_levels = Vec::with_capacity(rows.len());
+
+            match column.untyped() {
+                ColumnWriter::Int64ColumnWriter(writer) => {
+                    let values: Vec<i64> = cells
+                        .map(|cell| match cell {
+                            Some(ExportValue::Int(v)) => *v,
+                            Some(ExportValue::Timestamp(v)) => v.map(|t| t.timestamp_millis()),
+                            _ => None,
+                        })
+                        .filter_map(|v| present(v, &mut def_levels))
+                        .collect();
+                    writer.write_batch(&values, Some(&def_levels), None)?;
+                }
+                ColumnWriter::DoubleColumnWriter(writer) => {
+                    let values: Vec<f64> = cells
+                        .map(|cell| match cell {
+                            Some(ExportValue::Float(v)) => *v,
+                            _ => None,
+                        })
+                        .filter_map(|v| present(v, &mut def_levels))
+                        .collect();
+                    writer.write_batch(&values, Some(&def_levels), None)?;
+                }
+                ColumnWriter::ByteArrayColumnWriter(writer) => {
+                    let values: Vec<ByteArray> = cells
+                        .map(|cell| match cell {
+                            Some(ExportValue::Text(v)) => v.as_deref().map(ByteArray::from),
+                            _ => None,
+                        })
+                        .filter_map(|v| present(v, &mut def_levels))
+                        .collect();
+                    writer.write_batch(&values, Some(&def_levels), None)?;
+                }
+                _ => return Err(anyhow!("Unsupported parquet column type for {}", name)),
+            }
+
+            column.close()?;
+            index += 1;
+        }
+
+        row_group.close()?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+// Record the definition level (1 = value, 0 = null) and keep only present values
+fn present<T>(value: Option<T>, def_levels: &mut Vec<i16>) -> Option<T> {
+    def_levels.push(if value.is_some() { 1 } else { 0 });
+    value
+}
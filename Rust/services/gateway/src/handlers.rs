@@ -1,7 +1,18 @@
 use actix_web::{web, HttpResponse, Responder, HttpRequest};
 use serde::{Deserialize, Serialize};
 use authz::Claims;
-use crate::service::PaymentService;
+use apikeys::ApiKeyPrincipal;
+use chrono::NaiveDate;
+use crate::domain::{
+    AuditActor, AuditContext, AuditEntry, Dispute, ExportFilters, ExportJob, Invoice, InvoiceLineItem, Refund,
+    WebhookVerifier,
+};
+use crate::invoices::InvoiceDraft;
+use crate::service::{
+    AuditService, ExportService, InvoiceService, NewPayment, PaymentService, PayoutService, ReconciliationService,
+    RefundService, ReportGroupBy, ReportService,
+};
+use crate::statements::StatementFormat;
 
 #[derive(Serialize)]
 struct HealthResponse {
@@ -15,6 +26,11 @@ pub struct CreatePaymentRequest {
     pub amount: f64,
     pub currency: Option<String>,
     pub payment_method: Option<String>,
+    /// Connected seller account receiving the payment minus the application fee
+    pub destination_account_id: Option<i32>,
+    pub application_fee_amount: Option<f64>,
+    /// "manual" authorizes the card now and leaves the capture to a later call
+    pub capture_method: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -38,6 +54,67 @@ pub struct PaymentStatusResponse {
     pub stripe_payment_intent_id: String,
 }
 
+#[derive(Deserialize)]
+pub struct CancelPaymentRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AuditTrailResponse {
+    pub payment_id: i32,
+    pub stripe_payment_intent_id: String,
+    pub intact: bool, // every entry still matches its hash
+    pub entries: Vec<AuditEntry>,
+}
+
+/// Request id from the X-Request-Id header, or a new one
+fn request_id(req: &HttpRequest) -> String {
+    req.headers()
+        .get("X-Request-Id")
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 64)
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn audit_context(req: &HttpRequest, actor: AuditActor) -> AuditContext {
+    AuditContext {
+        actor,
+        // Client address as reported by the proxy in front of the gateway
+        source_ip: req.connection_info().realip_remote_addr().map(|ip| ip.to_string()),
+        request_id: request_id(req),
+    }
+}
+
+/// Money leaving the platform above this amount needs a recent second factor
+fn step_up_threshold() -> f64 {
+    std::env::var("STEP_UP_TRANSFER_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1000.0)
+}
+
+/// 403 unless the token carries a recent second factor. `None` amounts
+/// (whole balance) always count as large.
+fn require_step_up(claims: &Claims, amount: Option<f64>) -> Option<HttpResponse> {
+    let large = amount.is_none_or(|a| a > step_up_threshold());
+    if !large || claims.has_recent_mfa(authz::step_up_max_age()) {
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Recent two-factor authentication required, use /api/v1/auth/mfa/step-up",
+        "step_up_required": true
+    })))
+}
+
+/// 403 telling the client that identity verification raises the limit
+fn payment_limit_response(error_msg: String) -> HttpResponse {
+    HttpResponse::Forbidden().json(serde_json::json!({
+        "error": error_msg,
+        "kyc_required": true
+    }))
+}
+
 pub async fn health_check() -> impl Responder {
     let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string());
     
@@ -51,15 +128,43 @@ pub async fn health_check() -> impl Responder {
 pub async fn create_payment(
     claims: web::ReqData<Claims>,
     payment_service: web::Data<PaymentService>,
+    req: HttpRequest,
     request: web::Json<CreatePaymentRequest>,
 ) -> impl Responder {
     let user_id = claims.user_id;
     tracing::info!("Creating payment for user: {} ({})", claims.sub, user_id);
+
+    // Payments routed to a connected account move funds out of the user's control
+    if request.destination_account_id.is_some() {
+        if let Some(response) = require_step_up(&claims, Some(request.amount)) {
+            return response;
+        }
+    }
     
     let currency = request.currency.clone().unwrap_or_else(|| "USD".to_string());
     let payment_method = request.payment_method.clone().unwrap_or_else(|| "card".to_string());
+    let manual_capture = match request.capture_method.as_deref() {
+        None | Some("automatic") => false,
+        Some("manual") => true,
+        Some(_) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid capture_method. Must be 'automatic' or 'manual'"
+            }));
+        }
+    };
+
+    let new_payment = NewPayment {
+        user_id,
+        amount: request.amount,
+        currency: currency.clone(),
+        payment_method,
+        destination_account_id: request.destination_account_id,
+        application_fee_amount: request.application_fee_amount,
+        manual_capture,
+    };
+    let ctx = audit_context(&req, AuditActor::user(user_id));
 
-    match payment_service.create_payment(user_id, request.amount, &currency, &payment_method).await {
+    match payment_service.create_payment(new_payment, &ctx).await {
         Ok((payment_id, client_secret, stripe_payment_intent_id)) => {
             HttpResponse::Created().json(CreatePaymentResponse {
                 id: payment_id,
@@ -73,28 +178,36 @@ pub async fn create_payment(
         }
         Err(e) => {
             tracing::error!("Payment creation error: {}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to create payment: {}", e)
-            }))
+            let error_msg = e.to_string();
+
+            if error_msg.contains("Connected account") || error_msg.contains("Invalid application fee") {
+                HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+            } else if error_msg.starts_with("Payment limit exceeded") {
+                payment_limit_response(error_msg)
+            } else if error_msg.starts_with("Payments to this recipient") {
+                HttpResponse::Forbidden().json(serde_json::json!({ "error": error_msg }))
+            } else if error_msg.starts_with("Recipient check unavailable") {
+                HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                    "error": "Could not check the recipient, try again later"
+                }))
+            } else {
+                HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": format!("Failed to create payment: {}", e)
+                }))
+            }
         }
     }
 }
 
 pub async fn retrieve_payment(
+    claims: web::ReqData<Claims>,
     payment_service: web::Data<PaymentService>,
+    req: HttpRequest,
     intent_id: web::Path<String>,
 ) -> impl Responder {
-    match payment_service.retrieve_payment(&intent_id).await {
-        Ok(payment) => {
-            HttpResponse::Ok().json(PaymentStatusResponse {
-                id: payment.id,
-                user_id: payment.user_id,
-                amount: payment.amount,
-                currency: payment.currency,
-                status: payment.status,
-                stripe_payment_intent_id: payment.stripe_payment_intent_id.unwrap_or_default(),
-            })
-        }
+    let ctx = audit_context(&req, AuditActor::user(claims.user_id));
+    match payment_service.retrieve_payment(&intent_id, &ctx).await {
+        Ok(payment) => HttpResponse::Ok().json(payment_status_response(payment)),
         Err(e) => {
             tracing::error!("Payment retrieval error: {}", e);
             HttpResponse::NotFound().json(serde_json::json!({
@@ -104,6 +217,144 @@ pub async fn retrieve_payment(
     }
 }
 
+/// Payment status for backend services calling with an API key
+pub async fn retrieve_payment_internal(
+    principal: ApiKeyPrincipal,
+    payment_service: web::Data<PaymentService>,
+    req: HttpRequest,
+    intent_id: web::Path<String>,
+) -> impl Responder {
+    let ctx = audit_context(&req, AuditActor::api_key(&principal.prefix));
+    match payment_service.retrieve_payment(&intent_id, &ctx).await {
+        Ok(payment) => HttpResponse::Ok().json(payment_status_response(payment)),
+        Err(e) => {
+            tracing::error!("Payment retrieval error for API key {}: {}", principal.prefix, e);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Payment not found"
+            }))
+        }
+    }
+}
+
+pub async fn capture_payment(
+    claims: web::ReqData<Claims>,
+    payment_service: web::Data<PaymentService>,
+    req: HttpRequest,
+    intent_id: web::Path<String>,
+) -> impl Responder {
+    let ctx = audit_context(&req, AuditActor::user(claims.user_id));
+    match payment_service.capture_payment(&intent_id, claims.user_id, &ctx).await {
+        Ok(payment) => HttpResponse::Ok().json(payment_status_response(payment)),
+        Err(e) => {
+            tracing::error!("Payment capture error: {}", e);
+            payment_action_error(e)
+        }
+    }
+}
+
+pub async fn cancel_payment(
+    claims: web::ReqData<Claims>,
+    payment_service: web::Data<PaymentService>,
+    req: HttpRequest,
+    intent_id: web::Path<String>,
+    request: Option<web::Json<CancelPaymentRequest>>,
+) -> impl Responder {
+    let ctx = audit_context(&req, AuditActor::user(claims.user_id));
+    let reason = request.as_ref().and_then(|r| r.reason.as_deref());
+    match payment_service.cancel_payment(&intent_id, claims.user_id, reason, &ctx).await {
+        Ok(payment) => HttpResponse::Ok().json(payment_status_response(payment)),
+        Err(e) => {
+            tracing::error!("Payment cancel error: {}", e);
+            payment_action_error(e)
+        }
+    }
+}
+
+// Admins acting on payments they do not own; each call is audited as an override
+#[derive(Deserialize)]
+pub struct AdminCancelRequest {
+    pub reason: Option<String>,
+    pub note: String, // why the admin stepped in
+}
+
+pub async fn admin_cancel_payment(
+    claims: web::ReqData<Claims>,
+    payment_service: web::Data<PaymentService>,
+    req: HttpRequest,
+    intent_id: web::Path<String>,
+    request: web::Json<AdminCancelRequest>,
+) -> impl Responder {
+    let ctx = audit_context(&req, AuditActor::user(claims.user_id));
+    match payment_service
+        .override_cancel(&intent_id, request.reason.as_deref(), &request.note, &ctx)
+        .await
+    {
+        Ok(payment) => HttpResponse::Ok().json(payment_status_response(payment)),
+        Err(e) => {
+            tracing::error!("Admin payment cancel error: {}", e);
+            payment_action_error(e)
+        }
+    }
+}
+
+pub async fn get_payment_audit_trail(
+    claims: web::ReqData<Claims>,
+    audit_service: web::Data<AuditService>,
+    intent_id: web::Path<String>,
+) -> impl Responder {
+    match audit_service.payment_trail(&intent_id, claims.user_id).await {
+        Ok((payment, entries, intact)) => HttpResponse::Ok().json(AuditTrailResponse {
+            payment_id: payment.id,
+            stripe_payment_intent_id: payment.stripe_payment_intent_id.unwrap_or_default(),
+            intact,
+            entries,
+        }),
+        Err(e) => {
+            tracing::error!("Audit trail error: {}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("not found") {
+                HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+            } else {
+                HttpResponse::InternalServerError().json(serde_json::json!({ "error": "Failed to load audit trail" }))
+            }
+        }
+    }
+}
+
+pub async fn verify_audit_chain(audit_service: web::Data<AuditService>) -> impl Responder {
+    match audit_service.verify_chain().await {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(e) => {
+            tracing::error!("Audit chain verification error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": "Failed to verify audit chain" }))
+        }
+    }
+}
+
+fn payment_status_response(payment: crate::domain::Payment) -> PaymentStatusResponse {
+    PaymentStatusResponse {
+        id: payment.id,
+        user_id: payment.user_id,
+        amount: payment.amount,
+        currency: payment.currency,
+        status: payment.status,
+        stripe_payment_intent_id: payment.stripe_payment_intent_id.unwrap_or_default(),
+    }
+}
+
+fn payment_action_error(e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Stripe API error") {
+        HttpResponse::BadGateway().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    }
+}
+
 #[derive(Deserialize)]
 pub struct StripeWebhook {
     #[serde(rename = "type")]
@@ -113,26 +364,91 @@ pub struct StripeWebhook {
 
 pub async fn stripe_webhook(
     payment_service: web::Data<PaymentService>,
-    _req: HttpRequest,
-    payload: web::Json<StripeWebhook>,
+    payout_service: web::Data<PayoutService>,
+    invoice_service: web::Data<InvoiceService>,
+    refund_service: web::Data<RefundService>,
+    verifier: web::Data<WebhookVerifier>,
+    req: HttpRequest,
+    body: web::Bytes,
 ) -> impl Responder {
+    let signature = req.headers().get("Stripe-Signature").and_then(|h| h.to_str().ok());
+    if let Err(e) = verifier.verify(signature, &body, chrono::Utc::now().timestamp()) {
+        tracing::warn!("Rejected Stripe webhook: {}", e);
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }));
+    }
+    let payload: StripeWebhook = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": format!("Invalid webhook payload: {}", e) }));
+        }
+    };
+
     tracing::info!("Received Stripe webhook: {}", payload.event_type);
+    let ctx = audit_context(&req, AuditActor::stripe());
 
     match payload.event_type.as_str() {
         "payment_intent.succeeded" => {
             if let Some(intent_id) = payload.data.get("object").and_then(|o| o.get("id")).and_then(|id| id.as_str()) {
-                if let Err(e) = payment_service.update_payment_status(intent_id, "succeeded").await {
+                if let Err(e) = payment_service.update_payment_status(intent_id, "succeeded", &ctx).await {
                     tracing::error!("Failed to update payment: {}", e);
                 }
+                if let Err(e) = invoice_service.handle_payment_succeeded(intent_id).await {
+                    tracing::error!("Failed to update invoice: {}", e);
+                }
             }
         }
         "payment_intent.payment_failed" => {
             if let Some(intent_id) = payload.data.get("object").and_then(|o| o.get("id")).and_then(|id| id.as_str()) {
-                if let Err(e) = payment_service.update_payment_status(intent_id, "failed").await {
+                if let Err(e) = payment_service.update_payment_status(intent_id, "failed", &ctx).await {
                     tracing::error!("Failed to update payment: {}", e);
                 }
             }
         }
+        "payout.paid" | "payout.failed" | "payout.canceled" | "payout.updated" => {
+            if let Some(payout_id) = payload.data.get("object").and_then(|o| o.get("id")).and_then(|id| id.as_str()) {
+                if let Err(e) = payout_service.sync_payout(payout_id).await {
+                    tracing::error!("Failed to update payout: {}", e);
+                }
+            }
+        }
+        "charge.refund.updated" | "refund.updated" | "refund.failed" => {
+            let object = payload.data.get("object");
+            let refund_id = object.and_then(|o| o.get("id")).and_then(|id| id.as_str());
+            let status = object.and_then(|o| o.get("status")).and_then(|s| s.as_str());
+            let failure_reason = object.and_then(|o| o.get("failure_reason")).and_then(|r| r.as_str());
+
+            if let (Some(refund_id), Some(status)) = (refund_id, status) {
+                if let Err(e) = refund_service.update_refund_status(refund_id, status, failure_reason, &ctx).await {
+                    tracing::error!("Failed to update refund: {}", e);
+                }
+            }
+        }
+        "charge.dispute.created" | "charge.dispute.updated" | "charge.dispute.closed" => {
+            let object = payload.data.get("object");
+            let dispute_id = object.and_then(|o| o.get("id")).and_then(|id| id.as_str());
+            let intent_id = object.and_then(|o| o.get("payment_intent")).and_then(|p| p.as_str());
+            let amount = object.and_then(|o| o.get("amount")).and_then(|a| a.as_i64()).unwrap_or(0);
+            let currency = object.and_then(|o| o.get("currency")).and_then(|c| c.as_str()).unwrap_or("usd");
+            let status = object.and_then(|o| o.get("status")).and_then(|s| s.as_str()).unwrap_or("needs_response");
+            let reason = object.and_then(|o| o.get("reason")).and_then(|r| r.as_str());
+
+            if let (Some(dispute_id), Some(intent_id)) = (dispute_id, intent_id) {
+                if let Err(e) = refund_service.record_dispute(dispute_id, intent_id, amount, currency, status, reason, &ctx).await {
+                    tracing::error!("Failed to record dispute: {}", e);
+                }
+            }
+        }
+        "account.updated" => {
+            let object = payload.data.get("object");
+            let account_id = object.and_then(|o| o.get("id")).and_then(|id| id.as_str());
+            let payouts_enabled = object.and_then(|o| o.get("payouts_enabled")).and_then(|p| p.as_bool()).unwrap_or(false);
+
+            if let Some(account_id) = account_id {
+                if let Err(e) = payout_service.update_account_status(account_id, payouts_enabled).await {
+                    tracing::error!("Failed to update connected account: {}", e);
+                }
+            }
+        }
         _ => {
             tracing::info!("Unhandled webhook event: {}", payload.event_type);
         }
@@ -142,3 +458,681 @@ pub async fn stripe_webhook(
         "received": true
     }))
 }
+
+// ============================================
+// Bank Statement Reconciliation Handlers
+// ============================================
+
+#[derive(Deserialize)]
+pub struct ImportStatementQuery {
+    pub format: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewQueueQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolveLineRequest {
+    pub action: String, // match, ignore
+    pub target_type: Option<String>, // payment, payout, transfer (by payment id)
+    pub target_id: Option<i64>,
+    pub note: Option<String>,
+}
+
+pub async fn import_statement(
+    claims: web::ReqData<Claims>,
+    reconciliation_service: web::Data<ReconciliationService>,
+    query: web::Query<ImportStatementQuery>,
+    body: String,
+) -> impl Responder {
+    let format = match query.format.as_deref() {
+        Some(f) => match StatementFormat::parse(f) {
+            Some(format) => Some(format),
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": "Unsupported format. Use 'camt053' or 'mt940'"
+                }));
+            }
+        },
+        None => None,
+    };
+
+    match reconciliation_service.import(format, &body, claims.user_id).await {
+        Ok(summary) => HttpResponse::Created().json(summary),
+        Err(e) => {
+            tracing::error!("Statement import error: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Failed to import statement: {}", e)
+            }))
+        }
+    }
+}
+
+pub async fn get_statement_lines(
+    reconciliation_service: web::Data<ReconciliationService>,
+    statement_id: web::Path<i64>,
+) -> impl Responder {
+    match reconciliation_service.statement_lines(statement_id.into_inner()).await {
+        Ok(lines) => HttpResponse::Ok().json(lines),
+        Err(e) => {
+            tracing::error!("Statement retrieval error: {}", e);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Statement not found"
+            }))
+        }
+    }
+}
+
+pub async fn get_review_queue(
+    reconciliation_service: web::Data<ReconciliationService>,
+    query: web::Query<ReviewQueueQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match reconciliation_service.review_queue(limit, offset).await {
+        Ok(lines) => HttpResponse::Ok().json(lines),
+        Err(e) => {
+            tracing::error!("Review queue error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to fetch review queue"
+            }))
+        }
+    }
+}
+
+pub async fn resolve_statement_line(
+    claims: web::ReqData<Claims>,
+    reconciliation_service: web::Data<ReconciliationService>,
+    line_id: web::Path<i64>,
+    request: web::Json<ResolveLineRequest>,
+) -> impl Responder {
+    let line_id = line_id.into_inner();
+    let note = request.note.as_deref();
+
+    let result = match request.action.as_str() {
+        "match" => match (request.target_type.as_deref(), request.target_id) {
+            (Some(target_type), Some(target_id)) => {
+                reconciliation_service
+                    .match_line_manually(line_id, target_type, target_id, claims.user_id, note)
+                    .await
+            }
+            _ => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": "target_type and target_id are required to match a line"
+                }));
+            }
+        },
+        "ignore" => reconciliation_service.ignore_line(line_id, claims.user_id, note).await,
+        _ => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid action. Must be 'match' or 'ignore'"
+            }));
+        }
+    };
+
+    match result {
+        Ok(line) => HttpResponse::Ok().json(line),
+        Err(e) => {
+            tracing::error!("Statement line resolution error: {}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("not found") {
+                HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+            } else if error_msg.contains("already") {
+                HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+            } else {
+                HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+            }
+        }
+    }
+}
+
+// ============================================
+// Marketplace Payout Handlers
+// ============================================
+
+#[derive(Deserialize)]
+pub struct RegisterAccountRequest {
+    pub display_name: String,
+    pub default_currency: Option<String>,
+    pub payout_schedule: Option<String>, // daily, weekly, manual
+    /// Two-letter country of the seller, the platform's country by default
+    pub country: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePayoutRequest {
+    pub amount: Option<f64>,
+    pub currency: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ListPayoutsQuery {
+    pub limit: Option<i64>,
+}
+
+fn connected_account_error(e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Stripe API error") {
+        HttpResponse::BadGateway().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    }
+}
+
+/// Creates the seller's Stripe account and returns the link to its onboarding
+pub async fn register_connected_account(
+    claims: web::ReqData<Claims>,
+    payout_service: web::Data<PayoutService>,
+    request: web::Json<RegisterAccountRequest>,
+) -> impl Responder {
+    let currency = request.default_currency.clone().unwrap_or_else(|| "USD".to_string());
+    let schedule = request.payout_schedule.clone().unwrap_or_else(|| "weekly".to_string());
+
+    match payout_service
+        .register_account(claims.user_id, &request.display_name, &currency, &schedule, request.country.as_deref())
+        .await
+    {
+        Ok((account, link)) => HttpResponse::Created().json(serde_json::json!({
+            "account": account,
+            "onboarding_url": link.url,
+            "onboarding_url_expires_at": link.expires_at,
+        })),
+        Err(e) => {
+            tracing::error!("Connected account registration error: {}", e);
+            connected_account_error(e)
+        }
+    }
+}
+
+pub async fn create_onboarding_link(
+    claims: web::ReqData<Claims>,
+    payout_service: web::Data<PayoutService>,
+    account_id: web::Path<i32>,
+) -> impl Responder {
+    match payout_service.onboarding_link(account_id.into_inner(), claims.user_id).await {
+        Ok(link) => HttpResponse::Ok().json(serde_json::json!({
+            "onboarding_url": link.url,
+            "onboarding_url_expires_at": link.expires_at,
+        })),
+        Err(e) => {
+            tracing::error!("Onboarding link error: {}", e);
+            connected_account_error(e)
+        }
+    }
+}
+
+pub async fn list_connected_accounts(
+    claims: web::ReqData<Claims>,
+    payout_service: web::Data<PayoutService>,
+) -> impl Responder {
+    match payout_service.list_accounts(claims.user_id).await {
+        Ok(accounts) => HttpResponse::Ok().json(accounts),
+        Err(e) => {
+            tracing::error!("Connected account listing error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to fetch connected accounts"
+            }))
+        }
+    }
+}
+
+pub async fn get_seller_balance(
+    claims: web::ReqData<Claims>,
+    payout_service: web::Data<PayoutService>,
+    account_id: web::Path<i32>,
+) -> impl Responder {
+    match payout_service.balance(account_id.into_inner(), claims.user_id).await {
+        Ok(balances) => HttpResponse::Ok().json(balances),
+        Err(e) => {
+            tracing::error!("Seller balance error: {}", e);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Connected account not found"
+            }))
+        }
+    }
+}
+
+pub async fn list_payouts(
+    claims: web::ReqData<Claims>,
+    payout_service: web::Data<PayoutService>,
+    account_id: web::Path<i32>,
+    query: web::Query<ListPayoutsQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    match payout_service.list_payouts(account_id.into_inner(), claims.user_id, limit).await {
+        Ok(payouts) => HttpResponse::Ok().json(payouts),
+        Err(e) => {
+            tracing::error!("Payout listing error: {}", e);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Connected account not found"
+            }))
+        }
+    }
+}
+
+pub async fn create_payout(
+    claims: web::ReqData<Claims>,
+    payout_service: web::Data<PayoutService>,
+    account_id: web::Path<i32>,
+    request: web::Json<CreatePayoutRequest>,
+) -> impl Responder {
+    if let Some(response) = require_step_up(&claims, request.amount) {
+        return response;
+    }
+
+    match payout_service
+        .request_payout(account_id.into_inner(), claims.user_id, request.amount, request.currency.as_deref())
+        .await
+    {
+        Ok(payout) => HttpResponse::Created().json(payout),
+        Err(e) => {
+            tracing::error!("Payout creation error: {}", e);
+            connected_account_error(e)
+        }
+    }
+}
+
+// ============================================
+// Invoice Handlers
+// ============================================
+
+#[derive(Deserialize)]
+pub struct ListInvoicesQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct InvoiceResponse {
+    #[serde(flatten)]
+    pub invoice: Invoice,
+    pub items: Vec<InvoiceLineItem>,
+}
+
+#[derive(Serialize)]
+pub struct PayInvoiceResponse {
+    pub invoice: Invoice,
+    pub client_secret: String,
+    pub stripe_payment_intent_id: String,
+}
+
+fn invoice_error(e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+
+    if error_msg.contains("not found") {
+        HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Only the billed customer") {
+        HttpResponse::Forbidden().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.starts_with("Payment limit exceeded") {
+        payment_limit_response(error_msg)
+    } else if error_msg.contains("Invalid invoice status") || error_msg.contains("no longer a draft") || error_msg.contains("status changed") {
+        HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+    } else if error_msg.contains("Invalid invoice") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        HttpResponse::InternalServerError().json(serde_json::json!({ "error": error_msg }))
+    }
+}
+
+pub async fn create_invoice(
+    claims: web::ReqData<Claims>,
+    invoice_service: web::Data<InvoiceService>,
+    request: web::Json<InvoiceDraft>,
+) -> impl Responder {
+    match invoice_service.create_invoice(claims.user_id, &request).await {
+        Ok(invoice) => HttpResponse::Created().json(invoice),
+        Err(e) => {
+            tracing::error!("Invoice creation error: {}", e);
+            invoice_error(e)
+        }
+    }
+}
+
+pub async fn list_invoices(
+    claims: web::ReqData<Claims>,
+    invoice_service: web::Data<InvoiceService>,
+    query: web::Query<ListInvoicesQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match invoice_service.list_invoices(claims.user_id, limit, offset).await {
+        Ok(invoices) => HttpResponse::Ok().json(invoices),
+        Err(e) => {
+            tracing::error!("Invoice listing error: {}", e);
+            invoice_error(e)
+        }
+    }
+}
+
+pub async fn get_invoice(
+    claims: web::ReqData<Claims>,
+    invoice_service: web::Data<InvoiceService>,
+    invoice_id: web::Path<i64>,
+) -> impl Responder {
+    match invoice_service.get_invoice(invoice_id.into_inner(), claims.user_id).await {
+        Ok((invoice, items)) => HttpResponse::Ok().json(InvoiceResponse { invoice, items }),
+        Err(e) => {
+            tracing::error!("Invoice retrieval error: {}", e);
+            invoice_error(e)
+        }
+    }
+}
+
+pub async fn finalize_invoice(
+    claims: web::ReqData<Claims>,
+    invoice_service: web::Data<InvoiceService>,
+    invoice_id: web::Path<i64>,
+) -> impl Responder {
+    match invoice_service.finalize_invoice(invoice_id.into_inner(), claims.user_id).await {
+        Ok(invoice) => HttpResponse::Ok().json(invoice),
+        Err(e) => {
+            tracing::error!("Invoice finalize error: {}", e);
+            invoice_error(e)
+        }
+    }
+}
+
+pub async fn void_invoice(
+    claims: web::ReqData<Claims>,
+    invoice_service: web::Data<InvoiceService>,
+    invoice_id: web::Path<i64>,
+) -> impl Responder {
+    match invoice_service.void_invoice(invoice_id.into_inner(), claims.user_id).await {
+        Ok(invoice) => HttpResponse::Ok().json(invoice),
+        Err(e) => {
+            tracing::error!("Invoice void error: {}", e);
+            invoice_error(e)
+        }
+    }
+}
+
+pub async fn pay_invoice(
+    claims: web::ReqData<Claims>,
+    invoice_service: web::Data<InvoiceService>,
+    req: HttpRequest,
+    invoice_id: web::Path<i64>,
+) -> impl Responder {
+    let ctx = audit_context(&req, AuditActor::user(claims.user_id));
+    match invoice_service.pay_invoice(invoice_id.into_inner(), claims.user_id, &ctx).await {
+        Ok((invoice, client_secret, stripe_payment_intent_id)) => HttpResponse::Ok().json(PayInvoiceResponse {
+            invoice,
+            client_secret,
+            stripe_payment_intent_id,
+        }),
+        Err(e) => {
+            tracing::error!("Invoice payment error: {}", e);
+            invoice_error(e)
+        }
+    }
+}
+
+pub async fn get_invoice_pdf(
+    claims: web::ReqData<Claims>,
+    invoice_service: web::Data<InvoiceService>,
+    invoice_id: web::Path<i64>,
+) -> impl Responder {
+    match invoice_service.render_pdf(invoice_id.into_inner(), claims.user_id).await {
+        Ok((filename, pdf)) => HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header(("Content-Disposition", format!("inline; filename=\"{}\"", filename)))
+            .body(pdf),
+        Err(e) => {
+            tracing::error!("Invoice PDF error: {}", e);
+            invoice_error(e)
+        }
+    }
+}
+
+// ============================================
+// Refund Handlers
+// ============================================
+
+#[derive(Deserialize)]
+pub struct CreateRefundRequest {
+    pub amount: Option<f64>, // defaults to the remaining refundable amount
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RefundListResponse {
+    pub refunds: Vec<Refund>,
+    pub disputes: Vec<Dispute>,
+}
+
+pub async fn create_refund(
+    claims: web::ReqData<Claims>,
+    refund_service: web::Data<RefundService>,
+    req: HttpRequest,
+    intent_id: web::Path<String>,
+    request: web::Json<CreateRefundRequest>,
+) -> impl Responder {
+    let ctx = audit_context(&req, AuditActor::user(claims.user_id));
+    match refund_service
+        .create_refund(claims.user_id, &intent_id, request.amount, request.reason.as_deref(), &ctx)
+        .await
+    {
+        Ok(refund) => HttpResponse::Created().json(refund),
+        Err(e) => {
+            tracing::error!("Refund creation error: {}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("not found") {
+                HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+            } else if error_msg.contains("Stripe API error") {
+                HttpResponse::BadGateway().json(serde_json::json!({ "error": error_msg }))
+            } else {
+                HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AdminRefundRequest {
+    pub amount: Option<f64>,
+    pub reason: Option<String>,
+    pub note: String, // why the admin stepped in
+}
+
+pub async fn admin_create_refund(
+    claims: web::ReqData<Claims>,
+    refund_service: web::Data<RefundService>,
+    req: HttpRequest,
+    intent_id: web::Path<String>,
+    request: web::Json<AdminRefundRequest>,
+) -> impl Responder {
+    let ctx = audit_context(&req, AuditActor::user(claims.user_id));
+    match refund_service
+        .override_refund(claims.user_id, &intent_id, request.amount, request.reason.as_deref(), &request.note, &ctx)
+        .await
+    {
+        Ok(refund) => HttpResponse::Created().json(refund),
+        Err(e) => {
+            tracing::error!("Admin refund error: {}", e);
+            payment_action_error(e)
+        }
+    }
+}
+
+pub async fn list_refunds(
+    claims: web::ReqData<Claims>,
+    refund_service: web::Data<RefundService>,
+    intent_id: web::Path<String>,
+) -> impl Responder {
+    match refund_service.list_refunds(claims.user_id, &intent_id).await {
+        Ok((refunds, disputes)) => HttpResponse::Ok().json(RefundListResponse { refunds, disputes }),
+        Err(e) => {
+            tracing::error!("Refund listing error: {}", e);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Payment not found"
+            }))
+        }
+    }
+}
+
+// ============================================
+// Reporting & Export Handlers
+// ============================================
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    pub group_by: Option<String>, // day, currency, payment_method
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateExportRequest {
+    pub kind: String,   // payments, refunds
+    pub format: String, // csv, parquet
+    #[serde(flatten)]
+    pub filters: ExportFilters,
+}
+
+#[derive(Serialize)]
+pub struct ExportJobResponse {
+    #[serde(flatten)]
+    pub job: ExportJob,
+    pub download_url: Option<String>,
+}
+
+impl From<ExportJob> for ExportJobResponse {
+    fn from(job: ExportJob) -> Self {
+        let download_url = (job.status == "completed").then(|| format!("/api/v1/exports/{}/download", job.id));
+        Self { job, download_url }
+    }
+}
+
+pub async fn get_payment_report(
+    report_service: web::Data<ReportService>,
+    query: web::Query<ReportQuery>,
+) -> impl Responder {
+    let group_by = match ReportGroupBy::parse(query.group_by.as_deref().unwrap_or("day")) {
+        Some(group_by) => group_by,
+        None => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid group_by. Must be 'day', 'currency' or 'payment_method'"
+            }));
+        }
+    };
+
+    match report_service.payment_report(group_by, query.from, query.to).await {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            tracing::error!("Payment report error: {}", e);
+            report_error(e)
+        }
+    }
+}
+
+pub async fn get_refund_dispute_report(
+    report_service: web::Data<ReportService>,
+    query: web::Query<ReportQuery>,
+) -> impl Responder {
+    match report_service.refund_dispute_report(query.from, query.to).await {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            tracing::error!("Refund/dispute report error: {}", e);
+            report_error(e)
+        }
+    }
+}
+
+fn report_error(e: anyhow::Error) -> HttpResponse {
+    let error_msg = e.to_string();
+
+    if error_msg.contains("Invalid") {
+        HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+    } else {
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to build report"
+        }))
+    }
+}
+
+pub async fn create_export(
+    claims: web::ReqData<Claims>,
+    export_service: web::Data<ExportService>,
+    request: web::Json<CreateExportRequest>,
+) -> impl Responder {
+    match export_service
+        .request_export(claims.user_id, &request.kind, &request.format, &request.filters)
+        .await
+    {
+        Ok(job) => HttpResponse::Accepted().json(ExportJobResponse::from(job)),
+        Err(e) => {
+            tracing::error!("Export request error: {}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("queue is full") {
+                HttpResponse::ServiceUnavailable().json(serde_json::json!({ "error": error_msg }))
+            } else {
+                HttpResponse::BadRequest().json(serde_json::json!({ "error": error_msg }))
+            }
+        }
+    }
+}
+
+pub async fn list_exports(
+    claims: web::ReqData<Claims>,
+    export_service: web::Data<ExportService>,
+) -> impl Responder {
+    match export_service.list_jobs(claims.user_id, 50).await {
+        Ok(jobs) => HttpResponse::Ok().json(jobs.into_iter().map(ExportJobResponse::from).collect::<Vec<_>>()),
+        Err(e) => {
+            tracing::error!("Export listing error: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to fetch exports"
+            }))
+        }
+    }
+}
+
+pub async fn get_export(
+    claims: web::ReqData<Claims>,
+    export_service: web::Data<ExportService>,
+    export_id: web::Path<i64>,
+) -> impl Responder {
+    match export_service.get_job(export_id.into_inner(), claims.user_id).await {
+        Ok(job) => HttpResponse::Ok().json(ExportJobResponse::from(job)),
+        Err(e) => {
+            tracing::error!("Export retrieval error: {}", e);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Export not found"
+            }))
+        }
+    }
+}
+
+pub async fn download_export(
+    claims: web::ReqData<Claims>,
+    export_service: web::Data<ExportService>,
+    export_id: web::Path<i64>,
+) -> impl Responder {
+    match export_service.download(export_id.into_inner(), claims.user_id).await {
+        Ok((filename, content_type, file)) => HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+            .streaming(tokio_util::io::ReaderStream::new(file)),
+        Err(e) => {
+            tracing::error!("Export download error: {}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("not found") {
+                HttpResponse::NotFound().json(serde_json::json!({ "error": error_msg }))
+            } else {
+                HttpResponse::Conflict().json(serde_json::json!({ "error": error_msg }))
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// Invoice amount calculation and PDF rendering
+pub mod pdf;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+/// Everything needed to create a draft invoice
+#[derive(Debug, Clone, Deserialize)]
+pub struct InvoiceDraft {
+    pub user_id: i32, // customer
+    pub customer_name: String,
+    pub customer_email: Option<String>,
+    pub currency: Option<String>,
+    pub discount_percent: Option<f64>,
+    pub due_date: NaiveDate,
+    pub memo: Option<String>,
+    pub items: Vec<NewLineItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewLineItem {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+    pub tax_rate: Option<f64>, // percent
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineTotals {
+    pub amount_cents: i64,
+    pub discount_cents: i64,
+    pub tax_cents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InvoiceTotals {
+    pub subtotal_cents: i64,
+    pub discount_cents: i64,
+    pub tax_cents: i64,
+    pub total_cents: i64,
+}
+
+impl InvoiceTotals {
+    pub fn total(&self) -> f64 {
+        self.total_cents as f64 / 100.0
+    }
+}
+
+/// Work out line and invoice amounts in cents. The invoice discount applies
+/// to every line before tax, so each line is taxed on its discounted amount.
+pub fn compute_totals(items: &[NewLineItem], discount_percent: f64) -> (Vec<LineTotals>, InvoiceTotals) {
+    let mut totals = InvoiceTotals::default();
+
+    let lines = items
+        .iter()
+        .map(|item| {
+            let amount_cents = (item.quantity * item.unit_price * 100.0).round() as i64;
+            let discount_cents = (amount_cents as f64 * discount_percent / 100.0).round() as i64;
+            let tax_rate = item.tax_rate.unwrap_or(0.0);
+            let tax_cents = ((amount_cents - discount_cents) as f64 * tax_rate / 100.0).round() as i64;
+
+            totals.subtotal_cents += amount_cents;
+            totals.discount_cents += discount_cents;
+            totals.tax_cents += tax_cents;
+
+            LineTotals {
+                amount_cents,
+                discount_cents,
+                tax_cents,
+            }
+        })
+        .collect();
+
+    totals.total_cents = totals.subtotal_cents - totals.discount_cents + totals.tax_cents;
+    (lines, totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(quantity: f64, unit_price: f64, tax_rate: Option<f64>) -> NewLineItem {
+        NewLineItem {
+            description: "Item".to_string(),
+            quantity,
+            unit_price,
+            tax_rate,
+        }
+    }
+
+    #[test]
+    fn test_totals_without_discount() {
+        let (_, totals) = compute_totals(&[item(2.0, 10.0, Some(10.0)), item(1.0, 5.5, None)], 0.0);
+        assert_eq!(totals.subtotal_cents, 2550);
+        assert_eq!(totals.tax_cents, 200);
+        assert_eq!(totals.total_cents, 2750);
+    }
+
+    #[test]
+    fn test_discount_applies_before_tax() {
+        let (lines, totals) = compute_totals(&[item(1.0, 100.0, Some(20.0))], 10.0);
+        assert_eq!(lines[0], LineTotals { amount_cents: 10000, discount_cents: 1000, tax_cents: 1800 });
+        assert_eq!(totals.total_cents, 10800);
+    }
+}
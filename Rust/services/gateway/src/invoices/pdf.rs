@@ -0,0 +1,135 @@
+// A4 invoice rendered with the PDF builtin fonts, no external assets needed
+use anyhow::{Result, anyhow};
+use printpdf::{BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point};
+
+use crate::domain::{Invoice, InvoiceLineItem};
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const ROW_HEIGHT: f32 = 7.0;
+
+pub fn render(invoice: &Invoice, items: &[InvoiceLineItem]) -> Result<Vec<u8>> {
+    let number = invoice.number.clone().unwrap_or_else(|| format!("DRAFT-{}", invoice.id));
+    let (doc, page, layer) = PdfDocument::new(
+        format!("Invoice {}", number),
+        Mm(PAGE_WIDTH),
+        Mm(PAGE_HEIGHT),
+        "Invoice",
+    );
+
+    let regular = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(|e| anyhow!("PDF font error: {}", e))?;
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(|e| anyhow!("PDF font error: {}", e))?;
+
+    let mut layer = doc.get_page(page).get_layer(layer);
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    // Header
+    layer.use_text("INVOICE", 20.0, Mm(MARGIN), Mm(y), &bold);
+    layer.use_text(format!("No. {}", number), 11.0, Mm(130.0), Mm(y), &bold);
+    y -= 12.0;
+
+    let issued = invoice.issued_at.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "-".to_string());
+    for (label, value) in [
+        ("Status", invoice.status.to_uppercase()),
+        ("Issued", issued),
+        ("Due", invoice.due_date.format("%Y-%m-%d").to_string()),
+    ] {
+        layer.use_text(label, 10.0, Mm(130.0), Mm(y), &bold);
+        layer.use_text(value, 10.0, Mm(155.0), Mm(y), &regular);
+        y -= 5.0;
+    }
+
+    y -= 5.0;
+    layer.use_text("Bill to", 10.0, Mm(MARGIN), Mm(y), &bold);
+    y -= 5.0;
+    layer.use_text(invoice.customer_name.as_str(), 10.0, Mm(MARGIN), Mm(y), &regular);
+    if let Some(email) = &invoice.customer_email {
+        y -= 5.0;
+        layer.use_text(email.as_str(), 10.0, Mm(MARGIN), Mm(y), &regular);
+    }
+    y -= 15.0;
+
+    // Line items
+    table_header(&layer, &bold, y);
+    y -= ROW_HEIGHT;
+
+    for item in items {
+        if y < MARGIN + 40.0 {
+            let (next_page, next_layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Invoice");
+            layer = doc.get_page(next_page).get_layer(next_layer);
+            y = PAGE_HEIGHT - MARGIN;
+            table_header(&layer, &bold, y);
+            y -= ROW_HEIGHT;
+        }
+
+        layer.use_text(truncate(&item.description, 45), 9.0, Mm(MARGIN), Mm(y), &regular);
+        layer.use_text(format_quantity(item.quantity), 9.0, Mm(110.0), Mm(y), &regular);
+        layer.use_text(money(item.unit_price), 9.0, Mm(125.0), Mm(y), &regular);
+        layer.use_text(format!("{}%", format_quantity(item.tax_rate)), 9.0, Mm(150.0), Mm(y), &regular);
+        layer.use_text(money(item.amount), 9.0, Mm(170.0), Mm(y), &regular);
+        y -= ROW_HEIGHT;
+    }
+
+    rule(&layer, y + ROW_HEIGHT - 2.0);
+    y -= 3.0;
+
+    // Totals
+    let mut totals = vec![("Subtotal", invoice.subtotal)];
+    if invoice.discount_amount > 0.0 {
+        totals.push(("Discount", -invoice.discount_amount));
+    }
+    totals.push(("Tax", invoice.tax_amount));
+
+    for (label, value) in totals {
+        layer.use_text(label, 10.0, Mm(140.0), Mm(y), &regular);
+        layer.use_text(money(value), 10.0, Mm(170.0), Mm(y), &regular);
+        y -= 6.0;
+    }
+    layer.use_text(format!("Total ({})", invoice.currency), 11.0, Mm(140.0), Mm(y), &bold);
+    layer.use_text(money(invoice.total), 11.0, Mm(170.0), Mm(y), &bold);
+
+    if let Some(memo) = &invoice.memo {
+        layer.use_text(truncate(memo, 90), 9.0, Mm(MARGIN), Mm(MARGIN), &regular);
+    }
+
+    doc.save_to_bytes().map_err(|e| anyhow!("Failed to render invoice PDF: {}", e))
+}
+
+fn table_header(layer: &PdfLayerReference, font: &IndirectFontRef, y: f32) {
+    for (label, x) in [("Description", MARGIN), ("Qty", 110.0), ("Unit price", 125.0), ("Tax", 150.0), ("Amount", 170.0)] {
+        layer.use_text(label, 9.0, Mm(x), Mm(y), font);
+    }
+    rule(layer, y - 2.0);
+}
+
+fn rule(layer: &PdfLayerReference, y: f32) {
+    layer.set_outline_thickness(0.5);
+    layer.add_line(Line {
+        points: vec![
+            (Point::new(Mm(MARGIN), Mm(y)), false),
+            (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(y)), false),
+        ],
+        is_closed: false,
+    });
+}
+
+fn money(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+fn format_quantity(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(max_chars - 3).collect::<String>())
+    }
+}
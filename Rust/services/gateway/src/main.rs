@@ -5,16 +5,29 @@ mod handlers;
 mod domain;
 mod repo;
 mod service;
+mod statements;
+mod invoices;
+mod exports;
 
 use actix_web::{web, App, HttpServer};
 use actix_cors::Cors;
 use std::env;
+use contracts::events::PRIVACY_REPORT_MAX_BYTES;
 use messaging::kafka_producer::KafkaProducer;
-use clients::StripeClient;
-use repo::PaymentRepository;
-use service::PaymentService;
+use clients::{CoreClient, StripeClient};
+use repo::{
+    AuditRepository, ConnectedAccountRepository, ExportRepository, InvoiceRepository, PaymentRepository, PayoutRepository, PrivacyRepository,
+    RefundRepository, ReportRepository, StatementRepository,
+};
+use service::{
+    AuditService, ExportService, InvoiceService, PaymentLimitService, PaymentService, PayoutService, PrivacyService,
+    ReconciliationService, RefundService, ReportService,
+};
+use domain::payout::OnboardingUrls;
+use domain::{PaymentLimits, Pseudonymizer, WebhookVerifier};
 use common::cache::RedisCache;
 use middleware::rate_limit::RateLimiter;
+use apikeys::{ApiKeyStore, ApiKeyVerifier};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -29,6 +42,12 @@ async fn main() -> std::io::Result<()> {
     let pool = db::create_pool(&database_url)
         .await
         .expect("Failed to create database pool");
+
+    // Reports and exports read from a separate small pool, optionally a replica
+    let reporting_database_url = env::var("REPORTING_DATABASE_URL").unwrap_or_else(|_| database_url.clone());
+    let reporting_pool = db::create_pool_with_size(&reporting_database_url, 3)
+        .await
+        .expect("Failed to create reporting database pool");
     
     // Create Redis cache
     let redis_cache = RedisCache::new(&redis_url)
@@ -40,11 +59,81 @@ async fn main() -> std::io::Result<()> {
     
     // Create Stripe client
     let stripe_client = StripeClient::new(stripe_api_key);
+    let webhook_verifier = WebhookVerifier::from_env();
     
     // Initialize layers
     let payment_repo = PaymentRepository::new(pool.clone());
-    let payment_service = PaymentService::new(payment_repo, stripe_client.clone(), producer.clone(), redis_cache.clone());
+    // Audit entries for reads are chained in batches by a background writer
+    let (audit_queue, audit_receiver) = tokio::sync::mpsc::channel(1000);
+    let pseudonymizer = Pseudonymizer::from_env();
+    let audit_service = AuditService::new(AuditRepository::new(pool.clone()), payment_repo.clone(), pseudonymizer.clone(), audit_queue);
+    tokio::spawn(audit_service.clone().run_writer(audit_receiver));
+    let account_repo = ConnectedAccountRepository::new(pool.clone());
+    let payout_repo = PayoutRepository::new(pool.clone());
+    // Daily payment limits by KYC tier, looked up in core-service
+    let limit_service = PaymentLimitService::new(
+        CoreClient::from_env(),
+        payment_repo.clone(),
+        redis_cache.clone(),
+        PaymentLimits::from_env(),
+    );
+    let payment_service = PaymentService::new(
+        payment_repo.clone(),
+        account_repo.clone(),
+        stripe_client.clone(),
+        producer.clone(),
+        redis_cache.clone(),
+        audit_service.clone(),
+        limit_service,
+    );
+    let payout_service = PayoutService::new(
+        account_repo,
+        payout_repo.clone(),
+        stripe_client.clone(),
+        redis_cache.clone(),
+        OnboardingUrls::from_env(),
+    );
+    let statement_repo = StatementRepository::new(pool.clone());
+    let reconciliation_service = ReconciliationService::new(statement_repo, payment_repo.clone(), payout_repo);
+    let invoice_repo = InvoiceRepository::new(pool.clone());
+    let invoice_service = InvoiceService::new(invoice_repo, payment_repo.clone(), payment_service.clone(), producer.clone());
+    let refund_service = RefundService::new(RefundRepository::new(pool.clone()), payment_repo, stripe_client.clone(), audit_service.clone());
+    let report_repo = ReportRepository::new(reporting_pool);
+    let report_service = ReportService::new(report_repo.clone());
+
+    // Export jobs are written by a background worker
+    let export_dir = env::var("EXPORT_STORAGE_DIR").unwrap_or_else(|_| "./exports".to_string());
+    let (export_queue, export_receiver) = tokio::sync::mpsc::channel(100);
+    let export_service = ExportService::new(ExportRepository::new(pool.clone()), report_repo, export_dir.into(), export_queue);
+    tokio::spawn(export_service.clone().run_worker(export_receiver));
+
+    // Data exports and erasures requested through auth-service
+    let report_producer = KafkaProducer::with_max_message_bytes(&kafka_brokers, PRIVACY_REPORT_MAX_BYTES)
+        .expect("Failed to create Kafka producer");
+    let privacy_service = PrivacyService::new(PrivacyRepository::new(pool.clone()), report_producer, pseudonymizer);
+    tokio::spawn(privacy_service.run_consumer(kafka_brokers.clone()));
+
+    // Scheduled seller payouts
+    let payout_interval = env::var("PAYOUT_SCHEDULER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(3600);
+    let scheduler = payout_service.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(payout_interval));
+        loop {
+            interval.tick().await;
+            match scheduler.run_scheduled_payouts().await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Created {} scheduled payouts", count),
+                Err(e) => tracing::error!("Scheduled payouts failed: {}", e),
+            }
+        }
+    });
     
+    // API keys of backend services calling /internal routes
+    let api_key_verifier = ApiKeyVerifier::new(ApiKeyStore::new(pool.clone()));
+
     // Rate limiter: 10 requests capacity, 10/60 = 0.166... tokens/second
     // This allows 10 requests per minute with small burst tolerance
     let rate_limiter = RateLimiter::new(redis_cache.clone(), 10.0, 10.0 / 60.0);
@@ -63,10 +152,18 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(pool.clone()))
             .app_data(web::Data::new(producer.clone()))
             .app_data(web::Data::new(stripe_client.clone()))
+            .app_data(web::Data::new(webhook_verifier.clone()))
             .app_data(web::Data::new(payment_service.clone()))
+            .app_data(web::Data::new(payout_service.clone()))
+            .app_data(web::Data::new(reconciliation_service.clone()))
+            .app_data(web::Data::new(invoice_service.clone()))
+            .app_data(web::Data::new(refund_service.clone()))
+            .app_data(web::Data::new(report_service.clone()))
+            .app_data(web::Data::new(export_service.clone()))
+            .app_data(web::Data::new(audit_service.clone()))
             .app_data(web::Data::new(redis_cache.clone()))
             .wrap(rate_limiter.clone())
-            .configure(routes::configure)
+            .configure(|cfg| routes::configure(cfg, &api_key_verifier))
     })
     .bind(&server_address)?
     .run()
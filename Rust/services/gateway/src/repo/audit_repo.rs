@@ -0,0 +1,110 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::{AuditEntry, AuditRecord};
+
+const ENTRY_COLUMNS: &str = "id, payment_id, action, actor_type, actor_id, actor_ref, source_ip, source_ip_ref, request_id, before_state, after_state, prev_hash, entry_hash, created_at";
+
+#[derive(Clone)]
+pub struct AuditRepository {
+    pool: MySqlPool,
+}
+
+impl AuditRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Append an entry to the chain. The chain head row is locked for the
+    /// whole transaction so concurrent appends are serialized.
+    pub async fn append(&self, record: &AuditRecord<'_>) -> Result<i64> {
+        let ids = self.append_batch(std::slice::from_ref(record)).await?;
+        Ok(ids[0])
+    }
+
+    /// Append several entries in order under a single lock of the chain head
+    pub async fn append_batch(&self, records: &[AuditRecord<'_>]) -> Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+
+        let (mut prev_hash,): (String,) = sqlx::query_as(
+            "SELECT last_hash FROM audit_chain_head WHERE id = 1 FOR UPDATE"
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut ids = Vec::with_capacity(records.len());
+        for record in records {
+            let entry_hash = record.hash(&prev_hash);
+
+            let result = sqlx::query(
+                "INSERT INTO payment_audit_log
+                 (payment_id, action, actor_type, actor_id, actor_ref, source_ip, source_ip_ref, request_id, before_state, after_state, prev_hash, entry_hash, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(record.payment_id)
+            .bind(record.action)
+            .bind(record.actor_type)
+            .bind(record.actor_id)
+            .bind(record.actor_ref)
+            .bind(record.source_ip)
+            .bind(record.source_ip_ref)
+            .bind(record.request_id)
+            .bind(record.before_state)
+            .bind(record.after_state)
+            .bind(&prev_hash)
+            .bind(&entry_hash)
+            .bind(record.created_at)
+            .execute(&mut *tx)
+            .await?;
+
+            ids.push(result.last_insert_id() as i64);
+            prev_hash = entry_hash;
+        }
+
+        if let Some(last_id) = ids.last() {
+            sqlx::query("UPDATE audit_chain_head SET last_hash = ?, last_entry_id = ? WHERE id = 1")
+                .bind(&prev_hash)
+                .bind(last_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(ids)
+    }
+
+    pub async fn find_by_payment(&self, payment_id: i32) -> Result<Vec<AuditEntry>> {
+        let entries = sqlx::query_as::<_, AuditEntry>(&format!(
+            "SELECT {} FROM payment_audit_log WHERE payment_id = ? ORDER BY id",
+            ENTRY_COLUMNS
+        ))
+        .bind(payment_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Entries in chain order, for verification in batches
+    pub async fn find_after(&self, after_id: i64, limit: i64) -> Result<Vec<AuditEntry>> {
+        let entries = sqlx::query_as::<_, AuditEntry>(&format!(
+            "SELECT {} FROM payment_audit_log WHERE id > ? ORDER BY id LIMIT ?",
+            ENTRY_COLUMNS
+        ))
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    pub async fn chain_head(&self) -> Result<(String, Option<i64>)> {
+        let head = sqlx::query_as::<_, (String, Option<i64>)>(
+            "SELECT last_hash, last_entry_id FROM audit_chain_head WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(head)
+    }
+}
@@ -0,0 +1,180 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::payout::{seller_balances, LedgerTotal};
+use crate::domain::{ConnectedAccount, SellerBalance};
+
+const ACCOUNT_COLUMNS: &str = "id, user_id, stripe_account_id, display_name, default_currency, payout_schedule, status, created_at";
+
+// Payments (net of application fees), payouts, and the seller's share of
+// refunds and disputes on its payments, summed by currency and status. The
+// share matches the transfer reversed with each refund or dispute.
+const LEDGER_QUERY: &str = "SELECT UPPER(currency) AS currency, 'payment' AS source, status,
+            SUM(amount - COALESCE(application_fee_amount, 0)) AS amount
+     FROM payments
+     WHERE destination_account_id = ?
+     GROUP BY UPPER(currency), status
+     UNION ALL
+     SELECT UPPER(r.currency) AS currency, 'refund' AS source, r.status,
+            SUM(r.amount * (p.amount - COALESCE(p.application_fee_amount, 0)) / p.amount) AS amount
+     FROM refunds r JOIN payments p ON p.id = r.payment_id
+     WHERE p.destination_account_id = ?
+     GROUP BY UPPER(r.currency), r.status
+     UNION ALL
+     SELECT UPPER(d.currency) AS currency, 'dispute' AS source, d.status,
+            SUM(d.amount * (p.amount - COALESCE(p.application_fee_amount, 0)) / p.amount) AS amount
+     FROM disputes d JOIN payments p ON p.id = d.payment_id
+     WHERE p.destination_account_id = ?
+     GROUP BY UPPER(d.currency), d.status
+     UNION ALL
+     SELECT UPPER(currency) AS currency, 'payout' AS source, status, SUM(amount) AS amount
+     FROM payouts
+     WHERE connected_account_id = ?
+     GROUP BY UPPER(currency), status";
+
+#[derive(Clone)]
+pub struct ConnectedAccountRepository {
+    pool: MySqlPool,
+}
+
+impl ConnectedAccountRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        user_id: i32,
+        stripe_account_id: &str,
+        display_name: &str,
+        default_currency: &str,
+        payout_schedule: &str,
+    ) -> Result<i32> {
+        let result = sqlx::query(
+            "INSERT INTO connected_accounts (user_id, stripe_account_id, display_name, default_currency, payout_schedule, status)
+             VALUES (?, ?, ?, ?, ?, 'onboarding')"
+        )
+        .bind(user_id)
+        .bind(stripe_account_id)
+        .bind(display_name)
+        .bind(default_currency)
+        .bind(payout_schedule)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<ConnectedAccount>> {
+        let account = sqlx::query_as::<_, ConnectedAccount>(&format!(
+            "SELECT {} FROM connected_accounts WHERE id = ?",
+            ACCOUNT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(account)
+    }
+
+    pub async fn find_by_stripe_account_id(&self, stripe_account_id: &str) -> Result<Option<ConnectedAccount>> {
+        let account = sqlx::query_as::<_, ConnectedAccount>(&format!(
+            "SELECT {} FROM connected_accounts WHERE stripe_account_id = ?",
+            ACCOUNT_COLUMNS
+        ))
+        .bind(stripe_account_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(account)
+    }
+
+    pub async fn find_by_user(&self, user_id: i32) -> Result<Vec<ConnectedAccount>> {
+        let accounts = sqlx::query_as::<_, ConnectedAccount>(&format!(
+            "SELECT {} FROM connected_accounts WHERE user_id = ? ORDER BY id",
+            ACCOUNT_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
+    /// Active accounts on an automatic payout schedule
+    pub async fn find_scheduled(&self) -> Result<Vec<ConnectedAccount>> {
+        let accounts = sqlx::query_as::<_, ConnectedAccount>(&format!(
+            "SELECT {} FROM connected_accounts WHERE status = 'active' AND payout_schedule IN ('daily', 'weekly')",
+            ACCOUNT_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
+    pub async fn update_status(&self, id: i32, status: &str) -> Result<()> {
+        sqlx::query("UPDATE connected_accounts SET status = ? WHERE id = ?")
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Balance per currency: net destination payments minus payouts
+    pub async fn balance(&self, account_id: i32) -> Result<Vec<SellerBalance>> {
+        let totals = sqlx::query_as::<_, LedgerTotal>(LEDGER_QUERY)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(seller_balances(&totals))
+    }
+
+    /// Insert a pending payout of the amount `plan` picks out of the available
+    /// balance. The account row stays locked until the insert commits, so
+    /// concurrent requests and the scheduler see each other's payouts.
+    pub async fn reserve_payout(
+        &self,
+        account_id: i32,
+        currency: &str,
+        plan: impl FnOnce(f64) -> Result<f64>,
+    ) -> Result<(i64, f64)> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("SELECT id FROM connected_accounts WHERE id = ? FOR UPDATE")
+            .bind(account_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let totals = sqlx::query_as::<_, LedgerTotal>(LEDGER_QUERY)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        let available = seller_balances(&totals)
+            .into_iter()
+            .find(|b| b.currency == currency)
+            .map(|b| b.available)
+            .unwrap_or(0.0);
+        let amount = plan(available)?;
+
+        let result = sqlx::query(
+            "INSERT INTO payouts (connected_account_id, amount, currency, status) VALUES (?, ?, ?, 'pending')"
+        )
+        .bind(account_id)
+        .bind(amount)
+        .bind(currency)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok((result.last_insert_id() as i64, amount))
+    }
+}
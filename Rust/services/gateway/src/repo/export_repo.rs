@@ -0,0 +1,98 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::ExportJob;
+
+const JOB_COLUMNS: &str = "id, requested_by, kind, format, filters, status, row_count, file_path, error, created_at, completed_at";
+
+#[derive(Clone)]
+pub struct ExportRepository {
+    pool: MySqlPool,
+}
+
+impl ExportRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, requested_by: i32, kind: &str, format: &str, filters: &str) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO export_jobs (requested_by, kind, format, filters, status) VALUES (?, ?, ?, ?, 'queued')"
+        )
+        .bind(requested_by)
+        .bind(kind)
+        .bind(format)
+        .bind(filters)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i64)
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<ExportJob>> {
+        let job = sqlx::query_as::<_, ExportJob>(&format!(
+            "SELECT {} FROM export_jobs WHERE id = ?",
+            JOB_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    pub async fn find_by_user(&self, requested_by: i32, limit: i64) -> Result<Vec<ExportJob>> {
+        let jobs = sqlx::query_as::<_, ExportJob>(&format!(
+            "SELECT {} FROM export_jobs WHERE requested_by = ? ORDER BY id DESC LIMIT ?",
+            JOB_COLUMNS
+        ))
+        .bind(requested_by)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    /// Jobs left behind by a restart
+    pub async fn find_unfinished(&self) -> Result<Vec<i64>> {
+        let ids: Vec<(i64,)> = sqlx::query_as(
+            "SELECT id FROM export_jobs WHERE status IN ('queued', 'running') ORDER BY id"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub async fn mark_running(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE export_jobs SET status = 'running', started_at = NOW() WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_completed(&self, id: i64, row_count: i64, file_path: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE export_jobs SET status = 'completed', row_count = ?, file_path = ?, completed_at = NOW() WHERE id = ?"
+        )
+        .bind(row_count)
+        .bind(file_path)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_failed(&self, id: i64, error: &str) -> Result<()> {
+        sqlx::query("UPDATE export_jobs SET status = 'failed', error = ?, completed_at = NOW() WHERE id = ?")
+            .bind(error)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
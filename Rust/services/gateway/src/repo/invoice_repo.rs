@@ -0,0 +1,207 @@
+use sqlx::{MySql, MySqlPool, Transaction};
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, Utc};
+use crate::domain::{Invoice, InvoiceLineItem};
+use crate::invoices::{InvoiceDraft, InvoiceTotals, LineTotals};
+
+const INVOICE_COLUMNS: &str = "i.id, i.number, i.user_id, i.created_by, i.customer_name, i.customer_email, i.currency, i.status, i.discount_percent, i.subtotal, i.discount_amount, i.tax_amount, i.total, i.due_date, i.memo, i.payment_id, i.issued_at, i.paid_at, i.voided_at, i.created_at";
+
+#[derive(Clone)]
+pub struct InvoiceRepository {
+    pool: MySqlPool,
+}
+
+impl InvoiceRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, created_by: i32, draft: &InvoiceDraft, currency: &str, lines: &[LineTotals], totals: &InvoiceTotals) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "INSERT INTO invoices
+             (user_id, created_by, customer_name, customer_email, currency, status, discount_percent, subtotal, discount_amount, tax_amount, total, due_date, memo)
+             VALUES (?, ?, ?, ?, ?, 'draft', ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(draft.user_id)
+        .bind(created_by)
+        .bind(&draft.customer_name)
+        .bind(&draft.customer_email)
+        .bind(currency)
+        .bind(draft.discount_percent.unwrap_or(0.0))
+        .bind(totals.subtotal_cents as f64 / 100.0)
+        .bind(totals.discount_cents as f64 / 100.0)
+        .bind(totals.tax_cents as f64 / 100.0)
+        .bind(totals.total_cents as f64 / 100.0)
+        .bind(draft.due_date)
+        .bind(&draft.memo)
+        .execute(&mut *tx)
+        .await?;
+
+        let invoice_id = result.last_insert_id() as i64;
+
+        for (position, (item, line)) in draft.items.iter().zip(lines).enumerate() {
+            sqlx::query(
+                "INSERT INTO invoice_line_items (invoice_id, position, description, quantity, unit_price, tax_rate, amount)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(invoice_id)
+            .bind(position as i32 + 1)
+            .bind(&item.description)
+            .bind(item.quantity)
+            .bind(item.unit_price)
+            .bind(item.tax_rate.unwrap_or(0.0))
+            .bind(line.amount_cents as f64 / 100.0)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(invoice_id)
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Invoice>> {
+        let invoice = sqlx::query_as::<_, Invoice>(&format!(
+            "SELECT {} FROM invoices i WHERE i.id = ?",
+            INVOICE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    pub async fn find_by_payment_intent(&self, intent_id: &str) -> Result<Option<Invoice>> {
+        let invoice = sqlx::query_as::<_, Invoice>(&format!(
+            "SELECT {} FROM invoices i
+             JOIN payments p ON p.id = i.payment_id
+             WHERE p.stripe_payment_intent_id = ?",
+            INVOICE_COLUMNS
+        ))
+        .bind(intent_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    /// Invoices billed to or issued by the user, newest first
+    pub async fn find_for_user(&self, user_id: i32, limit: i64, offset: i64) -> Result<Vec<Invoice>> {
+        let invoices = sqlx::query_as::<_, Invoice>(&format!(
+            "SELECT {} FROM invoices i
+             WHERE i.user_id = ? OR i.created_by = ?
+             ORDER BY i.id DESC LIMIT ? OFFSET ?",
+            INVOICE_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(invoices)
+    }
+
+    pub async fn find_items(&self, invoice_id: i64) -> Result<Vec<InvoiceLineItem>> {
+        let items = sqlx::query_as::<_, InvoiceLineItem>(
+            "SELECT id, invoice_id, description, quantity, unit_price, tax_rate, amount
+             FROM invoice_line_items WHERE invoice_id = ? ORDER BY position"
+        )
+        .bind(invoice_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Open a draft and give it the next number of this year's sequence.
+    /// The sequence row is locked so numbers have no gaps or duplicates.
+    pub async fn finalize(&self, id: i64) -> Result<String> {
+        let mut tx = self.pool.begin().await?;
+        let prefix = format!("INV-{}", Utc::now().year());
+
+        sqlx::query("INSERT IGNORE INTO invoice_sequences (prefix, next_value) VALUES (?, 1)")
+            .bind(&prefix)
+            .execute(&mut *tx)
+            .await?;
+
+        let (next_value,): (i64,) = sqlx::query_as(
+            "SELECT next_value FROM invoice_sequences WHERE prefix = ? FOR UPDATE"
+        )
+        .bind(&prefix)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let number = format!("{}-{:06}", prefix, next_value);
+
+        let result = sqlx::query(
+            "UPDATE invoices SET number = ?, status = 'open', issued_at = NOW() WHERE id = ? AND status = 'draft'"
+        )
+        .bind(&number)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("Invoice is no longer a draft"));
+        }
+
+        sqlx::query("UPDATE invoice_sequences SET next_value = next_value + 1 WHERE prefix = ?")
+            .bind(&prefix)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(number)
+    }
+
+    pub async fn void(&self, id: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE invoices SET status = 'void', voided_at = NOW() WHERE id = ? AND status IN ('draft', 'open')"
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn mark_paid(&self, id: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE invoices SET status = 'paid', paid_at = NOW() WHERE id = ? AND status = 'open'"
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Load the invoice with its row locked until the returned transaction
+    /// commits or is dropped, so one payment at a time is started for it
+    pub async fn lock(&self, id: i64) -> Result<(Transaction<'static, MySql>, Option<Invoice>)> {
+        let mut tx = self.pool.begin().await?;
+        let invoice = sqlx::query_as::<_, Invoice>(&format!(
+            "SELECT {} FROM invoices i WHERE i.id = ? FOR UPDATE",
+            INVOICE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        Ok((tx, invoice))
+    }
+
+    pub async fn attach_payment(tx: &mut Transaction<'_, MySql>, id: i64, payment_id: i32) -> Result<()> {
+        sqlx::query("UPDATE invoices SET payment_id = ? WHERE id = ?")
+            .bind(payment_id)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+}
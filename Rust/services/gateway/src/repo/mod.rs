@@ -1,3 +1,21 @@
 pub mod payment_repo;
+pub mod statement_repo;
+pub mod connected_account_repo;
+pub mod payout_repo;
+pub mod invoice_repo;
+pub mod refund_repo;
+pub mod report_repo;
+pub mod export_repo;
+pub mod audit_repo;
+pub mod privacy_repo;
 
 pub use payment_repo::PaymentRepository;
+pub use statement_repo::StatementRepository;
+pub use connected_account_repo::ConnectedAccountRepository;
+pub use payout_repo::PayoutRepository;
+pub use invoice_repo::InvoiceRepository;
+pub use refund_repo::RefundRepository;
+pub use report_repo::{ReportRepository, ExportQuery};
+pub use export_repo::ExportRepository;
+pub use audit_repo::AuditRepository;
+pub use privacy_repo::PrivacyRepository;
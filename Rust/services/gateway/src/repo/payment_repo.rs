@@ -1,5 +1,6 @@
 use sqlx::MySqlPool;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use crate::domain::Payment;
 
 #[derive(Clone)]
@@ -12,36 +13,84 @@ impl PaymentRepository {
         Self { pool }
     }
 
-    pub async fn create(
+    /// Insert a pending payment, before the PaymentIntent exists, once
+    /// `check` accepts it against the user's payments in `currency` since
+    /// `since`. The user's lock row is held from the sum to the insert, so
+    /// parallel payments cannot all pass the same check.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn reserve(
         &self,
         user_id: i32,
         amount: f64,
         currency: &str,
-        status: &str,
         payment_method: &str,
-        stripe_payment_intent_id: &str,
-        stripe_client_secret: &str,
+        destination_account_id: Option<i32>,
+        application_fee_amount: Option<f64>,
+        since: DateTime<Utc>,
+        check: impl FnOnce(f64) -> Result<()>,
     ) -> Result<i32> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("INSERT INTO payment_limit_locks (user_id) VALUES (?) ON DUPLICATE KEY UPDATE user_id = user_id")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Raw Stripe states such as requires_capture are still live payments
+        let total: Option<f64> = sqlx::query_scalar(
+            "SELECT SUM(amount) FROM payments
+             WHERE user_id = ? AND UPPER(currency) = UPPER(?) AND created_at >= ? AND status NOT IN ('failed', 'canceled')"
+        )
+        .bind(user_id)
+        .bind(currency)
+        .bind(since)
+        .fetch_one(&mut *tx)
+        .await?;
+        check(total.unwrap_or(0.0))?;
+
         let result = sqlx::query(
-            "INSERT INTO payments (user_id, amount, currency, status, payment_method, stripe_payment_intent_id, stripe_client_secret) 
-             VALUES (?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO payments (user_id, amount, currency, status, payment_method, destination_account_id, application_fee_amount)
+             VALUES (?, ?, ?, 'pending', ?, ?, ?)"
         )
         .bind(user_id)
         .bind(amount)
         .bind(currency)
-        .bind(status)
         .bind(payment_method)
+        .bind(destination_account_id)
+        .bind(application_fee_amount)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.last_insert_id() as i32)
+    }
+
+    pub async fn attach_intent(&self, id: i32, stripe_payment_intent_id: &str, stripe_client_secret: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE payments SET stripe_payment_intent_id = ?, stripe_client_secret = ? WHERE id = ?"
+        )
         .bind(stripe_payment_intent_id)
         .bind(stripe_client_secret)
+        .bind(id)
         .execute(&self.pool)
         .await?;
 
-        Ok(result.last_insert_id() as i32)
+        Ok(())
+    }
+
+    /// A reserved payment whose PaymentIntent could not be created
+    pub async fn mark_failed(&self, id: i32) -> Result<()> {
+        sqlx::query("UPDATE payments SET status = 'failed' WHERE id = ? AND stripe_payment_intent_id IS NULL")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
     }
 
     pub async fn find_by_stripe_intent_id(&self, intent_id: &str) -> Result<Option<Payment>> {
         let payment = sqlx::query_as::<_, Payment>(
-            "SELECT id, user_id, amount, currency, status, payment_method, stripe_payment_intent_id, stripe_client_secret 
+            "SELECT id, user_id, amount, currency, status, payment_method, stripe_payment_intent_id, stripe_client_secret, destination_account_id, application_fee_amount 
              FROM payments WHERE stripe_payment_intent_id = ?"
         )
         .bind(intent_id)
@@ -51,6 +100,18 @@ impl PaymentRepository {
         Ok(payment)
     }
 
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<Payment>> {
+        let payment = sqlx::query_as::<_, Payment>(
+            "SELECT id, user_id, amount, currency, status, payment_method, stripe_payment_intent_id, stripe_client_secret, destination_account_id, application_fee_amount 
+             FROM payments WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(payment)
+    }
+
     pub async fn update_status(&self, intent_id: &str, status: &str) -> Result<()> {
         sqlx::query(
             "UPDATE payments SET status = ? WHERE stripe_payment_intent_id = ?"
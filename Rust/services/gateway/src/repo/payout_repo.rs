@@ -0,0 +1,119 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::domain::Payout;
+
+const PAYOUT_COLUMNS: &str = "id, connected_account_id, amount, currency, status, stripe_payout_id, failure_reason, arrival_date, created_at, updated_at";
+
+#[derive(Clone)]
+pub struct PayoutRepository {
+    pool: MySqlPool,
+}
+
+impl PayoutRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn attach_stripe_payout(&self, id: i64, stripe_payout_id: &str, status: &str, arrival_date: Option<NaiveDate>) -> Result<()> {
+        sqlx::query(
+            "UPDATE payouts SET stripe_payout_id = ?, status = ?, arrival_date = ? WHERE id = ?"
+        )
+        .bind(stripe_payout_id)
+        .bind(status)
+        .bind(arrival_date)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_failed(&self, id: i64, reason: &str) -> Result<()> {
+        sqlx::query("UPDATE payouts SET status = 'failed', failure_reason = ? WHERE id = ?")
+            .bind(reason)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_status_by_stripe_id(&self, stripe_payout_id: &str, status: &str, failure_reason: Option<&str>) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE payouts SET status = ?, failure_reason = COALESCE(?, failure_reason) WHERE stripe_payout_id = ?"
+        )
+        .bind(status)
+        .bind(failure_reason)
+        .bind(stripe_payout_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Payout>> {
+        let payout = sqlx::query_as::<_, Payout>(&format!(
+            "SELECT {} FROM payouts WHERE id = ?",
+            PAYOUT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(payout)
+    }
+
+    pub async fn find_by_stripe_id(&self, stripe_payout_id: &str) -> Result<Option<Payout>> {
+        let payout = sqlx::query_as::<_, Payout>(&format!(
+            "SELECT {} FROM payouts WHERE stripe_payout_id = ?",
+            PAYOUT_COLUMNS
+        ))
+        .bind(stripe_payout_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(payout)
+    }
+
+    /// Pending payouts whose request to Stripe got no answer, created
+    /// between `from` and `to`
+    pub async fn find_unsent(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Payout>> {
+        let payouts = sqlx::query_as::<_, Payout>(&format!(
+            "SELECT {} FROM payouts
+             WHERE status = 'pending' AND stripe_payout_id IS NULL AND created_at >= ? AND created_at < ?
+             ORDER BY id",
+            PAYOUT_COLUMNS
+        ))
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(payouts)
+    }
+
+    pub async fn find_by_account(&self, connected_account_id: i32, limit: i64) -> Result<Vec<Payout>> {
+        let payouts = sqlx::query_as::<_, Payout>(&format!(
+            "SELECT {} FROM payouts WHERE connected_account_id = ? ORDER BY id DESC LIMIT ?",
+            PAYOUT_COLUMNS
+        ))
+        .bind(connected_account_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(payouts)
+    }
+
+    pub async fn last_created_at(&self, connected_account_id: i32) -> Result<Option<DateTime<Utc>>> {
+        let last: Option<(DateTime<Utc>,)> = sqlx::query_as(
+            "SELECT created_at FROM payouts WHERE connected_account_id = ? AND status <> 'failed' ORDER BY created_at DESC LIMIT 1"
+        )
+        .bind(connected_account_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(last.map(|(created_at,)| created_at))
+    }
+}
@@ -0,0 +1,112 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+
+/// Name left on billing records of an erased user
+const ERASED_NAME: &str = "Erased user";
+/// Owner left on payments of an erased user, next to their pseudonym
+const ERASED_USER_ID: i32 = 0;
+
+#[derive(Clone)]
+pub struct PrivacyRepository {
+    pool: MySqlPool,
+}
+
+impl PrivacyRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// The user's payments, refunds, invoices and seller payouts
+    pub async fn export_user(&self, user_id: i32) -> Result<serde_json::Value> {
+        let queries = [
+            ("payments", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('id', id, 'amount', amount, 'currency', currency, \
+                'status', status, 'payment_method', payment_method, 'created_at', created_at)), JSON_ARRAY()) \
+                FROM payments WHERE user_id = ?"),
+            ("refunds", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('id', r.id, 'payment_id', r.payment_id, 'amount', r.amount, \
+                'currency', r.currency, 'status', r.status, 'reason', r.reason, 'created_at', r.created_at)), JSON_ARRAY()) \
+                FROM refunds r JOIN payments p ON p.id = r.payment_id WHERE p.user_id = ?"),
+            ("invoices", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('number', number, 'customer_name', customer_name, \
+                'customer_email', customer_email, 'currency', currency, 'status', status, 'total', total, \
+                'due_date', due_date, 'issued_at', issued_at, 'paid_at', paid_at)), JSON_ARRAY()) \
+                FROM invoices WHERE user_id = ?"),
+            ("connected_accounts", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('display_name', display_name, \
+                'default_currency', default_currency, 'payout_schedule', payout_schedule, 'status', status, \
+                'created_at', created_at)), JSON_ARRAY()) FROM connected_accounts WHERE user_id = ?"),
+            ("payouts", "SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('id', po.id, 'amount', po.amount, 'currency', po.currency, \
+                'status', po.status, 'arrival_date', po.arrival_date, 'created_at', po.created_at)), JSON_ARRAY()) \
+                FROM payouts po JOIN connected_accounts ca ON ca.id = po.connected_account_id WHERE ca.user_id = ?"),
+        ];
+
+        let mut export = serde_json::Map::new();
+        for (name, query) in queries {
+            let json: Option<String> = sqlx::query_scalar(query)
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .flatten();
+            let value = match json {
+                Some(json) => serde_json::from_str(&json)?,
+                None => serde_json::Value::Null,
+            };
+            export.insert(name.to_string(), value);
+        }
+
+        Ok(serde_json::Value::Object(export))
+    }
+
+    /// Strip the user's name and contact details. Payments, refunds and the
+    /// audit log are kept for accounting, under the user's pseudonym and
+    /// without their IP addresses.
+    /// Returns the files of the user's report exports, for the caller to remove.
+    pub async fn erase_user(&self, user_id: i32, pseudonym: &str) -> Result<Vec<String>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE invoices SET customer_name = ?, customer_email = NULL WHERE user_id = ?")
+            .bind(ERASED_NAME)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE connected_accounts SET display_name = ?, status = 'disabled' WHERE user_id = ?")
+            .bind(ERASED_NAME)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE refunds r JOIN payments p ON p.id = r.payment_id
+             SET r.created_by = ? WHERE p.user_id = ? AND r.created_by = ?"
+        )
+        .bind(ERASED_USER_ID)
+        .bind(user_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "UPDATE payments SET user_id = ?, user_pseudonym = ?, stripe_client_secret = NULL WHERE user_id = ?"
+        )
+        .bind(ERASED_USER_ID)
+        .bind(pseudonym)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        // The entries keep the hashed pseudonyms, only the link back to the user goes
+        sqlx::query(
+            "UPDATE payment_audit_log SET actor_id = NULL, source_ip = NULL WHERE actor_type = 'user' AND actor_id = ?"
+        )
+        .bind(user_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        let files: Vec<(String,)> = sqlx::query_as(
+            "SELECT file_path FROM export_jobs WHERE requested_by = ? AND file_path IS NOT NULL"
+        )
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM export_jobs WHERE requested_by = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(files.into_iter().map(|(path,)| path).collect())
+    }
+}
@@ -0,0 +1,209 @@
+use chrono::{DateTime, Utc};
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::{Dispute, Refund};
+
+const REFUND_COLUMNS: &str = "id, payment_id, amount, currency, status, reason, stripe_refund_id, failure_reason, created_by, created_at";
+
+#[derive(Clone)]
+pub struct RefundRepository {
+    pool: MySqlPool,
+}
+
+impl RefundRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Lock the payment row, let `plan` pick the amount from what is already
+    /// refunded, and insert the pending refund in the same transaction, so
+    /// concurrent refunds of one payment cannot exceed it. Returns the refund
+    /// id, its amount and the amount refunded before it.
+    pub async fn reserve(
+        &self,
+        payment_id: i32,
+        currency: &str,
+        reason: Option<&str>,
+        created_by: i32,
+        plan: impl FnOnce(f64) -> Result<f64>,
+    ) -> Result<(i64, f64, f64)> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("SELECT id FROM payments WHERE id = ? FOR UPDATE")
+            .bind(payment_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let refunded: (Option<f64>,) = sqlx::query_as(
+            "SELECT SUM(amount) FROM refunds WHERE payment_id = ? AND status IN ('pending', 'succeeded')"
+        )
+        .bind(payment_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let refunded = refunded.0.unwrap_or(0.0);
+        let amount = plan(refunded)?;
+
+        let result = sqlx::query(
+            "INSERT INTO refunds (payment_id, amount, currency, status, reason, created_by) VALUES (?, ?, ?, 'pending', ?, ?)"
+        )
+        .bind(payment_id)
+        .bind(amount)
+        .bind(currency)
+        .bind(reason)
+        .bind(created_by)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok((result.last_insert_id() as i64, amount, refunded))
+    }
+
+    pub async fn attach_stripe_refund(&self, id: i64, stripe_refund_id: &str, status: &str) -> Result<()> {
+        sqlx::query("UPDATE refunds SET stripe_refund_id = ?, status = ? WHERE id = ?")
+            .bind(stripe_refund_id)
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_failed(&self, id: i64, reason: &str) -> Result<()> {
+        sqlx::query("UPDATE refunds SET status = 'failed', failure_reason = ? WHERE id = ?")
+            .bind(reason)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_status_by_stripe_id(&self, stripe_refund_id: &str, status: &str, failure_reason: Option<&str>) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refunds SET status = ?, failure_reason = COALESCE(?, failure_reason) WHERE stripe_refund_id = ?"
+        )
+        .bind(status)
+        .bind(failure_reason)
+        .bind(stripe_refund_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Refund>> {
+        let refund = sqlx::query_as::<_, Refund>(&format!(
+            "SELECT {} FROM refunds WHERE id = ?",
+            REFUND_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(refund)
+    }
+
+    pub async fn find_by_stripe_id(&self, stripe_refund_id: &str) -> Result<Option<Refund>> {
+        let refund = sqlx::query_as::<_, Refund>(&format!(
+            "SELECT {} FROM refunds WHERE stripe_refund_id = ?",
+            REFUND_COLUMNS
+        ))
+        .bind(stripe_refund_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(refund)
+    }
+
+    /// Pending refunds of a payment that were never attached to a Stripe refund
+    pub async fn find_unsent(&self, payment_id: i32, since: DateTime<Utc>) -> Result<Vec<Refund>> {
+        let refunds = sqlx::query_as::<_, Refund>(&format!(
+            "SELECT {} FROM refunds
+             WHERE payment_id = ? AND status = 'pending' AND stripe_refund_id IS NULL AND created_at >= ?
+             ORDER BY id",
+            REFUND_COLUMNS
+        ))
+        .bind(payment_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(refunds)
+    }
+
+    pub async fn find_by_payment(&self, payment_id: i32) -> Result<Vec<Refund>> {
+        let refunds = sqlx::query_as::<_, Refund>(&format!(
+            "SELECT {} FROM refunds WHERE payment_id = ? ORDER BY id",
+            REFUND_COLUMNS
+        ))
+        .bind(payment_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(refunds)
+    }
+
+    /// Amount already refunded or on its way back to the customer
+    pub async fn refunded_amount(&self, payment_id: i32) -> Result<f64> {
+        let total: (Option<f64>,) = sqlx::query_as(
+            "SELECT SUM(amount) FROM refunds WHERE payment_id = ? AND status IN ('pending', 'succeeded')"
+        )
+        .bind(payment_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(total.0.unwrap_or(0.0))
+    }
+
+    /// Insert or update a dispute from a charge.dispute.* webhook
+    pub async fn upsert_dispute(
+        &self,
+        payment_id: i32,
+        stripe_dispute_id: &str,
+        amount: f64,
+        currency: &str,
+        status: &str,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO disputes (payment_id, stripe_dispute_id, amount, currency, status, reason)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON DUPLICATE KEY UPDATE status = VALUES(status), amount = VALUES(amount), reason = VALUES(reason)"
+        )
+        .bind(payment_id)
+        .bind(stripe_dispute_id)
+        .bind(amount)
+        .bind(currency)
+        .bind(status)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_disputes_by_payment(&self, payment_id: i32) -> Result<Vec<Dispute>> {
+        let disputes = sqlx::query_as::<_, Dispute>(
+            "SELECT id, payment_id, stripe_dispute_id, amount, currency, status, reason, created_at
+             FROM disputes WHERE payment_id = ? ORDER BY id"
+        )
+        .bind(payment_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(disputes)
+    }
+
+    pub async fn find_dispute_by_stripe_id(&self, stripe_dispute_id: &str) -> Result<Option<Dispute>> {
+        let dispute = sqlx::query_as::<_, Dispute>(
+            "SELECT id, payment_id, stripe_dispute_id, amount, currency, status, reason, created_at
+             FROM disputes WHERE stripe_dispute_id = ?"
+        )
+        .bind(stripe_dispute_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(dispute)
+    }
+}
@@ -0,0 +1,126 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use crate::domain::{PaymentReportRow, RefundDisputeReportRow, PaymentExportRow, RefundExportRow};
+
+/// Read-only queries for reports and exports. Runs on its own small pool
+/// (optionally a replica) so heavy reads do not starve payment requests.
+#[derive(Clone)]
+pub struct ReportRepository {
+    pool: MySqlPool,
+}
+
+/// Optional filters shared by the export queries
+pub struct ExportQuery<'a> {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub status: Option<&'a str>,
+    pub currency: Option<&'a str>,
+}
+
+impl ReportRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// `bucket_sql` is a fixed SQL expression chosen by the service, never user input
+    pub async fn payment_report(&self, bucket_sql: &str, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<PaymentReportRow>> {
+        let rows = sqlx::query_as::<_, PaymentReportRow>(&format!(
+            "SELECT {bucket} AS bucket,
+                    UPPER(currency) AS currency,
+                    COUNT(*) AS payment_count,
+                    CAST(SUM(status = 'succeeded') AS SIGNED) AS succeeded_count,
+                    CAST(SUM(status = 'failed') AS SIGNED) AS failed_count,
+                    COALESCE(SUM(CASE WHEN status = 'succeeded' THEN amount END), 0) AS volume
+             FROM payments
+             WHERE created_at >= ? AND created_at < ?
+             GROUP BY bucket, UPPER(currency)
+             ORDER BY bucket, currency",
+            bucket = bucket_sql
+        ))
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn refund_dispute_report(&self, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<RefundDisputeReportRow>> {
+        let rows = sqlx::query_as::<_, RefundDisputeReportRow>(
+            "SELECT UPPER(p.currency) AS currency,
+                    COUNT(*) AS payment_count,
+                    COALESCE(SUM(p.amount), 0) AS volume,
+                    CAST(COUNT(r.payment_id) AS SIGNED) AS refunded_count,
+                    COALESCE(SUM(r.refunded_amount), 0) AS refunded_amount,
+                    CAST(COUNT(d.payment_id) AS SIGNED) AS disputed_count,
+                    COALESCE(SUM(d.disputed_amount), 0) AS disputed_amount
+             FROM payments p
+             LEFT JOIN (
+                 SELECT payment_id, SUM(amount) AS refunded_amount
+                 FROM refunds WHERE status = 'succeeded' GROUP BY payment_id
+             ) r ON r.payment_id = p.id
+             LEFT JOIN (
+                 SELECT payment_id, SUM(amount) AS disputed_amount
+                 FROM disputes GROUP BY payment_id
+             ) d ON d.payment_id = p.id
+             WHERE p.status = 'succeeded' AND p.created_at >= ? AND p.created_at < ?
+             GROUP BY UPPER(p.currency)
+             ORDER BY currency"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Next page of payments after `after_id` (keyset pagination keeps each query short)
+    pub async fn payments_batch(&self, query: &ExportQuery<'_>, after_id: i64, limit: i64) -> Result<Vec<PaymentExportRow>> {
+        let rows = sqlx::query_as::<_, PaymentExportRow>(
+            "SELECT id, user_id, amount, currency, status, payment_method, stripe_payment_intent_id,
+                    destination_account_id, application_fee_amount, created_at
+             FROM payments
+             WHERE id > ? AND created_at >= ? AND created_at < ?
+               AND (? IS NULL OR status = ?)
+               AND (? IS NULL OR UPPER(currency) = ?)
+             ORDER BY id LIMIT ?"
+        )
+        .bind(after_id)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.status)
+        .bind(query.status)
+        .bind(query.currency)
+        .bind(query.currency)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn refunds_batch(&self, query: &ExportQuery<'_>, after_id: i64, limit: i64) -> Result<Vec<RefundExportRow>> {
+        let rows = sqlx::query_as::<_, RefundExportRow>(
+            "SELECT id, payment_id, amount, currency, status, reason, stripe_refund_id, created_at
+             FROM refunds
+             WHERE id > ? AND created_at >= ? AND created_at < ?
+               AND (? IS NULL OR status = ?)
+               AND (? IS NULL OR UPPER(currency) = ?)
+             ORDER BY id LIMIT ?"
+        )
+        .bind(after_id)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.status)
+        .bind(query.status)
+        .bind(query.currency)
+        .bind(query.currency)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
@@ -0,0 +1,272 @@
+use sqlx::MySqlPool;
+use anyhow::Result;
+use crate::domain::{BankStatement, LineMatch, StatementLine, MatchCandidate};
+use crate::statements::{Direction, ParsedStatement};
+
+const LINE_COLUMNS: &str = "id, statement_id, line_no, booking_date, value_date, amount, currency, direction, reference, bank_reference, description, match_status, matched_type, matched_id, matched_by, match_candidates, match_note";
+
+/// A kind of gateway record a statement line can settle
+struct CandidateSource {
+    kind: &'static str,
+    table: &'static str,
+    reference_column: &'static str,
+    amount: &'static str, // what reaches the bank
+    filter: &'static str, // records whose money actually moved
+}
+
+const PAYMENTS: CandidateSource = CandidateSource {
+    kind: "payment",
+    table: "payments",
+    reference_column: "stripe_payment_intent_id",
+    amount: "t.amount",
+    filter: "t.status = 'succeeded'",
+};
+
+const PAYOUTS: CandidateSource = CandidateSource {
+    kind: "payout",
+    table: "payouts",
+    reference_column: "stripe_payout_id",
+    amount: "t.amount",
+    filter: "t.status NOT IN ('failed', 'canceled')",
+};
+
+/// The seller's share of a destination charge, referenced by Stripe through
+/// the charge's PaymentIntent (transfer group `group_pi_...`)
+const TRANSFERS: CandidateSource = CandidateSource {
+    kind: "transfer",
+    table: "payments",
+    reference_column: "stripe_payment_intent_id",
+    amount: "t.amount - COALESCE(t.application_fee_amount, 0)",
+    filter: "t.destination_account_id IS NOT NULL AND t.status = 'succeeded'",
+};
+
+impl CandidateSource {
+    /// Binds the currency, the amount in cents, then each reference
+    fn select(&self, references: usize) -> String {
+        let CandidateSource { kind, table, reference_column, amount, filter } = self;
+        let reference_filter = if references == 0 {
+            String::new()
+        } else {
+            format!(" OR t.{} IN ({})", reference_column, vec!["?"; references].join(", "))
+        };
+
+        format!(
+            "(SELECT '{kind}' AS kind, CAST(t.id AS SIGNED) AS id, COALESCE(t.{reference_column}, '') AS reference,
+                     CAST(ROUND(({amount}) * 100) AS SIGNED) AS amount_cents, UPPER(t.currency) AS currency
+              FROM {table} t
+              WHERE ((UPPER(t.currency) = ? AND ROUND(({amount}) * 100) = ?){reference_filter})
+                AND {filter}
+                AND NOT EXISTS (
+                    SELECT 1 FROM bank_statement_lines l
+                    WHERE l.matched_type = '{kind}' AND l.matched_id = t.id
+                )
+              LIMIT 50)"
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct StatementRepository {
+    pool: MySqlPool,
+}
+
+impl StatementRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn exists(&self, account: &str, statement_ref: &str) -> Result<bool> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM bank_statements WHERE account = ? AND statement_ref = ?"
+        )
+        .bind(account)
+        .bind(statement_ref)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0 > 0)
+    }
+
+    /// Save the statements and their lines in one transaction, so a failed
+    /// import leaves nothing behind and can be retried
+    pub async fn save_import(
+        &self,
+        format: &str,
+        statements: &[(ParsedStatement, Vec<LineMatch>)],
+        imported_by: i32,
+    ) -> Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+        let mut statement_ids = Vec::with_capacity(statements.len());
+
+        for (statement, matches) in statements {
+            let result = sqlx::query(
+                "INSERT INTO bank_statements (format, account, statement_ref, currency, statement_date, imported_by)
+                 VALUES (?, ?, ?, ?, ?, ?)"
+            )
+            .bind(format)
+            .bind(&statement.account)
+            .bind(&statement.statement_ref)
+            .bind(&statement.currency)
+            .bind(statement.statement_date)
+            .bind(imported_by)
+            .execute(&mut *tx)
+            .await?;
+            let statement_id = result.last_insert_id() as i64;
+
+            for (index, (line, line_match)) in statement.lines.iter().zip(matches).enumerate() {
+                let matched = line_match.matched.as_ref();
+                sqlx::query(
+                    "INSERT INTO bank_statement_lines
+                     (statement_id, line_no, booking_date, value_date, amount, currency, direction, reference, bank_reference, description,
+                      match_status, matched_type, matched_id, matched_by, match_candidates)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                )
+                .bind(statement_id)
+                .bind(index as i32 + 1)
+                .bind(line.booking_date)
+                .bind(line.value_date)
+                .bind(line.amount_cents as f64 / 100.0)
+                .bind(&line.currency)
+                .bind(line.direction.as_str())
+                .bind(&line.reference)
+                .bind(&line.bank_reference)
+                .bind(&line.description)
+                .bind(line_match.status.as_str())
+                .bind(matched.map(|c| c.kind.as_str()))
+                .bind(matched.map(|c| c.id))
+                .bind(matched.map(|_| "auto"))
+                .bind(line_match.candidates.as_deref())
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            statement_ids.push(statement_id);
+        }
+
+        tx.commit().await?;
+        Ok(statement_ids)
+    }
+
+    /// Gateway records with the same amount, or whose reference appears in the
+    /// line, that are not yet reconciled against another statement line.
+    pub async fn find_candidates(
+        &self,
+        direction: Direction,
+        currency: &str,
+        amount_cents: i64,
+        references: &[String],
+    ) -> Result<Vec<MatchCandidate>> {
+        // Incoming money settles payments, outgoing money is a payout or the
+        // seller's share of a destination charge
+        let sources: &[CandidateSource] = match direction {
+            Direction::Credit => &[PAYMENTS],
+            Direction::Debit => &[PAYOUTS, TRANSFERS],
+        };
+
+        let selects: Vec<String> = sources.iter().map(|source| source.select(references.len())).collect();
+        let sql = format!("{} LIMIT 50", selects.join(" UNION ALL "));
+
+        let mut query = sqlx::query_as::<_, MatchCandidate>(&sql);
+        for _ in sources {
+            query = query.bind(currency.to_uppercase()).bind(amount_cents);
+            for reference in references {
+                query = query.bind(reference);
+            }
+        }
+
+        Ok(query.fetch_all(&self.pool).await?)
+    }
+
+    pub async fn find_statement(&self, id: i64) -> Result<Option<BankStatement>> {
+        let statement = sqlx::query_as::<_, BankStatement>(
+            "SELECT id, format, account, statement_ref, currency, statement_date, imported_by, created_at
+             FROM bank_statements WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(statement)
+    }
+
+    pub async fn find_lines_by_statement(&self, statement_id: i64) -> Result<Vec<StatementLine>> {
+        let lines = sqlx::query_as::<_, StatementLine>(&format!(
+            "SELECT {} FROM bank_statement_lines WHERE statement_id = ? ORDER BY line_no",
+            LINE_COLUMNS
+        ))
+        .bind(statement_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(lines)
+    }
+
+    pub async fn find_line(&self, id: i64) -> Result<Option<StatementLine>> {
+        let line = sqlx::query_as::<_, StatementLine>(&format!(
+            "SELECT {} FROM bank_statement_lines WHERE id = ?",
+            LINE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(line)
+    }
+
+    /// Unmatched and ambiguous lines waiting for a human decision, oldest first
+    pub async fn find_review_queue(&self, limit: i64, offset: i64) -> Result<Vec<StatementLine>> {
+        let lines = sqlx::query_as::<_, StatementLine>(&format!(
+            "SELECT {} FROM bank_statement_lines
+             WHERE match_status IN ('unmatched', 'ambiguous')
+             ORDER BY id LIMIT ? OFFSET ?",
+            LINE_COLUMNS
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(lines)
+    }
+
+    pub async fn is_reconciled(&self, kind: &str, id: i64) -> Result<bool> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM bank_statement_lines WHERE matched_type = ? AND matched_id = ?"
+        )
+        .bind(kind)
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0 > 0)
+    }
+
+    /// Resolve a line still waiting for review. Returns false when someone
+    /// else resolved it first.
+    pub async fn resolve_line(
+        &self,
+        id: i64,
+        match_status: &str,
+        matched_type: Option<&str>,
+        matched_id: Option<i64>,
+        resolved_by: i32,
+        note: Option<&str>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE bank_statement_lines
+             SET match_status = ?, matched_type = ?, matched_id = ?, matched_by = ?, match_note = ?, resolved_by = ?, resolved_at = NOW()
+             WHERE id = ? AND match_status IN ('unmatched', 'ambiguous')"
+        )
+        .bind(match_status)
+        .bind(matched_type)
+        .bind(matched_id)
+        .bind(matched_type.map(|_| "manual"))
+        .bind(note)
+        .bind(resolved_by)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,19 +1,100 @@
-use actix_web::web;
+use actix_web::{guard, web};
 use crate::handlers;
-use authz::AuthMiddleware;
+use authz::{permissions, roles, AuthMiddleware, Require};
+use apikeys::{scopes, ApiKeyAuth, ApiKeyVerifier};
 
-pub fn configure(cfg: &mut web::ServiceConfig) {
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "RushTech@2025xAjxh".to_string());
+// Bank statement files can be a few MB for busy accounts
+const STATEMENT_UPLOAD_LIMIT: usize = 10 * 1024 * 1024;
 
+pub fn configure(cfg: &mut web::ServiceConfig, api_keys: &ApiKeyVerifier) {
     cfg
         // Public routes (no auth required)
         .route("/health", web::get().to(handlers::health_check))
         .route("/webhooks/stripe", web::post().to(handlers::stripe_webhook))
+        // Server-to-server routes (API key required)
+        .service(
+            web::scope("/internal/v1")
+                .wrap(ApiKeyAuth::new(api_keys.clone(), scopes::PAYMENTS_READ))
+                .route("/payment_intents/{intent_id}", web::get().to(handlers::retrieve_payment_internal))
+        )
         // Protected routes (auth required)
         .service(
             web::scope("/api/v1")
-                .wrap(AuthMiddleware::new(jwt_secret))
+                // Moving money needs a verified email
+                .wrap(Require::verified())
+                .wrap(AuthMiddleware::from_env())
                 .route("/payments", web::post().to(handlers::create_payment))
                 .route("/payment_intents/{intent_id}", web::get().to(handlers::retrieve_payment))
+                .route("/payment_intents/{intent_id}/capture", web::post().to(handlers::capture_payment))
+                .route("/payment_intents/{intent_id}/cancel", web::post().to(handlers::cancel_payment))
+                .route("/payment_intents/{intent_id}/audit", web::get().to(handlers::get_payment_audit_trail))
+                .service(
+                    web::resource("/audit/verify")
+                        .wrap(Require::permission(permissions::AUDIT_VERIFY))
+                        .route(web::get().to(handlers::verify_audit_chain))
+                )
+                .service(
+                    web::resource("/payment_intents/{intent_id}/refunds")
+                        .guard(guard::Post())
+                        .wrap(Require::recent_mfa())
+                        .wrap(Require::permission(permissions::PAYMENTS_REFUND))
+                        .route(web::post().to(handlers::create_refund))
+                )
+                .route("/payment_intents/{intent_id}/refunds", web::get().to(handlers::list_refunds))
+                // Admin overrides on any user's payment
+                .service(
+                    web::scope("/admin/payment_intents/{intent_id}")
+                        .wrap(Require::recent_mfa())
+                        .wrap(Require::role(roles::ADMIN))
+                        .route("/cancel", web::post().to(handlers::admin_cancel_payment))
+                        .route("/refunds", web::post().to(handlers::admin_create_refund))
+                )
+                // Bank statement reconciliation
+                .service(
+                    web::scope("/reconciliation")
+                        .wrap(Require::permission(permissions::RECONCILIATION_MANAGE))
+                        .service(
+                            web::resource("/statements")
+                                .app_data(web::PayloadConfig::new(STATEMENT_UPLOAD_LIMIT))
+                                .route(web::post().to(handlers::import_statement))
+                        )
+                        .route("/statements/{statement_id}/lines", web::get().to(handlers::get_statement_lines))
+                        .route("/review", web::get().to(handlers::get_review_queue))
+                        .route("/lines/{line_id}/resolve", web::post().to(handlers::resolve_statement_line))
+                )
+                // Marketplace sellers and payouts
+                .service(
+                    web::scope("/connected_accounts")
+                        .wrap(Require::permission(permissions::MARKETPLACE_SELL))
+                        .route("", web::post().to(handlers::register_connected_account))
+                        .route("", web::get().to(handlers::list_connected_accounts))
+                        .route("/{account_id}/onboarding_link", web::post().to(handlers::create_onboarding_link))
+                        .route("/{account_id}/balance", web::get().to(handlers::get_seller_balance))
+                        .route("/{account_id}/payouts", web::get().to(handlers::list_payouts))
+                        .route("/{account_id}/payouts", web::post().to(handlers::create_payout))
+                )
+                // Invoices
+                .route("/invoices", web::post().to(handlers::create_invoice))
+                .route("/invoices", web::get().to(handlers::list_invoices))
+                .route("/invoices/{invoice_id}", web::get().to(handlers::get_invoice))
+                .route("/invoices/{invoice_id}/finalize", web::post().to(handlers::finalize_invoice))
+                .route("/invoices/{invoice_id}/void", web::post().to(handlers::void_invoice))
+                .route("/invoices/{invoice_id}/pay", web::post().to(handlers::pay_invoice))
+                .route("/invoices/{invoice_id}/pdf", web::get().to(handlers::get_invoice_pdf))
+                // Reporting and exports cover every user's payments
+                .service(
+                    web::scope("/reports")
+                        .wrap(Require::permission(permissions::REPORTS_READ))
+                        .route("/payments", web::get().to(handlers::get_payment_report))
+                        .route("/refunds-disputes", web::get().to(handlers::get_refund_dispute_report))
+                )
+                .service(
+                    web::scope("/exports")
+                        .wrap(Require::permission(permissions::REPORTS_READ))
+                        .route("", web::post().to(handlers::create_export))
+                        .route("", web::get().to(handlers::list_exports))
+                        .route("/{export_id}", web::get().to(handlers::get_export))
+                        .route("/{export_id}/download", web::get().to(handlers::download_export))
+                )
         );
 }
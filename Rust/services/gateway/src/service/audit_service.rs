@@ -0,0 +1,181 @@
+use anyhow::{Result, anyhow};
+use chrono::{SubsecRound, Utc};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::domain::{AuditAction, AuditContext, AuditEntry, ChainVerification, Payment, PendingRecord, Pseudonymizer, Refund, GENESIS_HASH};
+use crate::repo::{AuditRepository, PaymentRepository};
+
+const VERIFY_BATCH_SIZE: i64 = 1000;
+const WRITE_BATCH_SIZE: usize = 100;
+
+#[derive(Clone)]
+pub struct AuditService {
+    audit_repo: AuditRepository,
+    payment_repo: PaymentRepository,
+    pseudonymizer: Pseudonymizer,
+    queue: mpsc::Sender<PendingRecord>,
+}
+
+impl AuditService {
+    pub fn new(
+        audit_repo: AuditRepository,
+        payment_repo: PaymentRepository,
+        pseudonymizer: Pseudonymizer,
+        queue: mpsc::Sender<PendingRecord>,
+    ) -> Self {
+        Self {
+            audit_repo,
+            payment_repo,
+            pseudonymizer,
+            queue,
+        }
+    }
+
+    /// Append an entry for an action that has already happened. Failures are
+    /// logged rather than returned: by now the change is in Stripe and the
+    /// database and the caller must still see the result.
+    ///
+    /// Mutating actions are chained before returning. Reads go through the
+    /// queue and are written by `run_writer`, falling back to a direct append
+    /// when the queue is full.
+    pub async fn record(
+        &self,
+        payment_id: i32,
+        action: AuditAction,
+        ctx: &AuditContext,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) {
+        let references = (self.reference(ctx.actor.actor_id.as_deref()), self.reference(ctx.source_ip.as_deref()));
+        let (actor_ref, source_ip_ref) = match references {
+            (Ok(actor_ref), Ok(source_ip_ref)) => (actor_ref, source_ip_ref),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::error!("Failed to write audit entry ({} on payment {}, request {}): {}", action.as_str(), payment_id, ctx.request_id, e);
+                return;
+            }
+        };
+        let pending = PendingRecord {
+            payment_id,
+            action,
+            actor_type: ctx.actor.actor_type,
+            actor_id: ctx.actor.actor_id.clone(),
+            actor_ref,
+            source_ip: ctx.source_ip.clone(),
+            source_ip_ref,
+            request_id: ctx.request_id.clone(),
+            before_state: before.map(|v| v.to_string()),
+            after_state: after.map(|v| v.to_string()),
+            created_at: Utc::now().trunc_subsecs(0),
+        };
+
+        let pending = if action.is_read() {
+            match self.queue.try_send(pending) {
+                Ok(()) => return,
+                Err(TrySendError::Full(pending)) | Err(TrySendError::Closed(pending)) => pending,
+            }
+        } else {
+            pending
+        };
+
+        if let Err(e) = self.audit_repo.append(&pending.as_record()).await {
+            tracing::error!(
+                "Failed to write audit entry ({} on payment {}, request {}): {}",
+                action.as_str(),
+                payment_id,
+                ctx.request_id,
+                e
+            );
+        }
+    }
+
+    /// Keyed pseudonym hashed in place of an identifier an erasure may clear
+    fn reference(&self, value: Option<&str>) -> Result<Option<String>> {
+        value.map(|value| self.pseudonymizer.pseudonym_of(value)).transpose()
+    }
+
+    /// Write queued entries until the queue closes, one chain head lock per batch
+    pub async fn run_writer(self, mut receiver: mpsc::Receiver<PendingRecord>) {
+        let mut batch = Vec::with_capacity(WRITE_BATCH_SIZE);
+        while receiver.recv_many(&mut batch, WRITE_BATCH_SIZE).await > 0 {
+            let records: Vec<_> = batch.iter().map(PendingRecord::as_record).collect();
+            if let Err(e) = self.audit_repo.append_batch(&records).await {
+                tracing::error!("Failed to write {} queued audit entries: {}", records.len(), e);
+            }
+            batch.clear();
+        }
+    }
+
+    /// Audit trail of a payment owned by the user, and whether every entry
+    /// still matches its hash
+    pub async fn payment_trail(&self, intent_id: &str, user_id: i32) -> Result<(Payment, Vec<AuditEntry>, bool)> {
+        let payment = self.payment_repo
+            .find_by_stripe_intent_id(intent_id)
+            .await?
+            .filter(|payment| payment.user_id == user_id)
+            .ok_or_else(|| anyhow!("Payment not found"))?;
+
+        let entries = self.audit_repo.find_by_payment(payment.id).await?;
+        let intact = entries.iter().all(|entry| entry.verify(&self.pseudonymizer));
+        Ok((payment, entries, intact))
+    }
+
+    /// Walk the whole chain from the first entry and check every link
+    pub async fn verify_chain(&self) -> Result<ChainVerification> {
+        let mut expected_prev = GENESIS_HASH.to_string();
+        let mut last_id = 0;
+        let mut checked = 0;
+
+        loop {
+            let batch = self.audit_repo.find_after(last_id, VERIFY_BATCH_SIZE).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            for entry in batch {
+                checked += 1;
+                if entry.prev_hash != expected_prev || !entry.verify(&self.pseudonymizer) {
+                    tracing::warn!("Audit chain broken at entry {}", entry.id);
+                    return Ok(ChainVerification {
+                        entries_checked: checked,
+                        valid: false,
+                        first_invalid_id: Some(entry.id),
+                    });
+                }
+                expected_prev = entry.entry_hash;
+                last_id = entry.id;
+            }
+        }
+
+        // Entries cut off the end of the chain leave the head pointing past it
+        let (head_hash, _) = self.audit_repo.chain_head().await?;
+        Ok(ChainVerification {
+            entries_checked: checked,
+            valid: head_hash == expected_prev,
+            first_invalid_id: None,
+        })
+    }
+}
+
+/// Payment state as recorded in the audit log, without the client secret.
+/// The owner is left out too: states are hashed and an erasure could not
+/// replace it later; the payment row links the entry to its owner.
+pub fn payment_snapshot(payment: &Payment) -> Value {
+    let mut value = serde_json::to_value(payment).unwrap_or(Value::Null);
+    if let Some(object) = value.as_object_mut() {
+        object.remove("stripe_client_secret");
+        object.remove("user_id");
+    }
+    value
+}
+
+/// Refund state as recorded in the audit log, without the requesting user
+/// (the entry's actor)
+pub fn refund_snapshot(refund: &Refund) -> Value {
+    let mut value = serde_json::to_value(refund).unwrap_or(Value::Null);
+    if let Some(object) = value.as_object_mut() {
+        object.remove("created_by");
+    }
+    value
+}
@@ -0,0 +1,184 @@
+use anyhow::{Result, anyhow};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+use crate::domain::{ExportFilters, ExportJob, ExportStatus};
+use crate::exports::{self, ExportFormat, ExportKind, ExportValue};
+use crate::repo::{ExportQuery, ExportRepository, ReportRepository};
+use crate::service::report_service::date_range;
+
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+/// Export jobs are queued here and written by a single background worker,
+/// away from the request handlers
+#[derive(Clone)]
+pub struct ExportService {
+    export_repo: ExportRepository,
+    report_repo: ReportRepository,
+    storage_dir: PathBuf,
+    queue: mpsc::Sender<i64>,
+}
+
+impl ExportService {
+    pub fn new(export_repo: ExportRepository, report_repo: ReportRepository, storage_dir: PathBuf, queue: mpsc::Sender<i64>) -> Self {
+        Self {
+            export_repo,
+            report_repo,
+            storage_dir,
+            queue,
+        }
+    }
+
+    pub async fn request_export(&self, user_id: i32, kind: &str, format: &str, filters: &ExportFilters) -> Result<ExportJob> {
+        let kind = ExportKind::parse(kind)
+            .ok_or_else(|| anyhow!("Invalid export kind. Must be 'payments' or 'refunds'"))?;
+        let format = ExportFormat::parse(format)
+            .ok_or_else(|| anyhow!("Invalid export format. Must be 'csv' or 'parquet'"))?;
+        date_range(filters.from, filters.to)?;
+
+        let id = self.export_repo
+            .create(user_id, kind.as_str(), format.as_str(), &serde_json::to_string(filters)?)
+            .await?;
+
+        if self.queue.try_send(id).is_err() {
+            self.export_repo.mark_failed(id, "Export queue is full").await?;
+            return Err(anyhow!("Export queue is full, please retry later"));
+        }
+
+        tracing::info!("Queued {} {} export {} for user {}", kind.as_str(), format.as_str(), id, user_id);
+        self.get_job(id, user_id).await
+    }
+
+    pub async fn get_job(&self, id: i64, user_id: i32) -> Result<ExportJob> {
+        self.export_repo
+            .find_by_id(id)
+            .await?
+            .filter(|job| job.requested_by == user_id)
+            .ok_or_else(|| anyhow!("Export not found"))
+    }
+
+    pub async fn list_jobs(&self, user_id: i32, limit: i64) -> Result<Vec<ExportJob>> {
+        self.export_repo.find_by_user(user_id, limit).await
+    }
+
+    /// Returns the file name, content type and content of a finished export
+    /// Opens the export file, streamed to the client instead of held in memory
+    pub async fn download(&self, id: i64, user_id: i32) -> Result<(String, String, tokio::fs::File)> {
+        let job = self.get_job(id, user_id).await?;
+        if job.status != ExportStatus::Completed.as_str() {
+            return Err(anyhow!("Export is {}", job.status));
+        }
+
+        let path = job.file_path.ok_or_else(|| anyhow!("Export file not found"))?;
+        let file = tokio::fs::File::open(&path).await.map_err(|_| anyhow!("Export file not found"))?;
+        let format = ExportFormat::parse(&job.format).unwrap_or(ExportFormat::Csv);
+
+        Ok((
+            format!("{}-export-{}.{}", job.kind, job.id, format.as_str()),
+            format.content_type().to_string(),
+            file,
+        ))
+    }
+
+    /// Process queued jobs until the queue closes, starting with any jobs
+    /// interrupted by a restart
+    pub async fn run_worker(self, mut receiver: mpsc::Receiver<i64>) {
+        match self.export_repo.find_unfinished().await {
+            Ok(ids) => {
+                for id in ids {
+                    self.run_job(id).await;
+                }
+            }
+            Err(e) => tracing::error!("Failed to load unfinished exports: {}", e),
+        }
+
+        while let Some(id) = receiver.recv().await {
+            self.run_job(id).await;
+        }
+    }
+
+    async fn run_job(&self, id: i64) {
+        let job = match self.export_repo.find_by_id(id).await {
+            Ok(Some(job)) if job.status != ExportStatus::Completed.as_str() && job.status != ExportStatus::Failed.as_str() => job,
+            Ok(_) => return,
+            Err(e) => {
+                tracing::error!("Failed to load export {}: {}", id, e);
+                return;
+            }
+        };
+
+        if let Err(e) = self.export_repo.mark_running(id).await {
+            tracing::error!("Failed to start export {}: {}", id, e);
+            return;
+        }
+
+        let result = match self.write_export(&job).await {
+            Ok((row_count, path)) => self.export_repo.mark_completed(id, row_count, &path).await.map(|_| {
+                tracing::info!("Export {} completed with {} rows", id, row_count);
+            }),
+            Err(e) => {
+                tracing::error!("Export {} failed: {}", id, e);
+                let _ = tokio::fs::remove_file(self.storage_dir.join(format!("export-{}.part", id))).await;
+                self.export_repo.mark_failed(id, &e.to_string()).await
+            }
+        };
+
+        if let Err(e) = result {
+            tracing::error!("Failed to update export {}: {}", id, e);
+        }
+    }
+
+    async fn write_export(&self, job: &ExportJob) -> Result<(i64, String)> {
+        let kind = ExportKind::parse(&job.kind).ok_or_else(|| anyhow!("Unknown export kind {}", job.kind))?;
+        let format = ExportFormat::parse(&job.format).ok_or_else(|| anyhow!("Unknown export format {}", job.format))?;
+        let filters: ExportFilters = serde_json::from_str(&job.filters)?;
+        let (from, to) = date_range(filters.from, filters.to)?;
+        let currency = filters.currency.as_ref().map(|c| c.to_uppercase());
+        let query = ExportQuery {
+            from,
+            to,
+            status: filters.status.as_deref(),
+            currency: currency.as_deref(),
+        };
+
+        tokio::fs::create_dir_all(&self.storage_dir).await?;
+        let path = self.storage_dir.join(format!("export-{}.{}", job.id, format.as_str()));
+        let part_path = path.with_extension("part");
+
+        // File writing and encoding is blocking work, keep it off the async threads
+        let writer_path = part_path.clone();
+        let mut writer = tokio::task::spawn_blocking(move || exports::create_writer(format, &writer_path, kind.columns())).await??;
+
+        let mut after_id = 0;
+        let mut row_count = 0;
+        loop {
+            let (rows, last_id): (Vec<Vec<ExportValue>>, Option<i64>) = match kind {
+                ExportKind::Payments => {
+                    let batch = self.report_repo.payments_batch(&query, after_id, EXPORT_BATCH_SIZE).await?;
+                    let last_id = batch.last().map(|r| r.id as i64);
+                    (batch.into_iter().map(|r| r.into_values()).collect(), last_id)
+                }
+                ExportKind::Refunds => {
+                    let batch = self.report_repo.refunds_batch(&query, after_id, EXPORT_BATCH_SIZE).await?;
+                    let last_id = batch.last().map(|r| r.id);
+                    (batch.into_iter().map(|r| r.into_values()).collect(), last_id)
+                }
+            };
+
+            let Some(last_id) = last_id else { break };
+            after_id = last_id;
+            row_count += rows.len() as i64;
+
+            writer = tokio::task::spawn_blocking(move || -> Result<_> {
+                writer.write_rows(&rows)?;
+                Ok(writer)
+            })
+            .await??;
+        }
+
+        tokio::task::spawn_blocking(move || writer.finish()).await??;
+        tokio::fs::rename(&part_path, &path).await?;
+
+        Ok((row_count, path.to_string_lossy().to_string()))
+    }
+}
@@ -0,0 +1,246 @@
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use chrono::Utc;
+use messaging::events::{EventAttachment, InvoicePaidEvent};
+use messaging::kafka_producer::KafkaProducer;
+
+use crate::domain::{AuditContext, Invoice, InvoiceLineItem, InvoiceStatus, PaymentStatus};
+use crate::invoices::{self, InvoiceDraft};
+use crate::repo::{InvoiceRepository, PaymentRepository};
+use crate::service::{NewPayment, PaymentService};
+
+#[derive(Clone)]
+pub struct InvoiceService {
+    invoice_repo: InvoiceRepository,
+    payment_repo: PaymentRepository,
+    payment_service: PaymentService,
+    kafka_producer: KafkaProducer,
+}
+
+impl InvoiceService {
+    pub fn new(
+        invoice_repo: InvoiceRepository,
+        payment_repo: PaymentRepository,
+        payment_service: PaymentService,
+        kafka_producer: KafkaProducer,
+    ) -> Self {
+        Self {
+            invoice_repo,
+            payment_repo,
+            payment_service,
+            kafka_producer,
+        }
+    }
+
+    pub async fn create_invoice(&self, created_by: i32, draft: &InvoiceDraft) -> Result<Invoice> {
+        validate_draft(draft)?;
+
+        let currency = draft.currency.clone().unwrap_or_else(|| "USD".to_string()).to_uppercase();
+        let (lines, totals) = invoices::compute_totals(&draft.items, draft.discount_percent.unwrap_or(0.0));
+
+        let id = self.invoice_repo.create(created_by, draft, &currency, &lines, &totals).await?;
+        tracing::info!("Created draft invoice {} for user {} ({:.2} {})", id, draft.user_id, totals.total(), currency);
+
+        self.invoice_repo.find_by_id(id).await?.ok_or_else(|| anyhow!("Invoice not found"))
+    }
+
+    pub async fn get_invoice(&self, id: i64, user_id: i32) -> Result<(Invoice, Vec<InvoiceLineItem>)> {
+        let invoice = self.visible_invoice(id, user_id).await?;
+        let items = self.invoice_repo.find_items(id).await?;
+        Ok((invoice, items))
+    }
+
+    pub async fn list_invoices(&self, user_id: i32, limit: i64, offset: i64) -> Result<Vec<Invoice>> {
+        self.invoice_repo.find_for_user(user_id, limit, offset).await
+    }
+
+    /// draft -> open, assigning the invoice number
+    pub async fn finalize_invoice(&self, id: i64, user_id: i32) -> Result<Invoice> {
+        let invoice = self.issued_invoice(id, user_id).await?;
+        ensure_transition(&invoice, InvoiceStatus::Open)?;
+
+        let number = self.invoice_repo.finalize(id).await?;
+        tracing::info!("Finalized invoice {} as {}", id, number);
+
+        self.invoice_repo.find_by_id(id).await?.ok_or_else(|| anyhow!("Invoice not found"))
+    }
+
+    pub async fn void_invoice(&self, id: i64, user_id: i32) -> Result<Invoice> {
+        let invoice = self.issued_invoice(id, user_id).await?;
+        ensure_transition(&invoice, InvoiceStatus::Void)?;
+
+        if self.invoice_repo.void(id).await? == 0 {
+            return Err(anyhow!("Invoice status changed, please retry"));
+        }
+        tracing::info!("Voided invoice {}", id);
+
+        self.invoice_repo.find_by_id(id).await?.ok_or_else(|| anyhow!("Invoice not found"))
+    }
+
+    /// Create (or reuse) the PaymentIntent settling an open invoice.
+    /// Returns the invoice, client secret and PaymentIntent id.
+    pub async fn pay_invoice(&self, id: i64, user_id: i32, ctx: &AuditContext) -> Result<(Invoice, String, String)> {
+        // Concurrent requests wait here and then find the first one's PaymentIntent
+        let (mut tx, invoice) = self.invoice_repo.lock(id).await?;
+        let invoice = invoice
+            .filter(|invoice| invoice.user_id == user_id || invoice.created_by == user_id)
+            .ok_or_else(|| anyhow!("Invoice not found"))?;
+        if invoice.user_id != user_id {
+            return Err(anyhow!("Only the billed customer can pay this invoice"));
+        }
+        ensure_transition(&invoice, InvoiceStatus::Paid)?;
+
+        // A pending PaymentIntent can be confirmed again instead of charging twice,
+        // and a succeeded one only waits for its webhook to mark the invoice paid
+        if let Some(payment_id) = invoice.payment_id {
+            if let Some(payment) = self.payment_repo.find_by_id(payment_id).await? {
+                match PaymentStatus::from(payment.status.clone()) {
+                    PaymentStatus::Succeeded => {
+                        return Err(anyhow!("Invalid invoice status: already paid, awaiting confirmation"));
+                    }
+                    PaymentStatus::Pending => {
+                        if let (Some(secret), Some(intent_id)) = (payment.stripe_client_secret, payment.stripe_payment_intent_id) {
+                            return Ok((invoice, secret, intent_id));
+                        }
+                    }
+                    PaymentStatus::Failed | PaymentStatus::Canceled => {}
+                }
+            }
+        }
+
+        let (payment_id, client_secret, intent_id) = self.payment_service
+            .create_payment(
+                NewPayment {
+                    user_id,
+                    amount: invoice.total,
+                    currency: invoice.currency.clone(),
+                    payment_method: "card".to_string(),
+                    destination_account_id: None,
+                    application_fee_amount: None,
+                    manual_capture: false,
+                },
+                ctx,
+            )
+            .await?;
+        InvoiceRepository::attach_payment(&mut tx, id, payment_id).await?;
+        tx.commit().await?;
+
+        tracing::info!("Created payment {} for invoice {}", payment_id, id);
+        let invoice = self.invoice_repo.find_by_id(id).await?.ok_or_else(|| anyhow!("Invoice not found"))?;
+        Ok((invoice, client_secret, intent_id))
+    }
+
+    /// Returns the file name and PDF bytes
+    pub async fn render_pdf(&self, id: i64, user_id: i32) -> Result<(String, Vec<u8>)> {
+        let (invoice, items) = self.get_invoice(id, user_id).await?;
+        let pdf = invoices::pdf::render(&invoice, &items)?;
+        Ok((pdf_filename(&invoice), pdf))
+    }
+
+    /// Mark the invoice settled by this PaymentIntent as paid and publish the
+    /// invoice-paid event with the PDF attached
+    pub async fn handle_payment_succeeded(&self, intent_id: &str) -> Result<()> {
+        let invoice = match self.invoice_repo.find_by_payment_intent(intent_id).await? {
+            Some(invoice) => invoice,
+            None => return Ok(()),
+        };
+
+        if self.invoice_repo.mark_paid(invoice.id).await? == 0 {
+            tracing::info!("Invoice {} was not open, skipping paid transition", invoice.id);
+            return Ok(());
+        }
+
+        let invoice = self.invoice_repo.find_by_id(invoice.id).await?.ok_or_else(|| anyhow!("Invoice not found"))?;
+        let items = self.invoice_repo.find_items(invoice.id).await?;
+
+        let attachment = match invoices::pdf::render(&invoice, &items) {
+            Ok(pdf) => Some(EventAttachment {
+                filename: pdf_filename(&invoice),
+                content_type: "application/pdf".to_string(),
+                content_base64: base64::engine::general_purpose::STANDARD.encode(pdf),
+            }),
+            Err(e) => {
+                tracing::error!("Failed to render PDF for invoice {}: {}", invoice.id, e);
+                None
+            }
+        };
+
+        let event = InvoicePaidEvent {
+            invoice_id: invoice.id,
+            invoice_number: invoice.number.clone().unwrap_or_default(),
+            user_id: invoice.user_id,
+            customer_email: invoice.customer_email.clone(),
+            amount: invoice.total,
+            currency: invoice.currency.clone(),
+            attachment,
+            timestamp: Utc::now().to_rfc3339(),
+        };
+
+        let payload = serde_json::to_string(&event)?;
+        if let Err(e) = self.kafka_producer
+            .send_message("invoice-events", &invoice.id.to_string(), &payload)
+            .await
+        {
+            tracing::error!("Failed to send Kafka message: {}", e);
+        }
+
+        tracing::info!("Invoice {} paid via {}", invoice.id, intent_id);
+        Ok(())
+    }
+
+    // Customer and issuer can both read an invoice
+    async fn visible_invoice(&self, id: i64, user_id: i32) -> Result<Invoice> {
+        self.invoice_repo
+            .find_by_id(id)
+            .await?
+            .filter(|invoice| invoice.user_id == user_id || invoice.created_by == user_id)
+            .ok_or_else(|| anyhow!("Invoice not found"))
+    }
+
+    // Only the issuer can change an invoice
+    async fn issued_invoice(&self, id: i64, user_id: i32) -> Result<Invoice> {
+        self.invoice_repo
+            .find_by_id(id)
+            .await?
+            .filter(|invoice| invoice.created_by == user_id)
+            .ok_or_else(|| anyhow!("Invoice not found"))
+    }
+}
+
+fn validate_draft(draft: &InvoiceDraft) -> Result<()> {
+    if draft.items.is_empty() {
+        return Err(anyhow!("Invalid invoice: at least one line item is required"));
+    }
+    if draft.customer_name.trim().is_empty() {
+        return Err(anyhow!("Invalid invoice: customer_name is required"));
+    }
+
+    let discount = draft.discount_percent.unwrap_or(0.0);
+    if !(0.0..=100.0).contains(&discount) {
+        return Err(anyhow!("Invalid invoice: discount_percent must be between 0 and 100"));
+    }
+
+    for item in &draft.items {
+        if item.description.trim().is_empty() || item.quantity <= 0.0 || item.unit_price < 0.0 {
+            return Err(anyhow!("Invalid invoice: each line item needs a description, a positive quantity and a unit price"));
+        }
+        if !(0.0..=100.0).contains(&item.tax_rate.unwrap_or(0.0)) {
+            return Err(anyhow!("Invalid invoice: tax_rate must be between 0 and 100"));
+        }
+    }
+
+    Ok(())
+}
+
+fn ensure_transition(invoice: &Invoice, next: InvoiceStatus) -> Result<()> {
+    let current = InvoiceStatus::from(invoice.status.clone());
+    if current.can_transition_to(next) {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid invoice status transition: {} -> {}", current.as_str(), next.as_str()))
+    }
+}
+
+fn pdf_filename(invoice: &Invoice) -> String {
+    format!("{}.pdf", invoice.number.clone().unwrap_or_else(|| format!("invoice-{}", invoice.id)))
+}
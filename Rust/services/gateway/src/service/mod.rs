@@ -1,3 +1,21 @@
 pub mod payment_service;
+pub mod payout_service;
+pub mod reconciliation_service;
+pub mod invoice_service;
+pub mod refund_service;
+pub mod report_service;
+pub mod export_service;
+pub mod audit_service;
+pub mod privacy_service;
+pub mod payment_limit_service;
 
-pub use payment_service::PaymentService;
+pub use payment_service::{NewPayment, PaymentService};
+pub use payout_service::PayoutService;
+pub use reconciliation_service::ReconciliationService;
+pub use invoice_service::InvoiceService;
+pub use refund_service::RefundService;
+pub use report_service::{ReportService, ReportGroupBy};
+pub use export_service::ExportService;
+pub use audit_service::AuditService;
+pub use privacy_service::PrivacyService;
+pub use payment_limit_service::PaymentLimitService;
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use common::cache::RedisCache;
+
+use crate::clients::CoreClient;
+use crate::domain::PaymentLimits;
+use crate::repo::PaymentRepository;
+
+// A tier raised by a reviewer applies within a minute
+const KYC_LEVEL_CACHE_TTL: u64 = 60;
+
+/// Holds payments to the daily limit of the user's KYC tier, and keeps
+/// users who blocked each other from paying one another
+#[derive(Clone)]
+pub struct PaymentLimitService {
+    core_client: CoreClient,
+    payment_repo: PaymentRepository,
+    redis_cache: RedisCache,
+    limits: PaymentLimits,
+}
+
+impl PaymentLimitService {
+    pub fn new(core_client: CoreClient, payment_repo: PaymentRepository, redis_cache: RedisCache, limits: PaymentLimits) -> Self {
+        Self { core_client, payment_repo, redis_cache, limits }
+    }
+
+    /// Reserve the payment row once it fits the daily limit of the user's
+    /// tier, counting the last 24 hours in the same currency
+    #[allow(clippy::too_many_arguments)]
+    pub async fn reserve_payment(
+        &self,
+        user_id: i32,
+        amount: f64,
+        currency: &str,
+        payment_method: &str,
+        destination_account_id: Option<i32>,
+        application_fee_amount: Option<f64>,
+    ) -> Result<i32> {
+        let level = self.kyc_level(user_id).await;
+        self.payment_repo
+            .reserve(
+                user_id,
+                amount,
+                currency,
+                payment_method,
+                destination_account_id,
+                application_fee_amount,
+                Utc::now() - Duration::hours(24),
+                |paid| self.limits.check(level, paid, amount),
+            )
+            .await
+    }
+
+    /// Refused while either user blocks the other; unlike the tier lookup this
+    /// one fails closed, a block must hold when core-service is down
+    pub async fn check_recipient(&self, payer_id: i32, recipient_id: i32) -> Result<()> {
+        if payer_id == recipient_id {
+            return Ok(());
+        }
+        let relationship = self.core_client.relationship(payer_id, recipient_id).await
+            .map_err(|e| anyhow!("Recipient check unavailable: {}", e))?;
+        if relationship.blocked || relationship.blocked_by {
+            return Err(anyhow!("Payments to this recipient are not allowed"));
+        }
+        Ok(())
+    }
+
+    /// Unverified when core-service cannot be reached
+    async fn kyc_level(&self, user_id: i32) -> u8 {
+        let key = format!("kyc_level:{}", user_id);
+        if let Ok(Some(level)) = self.redis_cache.get::<u8>(&key) {
+            return level;
+        }
+
+        match self.core_client.kyc_level(user_id).await {
+            Ok(level) => {
+                let _ = self.redis_cache.set(&key, &level, KYC_LEVEL_CACHE_TTL);
+                level
+            }
+            Err(e) => {
+                tracing::warn!("KYC lookup for user {} failed, applying the unverified limit: {}", user_id, e);
+                0
+            }
+        }
+    }
+}
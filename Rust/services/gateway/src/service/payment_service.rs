@@ -2,66 +2,133 @@ use anyhow::{Result, anyhow};
 use messaging::kafka_producer::KafkaProducer;
 use messaging::events::PaymentCreatedEvent;
 use chrono::Utc;
+use serde_json::json;
 use common::cache::{RedisCache, payment_cache_key};
 
-use crate::domain::{Payment, PaymentStatus};
-use crate::repo::PaymentRepository;
-use crate::clients::StripeClient;
+use crate::domain::{AuditAction, AuditContext, Payment, PaymentStatus};
+use crate::domain::payout::{split_amounts, to_cents};
+use crate::repo::{ConnectedAccountRepository, PaymentRepository};
+use crate::clients::{PaymentSplit, StripeClient};
+use crate::service::audit_service::{AuditService, payment_snapshot};
+use crate::service::payment_limit_service::PaymentLimitService;
 
 const PAYMENT_CACHE_TTL: u64 = 86400; // 24 hours (1 day)
 
+// Reasons accepted by the Stripe cancel API
+const CANCELLATION_REASONS: [&str; 4] = ["duplicate", "fraudulent", "requested_by_customer", "abandoned"];
+
+#[derive(Debug, Clone)]
+pub struct NewPayment {
+    pub user_id: i32,
+    pub amount: f64,
+    pub currency: String,
+    pub payment_method: String,
+    pub destination_account_id: Option<i32>,
+    pub application_fee_amount: Option<f64>,
+    pub manual_capture: bool, // authorize now, capture later
+}
+
 #[derive(Clone)]
 pub struct PaymentService {
     payment_repo: PaymentRepository,
+    account_repo: ConnectedAccountRepository,
     stripe_client: StripeClient,
     kafka_producer: KafkaProducer,
     redis_cache: RedisCache,
+    audit_service: AuditService,
+    limit_service: PaymentLimitService,
 }
 
 impl PaymentService {
     pub fn new(
         payment_repo: PaymentRepository,
+        account_repo: ConnectedAccountRepository,
         stripe_client: StripeClient,
         kafka_producer: KafkaProducer,
         redis_cache: RedisCache,
+        audit_service: AuditService,
+        limit_service: PaymentLimitService,
     ) -> Self {
         Self {
             payment_repo,
+            account_repo,
             stripe_client,
             kafka_producer,
             redis_cache,
+            audit_service,
+            limit_service,
         }
     }
 
-    pub async fn create_payment(
-        &self,
-        user_id: i32,
-        amount: f64,
-        currency: &str,
-        payment_method: &str,
-    ) -> Result<(i32, String, String)> {
+    pub async fn create_payment(&self, new_payment: NewPayment, ctx: &AuditContext) -> Result<(i32, String, String)> {
+        let NewPayment {
+            user_id,
+            amount,
+            currency,
+            payment_method,
+            destination_account_id,
+            application_fee_amount,
+            manual_capture,
+        } = new_payment;
+
         // Calculate amount in cents for Stripe
-        let amount_cents = (amount * 100.0) as i64;
+        let amount_cents = to_cents(amount);
 
-        // Create payment intent with Stripe
-        let payment_intent = self.stripe_client
-            .create_payment_intent(amount_cents, currency)
-            .await
-            .map_err(|e| anyhow!("Stripe API error: {}", e))?;
+        // Marketplace payment: split between the platform and a seller
+        let split = match destination_account_id {
+            Some(account_id) => {
+                let account = self.account_repo
+                    .find_by_id(account_id)
+                    .await?
+                    .ok_or_else(|| anyhow!("Connected account not found"))?;
+                if account.status != "active" {
+                    return Err(anyhow!("Connected account is {}", account.status));
+                }
+                // A transfer to another user, who may have blocked the payer
+                self.limit_service.check_recipient(user_id, account.user_id).await?;
+
+                let amounts = split_amounts(amount, application_fee_amount)?;
+                Some(PaymentSplit {
+                    destination: account.stripe_account_id,
+                    application_fee_cents: amounts.application_fee_cents,
+                })
+            }
+            None if application_fee_amount.is_some() => {
+                return Err(anyhow!("Invalid application fee: a destination account is required"));
+            }
+            None => None,
+        };
 
-        // Save payment to database
-        let payment_id = self.payment_repo
-            .create(
+        // Held to the daily limit of the user's KYC tier: the row is saved
+        // before the PaymentIntent so it counts against parallel payments
+        let payment_id = self.limit_service
+            .reserve_payment(
                 user_id,
                 amount,
-                currency,
-                PaymentStatus::Pending.as_str(),
-                payment_method,
-                &payment_intent.id,
-                &payment_intent.client_secret,
+                &currency,
+                &payment_method,
+                destination_account_id,
+                split.as_ref().map(|_| application_fee_amount.unwrap_or(0.0)),
             )
             .await?;
 
+        // Create payment intent with Stripe
+        let payment_intent = match self.stripe_client
+            .create_payment_intent(amount_cents, &currency, split.as_ref(), manual_capture)
+            .await
+        {
+            Ok(payment_intent) => payment_intent,
+            Err(e) => {
+                if let Err(e) = self.payment_repo.mark_failed(payment_id).await {
+                    tracing::error!("Failed to release payment {}: {}", payment_id, e);
+                }
+                return Err(anyhow!("Stripe API error: {}", e));
+            }
+        };
+        self.payment_repo
+            .attach_intent(payment_id, &payment_intent.id, &payment_intent.client_secret)
+            .await?;
+
         // Publish event to Kafka
         let event = PaymentCreatedEvent {
             payment_id,
@@ -79,19 +146,29 @@ impl PaymentService {
             tracing::error!("Failed to send Kafka message: {}", e);
         }
 
+        if let Some(payment) = self.payment_repo.find_by_id(payment_id).await? {
+            self.audit_service
+                .record(payment_id, AuditAction::Create, ctx, None, Some(payment_snapshot(&payment)))
+                .await;
+        }
+
         Ok((payment_id, payment_intent.client_secret, payment_intent.id))
     }
 
-    pub async fn retrieve_payment(&self, intent_id: &str) -> Result<Payment> {
+    pub async fn retrieve_payment(&self, intent_id: &str, ctx: &AuditContext) -> Result<Payment> {
         let cache_key = payment_cache_key(intent_id);
         
         // Try to get from cache first
         if let Ok(Some(cached_payment)) = self.redis_cache.get::<Payment>(&cache_key) {
             tracing::info!("Cache hit for payment: {}", intent_id);
+            self.audit_service
+                .record(cached_payment.id, AuditAction::Retrieve, ctx, None, Some(payment_snapshot(&cached_payment)))
+                .await;
             return Ok(cached_payment);
         }
         
         tracing::info!("Cache miss for payment: {}", intent_id);
+        let before = self.payment_repo.find_by_stripe_intent_id(intent_id).await?;
         
         // Get payment intent from Stripe
         let payment_intent = self.stripe_client
@@ -99,9 +176,11 @@ impl PaymentService {
             .await
             .map_err(|e| anyhow!("Stripe API error: {}", e))?;
 
-        // Update payment status in database
+        // Update payment status in database, raw Stripe states such as
+        // requires_capture are stored as pending
+        let status = PaymentStatus::from(payment_intent.status.clone());
         if let Err(e) = self.payment_repo
-            .update_status(intent_id, &payment_intent.status)
+            .update_status(intent_id, status.as_str())
             .await
         {
             tracing::error!("Failed to update payment status: {}", e);
@@ -122,10 +201,95 @@ impl PaymentService {
             }
         }
 
+        self.audit_service
+            .record(
+                payment.id,
+                AuditAction::Retrieve,
+                ctx,
+                before.as_ref().map(payment_snapshot),
+                Some(payment_snapshot(&payment)),
+            )
+            .await;
+
         Ok(payment)
     }
 
-    pub async fn update_payment_status(&self, intent_id: &str, status: &str) -> Result<()> {
+    /// Capture a payment created with manual capture
+    pub async fn capture_payment(&self, intent_id: &str, user_id: i32, ctx: &AuditContext) -> Result<Payment> {
+        let before = self.pending_payment(intent_id, Some(user_id)).await?;
+
+        let payment_intent = self.stripe_client
+            .capture_payment_intent(intent_id)
+            .await
+            .map_err(|e| anyhow!("Stripe API error: {}", e))?;
+
+        let after = self.apply_intent_status(intent_id, &payment_intent.status).await?;
+        self.audit_service
+            .record(after.id, AuditAction::Capture, ctx, Some(payment_snapshot(&before)), Some(payment_snapshot(&after)))
+            .await;
+
+        tracing::info!("Captured payment {} ({})", after.id, intent_id);
+        Ok(after)
+    }
+
+    pub async fn cancel_payment(&self, intent_id: &str, user_id: i32, reason: Option<&str>, ctx: &AuditContext) -> Result<Payment> {
+        if let Some(reason) = reason {
+            if !CANCELLATION_REASONS.contains(&reason) {
+                return Err(anyhow!("Invalid cancellation reason. Must be one of: {}", CANCELLATION_REASONS.join(", ")));
+            }
+        }
+        let before = self.pending_payment(intent_id, Some(user_id)).await?;
+        self.cancel(intent_id, before, reason, ctx).await
+    }
+
+    /// Cancel any user's payment as an admin, chaining the override ahead of
+    /// the cancellation
+    pub async fn override_cancel(&self, intent_id: &str, reason: Option<&str>, note: &str, ctx: &AuditContext) -> Result<Payment> {
+        let note = note.trim();
+        if note.is_empty() {
+            return Err(anyhow!("Invalid override: a note explaining it is required"));
+        }
+        if let Some(reason) = reason {
+            if !CANCELLATION_REASONS.contains(&reason) {
+                return Err(anyhow!("Invalid cancellation reason. Must be one of: {}", CANCELLATION_REASONS.join(", ")));
+            }
+        }
+        let before = self.pending_payment(intent_id, None).await?;
+
+        self.audit_service
+            .record(
+                before.id,
+                AuditAction::AdminOverride,
+                ctx,
+                None,
+                Some(json!({
+                    "operation": AuditAction::Cancel.as_str(),
+                    "reason": reason,
+                    "note": note,
+                })),
+            )
+            .await;
+
+        self.cancel(intent_id, before, reason, ctx).await
+    }
+
+    async fn cancel(&self, intent_id: &str, before: Payment, reason: Option<&str>, ctx: &AuditContext) -> Result<Payment> {
+        let payment_intent = self.stripe_client
+            .cancel_payment_intent(intent_id, reason)
+            .await
+            .map_err(|e| anyhow!("Stripe API error: {}", e))?;
+
+        let after = self.apply_intent_status(intent_id, &payment_intent.status).await?;
+        self.audit_service
+            .record(after.id, AuditAction::Cancel, ctx, Some(payment_snapshot(&before)), Some(payment_snapshot(&after)))
+            .await;
+
+        tracing::info!("Canceled payment {} ({})", after.id, intent_id);
+        Ok(after)
+    }
+
+    pub async fn update_payment_status(&self, intent_id: &str, status: &str, ctx: &AuditContext) -> Result<()> {
+        let before = self.payment_repo.find_by_stripe_intent_id(intent_id).await?;
         self.payment_repo.update_status(intent_id, status).await?;
         
         // Invalidate cache when status changes
@@ -135,7 +299,49 @@ impl PaymentService {
         }
         
         tracing::info!("Payment status updated: {} -> {}", intent_id, status);
+
+        if let Some(before) = before {
+            let after = self.payment_repo.find_by_stripe_intent_id(intent_id).await?;
+            self.audit_service
+                .record(
+                    before.id,
+                    AuditAction::Webhook,
+                    ctx,
+                    Some(payment_snapshot(&before)),
+                    after.as_ref().map(payment_snapshot),
+                )
+                .await;
+        }
         Ok(())
     }
+
+    /// A pending payment, owned by the user unless `owner` is `None` (admins)
+    async fn pending_payment(&self, intent_id: &str, owner: Option<i32>) -> Result<Payment> {
+        let payment = self.payment_repo
+            .find_by_stripe_intent_id(intent_id)
+            .await?
+            .filter(|payment| owner.is_none_or(|user_id| payment.user_id == user_id))
+            .ok_or_else(|| anyhow!("Payment not found"))?;
+
+        // Raw Stripe states such as requires_capture count as pending
+        if !matches!(PaymentStatus::from(payment.status.clone()), PaymentStatus::Pending) {
+            return Err(anyhow!("Invalid operation: payment is {}", payment.status));
+        }
+        Ok(payment)
+    }
+
+    async fn apply_intent_status(&self, intent_id: &str, stripe_status: &str) -> Result<Payment> {
+        let status = PaymentStatus::from(stripe_status.to_string());
+        self.payment_repo.update_status(intent_id, status.as_str()).await?;
+
+        if let Err(e) = self.redis_cache.delete(&payment_cache_key(intent_id)) {
+            tracing::error!("Failed to invalidate cache: {}", e);
+        }
+
+        self.payment_repo
+            .find_by_stripe_intent_id(intent_id)
+            .await?
+            .ok_or_else(|| anyhow!("Payment not found"))
+    }
 }
 
@@ -0,0 +1,285 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use common::cache::RedisCache;
+
+use crate::clients::{AccountLink, StripeClient, StripeRejection};
+use crate::domain::payout::{account_status, plan_payout, OnboardingUrls, MIN_PAYOUT_AMOUNT};
+use crate::domain::{ConnectedAccount, Payout, PayoutSchedule, PayoutStatus, SellerBalance};
+use crate::repo::{ConnectedAccountRepository, PayoutRepository};
+
+const PAYOUT_LOCK_KEY: &str = "lock:scheduled_payouts";
+const PAYOUT_LOCK_TTL: u64 = 300; // 5 minutes
+
+#[derive(Clone)]
+pub struct PayoutService {
+    account_repo: ConnectedAccountRepository,
+    payout_repo: PayoutRepository,
+    stripe_client: StripeClient,
+    redis_cache: RedisCache,
+    onboarding: OnboardingUrls,
+}
+
+impl PayoutService {
+    pub fn new(
+        account_repo: ConnectedAccountRepository,
+        payout_repo: PayoutRepository,
+        stripe_client: StripeClient,
+        redis_cache: RedisCache,
+        onboarding: OnboardingUrls,
+    ) -> Self {
+        Self {
+            account_repo,
+            payout_repo,
+            stripe_client,
+            redis_cache,
+            onboarding,
+        }
+    }
+
+    /// Create a Stripe Express account for the seller and the link to its
+    /// onboarding. The account stays in onboarding until Stripe reports that
+    /// it can receive payouts.
+    pub async fn register_account(
+        &self,
+        user_id: i32,
+        display_name: &str,
+        default_currency: &str,
+        payout_schedule: &str,
+        country: Option<&str>,
+    ) -> Result<(ConnectedAccount, AccountLink)> {
+        PayoutSchedule::parse(payout_schedule)
+            .ok_or_else(|| anyhow!("Invalid payout schedule. Must be 'daily', 'weekly' or 'manual'"))?;
+
+        let stripe_account = self.stripe_client
+            .create_express_account(user_id, country, default_currency)
+            .await
+            .map_err(|e| anyhow!("Stripe API error: {}", e))?;
+
+        let id = self.account_repo
+            .create(user_id, &stripe_account.id, display_name, &default_currency.to_uppercase(), payout_schedule)
+            .await?;
+
+        tracing::info!("Created connected account {} ({}) for user {}", id, stripe_account.id, user_id);
+
+        let account = self.account_repo
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| anyhow!("Connected account not found"))?;
+        let link = self.account_link(&account).await?;
+        Ok((account, link))
+    }
+
+    /// A fresh onboarding link, the previous one is single use and expires
+    pub async fn onboarding_link(&self, account_id: i32, user_id: i32) -> Result<AccountLink> {
+        let account = self.owned_account(account_id, user_id).await?;
+        if account.status == "active" {
+            return Err(anyhow!("Connected account is already active"));
+        }
+        self.account_link(&account).await
+    }
+
+    pub async fn list_accounts(&self, user_id: i32) -> Result<Vec<ConnectedAccount>> {
+        self.account_repo.find_by_user(user_id).await
+    }
+
+    pub async fn balance(&self, account_id: i32, user_id: i32) -> Result<Vec<SellerBalance>> {
+        let account = self.owned_account(account_id, user_id).await?;
+        self.account_repo.balance(account.id).await
+    }
+
+    pub async fn list_payouts(&self, account_id: i32, user_id: i32, limit: i64) -> Result<Vec<Payout>> {
+        let account = self.owned_account(account_id, user_id).await?;
+        self.payout_repo.find_by_account(account.id, limit).await
+    }
+
+    /// Pay out the available balance, or part of it, on request of the seller
+    pub async fn request_payout(&self, account_id: i32, user_id: i32, amount: Option<f64>, currency: Option<&str>) -> Result<Payout> {
+        let account = self.owned_account(account_id, user_id).await?;
+        if account.status != "active" {
+            return Err(anyhow!("Connected account is {}", account.status));
+        }
+
+        let currency = currency.unwrap_or(&account.default_currency).to_uppercase();
+        self.create_payout(&account, amount, &currency).await
+    }
+
+    /// Pay out every account whose schedule is due. Only one gateway instance
+    /// runs this at a time.
+    pub async fn run_scheduled_payouts(&self) -> Result<usize> {
+        if !self.redis_cache.set_nx(PAYOUT_LOCK_KEY, "1", PAYOUT_LOCK_TTL)? {
+            tracing::info!("Scheduled payouts already running on another instance");
+            return Ok(0);
+        }
+
+        let result = self.pay_out_due_accounts().await;
+
+        if let Err(e) = self.redis_cache.delete(PAYOUT_LOCK_KEY) {
+            tracing::error!("Failed to release payout lock: {}", e);
+        }
+
+        result
+    }
+
+    async fn pay_out_due_accounts(&self) -> Result<usize> {
+        self.resend_unsent_payouts().await?;
+
+        let now = Utc::now();
+        let mut created = 0;
+
+        for account in self.account_repo.find_scheduled().await? {
+            let last_payout = self.payout_repo.last_created_at(account.id).await?;
+            let due = PayoutSchedule::parse(&account.payout_schedule).is_some_and(|s| s.is_due(last_payout, now));
+            if !due {
+                continue;
+            }
+
+            for balance in self.account_repo.balance(account.id).await? {
+                if balance.available < MIN_PAYOUT_AMOUNT {
+                    continue;
+                }
+
+                match self.create_payout(&account, None, &balance.currency).await {
+                    Ok(_) => created += 1,
+                    Err(e) => tracing::error!("Scheduled payout for account {} failed: {}", account.id, e),
+                }
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Apply a payout.* webhook. The event only says which payout changed,
+    /// its status is read back from Stripe.
+    pub async fn sync_payout(&self, stripe_payout_id: &str) -> Result<()> {
+        let payout = match self.payout_repo.find_by_stripe_id(stripe_payout_id).await? {
+            Some(payout) => payout,
+            None => {
+                tracing::warn!("Webhook for unknown payout: {}", stripe_payout_id);
+                return Ok(());
+            }
+        };
+        let account = self.account_repo
+            .find_by_id(payout.connected_account_id)
+            .await?
+            .ok_or_else(|| anyhow!("Connected account not found"))?;
+
+        let stripe_payout = self.stripe_client
+            .retrieve_payout(&account.stripe_account_id, stripe_payout_id)
+            .await?;
+        let status = PayoutStatus::from(stripe_payout.status);
+        let current = PayoutStatus::from(payout.status);
+        if !current.can_become(&status) {
+            tracing::info!("Ignoring payout {} going from {} to {}", stripe_payout_id, current.as_str(), status.as_str());
+            return Ok(());
+        }
+        self.payout_repo
+            .update_status_by_stripe_id(stripe_payout_id, status.as_str(), stripe_payout.failure_message.as_deref())
+            .await?;
+
+        tracing::info!("Payout status updated: {} -> {}", stripe_payout_id, status.as_str());
+        Ok(())
+    }
+
+    /// Apply an account.updated webhook: activate accounts that finished
+    /// onboarding, disable those that can no longer receive payouts
+    pub async fn update_account_status(&self, stripe_account_id: &str, payouts_enabled: bool) -> Result<()> {
+        let account = match self.account_repo.find_by_stripe_account_id(stripe_account_id).await? {
+            Some(account) => account,
+            None => {
+                tracing::warn!("Webhook for unknown connected account: {}", stripe_account_id);
+                return Ok(());
+            }
+        };
+
+        let status = account_status(&account.status, payouts_enabled);
+        if account.status != status {
+            self.account_repo.update_status(account.id, status).await?;
+            tracing::info!("Connected account {} is now {}", account.id, status);
+        }
+        Ok(())
+    }
+
+    async fn account_link(&self, account: &ConnectedAccount) -> Result<AccountLink> {
+        self.stripe_client
+            .create_account_link(&account.stripe_account_id, &self.onboarding.refresh_url, &self.onboarding.return_url)
+            .await
+            .map_err(|e| anyhow!("Stripe API error: {}", e))
+    }
+
+    async fn owned_account(&self, account_id: i32, user_id: i32) -> Result<ConnectedAccount> {
+        self.account_repo
+            .find_by_id(account_id)
+            .await?
+            .filter(|account| account.user_id == user_id)
+            .ok_or_else(|| anyhow!("Connected account not found"))
+    }
+
+    // The balance check and the pending row commit together under a lock on
+    // the account, so a concurrent request cannot pay out the same money twice
+    async fn create_payout(&self, account: &ConnectedAccount, amount: Option<f64>, currency: &str) -> Result<Payout> {
+        let (payout_id, amount) = self.account_repo
+            .reserve_payout(account.id, currency, |available| plan_payout(available, amount, currency))
+            .await?;
+
+        self.send_payout(account, payout_id, amount, currency).await?;
+        self.payout_repo
+            .find_by_id(payout_id)
+            .await?
+            .ok_or_else(|| anyhow!("Payout not found"))
+    }
+
+    /// Send the payout to Stripe, keyed by its id so a resend cannot pay out
+    /// twice. Only a refusal releases the reserved funds: after a timeout
+    /// Stripe may have created the payout, so it stays pending and is resent.
+    async fn send_payout(&self, account: &ConnectedAccount, payout_id: i64, amount: f64, currency: &str) -> Result<()> {
+        let amount_cents = (amount * 100.0).round() as i64;
+        let idempotency_key = format!("payout-{}", payout_id);
+        match self.stripe_client
+            .create_payout(&account.stripe_account_id, amount_cents, currency, &idempotency_key)
+            .await
+        {
+            Ok(stripe_payout) => {
+                let arrival_date = stripe_payout.arrival_date
+                    .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                    .map(|dt| dt.date_naive());
+                let status = PayoutStatus::from(stripe_payout.status);
+
+                self.payout_repo
+                    .attach_stripe_payout(payout_id, &stripe_payout.id, status.as_str(), arrival_date)
+                    .await?;
+
+                tracing::info!("Created payout {} ({}) of {:.2} {} for account {}", payout_id, stripe_payout.id, amount, currency, account.id);
+                Ok(())
+            }
+            Err(e) if e.is::<StripeRejection>() => {
+                tracing::error!("Stripe refused payout {} for account {}: {}", payout_id, account.id, e);
+                self.payout_repo.mark_failed(payout_id, &e.to_string()).await?;
+                Err(e)
+            }
+            Err(e) => {
+                tracing::warn!("Payout {} for account {} left pending, Stripe did not answer: {}", payout_id, account.id, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Resend payouts left pending by a request that got no answer. Stripe
+    /// keeps idempotency keys for 24 hours, older ones need a manual check.
+    async fn resend_unsent_payouts(&self) -> Result<()> {
+        let now = Utc::now();
+        let payouts = self.payout_repo
+            .find_unsent(now - Duration::hours(23), now - Duration::minutes(1))
+            .await?;
+
+        for payout in payouts {
+            let account = match self.account_repo.find_by_id(payout.connected_account_id).await? {
+                Some(account) => account,
+                None => continue,
+            };
+            if let Err(e) = self.send_payout(&account, payout.id, payout.amount, &payout.currency).await {
+                tracing::error!("Resending payout {} failed: {}", payout.id, e);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use contracts::events::{
+    PrivacyReportEvent, PrivacyRequestEvent, PrivacyRequestKind, PRIVACY_REPORTS_TOPIC, PRIVACY_REQUESTS_TOPIC,
+};
+use messaging::kafka_consumer::{InvalidMessage, KafkaConsumer};
+use messaging::kafka_producer::KafkaProducer;
+
+use crate::domain::Pseudonymizer;
+use crate::repo::PrivacyRepository;
+
+const SERVICE_NAME: &str = "gateway";
+
+/// Answers the data exports and erasures auth-service asks for on
+/// privacy-requests
+#[derive(Clone)]
+pub struct PrivacyService {
+    privacy_repo: PrivacyRepository,
+    producer: KafkaProducer,
+    pseudonymizer: Pseudonymizer,
+}
+
+impl PrivacyService {
+    /// `producer` must allow messages up to PRIVACY_REPORT_MAX_BYTES
+    pub fn new(privacy_repo: PrivacyRepository, producer: KafkaProducer, pseudonymizer: Pseudonymizer) -> Self {
+        Self { privacy_repo, producer, pseudonymizer }
+    }
+
+    pub async fn run_consumer(self, brokers: String) {
+        let consumer = match KafkaConsumer::new(&brokers, "gateway-privacy-group", &[PRIVACY_REQUESTS_TOPIC]) {
+            Ok(consumer) => consumer,
+            Err(e) => {
+                tracing::error!("Failed to start privacy request consumer: {}", e);
+                return;
+            }
+        };
+
+        let result = consumer.consume_async(|_key, payload| {
+            let service = self.clone();
+            async move {
+                let request: PrivacyRequestEvent = serde_json::from_str(&payload)
+                    .map_err(|e| InvalidMessage(format!("Parse error: {}", e)))?;
+                service.handle_request(&request).await
+            }
+        }).await;
+        if let Err(e) = result {
+            tracing::error!("Privacy request consumer stopped: {}", e);
+        }
+    }
+
+    /// Both kinds can run again on a redelivery; auth-service keeps the
+    /// first report
+    pub async fn handle_request(&self, request: &PrivacyRequestEvent) -> Result<()> {
+        let result = match request.kind {
+            PrivacyRequestKind::Export => self.privacy_repo.export_user(request.user_id).await.map(Some),
+            PrivacyRequestKind::Erasure => self.erase(request.user_id).await.map(|()| None),
+        };
+        if let Err(e) = &result {
+            tracing::error!("Privacy request {} for user {} failed: {}", request.request_id, request.user_id, e);
+        }
+
+        let report = PrivacyReportEvent::from_result(request, SERVICE_NAME, result.map_err(|e| e.to_string()));
+        self.producer
+            .send_message(PRIVACY_REPORTS_TOPIC, &request.request_id.to_string(), &report.to_payload()?)
+            .await
+    }
+
+    async fn erase(&self, user_id: i32) -> Result<()> {
+        let pseudonym = self.pseudonymizer.pseudonym(user_id)?;
+        for path in self.privacy_repo.erase_user(user_id, &pseudonym).await? {
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                tracing::warn!("Failed to remove export file {}: {}", path, e);
+            }
+        }
+        tracing::info!("Erased payment details of user {}", user_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::domain::{LineMatch, MatchCandidate, MatchStatus, StatementLine};
+use crate::repo::{PaymentRepository, PayoutRepository, StatementRepository};
+use crate::statements::{self, ParsedLine, StatementFormat};
+
+#[derive(Debug, PartialEq)]
+pub enum MatchOutcome {
+    Matched(MatchCandidate),
+    Ambiguous(Vec<MatchCandidate>),
+    Unmatched,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct ImportSummary {
+    pub statement_ids: Vec<i64>,
+    pub lines: usize,
+    pub matched: usize,
+    pub ambiguous: usize,
+    pub unmatched: usize,
+}
+
+#[derive(Clone)]
+pub struct ReconciliationService {
+    statement_repo: StatementRepository,
+    payment_repo: PaymentRepository,
+    payout_repo: PayoutRepository,
+}
+
+impl ReconciliationService {
+    pub fn new(statement_repo: StatementRepository, payment_repo: PaymentRepository, payout_repo: PayoutRepository) -> Self {
+        Self {
+            statement_repo,
+            payment_repo,
+            payout_repo,
+        }
+    }
+
+    pub async fn import(&self, format: Option<StatementFormat>, content: &str, imported_by: i32) -> Result<ImportSummary> {
+        let format = format
+            .or_else(|| StatementFormat::detect(content))
+            .ok_or_else(|| anyhow!("Unable to detect statement format"))?;
+
+        let parsed = statements::parse(format, content)?;
+        let mut summary = ImportSummary::default();
+
+        for statement in &parsed {
+            if self.statement_repo.exists(&statement.account, &statement.statement_ref).await? {
+                return Err(anyhow!(
+                    "Statement {} for account {} already imported",
+                    statement.statement_ref,
+                    statement.account
+                ));
+            }
+        }
+
+        // Match every line first, then save the whole import at once. A record
+        // auto-matched by one line is not offered to the lines after it.
+        let mut claimed = HashSet::new();
+        let mut matched_statements = Vec::with_capacity(parsed.len());
+        for statement in parsed {
+            let mut matches = Vec::with_capacity(statement.lines.len());
+            for line in &statement.lines {
+                let references = reference_tokens(line);
+                let candidates: Vec<MatchCandidate> = self.statement_repo
+                    .find_candidates(line.direction, &line.currency, line.amount_cents, &references)
+                    .await?
+                    .into_iter()
+                    .filter(|c| !claimed.contains(&(c.kind.clone(), c.id)))
+                    .collect();
+
+                let line_match = match match_line(line, &candidates) {
+                    MatchOutcome::Matched(candidate) => {
+                        claimed.insert((candidate.kind.clone(), candidate.id));
+                        summary.matched += 1;
+                        LineMatch { status: MatchStatus::Matched, matched: Some(candidate), candidates: None }
+                    }
+                    MatchOutcome::Ambiguous(options) => {
+                        summary.ambiguous += 1;
+                        LineMatch { status: MatchStatus::Ambiguous, matched: None, candidates: Some(serde_json::to_string(&options)?) }
+                    }
+                    MatchOutcome::Unmatched => {
+                        summary.unmatched += 1;
+                        LineMatch { status: MatchStatus::Unmatched, matched: None, candidates: None }
+                    }
+                };
+                matches.push(line_match);
+                summary.lines += 1;
+            }
+            matched_statements.push((statement, matches));
+        }
+
+        summary.statement_ids = self.statement_repo
+            .save_import(format.as_str(), &matched_statements, imported_by)
+            .await?;
+
+        for (statement, _) in &matched_statements {
+            tracing::info!(
+                "Imported {} statement {} ({} lines) for account {}",
+                format.as_str(),
+                statement.statement_ref,
+                statement.lines.len(),
+                statement.account
+            );
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn review_queue(&self, limit: i64, offset: i64) -> Result<Vec<StatementLine>> {
+        self.statement_repo.find_review_queue(limit, offset).await
+    }
+
+    pub async fn statement_lines(&self, statement_id: i64) -> Result<Vec<StatementLine>> {
+        self.statement_repo
+            .find_statement(statement_id)
+            .await?
+            .ok_or_else(|| anyhow!("Statement not found"))?;
+
+        self.statement_repo.find_lines_by_statement(statement_id).await
+    }
+
+    /// Manually reconcile a line against a gateway record: a payment, a payout,
+    /// or the transfer of a destination charge to its seller (by payment id)
+    pub async fn match_line_manually(&self, line_id: i64, kind: &str, target_id: i64, user_id: i32, note: Option<&str>) -> Result<StatementLine> {
+        let line = self.find_open_line(line_id).await?;
+
+        match kind {
+            "payment" => {
+                self.payment_repo
+                    .find_by_id(target_id as i32)
+                    .await?
+                    .ok_or_else(|| anyhow!("Payment {} not found", target_id))?;
+            }
+            "payout" => {
+                self.payout_repo
+                    .find_by_id(target_id)
+                    .await?
+                    .ok_or_else(|| anyhow!("Payout {} not found", target_id))?;
+            }
+            "transfer" => {
+                self.payment_repo
+                    .find_by_id(target_id as i32)
+                    .await?
+                    .filter(|payment| payment.destination_account_id.is_some())
+                    .ok_or_else(|| anyhow!("Transfer {} not found", target_id))?;
+            }
+            other => return Err(anyhow!("Unknown match target: {}", other)),
+        }
+
+        if self.statement_repo.is_reconciled(kind, target_id).await? {
+            return Err(anyhow!("The {} {} is already reconciled", kind, target_id));
+        }
+
+        if !self.statement_repo
+            .resolve_line(line.id, MatchStatus::Matched.as_str(), Some(kind), Some(target_id), user_id, note)
+            .await?
+        {
+            return Err(anyhow!("Statement line already resolved"));
+        }
+
+        self.statement_repo.find_line(line.id).await?.ok_or_else(|| anyhow!("Statement line not found"))
+    }
+
+    /// Mark a line as not needing reconciliation (bank fees, interest, ...)
+    pub async fn ignore_line(&self, line_id: i64, user_id: i32, note: Option<&str>) -> Result<StatementLine> {
+        let line = self.find_open_line(line_id).await?;
+
+        if !self.statement_repo
+            .resolve_line(line.id, MatchStatus::Ignored.as_str(), None, None, user_id, note)
+            .await?
+        {
+            return Err(anyhow!("Statement line already resolved"));
+        }
+
+        self.statement_repo.find_line(line.id).await?.ok_or_else(|| anyhow!("Statement line not found"))
+    }
+
+    async fn find_open_line(&self, line_id: i64) -> Result<StatementLine> {
+        let line = self.statement_repo
+            .find_line(line_id)
+            .await?
+            .ok_or_else(|| anyhow!("Statement line not found"))?;
+
+        match MatchStatus::from(line.match_status.clone()) {
+            MatchStatus::Unmatched | MatchStatus::Ambiguous => Ok(line),
+            status => Err(anyhow!("Statement line already {}", status.as_str())),
+        }
+    }
+}
+
+/// Tokens from the line that could be a gateway reference (e.g. pi_3AbC...)
+pub fn reference_tokens(line: &ParsedLine) -> Vec<String> {
+    let text = line.searchable_text();
+    let mut seen = HashSet::new();
+
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|t| t.len() >= 6)
+        .filter(|t| seen.insert(t.to_string()))
+        .take(20)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Decide how a statement line reconciles against the candidate records.
+///
+/// Exactly one candidate agreeing on both reference and amount is an automatic
+/// match. Several such candidates, or candidates agreeing on only one of the
+/// two, go to the review queue as ambiguous.
+pub fn match_line(line: &ParsedLine, candidates: &[MatchCandidate]) -> MatchOutcome {
+    let text = line.searchable_text();
+
+    let amount_matches = |c: &MatchCandidate| {
+        c.amount_cents == line.amount_cents && c.currency.eq_ignore_ascii_case(&line.currency)
+    };
+    let reference_matches = |c: &MatchCandidate| !c.reference.is_empty() && text.contains(&c.reference);
+
+    let exact: Vec<MatchCandidate> = candidates
+        .iter()
+        .filter(|c| amount_matches(c) && reference_matches(c))
+        .cloned()
+        .collect();
+
+    match exact.len() {
+        1 => return MatchOutcome::Matched(exact.into_iter().next().unwrap()),
+        n if n > 1 => return MatchOutcome::Ambiguous(exact),
+        _ => {}
+    }
+
+    let partial: Vec<MatchCandidate> = candidates
+        .iter()
+        .filter(|c| amount_matches(c) || reference_matches(c))
+        .cloned()
+        .collect();
+
+    if partial.is_empty() {
+        MatchOutcome::Unmatched
+    } else {
+        MatchOutcome::Ambiguous(partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statements::Direction;
+
+    fn line(reference: Option<&str>, amount_cents: i64) -> ParsedLine {
+        ParsedLine {
+            booking_date: None,
+            value_date: None,
+            amount_cents,
+            currency: "EUR".to_string(),
+            direction: Direction::Credit,
+            reference: reference.map(str::to_string),
+            bank_reference: None,
+            description: Some("Order 42".to_string()),
+        }
+    }
+
+    fn candidate(id: i64, reference: &str, amount_cents: i64) -> MatchCandidate {
+        MatchCandidate {
+            kind: "payment".to_string(),
+            id,
+            reference: reference.to_string(),
+            amount_cents,
+            currency: "EUR".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_reference_and_amount_match() {
+        let candidates = vec![candidate(1, "pi_abc123", 12550), candidate(2, "pi_def456", 12550)];
+        assert_eq!(
+            match_line(&line(Some("pi_abc123"), 12550), &candidates),
+            MatchOutcome::Matched(candidates[0].clone())
+        );
+    }
+
+    #[test]
+    fn test_amount_only_goes_to_review() {
+        let candidates = vec![candidate(1, "pi_abc123", 12550), candidate(2, "pi_def456", 12550)];
+        match match_line(&line(None, 12550), &candidates) {
+            MatchOutcome::Ambiguous(options) => assert_eq!(options.len(), 2),
+            other => panic!("expected ambiguous, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reference_with_wrong_amount_goes_to_review() {
+        let candidates = vec![candidate(1, "pi_abc123", 10000)];
+        assert!(matches!(
+            match_line(&line(Some("pi_abc123"), 12550), &candidates),
+            MatchOutcome::Ambiguous(_)
+        ));
+    }
+
+    #[test]
+    fn test_no_candidates_is_unmatched() {
+        assert_eq!(match_line(&line(Some("pi_abc123"), 12550), &[]), MatchOutcome::Unmatched);
+    }
+}
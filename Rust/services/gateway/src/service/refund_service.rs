@@ -0,0 +1,280 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::clients::{StripeClient, StripeRejection};
+use crate::domain::{AuditAction, AuditContext, Dispute, Payment, PaymentStatus, Refund, RefundStatus};
+use crate::repo::{PaymentRepository, RefundRepository};
+use crate::service::audit_service::{AuditService, refund_snapshot};
+
+// Reasons accepted by the Stripe refunds API
+const REFUND_REASONS: [&str; 3] = ["duplicate", "fraudulent", "requested_by_customer"];
+
+#[derive(Clone)]
+pub struct RefundService {
+    refund_repo: RefundRepository,
+    payment_repo: PaymentRepository,
+    stripe_client: StripeClient,
+    audit_service: AuditService,
+}
+
+impl RefundService {
+    pub fn new(
+        refund_repo: RefundRepository,
+        payment_repo: PaymentRepository,
+        stripe_client: StripeClient,
+        audit_service: AuditService,
+    ) -> Self {
+        Self {
+            refund_repo,
+            payment_repo,
+            stripe_client,
+            audit_service,
+        }
+    }
+
+    /// Refund a succeeded payment in full, or partially when an amount is given
+    pub async fn create_refund(
+        &self,
+        user_id: i32,
+        intent_id: &str,
+        amount: Option<f64>,
+        reason: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<Refund> {
+        let payment = self.payment_repo
+            .find_by_stripe_intent_id(intent_id)
+            .await?
+            .filter(|payment| payment.user_id == user_id)
+            .ok_or_else(|| anyhow!("Payment not found"))?;
+
+        self.refund(intent_id, payment, user_id, amount, reason, ctx).await
+    }
+
+    /// Refund any user's payment as an admin. The override is chained ahead
+    /// of the refund itself so the trail shows who stepped in and why.
+    pub async fn override_refund(
+        &self,
+        admin_id: i32,
+        intent_id: &str,
+        amount: Option<f64>,
+        reason: Option<&str>,
+        note: &str,
+        ctx: &AuditContext,
+    ) -> Result<Refund> {
+        let note = note.trim();
+        if note.is_empty() {
+            return Err(anyhow!("Invalid override: a note explaining it is required"));
+        }
+        let payment = self.payment_repo
+            .find_by_stripe_intent_id(intent_id)
+            .await?
+            .ok_or_else(|| anyhow!("Payment not found"))?;
+
+        self.audit_service
+            .record(
+                payment.id,
+                AuditAction::AdminOverride,
+                ctx,
+                None,
+                Some(json!({
+                    "operation": AuditAction::Refund.as_str(),
+                    "amount": amount,
+                    "reason": reason,
+                    "note": note,
+                })),
+            )
+            .await;
+
+        self.refund(intent_id, payment, admin_id, amount, reason, ctx).await
+    }
+
+    async fn refund(
+        &self,
+        intent_id: &str,
+        payment: Payment,
+        requested_by: i32,
+        amount: Option<f64>,
+        reason: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<Refund> {
+        if payment.status != PaymentStatus::Succeeded.as_str() {
+            return Err(anyhow!("Invalid refund: payment is {}", payment.status));
+        }
+        if let Some(reason) = reason {
+            if !REFUND_REASONS.contains(&reason) {
+                return Err(anyhow!("Invalid refund reason. Must be one of: {}", REFUND_REASONS.join(", ")));
+            }
+        }
+
+        // A refund left pending by an earlier attempt goes out first, under its own key
+        self.resend_unsent_refunds(intent_id, &payment).await?;
+
+        let (refund_id, amount, refunded) = self.refund_repo
+            .reserve(payment.id, &payment.currency, reason, requested_by, |refunded| {
+                let refundable = payment.amount - refunded;
+                let amount = amount.unwrap_or(refundable);
+                if amount <= 0.0 || amount > refundable + f64::EPSILON {
+                    return Err(anyhow!("Invalid refund amount: {:.2} {} refundable", refundable, payment.currency));
+                }
+                Ok(amount)
+            })
+            .await?;
+
+        let stripe_result = self.send_refund(intent_id, &payment, refund_id, amount, reason).await;
+
+        // Failed attempts are audited too
+        let refund = self.refund_repo
+            .find_by_id(refund_id)
+            .await?
+            .ok_or_else(|| anyhow!("Refund not found"))?;
+        self.audit_service
+            .record(
+                payment.id,
+                AuditAction::Refund,
+                ctx,
+                Some(json!({ "refunded_amount": refunded })),
+                Some(json!({ "refunded_amount": self.refund_repo.refunded_amount(payment.id).await?, "refund": refund_snapshot(&refund) })),
+            )
+            .await;
+
+        stripe_result?;
+        Ok(refund)
+    }
+
+    /// Send a reserved refund to Stripe. A refusal fails it and releases the
+    /// amount; without an answer it stays pending, as Stripe may have made it.
+    async fn send_refund(
+        &self,
+        intent_id: &str,
+        payment: &Payment,
+        refund_id: i64,
+        amount: f64,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        let amount_cents = (amount * 100.0).round() as i64;
+        let idempotency_key = format!("refund-{}", refund_id);
+        match self.stripe_client
+            .create_refund(intent_id, amount_cents, reason, payment.destination_account_id.is_some(), &idempotency_key)
+            .await
+        {
+            Ok(stripe_refund) => {
+                let status = RefundStatus::from(stripe_refund.status);
+                self.refund_repo.attach_stripe_refund(refund_id, &stripe_refund.id, status.as_str()).await?;
+                tracing::info!("Created refund {} ({}) of {:.2} for payment {}", refund_id, stripe_refund.id, amount, payment.id);
+                Ok(())
+            }
+            Err(e) if e.is::<StripeRejection>() => {
+                tracing::error!("Stripe refused refund {} for payment {}: {}", refund_id, payment.id, e);
+                self.refund_repo.mark_failed(refund_id, &e.to_string()).await?;
+                Err(e)
+            }
+            Err(e) => {
+                tracing::warn!("Refund {} for payment {} left pending, Stripe did not answer: {}", refund_id, payment.id, e);
+                Err(anyhow!("Stripe API error: refund {} is pending, no answer from Stripe", refund_id))
+            }
+        }
+    }
+
+    /// Resend the refunds of a payment that never got an answer from Stripe.
+    /// Stripe keeps idempotency keys for 24 hours, older ones need a manual check.
+    async fn resend_unsent_refunds(&self, intent_id: &str, payment: &Payment) -> Result<()> {
+        let refunds = self.refund_repo
+            .find_unsent(payment.id, Utc::now() - Duration::hours(23))
+            .await?;
+
+        for refund in refunds {
+            if let Err(e) = self.send_refund(intent_id, payment, refund.id, refund.amount, refund.reason.as_deref()).await {
+                tracing::warn!("Resending refund {} failed: {}", refund.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn list_refunds(&self, user_id: i32, intent_id: &str) -> Result<(Vec<Refund>, Vec<Dispute>)> {
+        let payment = self.payment_repo
+            .find_by_stripe_intent_id(intent_id)
+            .await?
+            .filter(|payment| payment.user_id == user_id)
+            .ok_or_else(|| anyhow!("Payment not found"))?;
+
+        let refunds = self.refund_repo.find_by_payment(payment.id).await?;
+        let disputes = self.refund_repo.find_disputes_by_payment(payment.id).await?;
+        Ok((refunds, disputes))
+    }
+
+    /// Apply a refund.* webhook from Stripe
+    pub async fn update_refund_status(
+        &self,
+        stripe_refund_id: &str,
+        status: &str,
+        failure_reason: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<()> {
+        let before = match self.refund_repo.find_by_stripe_id(stripe_refund_id).await? {
+            Some(refund) => refund,
+            None => {
+                tracing::warn!("Webhook for unknown refund: {}", stripe_refund_id);
+                return Ok(());
+            }
+        };
+
+        let status = RefundStatus::from(status.to_string());
+        self.refund_repo
+            .update_status_by_stripe_id(stripe_refund_id, status.as_str(), failure_reason)
+            .await?;
+        tracing::info!("Refund status updated: {} -> {}", stripe_refund_id, status.as_str());
+
+        let after = self.refund_repo.find_by_id(before.id).await?;
+        self.audit_service
+            .record(
+                before.payment_id,
+                AuditAction::Webhook,
+                ctx,
+                Some(json!({ "refund": refund_snapshot(&before) })),
+                after.map(|refund| json!({ "refund": refund_snapshot(&refund) })),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Apply a charge.dispute.* webhook from Stripe
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_dispute(
+        &self,
+        stripe_dispute_id: &str,
+        intent_id: &str,
+        amount_cents: i64,
+        currency: &str,
+        status: &str,
+        reason: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<()> {
+        let payment = match self.payment_repo.find_by_stripe_intent_id(intent_id).await? {
+            Some(payment) => payment,
+            None => {
+                tracing::warn!("Dispute {} for unknown payment intent {}", stripe_dispute_id, intent_id);
+                return Ok(());
+            }
+        };
+
+        let before = self.refund_repo.find_dispute_by_stripe_id(stripe_dispute_id).await?;
+        self.refund_repo
+            .upsert_dispute(payment.id, stripe_dispute_id, amount_cents as f64 / 100.0, &currency.to_uppercase(), status, reason)
+            .await?;
+
+        tracing::info!("Dispute {} on payment {} is {}", stripe_dispute_id, payment.id, status);
+
+        let after = self.refund_repo.find_dispute_by_stripe_id(stripe_dispute_id).await?;
+        self.audit_service
+            .record(
+                payment.id,
+                AuditAction::Webhook,
+                ctx,
+                before.map(|dispute| json!({ "dispute": dispute })),
+                after.map(|dispute| json!({ "dispute": dispute })),
+            )
+            .await;
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use serde::Serialize;
+
+use crate::domain::{PaymentReportRow, RefundDisputeReportRow};
+use crate::repo::ReportRepository;
+
+const DEFAULT_REPORT_DAYS: i64 = 30;
+const MAX_REPORT_DAYS: i64 = 366;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportGroupBy {
+    Day,
+    Currency,
+    PaymentMethod,
+}
+
+impl ReportGroupBy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "day" => Some(ReportGroupBy::Day),
+            "currency" => Some(ReportGroupBy::Currency),
+            "payment_method" => Some(ReportGroupBy::PaymentMethod),
+            _ => None,
+        }
+    }
+
+    fn bucket_sql(&self) -> &'static str {
+        match self {
+            ReportGroupBy::Day => "DATE_FORMAT(created_at, '%Y-%m-%d')",
+            ReportGroupBy::Currency => "UPPER(currency)",
+            ReportGroupBy::PaymentMethod => "COALESCE(payment_method, 'unknown')",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentReportEntry {
+    #[serde(flatten)]
+    pub row: PaymentReportRow,
+    pub success_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefundDisputeReportEntry {
+    #[serde(flatten)]
+    pub row: RefundDisputeReportRow,
+    pub refund_rate: f64,
+    pub refund_volume_rate: f64,
+    pub dispute_rate: f64,
+}
+
+#[derive(Clone)]
+pub struct ReportService {
+    report_repo: ReportRepository,
+}
+
+impl ReportService {
+    pub fn new(report_repo: ReportRepository) -> Self {
+        Self { report_repo }
+    }
+
+    pub async fn payment_report(&self, group_by: ReportGroupBy, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<PaymentReportEntry>> {
+        let (from, to) = report_range(from, to)?;
+        let rows = self.report_repo.payment_report(group_by.bucket_sql(), from, to).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PaymentReportEntry {
+                success_rate: ratio(row.succeeded_count as f64, row.payment_count as f64),
+                row,
+            })
+            .collect())
+    }
+
+    pub async fn refund_dispute_report(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<RefundDisputeReportEntry>> {
+        let (from, to) = report_range(from, to)?;
+        let rows = self.report_repo.refund_dispute_report(from, to).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RefundDisputeReportEntry {
+                refund_rate: ratio(row.refunded_count as f64, row.payment_count as f64),
+                refund_volume_rate: ratio(row.refunded_amount, row.volume),
+                dispute_rate: ratio(row.disputed_count as f64, row.payment_count as f64),
+                row,
+            })
+            .collect())
+    }
+}
+
+/// Half-open [from, to + 1 day) range; defaults to the last 30 days
+pub fn date_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(NaiveDateTime, NaiveDateTime)> {
+    let to = to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = from.unwrap_or(to - Duration::days(DEFAULT_REPORT_DAYS - 1));
+
+    if from > to {
+        return Err(anyhow!("Invalid date range: from is after to"));
+    }
+
+    let start = from.and_hms_opt(0, 0, 0).ok_or_else(|| anyhow!("Invalid date range"))?;
+    let end = (to + Duration::days(1)).and_hms_opt(0, 0, 0).ok_or_else(|| anyhow!("Invalid date range"))?;
+    Ok((start, end))
+}
+
+fn report_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(NaiveDateTime, NaiveDateTime)> {
+    let (start, end) = date_range(from, to)?;
+    if end - start > Duration::days(MAX_REPORT_DAYS) {
+        return Err(anyhow!("Invalid date range: reports cover at most {} days, use an export instead", MAX_REPORT_DAYS));
+    }
+    Ok((start, end))
+}
+
+fn ratio(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 { part / whole } else { 0.0 }
+}
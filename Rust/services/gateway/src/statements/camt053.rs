@@ -0,0 +1,251 @@
+// ISO 20022 CAMT.053 (BankToCustomerStatement) parser
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+
+use super::{parse_amount_cents, Direction, ParsedLine, ParsedStatement};
+
+pub fn parse(content: &str) -> Result<Vec<ParsedStatement>> {
+    let content = content.trim_start_matches('\u{feff}');
+    let doc = Document::parse(content).map_err(|e| anyhow!("Invalid CAMT.053 XML: {}", e))?;
+
+    let report = find_child(doc.root_element(), "BkToCstmrStmt")
+        .ok_or_else(|| anyhow!("Missing BkToCstmrStmt element"))?;
+
+    children(report, "Stmt").map(parse_statement).collect()
+}
+
+fn parse_statement(stmt: Node) -> Result<ParsedStatement> {
+    let statement_ref = child_text(stmt, &["Id"])
+        .ok_or_else(|| anyhow!("Statement is missing Id"))?;
+
+    let account = child_text(stmt, &["Acct", "Id", "IBAN"])
+        .or_else(|| child_text(stmt, &["Acct", "Id", "Othr", "Id"]))
+        .ok_or_else(|| anyhow!("Statement {} is missing an account id", statement_ref))?;
+
+    let mut currency = child_text(stmt, &["Acct", "Ccy"]);
+
+    let statement_date = child_text(stmt, &["CreDtTm"])
+        .and_then(|s| parse_date(&s));
+
+    let mut lines = Vec::new();
+    for entry in children(stmt, "Ntry") {
+        let mut entry_lines = parse_entry(entry)?;
+        if currency.is_none() {
+            currency = entry_lines.first().map(|l| l.currency.clone());
+        }
+        lines.append(&mut entry_lines);
+    }
+
+    Ok(ParsedStatement {
+        statement_ref,
+        account,
+        currency: currency.unwrap_or_default(),
+        statement_date,
+        lines,
+    })
+}
+
+/// An entry becomes one line, or one line per transaction when a batch
+/// entry carries individual transaction amounts.
+fn parse_entry(entry: Node) -> Result<Vec<ParsedLine>> {
+    let (amount_cents, currency) = parse_amount(entry)?;
+
+    let direction = parse_direction(entry)?;
+    let booking_date = date_of(entry, "BookgDt");
+    let value_date = date_of(entry, "ValDt");
+    let bank_reference = child_text(entry, &["AcctSvcrRef"]);
+    let entry_info = child_text(entry, &["AddtlNtryInf"]);
+
+    let transactions: Vec<Node> = children(entry, "NtryDtls")
+        .flat_map(|details| children(details, "TxDtls"))
+        .collect();
+
+    let batch_amounts: Option<Vec<(i64, String)>> = if transactions.len() > 1 {
+        transactions
+            .iter()
+            .map(|tx| {
+                find_path(*tx, &["AmtDtls", "TxAmt"])
+                    .or_else(|| find_child(*tx, "Amt").map(|_| *tx))
+                    .and_then(|n| parse_amount(n).ok())
+            })
+            .collect()
+    } else {
+        None
+    };
+
+    let build = |tx: Option<Node>, amount_cents: i64, currency: String| {
+        let reference = tx
+            .and_then(|t| child_text(t, &["Refs", "EndToEndId"]))
+            .filter(|r| r != "NOTPROVIDED");
+        let tx_bank_reference = tx
+            .and_then(|t| child_text(t, &["Refs", "AcctSvcrRef"]))
+            .or_else(|| bank_reference.clone());
+
+        let mut description: Vec<String> = tx
+            .map(|t| {
+                find_path(t, &["RmtInf"])
+                    .map(|rmt| {
+                        rmt.descendants()
+                            .filter(|n| n.is_element() && (n.tag_name().name() == "Ustrd" || n.tag_name().name() == "Ref"))
+                            .filter_map(|n| n.text().map(|s| s.trim().to_string()))
+                            .filter(|s| !s.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+        if let Some(info) = &entry_info {
+            description.push(info.clone());
+        }
+
+        ParsedLine {
+            booking_date,
+            value_date,
+            amount_cents,
+            currency,
+            direction,
+            reference,
+            bank_reference: tx_bank_reference,
+            description: if description.is_empty() { None } else { Some(description.join(" ")) },
+        }
+    };
+
+    let lines = match batch_amounts {
+        Some(amounts) => transactions
+            .iter()
+            .zip(amounts)
+            .map(|(tx, (cents, ccy))| build(Some(*tx), cents, ccy))
+            .collect(),
+        None => vec![build(transactions.first().copied(), amount_cents, currency)],
+    };
+
+    Ok(lines)
+}
+
+fn parse_amount(node: Node) -> Result<(i64, String)> {
+    let amt = find_child(node, "Amt").ok_or_else(|| anyhow!("Entry is missing Amt"))?;
+    let value = amt.text().ok_or_else(|| anyhow!("Entry has an empty Amt"))?;
+    let currency = amt.attribute("Ccy").unwrap_or_default().to_uppercase();
+    Ok((parse_amount_cents(value)?, currency))
+}
+
+fn parse_direction(entry: Node) -> Result<Direction> {
+    let indicator = child_text(entry, &["CdtDbtInd"]).ok_or_else(|| anyhow!("Entry is missing CdtDbtInd"))?;
+
+    // Reversals flip the booking direction
+    let reversed = child_text(entry, &["RvslInd"]).map(|v| v == "true").unwrap_or(false);
+
+    let direction = match indicator.as_str() {
+        "CRDT" => Direction::Credit,
+        "DBIT" => Direction::Debit,
+        other => return Err(anyhow!("Unknown CdtDbtInd: {}", other)),
+    };
+
+    Ok(match (direction, reversed) {
+        (Direction::Credit, true) => Direction::Debit,
+        (Direction::Debit, true) => Direction::Credit,
+        (d, false) => d,
+    })
+}
+
+fn date_of(entry: Node, element: &str) -> Option<NaiveDate> {
+    child_text(entry, &[element, "Dt"])
+        .or_else(|| child_text(entry, &[element, "DtTm"]))
+        .and_then(|s| parse_date(&s))
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    // Accept both ISODate and ISODateTime
+    s.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+}
+
+// XML helpers matching on local names so any camt.053.001.xx namespace works
+
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn find_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn find_path<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |current, name| find_child(current, name))
+}
+
+fn child_text(node: Node, path: &[&str]) -> Option<String> {
+    find_path(node, path)
+        .and_then(|n| n.text())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>MSG1</MsgId><CreDtTm>2026-03-02T08:00:00</CreDtTm></GrpHdr>
+    <Stmt>
+      <Id>STMT-2026-03-01</Id>
+      <CreDtTm>2026-03-02T08:00:00</CreDtTm>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">125.50</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><Dt>2026-03-01</Dt></BookgDt>
+        <ValDt><Dt>2026-03-01</Dt></ValDt>
+        <AcctSvcrRef>BANKREF1</AcctSvcrRef>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>pi_3AbC123</EndToEndId></Refs>
+          <RmtInf><Ustrd>Order 42</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">30.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2026-03-01</Dt></BookgDt>
+        <NtryDtls>
+          <TxDtls><Refs><EndToEndId>A</EndToEndId></Refs><AmtDtls><TxAmt><Amt Ccy="EUR">10.00</Amt></TxAmt></AmtDtls></TxDtls>
+          <TxDtls><Refs><EndToEndId>B</EndToEndId></Refs><AmtDtls><TxAmt><Amt Ccy="EUR">20.00</Amt></TxAmt></AmtDtls></TxDtls>
+        </NtryDtls>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn test_parse_camt053_statement() {
+        let statements = parse(SAMPLE).unwrap();
+        assert_eq!(statements.len(), 1);
+
+        let stmt = &statements[0];
+        assert_eq!(stmt.statement_ref, "STMT-2026-03-01");
+        assert_eq!(stmt.account, "DE89370400440532013000");
+        assert_eq!(stmt.currency, "EUR");
+        assert_eq!(stmt.lines.len(), 3);
+
+        let first = &stmt.lines[0];
+        assert_eq!(first.amount_cents, 12550);
+        assert_eq!(first.direction, Direction::Credit);
+        assert_eq!(first.reference.as_deref(), Some("pi_3AbC123"));
+        assert_eq!(first.bank_reference.as_deref(), Some("BANKREF1"));
+        assert_eq!(first.description.as_deref(), Some("Order 42"));
+    }
+
+    #[test]
+    fn test_parse_camt053_batch_entry_is_split() {
+        let statements = parse(SAMPLE).unwrap();
+        let batch: Vec<_> = statements[0].lines[1..].iter().map(|l| (l.reference.clone().unwrap(), l.amount_cents)).collect();
+        assert_eq!(batch, vec![("A".to_string(), 1000), ("B".to_string(), 2000)]);
+        assert!(statements[0].lines[1..].iter().all(|l| l.direction == Direction::Debit));
+    }
+
+    #[test]
+    fn test_parse_camt053_rejects_other_documents() {
+        assert!(parse("<Document><Other/></Document>").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+// Bank statement parsers (ISO 20022 CAMT.053 and SWIFT MT940)
+pub mod camt053;
+pub mod mt940;
+
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementFormat {
+    Camt053,
+    Mt940,
+}
+
+impl StatementFormat {
+    pub fn as_str(&self) -> &str {
+        match self {
+            StatementFormat::Camt053 => "camt053",
+            StatementFormat::Mt940 => "mt940",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().replace(['.', '_', '-'], "").as_str() {
+            "camt053" | "camt" | "xml" => Some(StatementFormat::Camt053),
+            "mt940" | "swift" => Some(StatementFormat::Mt940),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the file content
+    pub fn detect(content: &str) -> Option<Self> {
+        let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+        if trimmed.starts_with('<') {
+            Some(StatementFormat::Camt053)
+        } else if trimmed.contains(":20:") && (trimmed.contains(":61:") || trimmed.contains(":60F:")) {
+            Some(StatementFormat::Mt940)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Credit,
+    Debit,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Direction::Credit => "credit",
+            Direction::Debit => "debit",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedStatement {
+    pub statement_ref: String,
+    pub account: String,
+    pub currency: String,
+    pub statement_date: Option<NaiveDate>,
+    pub lines: Vec<ParsedLine>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedLine {
+    pub booking_date: Option<NaiveDate>,
+    pub value_date: Option<NaiveDate>,
+    pub amount_cents: i64,
+    pub currency: String,
+    pub direction: Direction,
+    /// Reference supplied by the counterparty (EndToEndId / MT940 customer reference)
+    pub reference: Option<String>,
+    /// Reference assigned by the bank (AcctSvcrRef / MT940 bank reference)
+    pub bank_reference: Option<String>,
+    /// Free-text remittance information
+    pub description: Option<String>,
+}
+
+impl ParsedLine {
+    /// All text a payment reference could appear in
+    pub fn searchable_text(&self) -> String {
+        [&self.reference, &self.bank_reference, &self.description]
+            .iter()
+            .filter_map(|s| s.as_deref())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+pub fn parse(format: StatementFormat, content: &str) -> Result<Vec<ParsedStatement>> {
+    let statements = match format {
+        StatementFormat::Camt053 => camt053::parse(content)?,
+        StatementFormat::Mt940 => mt940::parse(content)?,
+    };
+
+    if statements.is_empty() {
+        return Err(anyhow!("No statements found in {} file", format.as_str()));
+    }
+
+    Ok(statements)
+}
+
+/// Parse a decimal amount ("1234.56" or "1234,56") into minor units
+pub fn parse_amount_cents(raw: &str) -> Result<i64> {
+    let normalized = raw.trim().replace(',', ".");
+    let (whole, fraction) = match normalized.split_once('.') {
+        Some((w, f)) => (w, f),
+        None => (normalized.as_str(), ""),
+    };
+
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(anyhow!("Empty amount"));
+    }
+
+    let whole: i64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| anyhow!("Invalid amount: {}", raw))?
+    };
+
+    let mut fraction = fraction.to_string();
+    if fraction.len() > 2 {
+        return Err(anyhow!("Amount has more than 2 decimals: {}", raw));
+    }
+    while fraction.len() < 2 {
+        fraction.push('0');
+    }
+    let fraction: i64 = fraction.parse().map_err(|_| anyhow!("Invalid amount: {}", raw))?;
+
+    Ok(whole * 100 + fraction)
+}
+
@@ -0,0 +1,287 @@
+// SWIFT MT940 (Customer Statement Message) parser
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, NaiveDate};
+
+use super::{parse_amount_cents, Direction, ParsedLine, ParsedStatement};
+
+pub fn parse(content: &str) -> Result<Vec<ParsedStatement>> {
+    let mut statements = Vec::new();
+    let mut current: Option<ParsedStatement> = None;
+
+    for (tag, value) in split_fields(content) {
+        match tag.as_str() {
+            "20" => {
+                if let Some(stmt) = current.take() {
+                    statements.push(stmt);
+                }
+                current = Some(ParsedStatement {
+                    statement_ref: value.trim().to_string(),
+                    account: String::new(),
+                    currency: String::new(),
+                    statement_date: None,
+                    lines: Vec::new(),
+                });
+            }
+            "25" => {
+                let stmt = current.as_mut().ok_or_else(|| anyhow!("Field :25: before :20:"))?;
+                stmt.account = value.trim().to_string();
+            }
+            "60F" | "60M" => {
+                let stmt = current.as_mut().ok_or_else(|| anyhow!("Field :60F: before :20:"))?;
+                let (_, date, currency, _) = parse_balance(&value)?;
+                stmt.currency = currency;
+                stmt.statement_date = stmt.statement_date.or(Some(date));
+            }
+            "62F" => {
+                let stmt = current.as_mut().ok_or_else(|| anyhow!("Field :62F: before :20:"))?;
+                let (_, date, _, _) = parse_balance(&value)?;
+                stmt.statement_date = Some(date);
+            }
+            "61" => {
+                let stmt = current.as_mut().ok_or_else(|| anyhow!("Field :61: before :20:"))?;
+                let line = parse_statement_line(&value, &stmt.currency)?;
+                stmt.lines.push(line);
+            }
+            "86" => {
+                // Information to account owner belongs to the preceding :61:
+                if let Some(line) = current.as_mut().and_then(|s| s.lines.last_mut()) {
+                    let info = value.split('\n').map(str::trim).collect::<Vec<_>>().join(" ");
+                    line.description = match line.description.take() {
+                        Some(existing) => Some(format!("{} {}", existing, info)),
+                        None => Some(info),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(stmt) = current.take() {
+        statements.push(stmt);
+    }
+
+    Ok(statements)
+}
+
+/// Split the message body into (tag, value) pairs, joining continuation lines
+fn split_fields(content: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for raw_line in content.lines() {
+        let mut line = raw_line.trim_end_matches('\r');
+
+        // Strip SWIFT block headers ({1:...}{2:...}{4:) and trailer (-})
+        if let Some(idx) = line.find("{4:") {
+            line = &line[idx + 3..];
+        }
+        if line.starts_with('{') || line.trim() == "-}" || line.trim() == "-" || line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix(':') {
+            if let Some((tag, value)) = rest.split_once(':') {
+                if !tag.is_empty() && tag.len() <= 3 && tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    fields.push((tag.to_string(), value.to_string()));
+                    continue;
+                }
+            }
+        }
+
+        if let Some((_, value)) = fields.last_mut() {
+            value.push('\n');
+            value.push_str(line);
+        }
+    }
+
+    fields
+}
+
+/// Balance field: D/C mark, YYMMDD, currency, amount
+fn parse_balance(value: &str) -> Result<(Direction, NaiveDate, String, i64)> {
+    let value = value.trim();
+    // Fixed-width fields are sliced by byte
+    if !value.is_ascii() {
+        return Err(anyhow!("Balance field is not ASCII: {}", value));
+    }
+    if value.len() < 11 {
+        return Err(anyhow!("Balance field too short: {}", value));
+    }
+
+    let direction = match &value[..1] {
+        "C" => Direction::Credit,
+        "D" => Direction::Debit,
+        other => return Err(anyhow!("Invalid balance mark: {}", other)),
+    };
+    let date = parse_yymmdd(&value[1..7])?;
+    let currency = value[7..10].to_uppercase();
+    let amount = parse_amount_cents(&value[10..])?;
+
+    Ok((direction, date, currency, amount))
+}
+
+/// :61: YYMMDD[MMDD](C|D|RC|RD)[funds code]amount(N|F|S)XXX customer-ref[//bank-ref][\nsupplementary]
+fn parse_statement_line(value: &str, currency: &str) -> Result<ParsedLine> {
+    let (first, supplementary) = match value.split_once('\n') {
+        Some((f, s)) => (f.trim(), Some(s.trim().to_string()).filter(|s| !s.is_empty())),
+        None => (value.trim(), None),
+    };
+
+    // Fixed-width fields are sliced by byte
+    if !first.is_ascii() {
+        return Err(anyhow!("Statement line is not ASCII: {}", first));
+    }
+    if first.len() < 6 {
+        return Err(anyhow!("Statement line too short: {}", first));
+    }
+
+    let value_date = parse_yymmdd(&first[..6])?;
+    let mut rest = &first[6..];
+
+    // Optional entry date (MMDD)
+    let mut booking_date = None;
+    if rest.len() >= 4 && rest[..4].chars().all(|c| c.is_ascii_digit()) {
+        let month: u32 = rest[..2].parse()?;
+        let day: u32 = rest[2..4].parse()?;
+        booking_date = entry_date(value_date, month, day);
+        rest = &rest[4..];
+    }
+
+    let direction = if let Some(r) = rest.strip_prefix("RC") {
+        rest = r;
+        Direction::Debit
+    } else if let Some(r) = rest.strip_prefix("RD") {
+        rest = r;
+        Direction::Credit
+    } else if let Some(r) = rest.strip_prefix('C') {
+        rest = r;
+        Direction::Credit
+    } else if let Some(r) = rest.strip_prefix('D') {
+        rest = r;
+        Direction::Debit
+    } else {
+        return Err(anyhow!("Invalid debit/credit mark in :61: {}", first));
+    };
+
+    // Optional funds code (third letter of the currency code)
+    if rest.chars().next().map(|c| c.is_ascii_alphabetic()).unwrap_or(false) {
+        rest = &rest[1..];
+    }
+
+    let amount_end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ','))
+        .ok_or_else(|| anyhow!("Missing transaction type in :61: {}", first))?;
+    let amount_cents = parse_amount_cents(&rest[..amount_end])?;
+    rest = &rest[amount_end..];
+
+    // Transaction type identification code, e.g. NTRF
+    if rest.len() < 4 {
+        return Err(anyhow!("Missing transaction type in :61: {}", first));
+    }
+    rest = &rest[4..];
+
+    let (customer_ref, bank_ref) = match rest.split_once("//") {
+        Some((c, b)) => (c, Some(b)),
+        None => (rest, None),
+    };
+
+    let reference = Some(customer_ref.trim().to_string())
+        .filter(|r| !r.is_empty() && r != "NONREF");
+    let bank_reference = bank_ref
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty());
+
+    Ok(ParsedLine {
+        booking_date: booking_date.or(Some(value_date)),
+        value_date: Some(value_date),
+        amount_cents,
+        currency: currency.to_string(),
+        direction,
+        reference,
+        bank_reference,
+        description: supplementary,
+    })
+}
+
+/// The entry date carries no year: take the one closest to the value date,
+/// so a line valued on 31 December and booked on 2 January lands in the next year
+fn entry_date(value_date: NaiveDate, month: u32, day: u32) -> Option<NaiveDate> {
+    let year = value_date.year();
+    [year - 1, year, year + 1]
+        .into_iter()
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .min_by_key(|date| (*date - value_date).num_days().abs())
+}
+
+fn parse_yymmdd(s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("20{}", s), "%Y%m%d")
+        .map_err(|_| anyhow!("Invalid date: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "{1:F01BANKDEFFAXXX0000000000}{2:O9400000000000BANKDEFFAXXX00000000000000000000N}{4:
+:20:STMT0301
+:25:DE89370400440532013000
+:28C:00001/001
+:60F:C260228EUR1000,00
+:61:2603010301C125,50NTRFpi_3AbC123//BANKREF1
+:86:Payment for order 42
+continued text
+:61:260301D30,NCHGNONREF
+:86:Bank fees
+:62F:C260301EUR1095,50
+-}";
+
+    #[test]
+    fn test_parse_mt940_statement() {
+        let statements = parse(SAMPLE).unwrap();
+        assert_eq!(statements.len(), 1);
+
+        let stmt = &statements[0];
+        assert_eq!(stmt.statement_ref, "STMT0301");
+        assert_eq!(stmt.account, "DE89370400440532013000");
+        assert_eq!(stmt.currency, "EUR");
+        assert_eq!(stmt.statement_date, NaiveDate::from_ymd_opt(2026, 3, 1));
+        assert_eq!(stmt.lines.len(), 2);
+
+        let credit = &stmt.lines[0];
+        assert_eq!(credit.amount_cents, 12550);
+        assert_eq!(credit.direction, Direction::Credit);
+        assert_eq!(credit.reference.as_deref(), Some("pi_3AbC123"));
+        assert_eq!(credit.bank_reference.as_deref(), Some("BANKREF1"));
+        assert_eq!(credit.description.as_deref(), Some("Payment for order 42 continued text"));
+
+        let fee = &stmt.lines[1];
+        assert_eq!(fee.amount_cents, 3000);
+        assert_eq!(fee.direction, Direction::Debit);
+        assert_eq!(fee.reference, None);
+    }
+
+    #[test]
+    fn test_parse_mt940_reversal_and_funds_code() {
+        let line = parse_statement_line("260301RCR12,00NTRFREF1", "EUR").unwrap();
+        assert_eq!(line.direction, Direction::Debit);
+        assert_eq!(line.amount_cents, 1200);
+        assert_eq!(line.reference.as_deref(), Some("REF1"));
+    }
+
+    #[test]
+    fn test_parse_mt940_entry_date_across_year_end() {
+        let line = parse_statement_line("2512310102C10,00NTRFREF1", "EUR").unwrap();
+        assert_eq!(line.value_date, NaiveDate::from_ymd_opt(2025, 12, 31));
+        assert_eq!(line.booking_date, NaiveDate::from_ymd_opt(2026, 1, 2));
+
+        let line = parse_statement_line("2601021231D10,00NTRFREF2", "EUR").unwrap();
+        assert_eq!(line.booking_date, NaiveDate::from_ymd_opt(2025, 12, 31));
+    }
+
+    #[test]
+    fn test_parse_mt940_rejects_non_ascii_fields() {
+        assert!(parse_statement_line("2603é1C10,00NTRFREF1", "EUR").is_err());
+        assert!(parse_statement_line("260301C1€,00NTRFREF1", "EUR").is_err());
+        assert!(parse_balance("C2602€8EUR1000,00").is_err());
+        assert!(parse(":20:S1\n:60F:CéééEUR1,00\n").is_err());
+    }
+}
@@ -1,6 +1,8 @@
 use messaging::kafka_consumer::KafkaConsumer;
-use messaging::events::PaymentCreatedEvent;
+use messaging::events::{AccountEmailEvent, EventAttachment, InvoicePaidEvent, PaymentCreatedEvent};
+use contracts::events::{UserEvent, UserEventMessage, USER_EVENTS_TOPIC};
 use anyhow::Result;
+use base64::Engine;
 
 pub async fn start(brokers: &str) -> Result<()> {
     tracing::info!("📧 Email consumer starting...");
@@ -10,8 +12,26 @@ pub async fn start(brokers: &str) -> Result<()> {
         "email-group",
         &["payment-events"]
     )?;
+
+    let invoice_consumer = KafkaConsumer::new(
+        brokers,
+        "email-invoice-group",
+        &["invoice-events"]
+    )?;
+
+    let account_consumer = KafkaConsumer::new(
+        brokers,
+        "email-account-group",
+        &["account-email-events"]
+    )?;
+
+    let user_consumer = KafkaConsumer::new(
+        brokers,
+        "email-user-group",
+        &[USER_EVENTS_TOPIC]
+    )?;
     
-    consumer.consume(|key, payload| {
+    let payments = consumer.consume(|key, payload| {
         tracing::info!("Email consumer received message - Key: {}", key);
         
         match serde_json::from_str::<PaymentCreatedEvent>(&payload) {
@@ -30,5 +50,130 @@ pub async fn start(brokers: &str) -> Result<()> {
                 Err(anyhow::anyhow!("Parse error: {}", e))
             }
         }
-    }).await
+    });
+
+    let invoices = invoice_consumer.consume(|key, payload| {
+        tracing::info!("Email consumer received invoice event - Key: {}", key);
+
+        match serde_json::from_str::<InvoicePaidEvent>(&payload) {
+            Ok(event) => send_invoice_paid_email(&event),
+            Err(e) => {
+                tracing::error!("Failed to parse event: {}", e);
+                Err(anyhow::anyhow!("Parse error: {}", e))
+            }
+        }
+    });
+
+    let accounts = account_consumer.consume(|key, payload| {
+        tracing::info!("Email consumer received account email - Key: {}", key);
+
+        match serde_json::from_str::<AccountEmailEvent>(&payload) {
+            Ok(event) => send_account_email(&event),
+            Err(e) => {
+                tracing::error!("Failed to parse event: {}", e);
+                Err(anyhow::anyhow!("Parse error: {}", e))
+            }
+        }
+    });
+
+    let users = user_consumer.consume(|key, payload| {
+        tracing::info!("Email consumer received user event - Key: {}", key);
+
+        match serde_json::from_str::<UserEventMessage>(&payload) {
+            Ok(message) => send_user_email(&message),
+            Err(e) => {
+                tracing::error!("Failed to parse event: {}", e);
+                Err(anyhow::anyhow!("Parse error: {}", e))
+            }
+        }
+    });
+
+    tokio::try_join!(payments, invoices, accounts, users)?;
+    Ok(())
+}
+
+fn send_account_email(event: &AccountEmailEvent) -> Result<()> {
+    let subject = match event.kind.as_str() {
+        "email_verification" => "Verify your email address",
+        "password_reset" => "Reset your password",
+        "email_change" => "Confirm your new email address",
+        other => return Err(anyhow::anyhow!("Unknown account email kind: {}", other)),
+    };
+
+    // Simulate sending email; the link holds a live token so it is not logged
+    tracing::info!(
+        "📧 Sending \"{}\" to {} <{}> (user {})",
+        subject,
+        event.name,
+        event.email,
+        event.user_id
+    );
+    tracing::info!("   Link expires at {}", event.expires_at);
+    Ok(())
+}
+
+/// Welcome mail for new accounts and security alerts. Events arrive at
+/// least once, `event_id` is logged to trace duplicates.
+fn send_user_email(message: &UserEventMessage) -> Result<()> {
+    let (email, subject) = match &message.event {
+        UserEvent::Created(event) => (event.email.as_str(), "Welcome to RushTech"),
+        UserEvent::PasswordChanged(event) => match &event.email {
+            Some(email) => (email.as_str(), "Your password was changed"),
+            None => return Err(anyhow::anyhow!("Password change of user {} has no email", event.user_id)),
+        },
+        UserEvent::Locked(event) => {
+            tracing::info!("   Login locked until {}", event.locked_until);
+            (event.email.as_str(), "Your account was locked after failed logins")
+        }
+        UserEvent::EmailVerified(_) | UserEvent::Updated(_) | UserEvent::Deleted(_) => {
+            tracing::debug!("No email for {} event {}", message.event.event_type(), message.event_id);
+            return Ok(());
+        }
+    };
+
+    // Simulate sending email
+    tracing::info!(
+        "📧 Sending \"{}\" to {} (user {}, event {})",
+        subject,
+        email,
+        message.event.user_id(),
+        message.event_id
+    );
+    Ok(())
+}
+
+fn send_invoice_paid_email(event: &InvoicePaidEvent) -> Result<()> {
+    let recipient = event
+        .customer_email
+        .clone()
+        .unwrap_or_else(|| format!("user {}", event.user_id));
+
+    // Simulate sending email
+    tracing::info!(
+        "📧 Sending receipt for invoice {} to {}",
+        event.invoice_number,
+        recipient
+    );
+    tracing::info!("   Amount: {} {}", event.amount, event.currency);
+
+    match &event.attachment {
+        Some(attachment) => {
+            let content = decode_attachment(attachment)?;
+            tracing::info!(
+                "   Attachment: {} ({}, {} bytes)",
+                attachment.filename,
+                attachment.content_type,
+                content.len()
+            );
+        }
+        None => tracing::warn!("   Invoice {} has no PDF attached", event.invoice_number),
+    }
+
+    Ok(())
+}
+
+fn decode_attachment(attachment: &EventAttachment) -> Result<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(&attachment.content_base64)
+        .map_err(|e| anyhow::anyhow!("Invalid attachment {}: {}", attachment.filename, e))
 }